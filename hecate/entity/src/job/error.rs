@@ -1,6 +1,7 @@
+use hecate::codegen::input_schema::monitor::MonitorSeriesError;
 use hecate::{CodeGenError, SchemaValidationError};
 use hecate_executor::{BoxError, ExecutorError};
-use rmcp::Error as RmcpError;
+use rmcp::ErrorData as McpError;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -25,6 +26,14 @@ pub enum JobError {
     JSONSerializationError(#[from] serde_json::Error),
     #[error("job execution failed")]
     ExecutionFailed(#[from] RunJobError),
+    #[error("monitor {0} not found in the input schema of the job")]
+    MonitorNotFound(String),
+    #[error("invalid monitor file: {0}")]
+    InvalidMonitorFile(#[from] MonitorSeriesError),
+    #[error("job {0} was not interrupted, only interrupted jobs are restarted")]
+    NotInterrupted(i64),
+    #[error("job {0} saves no checkpoint to restart from")]
+    NoCheckpoint(i64),
     #[error("TODO")]
     NotImplemented,
 }
@@ -33,14 +42,17 @@ use JobError::*;
 
 use crate::job::workflow::RunJobError;
 
-impl From<JobError> for rmcp::Error {
+impl From<JobError> for McpError {
     fn from(err: JobError) -> Self {
         match err {
-            JobNotFound(_) => RmcpError::resource_not_found(err.to_string(), None),
-            InvalidJobConfig(_) | SchemaValidationError(_) => {
-                RmcpError::invalid_params(err.to_string(), None)
+            JobNotFound(_) | MonitorNotFound(_) => {
+                McpError::resource_not_found(err.to_string(), None)
             }
-            _ => RmcpError::internal_error(err.to_string(), None),
+            InvalidJobConfig(_)
+            | SchemaValidationError(_)
+            | NotInterrupted(_)
+            | NoCheckpoint(_) => McpError::invalid_params(err.to_string(), None),
+            _ => McpError::internal_error(err.to_string(), None),
         }
     }
 }
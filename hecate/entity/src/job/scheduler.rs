@@ -92,7 +92,7 @@ impl JobScheduler {
     }
 
     pub fn job_status_cmd(&self, job_id: &str) -> String {
-        format!(r"oarstat -j {job_id} -f | grep -e exit_code -e 'state\s*='")
+        format!(r"oarstat -j {job_id} -f | grep -e exit_code -e 'state\s*=' -e 'types\s*='")
     }
 
     pub fn parse_job_status(&self, response: &str) -> Option<JobStatus> {
@@ -112,6 +112,9 @@ impl JobScheduler {
             } else {
                 Some(JobStatus::Finished)
             }
+        } else if response.contains("Error") && response.contains("besteffort") {
+            // Best-effort jobs are killed when the resources they use are requested
+            Some(JobStatus::Interupted)
         } else if response.contains("Error") {
             Some(JobStatus::SchedulerError)
         } else if response.contains("Failed") {
@@ -174,4 +177,19 @@ OAR_JOB_ID=5548155
     exit_code = 512 (2,0,0)";
         assert_eq!(Some(JobStatus::Failed), Oarsub.parse_job_status(response));
     }
+
+    #[test]
+    fn oarsub_killed_besteffort_job_status() {
+        let response = "state = Error
+    types = besteffort
+    exit_code = 32512 (127,0,0)";
+        assert_eq!(
+            Some(JobStatus::Interupted),
+            Oarsub.parse_job_status(response)
+        );
+        assert_eq!(
+            Some(JobStatus::SchedulerError),
+            Oarsub.parse_job_status("state = Error")
+        );
+    }
 }
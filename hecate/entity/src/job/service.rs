@@ -87,6 +87,7 @@ impl Job {
             file_name,
             cmakelists,
             schema: _,
+            mesh_files: _,
         } = schema.generate_sources()?;
         let job = job::ActiveModel {
             name: Set(name),
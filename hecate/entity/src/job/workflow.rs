@@ -1,12 +1,16 @@
 use std::path::PathBuf;
 
-use hecate_executor::ExecutorError;
+use hecate::InputSchema;
+use hecate::codegen::input_schema::CHECKPOINT_LATEST_FILE;
+use hecate::codegen::input_schema::mesh::file::{MeshFileError, MeshSource};
+use hecate::codegen::input_schema::monitor::{Monitor, MonitorSeries};
+use hecate_executor::{ExecutorError, shell_quote};
 use log::{debug, error, info};
 use sea_orm::{ActiveModelTrait, ActiveValue::Set, DbConn, EntityTrait, IntoActiveModel};
 use thiserror::Error;
 
 use crate::{
-    Job, JobError,
+    File, Job, JobError,
     job::{self, JobStatus, scheduler::SchedulerJobConfig},
 };
 
@@ -22,8 +26,21 @@ pub enum RunJobError {
     MissingRemoteId,
     #[error("failed to update job in database")]
     DatabaseError(#[from] sea_orm::DbErr),
+    #[error("invalid input schema stored in database: {0}")]
+    InvalidSchema(#[from] serde_json::Error),
+    #[error("invalid mesh file: {0}")]
+    MeshFile(#[from] MeshFileError),
+    #[error("failed to read mesh file {0}: {1}")]
+    MeshReadFailed(String, std::io::Error),
+    #[error("registered file {0} not found")]
+    FileNotFound(i32),
+    #[error("registered file {id} is stored on {host}, which the job cannot reach")]
+    UnreachableFile { id: i32, host: String },
 }
 
+/// Host name of the registered files stored on the machine running Hecate.
+const LOCAL_HOST: &str = "localhost";
+
 impl super::Model {
     pub async fn cancel_job(&self) {}
 
@@ -81,11 +98,8 @@ impl super::Model {
 
         // executor.execute("touch duckies").await?;
         // info!("duckies have appeared");
-        let name = whoami::username();
-        let job_name = &job.name;
-        let job_id = job.id;
 
-        let job_dir = String::from(format!("hecate/{name}/{job_id}#{job_name}"));
+        let job_dir = job.job_dir();
         let sources_dir = format!("~/{job_dir}");
         let results_dir = format!("{sources_dir}/results");
         let executor = job.executor().await?;
@@ -109,6 +123,94 @@ impl super::Model {
             .write_file(&format!("{sources_dir}/CMakeLists.txt"), &cmakelists)
             .await?;
 
+        let schema: InputSchema =
+            serde_json::from_value(job.schema.clone()).map_err(RunJobError::from)?;
+        for (file_name, source) in schema.mesh_files().map_err(RunJobError::from)? {
+            let destination = format!("{sources_dir}/{file_name}");
+            info!("uploading mesh file to {destination}");
+            match source {
+                MeshSource::Path(path) => {
+                    // Read as bytes, ExodusII and binary Gmsh files not being text
+                    let content = std::fs::read(path)
+                        .map_err(|e| RunJobError::MeshReadFailed(path.clone(), e))?;
+                    executor.write_bytes(&destination, &content).await?;
+                }
+                MeshSource::Registered { id } => {
+                    let file = File::find_by_id(*id)
+                        .one(db)
+                        .await?
+                        .ok_or(RunJobError::FileNotFound(*id))?;
+                    // Registered files are copied directly on the host running the job when
+                    // they are stored there
+                    let job_host = job.cluster_access_name.as_deref().unwrap_or(LOCAL_HOST);
+                    if file.host == job_host {
+                        executor.copy_file(&file.path, &destination).await?;
+                    } else if file.host == LOCAL_HOST {
+                        let content = std::fs::read(&file.path)
+                            .map_err(|e| RunJobError::MeshReadFailed(file.path.clone(), e))?;
+                        executor.write_bytes(&destination, &content).await?;
+                    } else {
+                        Err(RunJobError::UnreachableFile {
+                            id: file.id,
+                            host: file.host,
+                        })?;
+                    }
+                }
+            }
+        }
+
+        job.submit(db, None).await
+    }
+
+    /// The latest checkpoint saved by an interrupted job, to `restart` it from.
+    pub async fn latest_checkpoint(&self) -> Result<String, JobError> {
+        if self.status != JobStatus::Interupted {
+            return Err(JobError::NotInterrupted(self.id));
+        }
+        let schema: InputSchema = serde_json::from_value(self.schema.clone())?;
+        if schema.checkpoint.is_none() {
+            return Err(JobError::NoCheckpoint(self.id));
+        }
+        let latest = self.results_path(format!(
+            "{}{CHECKPOINT_LATEST_FILE}",
+            schema.output.path_prefix()
+        ));
+        let executor = self.executor().await?;
+        // The job may have been interrupted before writing its first checkpoint
+        let checkpoint = match executor
+            .execute(&format!("cat {}", shell_quote(&latest)))
+            .await
+        {
+            Err(ExecutorError::NonZeroExitCode(..)) => String::new(),
+            checkpoint => checkpoint?,
+        };
+        let checkpoint = checkpoint.trim();
+        if checkpoint.is_empty() {
+            return Err(JobError::NoCheckpoint(self.id));
+        }
+        Ok(checkpoint.to_string())
+    }
+
+    /// Resubmits an interrupted job, resuming the simulation from its latest `checkpoint`
+    /// instead of starting over.
+    pub async fn restart(self, db: &DbConn, checkpoint: &str) -> Result<Self, JobError> {
+        if self.status != JobStatus::Interupted {
+            return Err(JobError::NotInterrupted(self.id));
+        }
+        info!("restarting job {} from checkpoint {checkpoint}", self.id);
+        self.submit(db, Some(checkpoint)).await
+    }
+
+    /// Builds and runs the sources of the job through its scheduler if any, resuming from the
+    /// `restart` checkpoint when given.
+    async fn submit(self, db: &DbConn, restart: Option<&str>) -> Result<Self, JobError> {
+        let job = self;
+        let job_id = job.id;
+        let job_dir = job.job_dir();
+        let sources_dir = format!("~/{job_dir}");
+        let results_dir = format!("{sources_dir}/results");
+        let executor = job.executor().await?;
+
         let compiler = job.compiler.as_deref().unwrap_or_else(|| "g++");
         let build_dir = PathBuf::from("/tmp").join(&job_dir).join("build");
         let cmake_cmd = format!(
@@ -122,6 +224,9 @@ impl super::Model {
         let mut final_command = format!(
             "source ~/.bashrc && {cmake_cmd} && cmake --build {build_dir} && OMP_PROC_BIND=spread OMP_PLACES=threads {build_dir}/run_sim"
         );
+        if let Some(checkpoint) = restart {
+            final_command += &format!(" --restart {}", shell_quote(checkpoint));
+        }
 
         if let Some(scheduler) = &job.scheduler {
             final_command = scheduler.create_job_cmd(SchedulerJobConfig {
@@ -154,6 +259,36 @@ impl super::Model {
 
         Ok(job)
     }
+
+    /// The directory of the job, relative to the home directory of the host running it.
+    fn job_dir(&self) -> String {
+        format!("hecate/{}/{}#{}", whoami::username(), self.id, self.name)
+    }
+
+    /// The path of a `file` written by the simulation, which runs in the results directory.
+    fn results_path(&self, file: String) -> String {
+        if file.starts_with('/') {
+            file
+        } else {
+            format!("~/{}/results/{file}", self.job_dir())
+        }
+    }
+
+    /// Reads the records of the monitor `name` written so far by the job.
+    pub async fn read_monitor(&self, name: &str) -> Result<MonitorSeries, JobError> {
+        let schema: InputSchema = serde_json::from_value(self.schema.clone())?;
+        if !schema.monitors.contains_key(name) {
+            return Err(JobError::MonitorNotFound(name.to_string()));
+        }
+        let path = self.results_path(format!(
+            "{}{}",
+            schema.output.path_prefix(),
+            Monitor::file_name(name)
+        ));
+        let executor = self.executor().await?;
+        let content = executor.execute(&format!("cat {path}")).await?;
+        Ok(content.parse()?)
+    }
 }
 pub async fn update_job_status(job_id: i64, db: &DbConn) -> Result<job::Model, JobError> {
     let job = Job::find_by_id(job_id)
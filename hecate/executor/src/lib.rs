@@ -44,6 +44,7 @@ pub enum ExecutorError {
 use ExecutorError::*;
 
 use crate::utils::BoxFuture;
+pub use crate::utils::shell_quote;
 
 pub trait Executor {
     fn execute<'a>(&'a self, cmd: &'a str) -> BoxFuture<'a, Result<String, ExecutorError>>;
@@ -54,6 +55,22 @@ pub trait Executor {
         &'a self,
         path: &'a str,
         content: &'a str,
+    ) -> BoxFuture<'a, Result<(), ExecutorError>> {
+        self.write_bytes(path, content.as_bytes())
+    }
+
+    /// Writes raw bytes, such as the ones of binary mesh files.
+    fn write_bytes<'a>(
+        &'a self,
+        path: &'a str,
+        content: &'a [u8],
+    ) -> BoxFuture<'a, Result<(), ExecutorError>>;
+
+    /// Copies the file `from` to `to`, both on the host of the executor.
+    fn copy_file<'a>(
+        &'a self,
+        from: &'a str,
+        to: &'a str,
     ) -> BoxFuture<'a, Result<(), ExecutorError>>;
 }
 
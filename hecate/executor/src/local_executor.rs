@@ -29,10 +29,10 @@ impl Executor for LocalExecutor {
         })
     }
 
-    fn write_file<'a>(
+    fn write_bytes<'a>(
         &'a self,
         path: &'a str,
-        content: &'a str,
+        content: &'a [u8],
     ) -> BoxFuture<'a, Result<(), ExecutorError>> {
         Box::pin(async move {
             async_fs::write(path, content)
@@ -43,6 +43,20 @@ impl Executor for LocalExecutor {
         })
     }
 
+    fn copy_file<'a>(
+        &'a self,
+        from: &'a str,
+        to: &'a str,
+    ) -> BoxFuture<'a, Result<(), ExecutorError>> {
+        Box::pin(async move {
+            async_fs::copy(from, to)
+                .await
+                .map(|_| debug!("copied local file {from} to {to}"))
+                .inspect_err(|e| error!("failed to copy local file {from} to {to}: {e}"))
+                .map_err(|e| ExecutorError::FileWriteFailed(Box::new(e)))
+        })
+    }
+
     // async fn write_file<F: AsRef<Path>>(
     //     &self,
     //     file: F,
@@ -2,7 +2,7 @@ use std::io;
 
 use crate::{
     Executor, ExecutorError,
-    utils::{BoxFuture, ExpandCommandError, expand_command},
+    utils::{BoxFuture, ExpandCommandError, expand_command, shell_quote},
 };
 use log::{debug, info};
 use russh::{ChannelMsg, client, keys::PrivateKeyWithHashAlg};
@@ -201,13 +201,25 @@ impl Executor for SshExecutor {
         todo!()
     }
 
-    fn write_file<'a>(
+    fn write_bytes<'a>(
         &'a self,
         path: &'a str,
-        content: &'a str,
+        content: &'a [u8],
     ) -> BoxFuture<'a, Result<(), ExecutorError>> {
         todo!()
     }
+
+    fn copy_file<'a>(
+        &'a self,
+        from: &'a str,
+        to: &'a str,
+    ) -> BoxFuture<'a, Result<(), ExecutorError>> {
+        Box::pin(async move {
+            self.execute(&format!("cp {} {}", shell_quote(from), shell_quote(to)))
+                .await
+                .map(|_| ())
+        })
+    }
     // type Error = SshExecutorError;
 
     // async fn mkdirs<D: AsRef<Path>>(&self, dir: D) -> Result<(), ExecutorError<Self::Error>> {
@@ -39,3 +39,28 @@ pub async fn expand_command(cmd: &str) -> Result<String, ExpandCommandError> {
     new.push_str(&cmd[last..]);
     Ok(new)
 }
+
+/// Quotes `arg` as a single word of a POSIX shell command, leaving a leading `~/` unquoted for
+/// the shell to expand it to the home directory.
+pub fn shell_quote(arg: &str) -> String {
+    let (home, rest) = match arg.strip_prefix("~/") {
+        Some(rest) => ("~/", rest),
+        None => ("", arg),
+    };
+    format!("{home}'{}'", rest.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("/tmp/mesh file.msh"), "'/tmp/mesh file.msh'");
+        assert_eq!(
+            shell_quote("~/hecate/user/1#job's run/mesh.exo"),
+            r"~/'hecate/user/1#job'\''s run/mesh.exo'"
+        );
+        assert_eq!(shell_quote("$(rm -rf ~)"), "'$(rm -rf ~)'");
+    }
+}
@@ -13,21 +13,34 @@ use thiserror::Error;
 use crate::codegen::input_schema::{FunctionDef, GenConfig};
 use symrs::{Equation, Expr};
 
-use super::input_schema::{FiniteElement, mesh::Mesh};
+use super::input_schema::{
+    FiniteElement,
+    checkpoint::Checkpoint,
+    mesh::{Mesh, file::MeshFileError},
+    monitor::Norm,
+    refinement::Refinement,
+    solver::LinearSolver,
+    stabilization::Stabilization,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BuildingBlock {
     pub includes: IndexSet<String>,
     pub data: Vec<String>,
+    /// Generation of the mesh, done once before setting up the system
+    pub mesh_setup: Vec<String>,
+    /// Setup of the dofs and of the linear algebra objects, redone when the mesh changes
     pub setup: Vec<String>,
+    /// Assembly of the operators constant in time, once the constraints are closed
+    pub assembly: Vec<String>,
     pub additional_names: HashSet<String>,
     pub constructor: Vec<String>,
     pub methods_defs: Vec<String>,
     pub methods_impls: Vec<String>,
     pub main: Vec<String>,
     pub main_setup: Vec<String>,
-    pub additional_vectors: HashSet<String>,
-    pub additional_matrixes: HashSet<String>,
+    pub additional_vectors: IndexSet<String>,
+    pub additional_matrixes: IndexSet<String>,
     pub global: Vec<String>,
     pub output: Vec<String>,
 }
@@ -37,15 +50,17 @@ impl BuildingBlock {
         BuildingBlock {
             includes: IndexSet::new(),
             data: Vec::new(),
+            mesh_setup: Vec::new(),
             setup: Vec::new(),
+            assembly: Vec::new(),
             additional_names: HashSet::new(),
             constructor: Vec::new(),
             methods_defs: Vec::new(),
             methods_impls: Vec::new(),
             main: Vec::new(),
             main_setup: Vec::new(),
-            additional_vectors: HashSet::new(),
-            additional_matrixes: HashSet::new(),
+            additional_vectors: IndexSet::new(),
+            additional_matrixes: IndexSet::new(),
             global: Vec::new(),
             output: Vec::new(),
         }
@@ -140,6 +155,14 @@ pub struct MatrixConfig<'a> {
 pub enum ShapeMatrix {
     Laplace,
     Mass,
+    /// ∫ div(φ_i) div(φ_j), from the gradient of the divergence of vector unknowns
+    GradDiv,
+    /// ∫ ε(φ_i) : ε(φ_j), from the divergence of the strain of vector unknowns
+    Strain,
+    /// ∫ φ_i (v·∇φ_j), from the advection of the unknowns along a velocity v
+    Advection,
+    /// ∫ τ (v·∇φ_i) φ_j, testing the time derivative of the unknowns with SUPG
+    Supg,
 }
 
 impl Display for ShapeMatrix {
@@ -154,15 +177,57 @@ pub struct ShapeMatrixConfig<'a> {
     pub element: &'a str,
     pub matrix_config: &'a MatrixConfig<'a>,
     pub kind: ShapeMatrix,
+    /// Whether the matrix is assembled with `constraints` applied
+    pub constrained: bool,
+    /// Whether the element is discontinuous, the Laplace matrix then getting face terms
+    pub discontinuous: bool,
+    /// The function weighting the matrix at the quadrature points, if any
+    pub coefficient: Option<ShapeMatrixCoefficient<'a>>,
+    /// The stabilization of the advection matrices
+    pub stabilization: Option<Stabilization>,
+    /// Number of components of the element, the shape functions of different components not
+    /// being coupled
+    pub n_components: usize,
+}
+
+pub struct ShapeMatrixCoefficient<'a> {
+    pub function: &'a str,
+    /// The matrix keeping the values of the previous time step, when the function depends on
+    /// time and the matrix is reassembled at each step
+    pub previous: Option<&'a str>,
 }
 
 pub struct DofHandlerConfig<'a> {
     pub mesh: &'a str,
     pub element: &'a str,
+    /// Whether the mesh is locally refined, the dofs of hanging nodes being constrained
+    pub adaptive: bool,
 }
 
 pub struct SparsityPatternConfig<'a> {
     pub dof_handler: &'a str,
+    /// Whether the pattern must account for the entries introduced by `constraints`
+    pub constrained: bool,
+    /// Whether face integrals couple the dofs of neighboring cells
+    pub flux: bool,
+}
+
+pub struct PeriodicityConstraintsConfig<'a> {
+    pub dof_handler: &'a str,
+    pub direction: u32,
+    /// Boundary ids of the matched faces, which may be the same
+    pub boundary_ids: [u32; 2],
+}
+
+pub struct RefinementConfig<'a> {
+    pub mesh: &'a str,
+    pub dof_handler: &'a str,
+    pub element: &'a str,
+    /// Vector the error is estimated from
+    pub estimated: &'a str,
+    /// Vectors interpolated onto the refined mesh
+    pub transferred: &'a [String],
+    pub refinement: &'a Refinement,
 }
 
 pub struct InitialConditionConfig<'a> {
@@ -189,6 +254,21 @@ pub struct SolveUnknownConfig<'a> {
     pub rhs: &'a str,
     pub unknown_vec: &'a str,
     pub unknown_mat: &'a str,
+    /// Whether the solution must be distributed to the constrained dofs after solving
+    pub constrained: bool,
+    /// Whether the unknown is vector valued, the preconditioner then accounting for its
+    /// components
+    pub vector_valued: bool,
+    /// Whether the matrix is symmetric, non-symmetric systems being solved with GMRES
+    pub symmetric: bool,
+    /// The mass matrix when the matrix is a scaled mass matrix, lumped into its row sums to
+    /// update the unknown without solving
+    pub lumped_mass: Option<&'a str>,
+    /// The settings of the linear solver, completed by defaults chosen from the system
+    pub solver: LinearSolver,
+    /// Whether the matrix is the same at every call, its preconditioner or factorization then
+    /// being set up once
+    pub constant: bool,
 }
 
 pub struct EquationSetupConfig<'a> {
@@ -196,12 +276,32 @@ pub struct EquationSetupConfig<'a> {
     pub unknown: &'a dyn Expr,
     pub vectors: &'a [&'a dyn Expr],
     pub matrixes: &'a [&'a dyn Expr],
+    /// Names of the parameters, whose products are computed once for the whole simulation
+    pub parameters: &'a [&'a str],
+    /// The matrix a time invariant left-hand side is assembled into once, before the time
+    /// stepping, then copied into the system matrix at each step
+    pub pristine_matrix: Option<&'a str>,
+    /// Whether the operators applied to the vectors of the right-hand side are time invariant,
+    /// each then being assembled once into its own matrix before the time stepping
+    pub constant_rhs_operators: bool,
 }
 
+/// A vector assembled from the integral of a function against the shape functions, such as
+/// the source terms of the equations.
 pub struct VectorFromFnConfig<'a> {
     pub function: &'a str,
     pub dof_handler: &'a str,
     pub element: &'a str,
+    pub vector_config: &'a VectorConfig<'a>,
+    /// The time step the function is evaluated at
+    pub iteration: TargetIteration,
+    /// Whether the function depends on time, the vector then being reassembled at each step
+    pub time_dependent: bool,
+    /// Whether the vector is assembled with `constraints` applied
+    pub constrained: bool,
+    /// Number of components of the element, the function being a vector function when there
+    /// are several
+    pub n_components: usize,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -211,33 +311,167 @@ pub enum TargetIteration {
     Previous,
 }
 
+pub enum BoundaryConditionKind<'a> {
+    /// Imposes `function` on the dofs of the given `components`, on all of them when `None`
+    Dirichlet {
+        function: &'a str,
+        components: Option<&'a [u32]>,
+    },
+    Neumann {
+        flux: &'a str,
+    },
+    Robin {
+        alpha: &'a str,
+        value: &'a str,
+    },
+}
+
+/// Weights of the Laplace matrix applied to the solved unknown in its discretized equation.
+///
+/// The boundary integrals of natural conditions come from the integration by parts of the
+/// Laplacian, so they are weighted like the Laplace matrix on both sides of the equation.
+/// For the divergence of the stress of a vector unknown, they are weighted like the strain
+/// matrix divided by 2μ, the natural conditions then imposing the traction σ(u)·n.
+pub struct LaplaceCoefficients {
+    /// Weight on the left hand side, applied to the unknown at the current step
+    pub current: Box<dyn Expr>,
+    /// Weight on the right hand side, applied to the unknown at the previous step
+    pub previous: Box<dyn Expr>,
+}
+
 pub struct ApplyBoundaryConditionConfig<'a> {
-    pub function: &'a str,
+    pub condition: BoundaryConditionKind<'a>,
+    /// Name of the part of the boundary the condition is imposed on, the whole boundary when `None`
+    pub boundary: Option<&'a str>,
+    /// Boundary ids of the faces the condition is imposed on
+    pub boundary_ids: &'a [u32],
     pub dof_handler: &'a str,
+    pub element: &'a str,
     pub matrix: &'a str,
     pub solution: &'a str,
+    pub previous: &'a str,
     pub rhs: &'a str,
+    pub laplace_coefficients: &'a LaplaceCoefficients,
+    /// Number of components of the unknown, its boundary functions being vector functions when
+    /// there are several
+    pub n_components: usize,
+}
+
+pub struct VectorOutputConfig<'a> {
+    pub vector: &'a str,
+    /// DoF handler of the vector, whose locally relevant dofs are written with MPI
+    pub dof_handler: &'a str,
+    /// Number of components of the vector, written as a vector field when there are several
+    pub n_components: usize,
+    /// Unknown whose difference with its previous value over the time step fills the vector
+    /// before it is written
+    pub time_derivative_of: Option<&'a str>,
+}
+
+/// The quantity a monitor computes from its fields.
+pub enum MonitorQuantity<'a> {
+    /// Values of the fields at points
+    Points(&'a [Vec<f64>]),
+    /// Values of the fields at evenly spaced samples of the segment from `from` to `to`
+    Line {
+        from: &'a [f64],
+        to: &'a [f64],
+        samples: u32,
+    },
+    /// Sum of the norms of the fields weighted by coefficients, one per field, the norms being
+    /// squared when `squared`
+    Norms {
+        norms: &'a [(Norm, f64)],
+        squared: bool,
+    },
+}
+
+pub struct MonitorConfig<'a> {
+    pub quantity: MonitorQuantity<'a>,
+    /// Vectors of the recorded fields, with the unknown they are the time derivative of when
+    /// they are computed from the previous step
+    pub fields: &'a [(String, Option<String>)],
+    /// Path of the CSV file the records are appended to
+    pub file: &'a str,
+    pub mesh: &'a str,
+    pub dof_handler: &'a str,
+    pub element: &'a str,
+    pub n_components: usize,
 }
 
+pub struct ErrorsConfig<'a> {
+    /// Vectors of the unknowns with an exact solution, with the function of their exact solution
+    pub errors: &'a [(String, String)],
+    /// Path of the CSV file the errors are appended to
+    pub file: &'a str,
+    pub mesh: &'a str,
+    pub dof_handler: &'a str,
+    pub element: &'a str,
+}
+
+pub struct CheckpointConfig<'a> {
+    pub mesh: &'a str,
+    pub dof_handler: &'a str,
+    pub element: &'a str,
+    /// Vectors of the previous values saved with the mesh
+    pub saved: &'a [String],
+    /// Prefix of the paths of the checkpoint directories
+    pub prefix: &'a str,
+    /// Start of the time stepping, from which the checkpoint times are counted
+    pub time_start: f64,
+    /// Whether the mesh has named parts to mark again once loaded
+    pub marked: bool,
+    pub checkpoint: &'a Checkpoint,
+}
+
+// The closures generating each building block of the factory
+type VectorGetter<'a> = block_getter!(VectorConfig);
+type MatrixGetter<'a> = block_getter!(MatrixConfig);
+type DofHandlerGetter<'a> = block_getter!(DofHandlerConfig);
+type FiniteElementGetter<'a> = block_getter!(FiniteElement);
+type SparsityPatternGetter<'a> = block_getter!(SparsityPatternConfig);
+type ShapeMatrixGetter<'a> =
+    &'a dyn Fn(&str, BuildingBlock, &ShapeMatrixConfig, &GenConfig) -> BlockRes;
+type SolveUnknownGetter<'a> = block_getter!(SolveUnknownConfig);
+type EquationSetupGetter<'a> = block_getter!(EquationSetupConfig);
+type CallGetter<'a> = block_getter_no_context!([&str]);
+type ParameterGetter<'a> = block_getter!(f64);
+type FunctionGetter<'a> = block_getter!(FunctionDef);
+type VectorFromFnGetter<'a> =
+    &'a dyn Fn(&str, BuildingBlock, &VectorFromFnConfig, &GenConfig) -> BlockRes;
+type ApplyBoundaryConditionGetter<'a> = block_getter!(ApplyBoundaryConditionConfig);
+type PeriodicityConstraintsGetter<'a> = block_getter!(PeriodicityConstraintsConfig);
+type InitialConditionGetter<'a> = block_getter!(InitialConditionConfig);
+type RefineMeshGetter<'a> = block_getter!(RefinementConfig);
+type AddVectorOutputGetter<'a> = block_getter!(VectorOutputConfig);
+type MonitorGetter<'a> = block_getter!(MonitorConfig);
+type ErrorsGetter<'a> = block_getter!(ErrorsConfig);
+type CheckpointGetter<'a> = block_getter!(CheckpointConfig);
+
 #[derive(Clone)]
 pub struct BuildingBlockFactory<'a> {
     name: String,
     mesh: HashMap<String, BlockGetter<'a, dyn Mesh>>,
-    vector: Option<block_getter!(VectorConfig)>,
-    matrix: Option<block_getter!(MatrixConfig)>,
-    dof_handler: Option<block_getter!(DofHandlerConfig)>,
-    finite_element: Option<block_getter!(FiniteElement)>,
-    sparsity_pattern: Option<block_getter!(SparsityPatternConfig)>,
-    shape_matrix: Option<&'a dyn Fn(&str, BuildingBlock, &ShapeMatrixConfig) -> BlockRes>,
-    solve_unknown: Option<block_getter!(SolveUnknownConfig)>,
-    equation_setup: Option<block_getter!(EquationSetupConfig)>,
-    call: Option<block_getter_no_context!([&str])>,
-    parameter: Option<block_getter!(f64)>,
-    function: Option<block_getter!(FunctionDef)>,
-    vector_from_function: Option<block_getter!(VectorFromFnConfig)>,
-    apply_boundary_condition: Option<block_getter!(ApplyBoundaryConditionConfig)>,
-    initial_condition: Option<block_getter!(InitialConditionConfig)>,
-    add_vector_output: Option<block_getter!(str)>,
+    vector: Option<VectorGetter<'a>>,
+    matrix: Option<MatrixGetter<'a>>,
+    dof_handler: Option<DofHandlerGetter<'a>>,
+    finite_element: Option<FiniteElementGetter<'a>>,
+    sparsity_pattern: Option<SparsityPatternGetter<'a>>,
+    shape_matrix: Option<ShapeMatrixGetter<'a>>,
+    solve_unknown: Option<SolveUnknownGetter<'a>>,
+    equation_setup: Option<EquationSetupGetter<'a>>,
+    call: Option<CallGetter<'a>>,
+    parameter: Option<ParameterGetter<'a>>,
+    function: Option<FunctionGetter<'a>>,
+    vector_from_function: Option<VectorFromFnGetter<'a>>,
+    apply_boundary_condition: Option<ApplyBoundaryConditionGetter<'a>>,
+    periodicity_constraints: Option<PeriodicityConstraintsGetter<'a>>,
+    initial_condition: Option<InitialConditionGetter<'a>>,
+    refine_mesh: Option<RefineMeshGetter<'a>>,
+    add_vector_output: Option<AddVectorOutputGetter<'a>>,
+    monitor: Option<MonitorGetter<'a>>,
+    errors: Option<ErrorsGetter<'a>>,
+    checkpoint: Option<CheckpointGetter<'a>>,
 }
 
 impl<'a> BuildingBlockFactory<'a> {
@@ -263,8 +497,13 @@ impl<'a> BuildingBlockFactory<'a> {
             function: None,
             vector_from_function: None,
             apply_boundary_condition: None,
+            periodicity_constraints: None,
             initial_condition: None,
+            refine_mesh: None,
             add_vector_output: None,
+            monitor: None,
+            errors: None,
+            checkpoint: None,
         }
     }
 
@@ -275,22 +514,26 @@ impl<'a> BuildingBlockFactory<'a> {
         set_sparsity_pattern,
         SparsityPatternConfig
     );
-    block_accessers!(
-        vector_from_function,
-        set_vector_from_function,
-        VectorFromFnConfig
-    );
     block_accessers!(
         apply_boundary_condition,
         set_apply_boundary_condition,
         ApplyBoundaryConditionConfig
     );
+    block_accessers!(
+        periodicity_constraints,
+        set_periodicity_constraints,
+        PeriodicityConstraintsConfig
+    );
     block_accessers!(
         initial_condition,
         set_initial_condition,
         InitialConditionConfig
     );
-    block_accessers!(add_vector_output, set_add_vector_output, str);
+    block_accessers!(refine_mesh, set_refine_mesh, RefinementConfig);
+    block_accessers!(add_vector_output, set_add_vector_output, VectorOutputConfig);
+    block_accessers!(monitor, set_monitor, MonitorConfig);
+    block_accessers!(errors, set_errors, ErrorsConfig);
+    block_accessers!(checkpoint, set_checkpoint, CheckpointConfig);
 
     block_accessers!(matrix, set_matrix, MatrixConfig);
 
@@ -320,16 +563,33 @@ impl<'a> BuildingBlockFactory<'a> {
             ))?
         }
         let matrix = self.matrix(name, &config.matrix_config, gen_config)?;
-        Ok(self.shape_matrix.unwrap()(name, matrix, config)?)
+        self.shape_matrix.unwrap()(name, matrix, config, gen_config)
     }
 
-    pub fn set_shape_matrix(
-        &mut self,
-        block: &'a dyn Fn(&str, BuildingBlock, &ShapeMatrixConfig) -> BlockRes,
-    ) {
+    pub fn set_shape_matrix(&mut self, block: ShapeMatrixGetter<'a>) {
         self.shape_matrix = Some(block);
     }
 
+    pub fn vector_from_function<'b: 'a>(
+        &self,
+        name: &str,
+        config: &VectorFromFnConfig<'b>,
+        gen_config: &GenConfig,
+    ) -> BlockRes {
+        if self.vector_from_function.is_none() {
+            Err(BuildingBlockError::BlockMissing(
+                "vector_from_function".to_string(),
+                self.name.clone(),
+            ))?
+        }
+        let vector = self.vector(name, config.vector_config, gen_config)?;
+        self.vector_from_function.unwrap()(name, vector, config, gen_config)
+    }
+
+    pub fn set_vector_from_function(&mut self, block: VectorFromFnGetter<'a>) {
+        self.vector_from_function = Some(block);
+    }
+
     block_accessers!(solve_unknown, set_solve_unknown, SolveUnknownConfig);
     block_accessers!(equation_setup, set_equation_setup, EquationSetupConfig);
 
@@ -374,6 +634,10 @@ pub enum BuildingBlockError {
     BlockAlreadyExists(String),
     #[error("name {0} already exists")]
     NameAlreadyExists(String),
+    #[error("{0} is not supported by this factory")]
+    Unsupported(String),
+    #[error("invalid mesh file: {0}")]
+    MeshFile(#[from] MeshFileError),
     #[error("failed to generate expression code")]
     ExprCodeGen(#[from] ExprCodeGenError),
 }
@@ -1,20 +1,39 @@
 use std::collections::HashSet;
 use std::ops::Div;
 
+use indexmap::IndexMap;
 use itertools::Itertools;
 use regex::{Captures, Regex};
 
-use crate::codegen::building_block::{ApplyBoundaryConditionConfig, InitialConditionConfig};
-use crate::codegen::input_schema::GenConfig;
-use crate::codegen::input_schema::{FiniteElement, mesh::HyperCubeMesh};
+use crate::codegen::building_block::{
+    ApplyBoundaryConditionConfig, BoundaryConditionKind, CheckpointConfig, InitialConditionConfig,
+    LaplaceCoefficients, PeriodicityConstraintsConfig, RefinementConfig,
+};
+use crate::codegen::input_schema::{CHECKPOINT_LATEST_FILE, GenConfig};
+use crate::codegen::input_schema::{
+    FiniteElement,
+    checkpoint::Checkpoint,
+    element::ElementComponent,
+    mesh::{
+        CoordinateConditions, CylinderMesh, HyperBallMesh, HyperCubeMesh, HyperRectangleMesh,
+        HyperShellMesh, LShapeMesh, Mesh, MeshParts,
+        file::{FileMesh, MeshFormat},
+        refinements,
+    },
+    output::OutputInterval,
+    refinement::{ErrorEstimator, Refinement},
+    solver::{Preconditioner, SolverMethod},
+    stabilization::Stabilization,
+};
 use symrs::*;
 mod function_def;
-use function_def::function_def_to_deal_ii_code;
+use function_def::{coordinate_conditions, function_def_to_deal_ii_code};
 
 use super::{
     BuildingBlock, BuildingBlockError, BuildingBlockFactory, DofHandlerConfig, EquationSetupConfig,
-    ExprCodeGenError, MatrixConfig, ShapeMatrixConfig, SolveUnknownConfig, SparsityPatternConfig,
-    VectorConfig,
+    ErrorsConfig, ExprCodeGenError, MatrixConfig, MonitorConfig, MonitorQuantity, ShapeMatrix,
+    ShapeMatrixCoefficient, ShapeMatrixConfig, SolveUnknownConfig, SparsityPatternConfig,
+    TargetIteration, VectorConfig, VectorFromFnConfig, VectorOutputConfig,
 };
 
 macro_rules! lines {
@@ -39,10 +58,38 @@ pub fn dsp(dof_handler: &str) -> String {
     format!("{dof_handler}_dsp")
 }
 
-pub fn distributed(dof_handler: &str) -> String {
-    format!("{dof_handler}_distributed_vec")
+/// Copy of `vector` holding the values of the ghost dofs, read on the locally relevant cells.
+///
+/// Only the output, the refinement, the monitors, the errors and the checkpoints evaluate the
+/// unknowns on cells, and read such copies. The right-hand sides, including the boundary and
+/// face integrals, multiply the owned vectors by matrices and evaluate functions at the
+/// quadrature points, they never read the ghost dofs.
+pub fn ghosted(vector: &str) -> String {
+    format!("{vector}_ghosted")
+}
+
+pub fn mapping(element: &str) -> String {
+    format!("{element}_mapping")
+}
+
+pub fn quadrature(element: &str) -> String {
+    format!("{element}_quadrature")
+}
+
+pub fn face_quadrature(element: &str) -> String {
+    format!("{element}_face_quadrature")
 }
 
+pub fn matrix_free(dof_handler: &str) -> String {
+    format!("{dof_handler}_matrix_free")
+}
+
+/// The class of the matrix free operators, a combination of the mass and Laplace operators
+const MATRIX_FREE_OPERATOR: &str = include_str!("./deal_ii/matrix_free_operator.h");
+
+/// The vectors of matrix free code, ghosted on the dofs of the locally owned cells
+const MATRIX_FREE_VECTOR: &str = "LinearAlgebra::distributed::Vector<data_type>";
+
 pub fn deal_ii_factory<'a>() -> BuildingBlockFactory<'a> {
     let mut factory = BuildingBlockFactory::new("deal.II");
 
@@ -51,6 +98,8 @@ pub fn deal_ii_factory<'a>() -> BuildingBlockFactory<'a> {
             range,
             resolution,
             show_info,
+            colorize,
+            parts,
         }: &HyperCubeMesh = mesh
             .as_any()
             .downcast_ref()
@@ -58,53 +107,263 @@ pub fn deal_ii_factory<'a>() -> BuildingBlockFactory<'a> {
 
         let start = range.start.value;
         let end = range.end.value;
-        let mut block = BuildingBlock::new();
+        let mut block = triangulation_block(name, *mpi);
         let subdivisions = (end - start).div(resolution.value).abs().log2().ceil() as u64;
 
-        if *mpi {
-            block.add_includes(&[
-                "deal.II/distributed/tria.h",
-                "deal.II/distributed/grid_refinement.h",
-            ]);
-
-            block.constructor.push(format!(
-                "{name}(MPI_COMM_WORLD,
-        typename Triangulation<dim>::MeshSmoothing(
-            Triangulation<dim>::smoothing_on_refinement |
-            Triangulation<dim>::smoothing_on_coarsening))"
-            ))
+        block.mesh_setup.push(if *colorize {
+            format!("GridGenerator::hyper_cube({name}, {start}, {end}, true)")
         } else {
-            block.add_includes(&["deal.II/grid/tria.h"]);
-        }
+            format!("GridGenerator::hyper_cube({name}, {start}, {end})")
+        });
+        block
+            .mesh_setup
+            .push(format!("{name}.refine_global({subdivisions})"));
+        block.mesh_setup.push(format!(
+            r#"pcout << "Cell size: " << {} << " m" << std::endl"#,
+            (end - start).abs() / 2f64.powi(subdivisions as i32)
+        ));
+        mark_mesh(&mut block, name, mesh, parts, *show_info);
 
-        block.add_includes(&["deal.II/grid/grid_generator.h"]);
+        Ok(block)
+    });
 
-        block.data.push(format!(
-            "{}Triangulation<dim> {name}",
-            if *mpi { "parallel::distributed::" } else { "" }
+    factory.add_mesh("hyper_rectangle", &|name, mesh, GenConfig { mpi, .. }| {
+        let rectangle: &HyperRectangleMesh = mesh
+            .as_any()
+            .downcast_ref()
+            .ok_or(BuildingBlockError::WrongInput("hyper_rectangle".to_string()))?;
+        let HyperRectangleMesh {
+            show_info,
+            colorize,
+            parts,
+            ..
+        } = rectangle;
+
+        let ranges = rectangle.ranges();
+        let subdivisions = rectangle.subdivisions();
+        let starts = ranges.map(|range| range.start.meters());
+        let ends = ranges.map(|range| range.end.meters());
+        let cell_sizes: [f64; 3] = std::array::from_fn(|axis| {
+            (ends[axis] - starts[axis]).abs() / subdivisions[axis] as f64
+        });
+
+        let mut block = triangulation_block(name, *mpi);
+        block.mesh_setup.push(format!(
+            "std::vector<unsigned int> {name}_subdivisions = {{{}}}",
+            subdivisions.iter().join(", ")
+        ));
+        block.mesh_setup.push(format!("{name}_subdivisions.resize(dim)"));
+        block.mesh_setup.push(format!(
+            "GridGenerator::subdivided_hyper_rectangle({name}, {name}_subdivisions, {}, {}, {colorize})",
+            point_code(starts),
+            point_code(ends)
+        ));
+        block.mesh_setup.push(format!(
+            "const double {name}_cell_size[] = {{{}}}",
+            cell_sizes.iter().join(", ")
+        ));
+        block.mesh_setup.extend(lines!(
+            r#"
+pcout << "Cell size: " << {name}_cell_size[0] << " m"
+for (int d = 1; d < dim; ++d) pcout << " x " << {name}_cell_size[d] << " m"
+pcout << std::endl
+"#
+        ));
+        mark_mesh(&mut block, name, mesh, parts, *show_info);
+
+        Ok(block)
+    });
+
+    factory.add_mesh("hyper_ball", &|name, mesh, GenConfig { mpi, .. }| {
+        let HyperBallMesh {
+            center,
+            radius,
+            resolution,
+            show_info,
+            parts,
+        }: &HyperBallMesh = mesh
+            .as_any()
+            .downcast_ref()
+            .ok_or(BuildingBlockError::WrongInput("hyper_ball".to_string()))?;
+
+        let mut block = triangulation_block(name, *mpi);
+        block.mesh_setup.push(format!(
+            "GridGenerator::hyper_ball({name}, {}, {})",
+            point_code(center.coordinates()),
+            radius.meters()
         ));
+        block.mesh_setup.push(format!(
+            "{name}.refine_global({})",
+            refinements(radius.meters(), resolution.meters())
+        ));
+        report_cell_diameters(&mut block, name);
+        mark_mesh(&mut block, name, mesh, parts, *show_info);
+
+        Ok(block)
+    });
+
+    factory.add_mesh("hyper_shell", &|name, mesh, GenConfig { mpi, .. }| {
+        let HyperShellMesh {
+            center,
+            inner_radius,
+            outer_radius,
+            resolution,
+            show_info,
+            colorize,
+            parts,
+        }: &HyperShellMesh = mesh
+            .as_any()
+            .downcast_ref()
+            .ok_or(BuildingBlockError::WrongInput("hyper_shell".to_string()))?;
+
+        let mut block = triangulation_block(name, *mpi);
+        block.mesh_setup.push(format!(
+            "GridGenerator::hyper_shell({name}, {}, {}, {}, 0, {colorize})",
+            point_code(center.coordinates()),
+            inner_radius.meters(),
+            outer_radius.meters()
+        ));
+        block.mesh_setup.push(format!(
+            "{name}.refine_global({})",
+            refinements(
+                outer_radius.meters() - inner_radius.meters(),
+                resolution.meters()
+            )
+        ));
+        report_cell_diameters(&mut block, name);
+        mark_mesh(&mut block, name, mesh, parts, *show_info);
+
+        Ok(block)
+    });
+
+    factory.add_mesh("l_shape", &|name, mesh, GenConfig { mpi, .. }| {
+        let LShapeMesh {
+            range,
+            resolution,
+            show_info,
+            parts,
+        }: &LShapeMesh = mesh
+            .as_any()
+            .downcast_ref()
+            .ok_or(BuildingBlockError::WrongInput("l_shape".to_string()))?;
+
+        let start = range.start.meters();
+        let end = range.end.meters();
+        // The coarse mesh has two cells along each axis
+        let coarse_size = (end - start).abs() / 2.;
+        let subdivisions = refinements(coarse_size, resolution.meters());
+
+        let mut block = triangulation_block(name, *mpi);
         block
-            .setup
-            .push(format!("GridGenerator::hyper_cube({name}, {start}, {end})"));
+            .mesh_setup
+            .push(format!("GridGenerator::hyper_L({name}, {start}, {end})"));
         block
-            .setup
+            .mesh_setup
             .push(format!("{name}.refine_global({subdivisions})"));
+        block.mesh_setup.push(format!(
+            r#"pcout << "Cell size: " << {} << " m" << std::endl"#,
+            coarse_size / 2f64.powi(subdivisions as i32)
+        ));
+        mark_mesh(&mut block, name, mesh, parts, *show_info);
 
-        if *show_info {
-            block.setup.push(format!(
-                r#"pcout << "Number of active cells: " << {name}.n_active_cells() << std::endl"#
+        Ok(block)
+    });
+
+    factory.add_mesh("cylinder", &|name, mesh, GenConfig { mpi, .. }| {
+        let CylinderMesh {
+            radius,
+            half_length,
+            resolution,
+            show_info,
+            parts,
+        }: &CylinderMesh = mesh
+            .as_any()
+            .downcast_ref()
+            .ok_or(BuildingBlockError::WrongInput("cylinder".to_string()))?;
+
+        let mut block = triangulation_block(name, *mpi);
+        block.mesh_setup.push(format!(
+            "GridGenerator::cylinder({name}, {}, {})",
+            radius.meters(),
+            half_length.meters()
+        ));
+        block.mesh_setup.push(format!(
+            "{name}.refine_global({})",
+            refinements(radius.meters(), resolution.meters())
+        ));
+        report_cell_diameters(&mut block, name);
+        mark_mesh(&mut block, name, mesh, parts, *show_info);
+
+        Ok(block)
+    });
+
+    factory.add_mesh("file", &|name, mesh, GenConfig { mpi, .. }| {
+        let file_mesh: &FileMesh = mesh
+            .as_any()
+            .downcast_ref()
+            .ok_or(BuildingBlockError::WrongInput("file".to_string()))?;
+        let FileMesh {
+            refinements,
+            show_info,
+            ..
+        } = file_mesh;
+        let file_name = file_mesh.file_name()?;
+
+        let mut block = triangulation_block(name, *mpi);
+        block.add_includes(&["deal.II/grid/grid_in.h", "fstream"]);
+        // The mesh file is placed next to the sources, whatever the working directory
+        block.add_global("#ifndef SOURCE_DIR\n#define SOURCE_DIR \".\"\n#endif");
+        block.mesh_setup.push(format!("GridIn<dim> {name}_grid_in"));
+        block
+            .mesh_setup
+            .push(format!("{name}_grid_in.attach_triangulation({name})"));
+        let reader = match file_mesh.format()? {
+            MeshFormat::Exodusii => {
+                block.mesh_setup.push(format!(
+                    r#"{name}_grid_in.read_exodusii(SOURCE_DIR "/{file_name}")"#
+                ));
+                None
+            }
+            MeshFormat::Msh => Some("read_msh"),
+            MeshFormat::Ucd => Some("read_ucd"),
+            MeshFormat::Vtk => Some("read_vtk"),
+        };
+        if let Some(reader) = reader {
+            block.mesh_setup.push(format!(
+                r#"std::ifstream {name}_file(SOURCE_DIR "/{file_name}")"#
             ));
+            block
+                .mesh_setup
+                .push(format!("{name}_grid_in.{reader}({name}_file)"));
+        }
+        if *refinements > 0 {
+            block
+                .mesh_setup
+                .push(format!("{name}.refine_global({refinements})"));
         }
+        report_cell_diameters(&mut block, name);
+        // Boundary and material ids come from the tags of the file
+        mark_mesh(&mut block, name, mesh, &MeshParts::default(), *show_info);
 
         Ok(block)
     });
 
-    factory.set_vector(&|name, config, GenConfig { mpi, .. }| {
-        let VectorConfig {
-            dof_handler,
-            is_unknown,
-        } = config;
+    factory.set_vector(&|name,
+                         config,
+                         GenConfig {
+                             mpi, matrix_free, ..
+                         }| {
+        let VectorConfig { dof_handler, .. } = config;
         let mut vector = BuildingBlock::new();
+        if *matrix_free {
+            // The partitioning of the vectors is the one of the matrix free loops
+            vector.push_data(format!("{MATRIX_FREE_VECTOR} {name}"));
+            vector.setup.push(format!(
+                "{}->initialize_dof_vector({name})",
+                self::matrix_free(dof_handler)
+            ));
+            return Ok(vector);
+        }
         vector.add_includes(&["deal.II/lac/vector.h"]);
         if *mpi {
             vector.add_includes(&["deal.II/lac/petsc_vector.h"]);
@@ -115,14 +374,9 @@ pub fn deal_ii_factory<'a>() -> BuildingBlockFactory<'a> {
             format!("Vector<data_type> {name}")
         });
 
+        // Solvers write the locally owned dofs, ghosted copies are made where cells read values
         vector.setup.push(if *mpi {
-            let owned_dofs = owned_dofs(&dof_handler);
-            let relevant_dofs = relevant_dofs(&dof_handler);
-            if *is_unknown && false {
-                format!("{name}.reinit({owned_dofs}, {relevant_dofs}, MPI_COMM_WORLD)")
-            } else {
-                format!("{name}.reinit({owned_dofs}, MPI_COMM_WORLD)")
-            }
+            format!("{name}.reinit({}, MPI_COMM_WORLD)", owned_dofs(dof_handler))
         } else {
             format!("{name}.reinit({dof_handler}.n_dofs())")
         });
@@ -130,75 +384,119 @@ pub fn deal_ii_factory<'a>() -> BuildingBlockFactory<'a> {
         Ok(vector)
     });
 
-    factory.set_dof_handler(
-        &|name, DofHandlerConfig { mesh, element }, GenConfig { mpi, .. }| {
-            let mut block = BuildingBlock::new();
-            block.add_includes(&["deal.II/dofs/dof_handler.h"]);
-            block.constructor.push(format!("{name}({mesh})"));
-            block.push_data(format!("DoFHandler<dim> {name}"));
+    factory.set_dof_handler(&|name,
+                              DofHandlerConfig {
+                                  mesh,
+                                  element,
+                                  adaptive,
+                              },
+                              GenConfig {
+                                  mpi, matrix_free, ..
+                              }| {
+        let mut block = BuildingBlock::new();
+        block.add_includes(&["deal.II/dofs/dof_handler.h"]);
+        block.constructor.push(format!("{name}({mesh})"));
+        block.push_data(format!("DoFHandler<dim> {name}"));
+
+        block
+            .setup
+            .push(format!("{name}.distribute_dofs({element})"));
+        block.setup.push(format!(
+            r#"pcout << "Number of degrees of freedom: " << {name}.n_dofs() << "\n" << std::endl"#
+        ));
 
+        if *mpi {
+            let owned_dofs = owned_dofs(name);
+            let relevant_dofs = relevant_dofs(name);
+            let constraints = "constraints";
+            block.push_data(format!("IndexSet {owned_dofs}"));
+            block.push_data(format!("IndexSet {relevant_dofs}"));
             block
                 .setup
-                .push(format!("{name}.distribute_dofs({element})"));
+                .push(format!("{owned_dofs} = {name}.locally_owned_dofs()"));
+            block.add_includes(&["deal.II/dofs/dof_tools.h"]);
             block.setup.push(format!(
-                r#"pcout << "Number of degrees of freedom: " << {name}.n_dofs() << "\n" << std::endl"#
+                "{relevant_dofs} = DoFTools::extract_locally_relevant_dofs(dof_handler)"
             ));
+            block.push_setup([
+                format!("{constraints}.clear()"),
+                format!("{constraints}.reinit({owned_dofs}, {relevant_dofs})"),
+                format!("DoFTools::make_hanging_node_constraints({name}, {constraints})"),
+            ]);
 
+            block.additional_names.insert(owned_dofs);
+            block.additional_names.insert(relevant_dofs);
+        } else if *adaptive {
+            block.add_includes(&["deal.II/dofs/dof_tools.h"]);
+            block.setup.push(format!(
+                "DoFTools::make_hanging_node_constraints({name}, constraints)"
+            ));
+        }
 
-            if *mpi {
-                let owned_dofs = owned_dofs(&name);
-                let relevant_dofs = relevant_dofs(&name);
-                let constraints = "constraints";
-                let distributed = distributed(&name);
-                block.push_data(format!("IndexSet {owned_dofs}"));
-                block.push_data(format!("IndexSet {relevant_dofs}"));
-                block.push_data(format!("LA::MPI::Vector {distributed}"));
-                block.setup.push(format!("{owned_dofs} = {name}.locally_owned_dofs()"));
-                block.add_includes(&["deal.II/dofs/dof_tools.h"]);
-                block.setup.push(format!("{relevant_dofs} = DoFTools::extract_locally_relevant_dofs(dof_handler)"));
-                block.push_setup([
-                    format!("{constraints}.clear()"),
-                    format!("{constraints}.reinit({owned_dofs}, {relevant_dofs})"),
-                    format!("DoFTools::make_hanging_node_constraints({name}, {constraints})"),
-
-                ]);
-                block.setup.push(
-                    format!("{distributed}.reinit({owned_dofs}, MPI_COMM_WORLD)"),
-                );
-
-                block.additional_names.insert(owned_dofs);
-                block.additional_names.insert(relevant_dofs);
-                block.additional_names.insert(distributed);
-
-            }
+        if *matrix_free {
+            add_matrix_free_data(&mut block, name, element);
+        }
 
-            Ok(block)
-        },
-    );
+        Ok(block)
+    });
 
     factory.set_finite_element(&|name, element, _| {
         let mut block = BuildingBlock::new();
-        block.add_includes(&["deal.II/fe/fe_q.h"]);
-        block.constructor.push(format!(
-            "{name}({})",
-            match element {
-                FiniteElement::Q1 => 1,
-                FiniteElement::Q2 => 2,
-                FiniteElement::Q3 => 3,
-            }
+        let (class, args) = element_code(&mut block, element);
+        block.constructor.push(format!("{name}({args})"));
+        block.data.push(format!("const {class} {name}"));
+
+        // Cells are mapped linearly, and integrals computed exactly for products of shape functions
+        let mapping = mapping(name);
+        let quadrature = quadrature(name);
+        let face_quadrature = face_quadrature(name);
+        let (mapping_class, mapping_args, quadrature_class) = if element.is_simplex() {
+            block.add_includes(&["deal.II/fe/mapping_fe.h"]);
+            ("MappingFE<dim>", "FE_SimplexP<dim>(1)", "QGaussSimplex")
+        } else {
+            block.add_includes(&["deal.II/fe/mapping_q.h"]);
+            ("MappingQ<dim>", "1", "QGauss")
+        };
+        block.add_includes(&["deal.II/base/quadrature_lib.h"]);
+        block.constructor.push(format!("{mapping}({mapping_args})"));
+        block
+            .constructor
+            .push(format!("{quadrature}({name}.degree + 1)"));
+        block
+            .constructor
+            .push(format!("{face_quadrature}({name}.degree + 1)"));
+        block.data.push(format!("const {mapping_class} {mapping}"));
+        block
+            .data
+            .push(format!("const {quadrature_class}<dim> {quadrature}"));
+        block.data.push(format!(
+            "const {quadrature_class}<dim - 1> {face_quadrature}"
         ));
-        block.data.push(format!("const FE_Q<dim> {name}"));
         Ok(block)
     });
 
     factory.set_sparsity_pattern(&|name,
-                                   SparsityPatternConfig { dof_handler },
+                                   SparsityPatternConfig {
+                                       dof_handler,
+                                       constrained,
+                                       flux,
+                                   },
                                    GenConfig {
                                        mpi, matrix_free, ..
                                    }| {
         let mut block = BuildingBlock::new();
+        // Matrix free operators store no entries
+        if *matrix_free {
+            return Ok(block);
+        }
 
         let dsp = dsp(dof_handler);
+        // Face integrals couple the dofs of neighboring cells
+        let make_sparsity_pattern = if *flux {
+            "make_flux_sparsity_pattern"
+        } else {
+            "make_sparsity_pattern"
+        };
 
         block.add_includes(&[
             "deal.II/lac/dynamic_sparsity_pattern.h",
@@ -211,10 +509,12 @@ pub fn deal_ii_factory<'a>() -> BuildingBlockFactory<'a> {
             let relevant_dofs = relevant_dofs(dof_handler);
             let owned_dofs = owned_dofs(dof_handler);
             block.includes.insert("deal.II/lac/sparsity_tools.h".into());
+            // The pattern only couples the dofs left by closed constraints
+            block.setup.push(format!("{constraints}.close()"));
             block.push_setup([
                 format!("\n    // Sparsity Pattern"),
                 format!("DynamicSparsityPattern {dsp}({relevant_dofs})"),
-                format!("DoFTools::make_sparsity_pattern({dof_handler}, {dsp}, {constraints}, false)"),
+                format!("DoFTools::{make_sparsity_pattern}({dof_handler}, {dsp}, {constraints}, false)"),
                 format!("SparsityTools::distribute_sparsity_pattern({dsp}, {owned_dofs}, MPI_COMM_WORLD, {relevant_dofs})")
             ]);
         } else {
@@ -223,9 +523,15 @@ pub fn deal_ii_factory<'a>() -> BuildingBlockFactory<'a> {
                 format!(
                     "DynamicSparsityPattern {dsp}({dof_handler}.n_dofs(), {dof_handler}.n_dofs())"
                 ),
-                format!("DoFTools::make_sparsity_pattern({dof_handler}, {dsp})"),
-                format!("{name}.copy_from({dsp})"),
+                format!("DoFTools::{make_sparsity_pattern}({dof_handler}, {dsp})"),
             ]);
+            if *constrained {
+                block.push_setup([
+                    "constraints.close()".to_string(),
+                    format!("constraints.condense({dsp})"),
+                ]);
+            }
+            block.setup.push(format!("{name}.copy_from({dsp})"));
         }
         block.additional_names.insert(dsp);
 
@@ -242,6 +548,14 @@ pub fn deal_ii_factory<'a>() -> BuildingBlockFactory<'a> {
                              mpi, matrix_free, ..
                          }| {
         let mut block = BuildingBlock::new();
+        if *matrix_free {
+            block.push_data(format!("MatrixFreeOperator {name}"));
+            block.setup.push(format!(
+                "{name}.initialize({})",
+                self::matrix_free(dof_handler)
+            ));
+            return Ok(block);
+        }
         block.add_includes(&["deal.II/lac/sparse_matrix.h"]);
         if *mpi {
             block.add_includes(&["deal.II/lac/petsc_sparse_matrix.h"]);
@@ -265,17 +579,169 @@ pub fn deal_ii_factory<'a>() -> BuildingBlockFactory<'a> {
         Ok(block)
     });
 
-    factory.set_shape_matrix(&|name, mut matrix, ShapeMatrixConfig { dof_handler, element, matrix_config:_, kind }| {
+    factory.set_shape_matrix(&|name, mut matrix, config, gen_config| {
+        let ShapeMatrixConfig {
+            dof_handler,
+            element,
+            matrix_config: _,
+            kind,
+            constrained,
+            discontinuous,
+            coefficient,
+            stabilization: _,
+            n_components,
+        } = config;
+        if gen_config.matrix_free {
+            // Matrix free operators are combinations of the mass and Laplace operators
+            let coefficients = match (kind, coefficient, discontinuous) {
+                (ShapeMatrix::Mass, None, false) => "1, 0",
+                (ShapeMatrix::Laplace, None, false) => "0, 1",
+                _ => Err(BuildingBlockError::Unsupported(format!(
+                    "{kind} matrix with matrix free code"
+                )))?,
+            };
+            matrix
+                .setup
+                .push(format!("{name}.set_coefficients({coefficients})"));
+            return Ok(matrix);
+        }
+        if matches!(kind, ShapeMatrix::Advection | ShapeMatrix::Supg) {
+            add_advection_assembly(&mut matrix, name, config)?;
+            return Ok(matrix);
+        }
+        if matches!(kind, ShapeMatrix::GradDiv | ShapeMatrix::Strain) {
+            add_vector_operator_assembly(&mut matrix, name, *kind, element, dof_handler);
+            return Ok(matrix);
+        }
+        if *discontinuous && coefficient.is_some() {
+            Err(BuildingBlockError::Unsupported(
+                "variable coefficient with discontinuous elements".to_string(),
+            ))?
+        }
+        let reassemble = if gen_config.mpi {
+            matrix.add_includes(&[
+                "deal.II/numerics/matrix_creator.h",
+                "deal.II/base/quadrature_lib.h",
+            ]);
+            let weight = coefficient.as_ref().map(|c| format!("&{}", c.function));
+            // The constrained entries are eliminated while assembling
+            let extra_args = match (weight, *constrained) {
+                (Some(weight), true) => format!(", {weight}, constraints"),
+                (Some(weight), false) => format!(", {weight}"),
+                (None, true) => ", nullptr, constraints".to_string(),
+                (None, false) => String::new(),
+            };
+            let create_matrix = format!(
+                "MatrixCreator::create_{kind}_matrix({}, {dof_handler}, {}, {name}{extra_args})",
+                mapping(element),
+                quadrature(element)
+            );
+            matrix.push_setup([create_matrix.clone()]);
+            format!("{name} = 0;\n{create_matrix};")
+        } else {
+            // Serial code assembles the cells on all the threads
+            let integral = match (kind, coefficient) {
+                (ShapeMatrix::Mass, _) => CellIntegral::Mass,
+                (_, coefficient) => CellIntegral::Laplace(coefficient.as_ref().map(|c| c.function)),
+            };
+            add_cell_assembly(
+                &mut matrix,
+                name,
+                integral,
+                element,
+                dof_handler,
+                *constrained,
+                *n_components,
+            );
+            format!("assemble_{name}();")
+        };
+        if let Some(ShapeMatrixCoefficient {
+            function,
+            previous: Some(previous),
+        }) = coefficient
+        {
+            // Matrices weighted by a function of time are reassembled at each time step
+            matrix.additional_matrixes.insert(previous.to_string());
+            matrix.main.extend(lines!(
+                r"
+// Reassemble {name} at the current time
+{previous}.copy_from({name});
+{function}.set_time(time);
+{reassemble}
+"
+            ));
+        }
+        if *discontinuous && *kind == ShapeMatrix::Laplace {
+            add_interior_penalty_assembly(&mut matrix, name, element, dof_handler);
+        }
+        Ok(matrix)
+    });
+
+    factory.set_vector_from_function(&|name,
+                                       mut vector,
+                                       config,
+                                       GenConfig {
+                                           mpi, matrix_free, ..
+                                       }| {
+        let VectorFromFnConfig {
+            function,
+            dof_handler,
+            element,
+            vector_config: _,
+            iteration,
+            time_dependent,
+            constrained,
+            n_components,
+        } = config;
+        if *mpi || *matrix_free {
+            vector.add_includes(&[
+                "deal.II/numerics/vector_tools_rhs.h",
+                "deal.II/base/quadrature_lib.h",
+            ]);
+            let mapping = mapping(element);
+            let quadrature = quadrature(element);
+            let constraints = match constrained {
+                true => ", constraints",
+                false => "",
+            };
+            vector.methods_defs.push(format!("void assemble_{name}()"));
+            vector.methods_impls.push(format!(
+                r"
+void Sim::assemble_{name}() {{
+  VectorTools::create_right_hand_side({mapping}, {dof_handler}, {quadrature}, {function},
+                                      {name}{constraints});
+}}"
+            ));
+            vector.assembly.push(format!("assemble_{name}()"));
+        } else {
+            // Serial code assembles the cells on all the threads
+            add_cell_assembly(
+                &mut vector,
+                name,
+                CellIntegral::Source(function),
+                element,
+                dof_handler,
+                *constrained,
+                *n_components,
+            );
+        }
 
-        matrix.add_includes(&[
-            "deal.II/numerics/matrix_creator.h",
-            "deal.II/base/quadrature_lib.h",
+        if *time_dependent {
+            let (time, step) = match iteration {
+                TargetIteration::Next => ("time + time_step", "next"),
+                TargetIteration::Current => ("time", "current"),
+                TargetIteration::Previous => ("time - time_step", "previous"),
+            };
+            vector.main.extend(lines!(
+                r"
+// Reassemble {name} at the {step} time
+{function}.set_time({time});
+assemble_{name}();
+"
+            ));
+        }
 
-        ]);
-        matrix.push_setup([
-                    format!("MatrixCreator::create_{kind}_matrix({dof_handler}, QGauss<dim>({element}.degree + 1), {name})")
-        ]);
-        Ok(matrix)
+        Ok(vector)
     });
 
     factory.set_solve_unknown(&|name,
@@ -284,10 +750,23 @@ pub fn deal_ii_factory<'a>() -> BuildingBlockFactory<'a> {
                                     rhs,
                                     unknown_vec,
                                     unknown_mat,
+                                    constrained,
+                                    vector_valued,
+                                    symmetric,
+                                    lumped_mass,
+                                    solver,
+                                    constant,
                                 },
-                                GenConfig { mpi, .. }| {
+                                GenConfig {
+                                    mpi, matrix_free, ..
+                                }| {
         let mut block = BuildingBlock::new();
 
+        if let Some(mass) = lumped_mass {
+            add_lumped_update(&mut block, name, unknown_vec, unknown_mat, rhs, mass, *mpi);
+            return Ok(block);
+        }
+
         block.add_includes(&[
             "deal.II/lac/solver_cg.h",
             "deal.II/lac/precondition.h",
@@ -296,78 +775,432 @@ pub fn deal_ii_factory<'a>() -> BuildingBlockFactory<'a> {
 
         block.methods_defs.push(format!("void {name}()"));
 
-        block.methods_impls.push(if *mpi {
-            let owned_dofs = owned_dofs(&dof_handler);
-            let distributed = &unknown_vec;
-            let constraints = "constraints";
+        // Advections make the matrix non-symmetric, which CG cannot solve
+        let method = solver.method.unwrap_or(match symmetric {
+            true => SolverMethod::Cg,
+            false => SolverMethod::Gmres,
+        });
+        let solver_preconditioner = solver.preconditioner;
+        let preconditioner = solver_preconditioner.unwrap_or(
+            match (matrix_free, mpi, vector_valued) {
+                (true, _, _) => Preconditioner::Chebyshev,
+                (false, true, _) => Preconditioner::Amg,
+                (false, false, true) => Preconditioner::Ssor,
+                (false, false, false) => Preconditioner::Identity,
+            },
+        );
+        let tolerance = solver.tolerance.unwrap_or(if *mpi { 1e-6 } else { 1e-8 });
+        let max_iterations = solver.max_iterations.map_or_else(
+            || match mpi {
+                true => "dof_handler.n_dofs()".to_string(),
+                false => "1000".to_string(),
+            },
+            |max_iterations| max_iterations.to_string(),
+        );
+        let (solver, solver_class) = match method {
+            SolverMethod::Cg => ("cg", "CG"),
+            SolverMethod::Gmres => {
+                block.add_includes(&["deal.II/lac/solver_gmres.h"]);
+                ("gmres", "GMRES")
+            }
+            SolverMethod::Bicgstab => {
+                block.add_includes(&["deal.II/lac/solver_bicgstab.h"]);
+                ("bicgstab", "Bicgstab")
+            }
+            SolverMethod::Minres => {
+                block.add_includes(&["deal.II/lac/solver_minres.h"]);
+                ("minres", "MinRes")
+            }
+            SolverMethod::Umfpack | SolverMethod::Mumps => ("solver", ""),
+        };
+
+        let method_impl = if *matrix_free {
+            if method.is_direct() || preconditioner.needs_matrix() {
+                return Err(BuildingBlockError::Unsupported(format!(
+                    "{method} with {preconditioner} preconditioner in matrix free code"
+                )));
+            }
+            let (preconditioner, preconditioner_arg) =
+                matrix_free_preconditioner(&mut block, name, unknown_mat, preconditioner, *constant);
             format!(
                 r#"
 void Sim::{name}() {{
-  SolverControl solver_control(dof_handler.n_dofs(), 1e-6 * {rhs}.l2_norm());
-  LA::SolverCG solver(solver_control);
+  SolverControl solver_control({max_iterations}, {tolerance:e} * {rhs}.l2_norm());
+  Solver{solver_class}<{MATRIX_FREE_VECTOR}> {solver}(solver_control);
+{preconditioner}
+  {solver}.solve({unknown_mat}, {unknown_vec}, {rhs}, {preconditioner_arg});
 
+  pcout << "    {name}: " << solver_control.last_step()
+            << "  {method} iterations." << std::endl;
+}}
+            "#
+            )
+        } else if *mpi {
+            let constraints = "constraints";
+            if method == SolverMethod::Mumps {
+                block.methods_impls.push(format!(
+                    r#"
+void Sim::{name}() {{
+  SolverControl solver_control;
+#ifdef USE_PETSC_LA
+  PETScWrappers::SparseDirectMUMPS solver(solver_control);
+#else
+  TrilinosWrappers::SolverDirect::AdditionalData data(false, "Amesos_Mumps");
+  TrilinosWrappers::SolverDirect solver(solver_control, data);
+#endif
+  solver.solve({unknown_mat}, {unknown_vec}, {rhs});
+
+  pcout << "    {name}: MUMPS direct solve." << std::endl;
+
+  {constraints}.distribute({unknown_vec});
+}}"#
+                ));
+                return Ok(block);
+            }
+            let solver_line = match method {
+                SolverMethod::Cg | SolverMethod::Gmres => {
+                    format!("LA::Solver{solver_class} solver(solver_control);")
+                }
+                SolverMethod::Bicgstab | SolverMethod::Minres => {
+                    format!("Solver{solver_class}<LA::MPI::Vector> solver(solver_control);")
+                }
+                _ => {
+                    return Err(BuildingBlockError::Unsupported(format!(
+                        "{method} with MPI"
+                    )));
+                }
+            };
+            let (configuration, class, data) = match preconditioner {
+                Preconditioner::Amg => {
+                    let trilinos_data = if *vector_valued {
+                        block.add_includes(&[
+                            "deal.II/dofs/dof_tools.h",
+                            "deal.II/fe/component_mask.h",
+                        ]);
+                        format!(
+                            "  // The coarse levels represent the translations of each component
+  DoFTools::extract_constant_modes({dof_handler}, ComponentMask(), data.constant_modes);"
+                        )
+                    } else {
+                        "  /* Trilinos defaults are good */".to_string()
+                    };
+                    (
+                        format!(
+                            r#"
   // Preconditioner configuration
   LA::MPI::PreconditionAMG::AdditionalData data;
 #ifdef USE_PETSC_LA
-  data.symmetric_operator = true;
+  data.symmetric_operator = {symmetric};
 #else
-  /* Trilinos defaults are good */
+{trilinos_data}
 #endif
-
+"#
+                        ),
+                        "LA::MPI::PreconditionAMG",
+                        ", data",
+                    )
+                }
+                Preconditioner::Identity => (String::new(), "", ""),
+                Preconditioner::Jacobi => (String::new(), "LA::MPI::PreconditionJacobi", ""),
+                Preconditioner::Ssor => (
+                    String::new(),
+                    "LA::MPI::PreconditionSSOR",
+                    ", LA::MPI::PreconditionSSOR::AdditionalData(1.2)",
+                ),
+                Preconditioner::Ilu => (String::new(), "LA::MPI::PreconditionILU", ""),
+                Preconditioner::Chebyshev => {
+                    return Err(BuildingBlockError::Unsupported(
+                        "Chebyshev preconditioner without matrix free code".to_string(),
+                    ));
+                }
+            };
+            let (preconditioner, precondtioner) = match (preconditioner, constant) {
+                (Preconditioner::Identity, _) => (
+                    format!(
+                        r#"
+  // Preconditioner initialization
+#ifdef USE_PETSC_LA
+  PETScWrappers::PreconditionNone precondtioner;
+#else
+  TrilinosWrappers::PreconditionIdentity precondtioner;
+#endif
+  precondtioner.initialize({unknown_mat});"#
+                    ),
+                    "precondtioner".to_string(),
+                ),
+                (_, true) => {
+                    let object = format!("{name}_preconditioner");
+                    let initialization = add_initialize_once(
+                        &mut block,
+                        name,
+                        class,
+                        &object,
+                        &format!(
+                            "{}\n  {object}.initialize({unknown_mat}{data});",
+                            configuration.trim_start_matches('\n')
+                        ),
+                    );
+                    (
+                        format!(
+                            "\n  // The matrix is the same at every step, the preconditioner is only initialized once{initialization}"
+                        ),
+                        object,
+                    )
+                }
+                (_, false) => (
+                    format!(
+                        r#"{configuration}
   // Preconditioner initialization
-  LA::MPI::PreconditionAMG precondtioner;
-  precondtioner.initialize({unknown_mat}, data);
+  {class} precondtioner;
+  precondtioner.initialize({unknown_mat}{data});"#
+                    ),
+                    "precondtioner".to_string(),
+                ),
+            };
+            format!(
+                r#"
+void Sim::{name}() {{
+  SolverControl solver_control({max_iterations}, {tolerance:e} * {rhs}.l2_norm());
+  {solver_line}
+{preconditioner}
 
-  solver.solve({unknown_mat}, {distributed}, {rhs}, precondtioner);
+  solver.solve({unknown_mat}, {unknown_vec}, {rhs}, {precondtioner});
 
   pcout << "    {name}: " << solver_control.last_step()
-            << "  CG iterations." << std::endl;
+            << "  {method} iterations." << std::endl;
 
-  {constraints}.distribute({distributed});
-  // {unknown_vec} = {distributed}; 
+  {constraints}.distribute({unknown_vec});
 }}"#
             )
         } else {
-            format!(
-                r#"
+            let distribute = if *constrained {
+                format!("\n  constraints.distribute({unknown_vec});")
+            } else {
+                String::new()
+            };
+            if method == SolverMethod::Umfpack && *constant {
+                block.add_includes(&["deal.II/lac/sparse_direct.h"]);
+                let object = format!("{name}_solver");
+                let initialization = add_initialize_once(
+                    &mut block,
+                    name,
+                    "SparseDirectUMFPACK",
+                    &object,
+                    &format!("  {object}.initialize({unknown_mat});"),
+                );
+                block.methods_impls.push(format!(
+                    r#"
 void Sim::{name}() {{
-  SolverControl solver_control(1000, 1e-8 * {rhs}.l2_norm());
-  SolverCG<Vector<data_type>> cg(solver_control);
+  // The matrix is the same at every step, it is only factorized once{initialization}
+  {object}.vmult({unknown_vec}, {rhs});
 
-  cg.solve({unknown_mat}, {unknown_vec}, {rhs}, PreconditionIdentity());
-
-  pcout << "    {name}: " << solver_control.last_step()
-            << "  CG iterations." << std::endl;
+  pcout << "    {name}: UMFPACK direct solve." << std::endl;{distribute}
 }}
             "#
-            )
-        });
-
-        Ok(block)
-    });
-
-    factory.set_equation_setup(&|_name,
-                                 EquationSetupConfig {
-                                     equation,
-                                     unknown,
-                                     vectors,
-                                     matrixes,
-                                 },
-                                 gen_config| {
-        let mut block = BuildingBlock::new();
+                ));
+                return Ok(block);
+            }
+            if method == SolverMethod::Umfpack {
+                block.add_includes(&["deal.II/lac/sparse_direct.h"]);
+                block.methods_impls.push(format!(
+                    r#"
+void Sim::{name}() {{
+  SparseDirectUMFPACK solver;
+  solver.initialize({unknown_mat});
+  solver.vmult({unknown_vec}, {rhs});
+
+  pcout << "    {name}: UMFPACK direct solve." << std::endl;{distribute}
+}}
+            "#
+                ));
+                return Ok(block);
+            }
+            if method == SolverMethod::Mumps {
+                return Err(BuildingBlockError::Unsupported(format!(
+                    "{method} without MPI"
+                )));
+            }
+            let (preconditioner, preconditioner_arg) = match preconditioner {
+                Preconditioner::Identity => (String::new(), "PreconditionIdentity()".to_string()),
+                Preconditioner::Amg => {
+                    return Err(BuildingBlockError::Unsupported(
+                        "AMG preconditioner without MPI".to_string(),
+                    ));
+                }
+                Preconditioner::Chebyshev => {
+                    return Err(BuildingBlockError::Unsupported(
+                        "Chebyshev preconditioner without matrix free code".to_string(),
+                    ));
+                }
+                preconditioner => {
+                    let (class, data) = match preconditioner {
+                        Preconditioner::Jacobi => {
+                            ("PreconditionJacobi<SparseMatrix<data_type>>", "")
+                        }
+                        Preconditioner::Ssor => {
+                            ("PreconditionSSOR<SparseMatrix<data_type>>", ", 1.2")
+                        }
+                        _ => {
+                            block.add_includes(&["deal.II/lac/sparse_ilu.h"]);
+                            ("SparseILU<data_type>", "")
+                        }
+                    };
+                    let comment = match solver_preconditioner {
+                        None => "\n  // The components of the unknown are coupled by the operators",
+                        Some(_) => "",
+                    };
+                    if *constant {
+                        let object = format!("{name}_preconditioner");
+                        let initialization = add_initialize_once(
+                            &mut block,
+                            name,
+                            class,
+                            &object,
+                            &format!("  {object}.initialize({unknown_mat}{data});"),
+                        );
+                        (
+                            format!(
+                                "{comment}
+  // The matrix is the same at every step, the preconditioner is only initialized once{initialization}
+"
+                            ),
+                            object,
+                        )
+                    } else {
+                        (
+                            format!(
+                                "{comment}
+  {class} preconditioner;
+  preconditioner.initialize({unknown_mat}{data});
+"
+                            ),
+                            "preconditioner".to_string(),
+                        )
+                    }
+                }
+            };
+            format!(
+                r#"
+void Sim::{name}() {{
+  SolverControl solver_control({max_iterations}, {tolerance:e} * {rhs}.l2_norm());
+  Solver{solver_class}<Vector<data_type>> {solver}(solver_control);
+{preconditioner}
+  {solver}.solve({unknown_mat}, {unknown_vec}, {rhs}, {preconditioner_arg});
+
+  pcout << "    {name}: " << solver_control.last_step()
+            << "  {method} iterations." << std::endl;{distribute}
+}}
+            "#
+            )
+        };
+        block.methods_impls.push(method_impl);
+
+        Ok(block)
+    });
+
+    factory.set_equation_setup(&|name,
+                                 EquationSetupConfig {
+                                     equation,
+                                     unknown,
+                                     vectors,
+                                     matrixes,
+                                     parameters,
+                                     pristine_matrix,
+                                     constant_rhs_operators,
+                                 },
+                                 gen_config| {
+        let mut block = BuildingBlock::new();
 
         block
             .main
             .push(format!("// # Setup equation {}", equation.str()));
 
-        let equation_code =
-            equation_to_deall_ii_setup_code(&equation, *unknown, vectors, matrixes, gen_config)?;
+        // Time invariant operators of the right-hand side get their own matrices
+        let mut operators: IndexMap<String, (Symbol, Box<dyn Expr>)> = IndexMap::new();
+        let hoisted_equation = Equation {
+            lhs: equation.lhs.clone_box(),
+            rhs: match constant_rhs_operators {
+                true => hoist_operators(
+                    equation.rhs.get_ref(),
+                    name,
+                    vectors,
+                    matrixes,
+                    &mut operators,
+                ),
+                false => equation.rhs.clone_box(),
+            },
+        };
+        let operator_mats = operators.values().map(|(matrix, _)| matrix).collect_vec();
+        let matrixes = &matrixes
+            .iter()
+            .copied()
+            .chain(operator_mats.iter().map(|matrix| matrix.get_ref()))
+            .collect_vec();
+
+        let mut constants: IndexMap<String, (Symbol, Box<dyn Expr>)> = IndexMap::new();
+        let system_mat = format!("matrix_{}", unknown.to_cpp());
+        let [system_code, rhs_code] = equation_to_deall_ii_setup_code(
+            &hoisted_equation,
+            *unknown,
+            pristine_matrix.unwrap_or(&system_mat),
+            vectors,
+            matrixes,
+            &mut |expr| hoist_constants(expr, name, parameters, &mut constants),
+            gen_config,
+        )?;
+        let mut operators_code = Vec::with_capacity(operators.len());
+        for (matrix, value) in operators.values() {
+            let matrix = matrix.to_cpp();
+            let value = hoist_constants(value.get_ref(), name, parameters, &mut constants);
+            let code = mat_code_gen(&matrix, value.get_ref(), vectors, matrixes)?;
+            operators_code.push(if gen_config.mpi && !gen_config.matrix_free {
+                format!("{code}\n\n{matrix}.compress(VectorOperation::add);")
+            } else {
+                code
+            });
+            block.additional_matrixes.insert(matrix);
+        }
+        let operators_code = operators_code.join("\n\n");
+        let equation_code = match pristine_matrix {
+            // The boundary values modify the system matrix, which starts from the pristine one
+            Some(pristine_matrix) => {
+                block.main_setup.push(format!(
+                    "// # Assemble the time invariant system of equation {}",
+                    equation.str()
+                ));
+                block
+                    .main_setup
+                    .extend(system_code.split('\n').map(|s| s.to_string()));
+                block.main_setup.push(String::new());
+                format!(
+                    "// ## Copy system for {unknown}, assembled once\n{system_mat}.copy_from({pristine_matrix});\n\n\n{rhs_code}"
+                )
+            }
+            None => format!("{system_code}\n\n\n{rhs_code}"),
+        };
+        if !operators.is_empty() {
+            block.main_setup.push(format!(
+                "// # Assemble the time invariant right-hand side operators of equation {}",
+                equation.str()
+            ));
+            block
+                .main_setup
+                .extend(operators_code.split('\n').map(|s| s.to_string()));
+            block.main_setup.push(String::new());
+        }
+
+        // Parameter-only coefficients are computed once for the whole simulation
+        for (value, (constant, _)) in &constants {
+            let constant = constant.to_cpp();
+            block.push_data(format!("const data_type {constant} = {value}"));
+            block.additional_names.insert(constant);
+        }
         let mut tmp_vecs: HashSet<String> = HashSet::new();
         let mut tmp_mats: HashSet<String> = HashSet::new();
 
         let tmp_re = Regex::new(r"\b[mv]tmp\d*\b").unwrap();
 
-        for capture in tmp_re.captures_iter(&equation_code) {
+        for capture in tmp_re.captures_iter(&format!("{system_code}\n{rhs_code}\n{operators_code}")) {
             let tmp = &capture[0];
 
             match tmp.chars().next().expect("tmp is not empty") {
@@ -412,117 +1245,2051 @@ void Sim::{name}() {{
         let class_name = format!("Fn_{fn_name}");
         let mut block = BuildingBlock::new();
 
-        block.add_includes(&["deal.II/base/function.h"]);
+        block.add_includes(&["deal.II/base/function.h"]);
+
+        let function_code = function_def_to_deal_ii_code(function_def)
+            .trim()
+            .split("\n")
+            .map(|line| format!("    {line}"))
+            .collect_vec()
+            .join("\n");
+
+        block.data.push(format!("{class_name} {name}"));
+
+        // Vector functions declare their number of components to deal.II
+        let constructor = match function_def.n_components() {
+            1 => String::new(),
+            n_components => format!("  {class_name}() : Function<dim>({n_components}) {{}}\n\n"),
+        };
+
+        block.add_global(
+            format!(
+                r"
+class {class_name} : public Function<dim> {{
+public:
+{constructor}  virtual double value(const Point<dim> &point,
+                       const unsigned int component = 0) const override {{
+{function_code}
+  }}
+}};
+"
+            )
+            .trim(),
+        );
+
+        Ok(block)
+    });
+
+    factory.set_apply_boundary_condition(&|_name, config, GenConfig { matrix_free, .. }| {
+        let ApplyBoundaryConditionConfig {
+            condition,
+            boundary,
+            boundary_ids,
+            dof_handler,
+            element,
+            matrix,
+            solution,
+            previous,
+            rhs,
+            laplace_coefficients:
+                LaplaceCoefficients {
+                    current,
+                    previous: previous_coeff,
+                },
+            n_components,
+        } = config;
+        let mut block = BuildingBlock::new();
+
+        // Boundary integrals of natural conditions are weighted like the Laplace matrix
+        let flux_coeff = (current.get_ref() - previous_coeff.get_ref())
+            .simplify()
+            .to_cpp();
+        let (suffix, on_boundary) = match boundary {
+            Some(boundary) => (format!("{solution}_{boundary}"), format!(" on {boundary}")),
+            None => (solution.to_string(), String::new()),
+        };
+        let flux = format!("boundary_flux_{suffix}");
+
+        match condition {
+            BoundaryConditionKind::Dirichlet {
+                function,
+                components,
+            } => {
+                block.add_includes(&["deal.II/numerics/vector_tools_boundary.h"]);
+                let mapping = mapping(element);
+                // Only the dofs of the imposed components are constrained
+                let mask = match components {
+                    Some(components) => {
+                        block.add_includes(&["deal.II/fe/component_mask.h"]);
+                        let selected = (0..*n_components as u32)
+                            .map(|c| components.contains(&c).to_string())
+                            .join(", ");
+                        format!(",\n      ComponentMask(std::vector<bool>{{{selected}}})")
+                    }
+                    None => String::new(),
+                };
+
+                let interpolations = boundary_ids
+                    .iter()
+                    .map(|id| {
+                        format!(
+                            "  VectorTools::interpolate_boundary_values(
+      {mapping}, {dof_handler}, {id}, {function}, boundary_values{mask});"
+                        )
+                    })
+                    .join("\n");
+                // Matrix free operators impose the values on their own rows
+                let apply = if *matrix_free {
+                    format!("{matrix}.apply_boundary_values(boundary_values, {solution}, {rhs});")
+                } else {
+                    block.add_includes(&["deal.II/numerics/matrix_tools.h"]);
+                    format!(
+                        "MatrixTools::apply_boundary_values(boundary_values, {matrix}, {solution},
+                                     {rhs});"
+                    )
+                };
+                block.main.extend(lines!(
+                    r"
+// Apply boundary condition{on_boundary} to the equation for solving {solution}
+{{
+  {function}.set_time(time);
+
+  std::map<types::global_dof_index, double> boundary_values;
+{interpolations}
+  {apply}
+}}
+               "
+                ));
+            }
+            _ if *matrix_free => Err(BuildingBlockError::Unsupported(
+                "natural boundary condition with matrix free code".to_string(),
+            ))?,
+            BoundaryConditionKind::Neumann { flux: function } => {
+                block.add_includes(&["deal.II/fe/fe_values.h"]);
+                add_boundary_assembly(&mut block, &flux, function, false, config);
+
+                block.main.extend(lines!(
+                    r"
+// Apply Neumann boundary condition{on_boundary} to the equation for solving {solution}
+{{
+  {function}.set_time(time);
+  assemble_{flux}();
+  {rhs}.add({flux_coeff}, {flux});
+}}
+               "
+                ));
+            }
+            BoundaryConditionKind::Robin { alpha, value } => {
+                block.add_includes(&["deal.II/fe/fe_values.h"]);
+                let mass = format!("boundary_mass_{suffix}");
+                let tmp = format!("boundary_tmp_{suffix}");
+                add_boundary_assembly(&mut block, &flux, value, false, config);
+                add_boundary_assembly(&mut block, &mass, alpha, true, config);
+                block.additional_vectors.insert(tmp.clone());
+
+                // The boundary mass matrix does not depend on time
+                block.assembly.push(format!("assemble_{mass}()"));
+
+                let current = current.to_cpp();
+                let previous_coeff = previous_coeff.to_cpp();
+                block.main.extend(lines!(
+                    r"
+// Apply Robin boundary condition{on_boundary} to the equation for solving {solution}
+{{
+  {value}.set_time(time);
+  assemble_{flux}();
+  {matrix}.add({current}, {mass});
+  {mass}.vmult({tmp}, {previous});
+  {rhs}.add({previous_coeff}, {tmp}, {flux_coeff}, {flux});
+}}
+               "
+                ));
+            }
+        }
+
+        Ok(block)
+    });
+
+    factory.set_periodicity_constraints(
+        &|_name,
+          PeriodicityConstraintsConfig {
+              dof_handler,
+              direction,
+              boundary_ids: [first, second],
+          },
+          GenConfig {
+              mpi, matrix_free, ..
+          }| {
+            if *mpi {
+                Err(BuildingBlockError::Unsupported(
+                    "periodic boundary condition with mpi".to_string(),
+                ))?
+            }
+            if *matrix_free {
+                Err(BuildingBlockError::Unsupported(
+                    "periodic boundary condition with matrix free code".to_string(),
+                ))?
+            }
+            let mut block = BuildingBlock::new();
+
+            block.add_includes(&["deal.II/dofs/dof_tools.h"]);
+            block.push_setup([if first == second {
+                format!(
+                    "DoFTools::make_periodicity_constraints({dof_handler}, {first}, {direction}, constraints)"
+                )
+            } else {
+                format!(
+                    "DoFTools::make_periodicity_constraints({dof_handler}, {first}, {second}, {direction}, constraints)"
+                )
+            }]);
+
+            Ok(block)
+        },
+    );
+
+    factory.set_refine_mesh(&|name,
+                              RefinementConfig {
+                                  mesh,
+                                  dof_handler,
+                                  element,
+                                  estimated,
+                                  transferred,
+                                  refinement:
+                                      Refinement {
+                                          estimator: ErrorEstimator::Kelly,
+                                          unknown: _,
+                                          refine_fraction,
+                                          coarsen_fraction,
+                                          max_level,
+                                          min_level,
+                                          frequency,
+                                      },
+                              },
+                              GenConfig {
+                                  mpi, matrix_free, ..
+                              }| {
+        if *matrix_free {
+            Err(BuildingBlockError::Unsupported(
+                "adaptive refinement with matrix free code".to_string(),
+            ))?
+        }
+        let mut block = BuildingBlock::new();
+
+        block.add_includes(&[
+            "deal.II/lac/vector.h",
+            "deal.II/numerics/error_estimator.h",
+        ]);
+        block.add_includes(if *mpi {
+            &[
+                "deal.II/distributed/grid_refinement.h",
+                "deal.II/distributed/solution_transfer.h",
+            ]
+        } else {
+            &[
+                "deal.II/grid/grid_refinement.h",
+                "deal.II/numerics/solution_transfer.h",
+            ]
+        });
+
+        let mapping = mapping(element);
+        let face_quadrature = face_quadrature(element);
+        let mut level_limits = Vec::new();
+        if let Some(max_level) = max_level {
+            level_limits.push(format!(
+                r"  if ({mesh}.n_levels() > {max_level})
+    for (const auto &cell : {mesh}.active_cell_iterators_on_level({max_level}))
+      cell->clear_refine_flag();"
+            ));
+        }
+        if *min_level > 0 {
+            level_limits.push(format!(
+                r"  if ({mesh}.n_levels() > {min_level})
+    for (const auto &cell : {mesh}.active_cell_iterators_on_level({min_level}))
+      cell->clear_coarsen_flag();"
+            ));
+        }
+        let level_limits = level_limits.join("\n");
+        let distribute = transferred
+            .iter()
+            .map(|vector| format!("  constraints.distribute({vector});"))
+            .join("\n");
+
+        let (estimate, transfer) = if *mpi {
+            let owned_dofs = owned_dofs(dof_handler);
+            let relevant_dofs = relevant_dofs(dof_handler);
+            let estimated_index = transferred
+                .iter()
+                .position(|vector| vector == estimated)
+                .expect("the estimated vector is transferred");
+            let ghosted = transferred
+                .iter()
+                .enumerate()
+                .map(|(i, vector)| format!("  previous[{i}] = {vector};"))
+                .join("\n");
+            let previous = (0..transferred.len())
+                .map(|i| format!("&previous[{i}]"))
+                .join(", ");
+            let targets = transferred.iter().map(|v| format!("&{v}")).join(", ");
+            (
+                format!(
+                    r"  // The estimator and the transfer read the values of the ghost dofs
+  std::vector<LA::MPI::Vector> previous(
+      {count}, LA::MPI::Vector({owned_dofs}, {relevant_dofs}, MPI_COMM_WORLD));
+{ghosted}
+
+  Vector<float> estimated_error_per_cell({mesh}.n_active_cells());
+  KellyErrorEstimator<dim>::estimate(
+      {mapping}, {dof_handler}, {face_quadrature},
+      std::map<types::boundary_id, const Function<dim> *>(),
+      previous[{estimated_index}], estimated_error_per_cell);
+  parallel::distributed::GridRefinement::refine_and_coarsen_fixed_fraction(
+      {mesh}, estimated_error_per_cell, {refine_fraction}, {coarsen_fraction});",
+                    count = transferred.len(),
+                ),
+                format!(
+                    r"  parallel::distributed::SolutionTransfer<dim, LA::MPI::Vector> solution_transfer({dof_handler});
+  {mesh}.prepare_coarsening_and_refinement();
+  solution_transfer.prepare_for_coarsening_and_refinement(
+      std::vector<const LA::MPI::Vector *>{{{previous}}});
+  {mesh}.execute_coarsening_and_refinement();
+
+  setup_system();
+  std::vector<LA::MPI::Vector *> transferred = {{{targets}}};
+  solution_transfer.interpolate(transferred);"
+                ),
+            )
+        } else {
+            let assignments = transferred
+                .iter()
+                .enumerate()
+                .map(|(i, vector)| format!("  {vector} = transferred[{i}];"))
+                .join("\n");
+            (
+                format!(
+                    r"  Vector<float> estimated_error_per_cell({mesh}.n_active_cells());
+  KellyErrorEstimator<dim>::estimate(
+      {mapping}, {dof_handler}, {face_quadrature},
+      std::map<types::boundary_id, const Function<dim> *>(), {estimated},
+      estimated_error_per_cell);
+  GridRefinement::refine_and_coarsen_fixed_fraction(
+      {mesh}, estimated_error_per_cell, {refine_fraction}, {coarsen_fraction});"
+                ),
+                format!(
+                    r"  SolutionTransfer<dim, Vector<data_type>> solution_transfer({dof_handler});
+  const std::vector<Vector<data_type>> previous = {{{}}};
+  {mesh}.prepare_coarsening_and_refinement();
+  solution_transfer.prepare_for_coarsening_and_refinement(previous);
+  {mesh}.execute_coarsening_and_refinement();
+
+  setup_system();
+  std::vector<Vector<data_type>> transferred(
+      previous.size(), Vector<data_type>({dof_handler}.n_dofs()));
+  solution_transfer.interpolate(previous, transferred);
+{assignments}",
+                    transferred.join(", ")
+                ),
+            )
+        };
+
+        block.methods_defs.push(format!("void {name}()"));
+        block.methods_impls.push(format!(
+            r#"
+void Sim::{name}() {{
+{estimate}
+{level_limits}
+
+  // Carry the previous values over to the refined mesh
+{transfer}
+{distribute}
+
+  pcout << "Refined mesh: " << {mesh}.n_global_active_cells() << " active cells" << std::endl;
+}}"#
+        ));
+
+        block.main.extend(lines!(
+            r"
+// Adapt the mesh to the estimated error every {frequency} steps
+if (timestep_number % {frequency} == 0)
+  {name}();
+            "
+        ));
+
+        Ok(block)
+    });
+
+    factory.set_initial_condition(&|_name,
+                                    InitialConditionConfig {
+                                        dof_handler,
+                                        function,
+                                        element,
+                                        target,
+                                    },
+                                    GenConfig { mpi, .. }| {
+        let mut block = BuildingBlock::new();
+
+        let unknown = target.split("_").next().unwrap();
+        let mapping = mapping(element);
+        let quadrature = quadrature(element);
+
+        if *mpi {
+            // Projecting needs the mass matrix of the whole mesh, each process interpolates its dofs
+            block.add_includes(&["deal.II/numerics/vector_tools_interpolate.h"]);
+            block.main_setup.extend(lines!(
+                r"// Apply Intial Condition for {unknown}
+VectorTools::interpolate({mapping}, {dof_handler}, {function}, {target});
+constraints.distribute({target});"
+            ));
+        } else {
+            block.add_includes(&["deal.II/numerics/vector_tools_project.h"]);
+            block.main_setup.extend(lines!(
+                r"// Apply Intial Condition for {unknown} 
+VectorTools::project({mapping}, {dof_handler}, constraints, {quadrature},
+                     {function}, {target});"
+            ));
+        }
+
+        Ok(block)
+    });
+
+    factory.set_add_vector_output(&|_name,
+                                    VectorOutputConfig {
+                                        vector,
+                                        dof_handler,
+                                        n_components,
+                                        time_derivative_of,
+                                    },
+                                    GenConfig {
+                                        mpi, matrix_free, ..
+                                    }| {
+        let mut block = BuildingBlock::new();
+
+        if let Some(unknown) = time_derivative_of {
+            block.output.extend(lines!(
+                r"// Backward difference of {unknown}, vanishing at the steady state
+{vector} = 0;
+if (timestep_number > 0) {{
+  {vector}.equ(1. / time_step, {unknown});
+  {vector}.add(-1. / time_step, {unknown}_prev);
+}}"
+            ));
+        }
+
+        // The values of the dofs of the locally owned cells are written
+        let data = if *mpi && *matrix_free {
+            block
+                .output
+                .push(format!("{vector}.update_ghost_values();"));
+            vector.to_string()
+        } else if *mpi {
+            let ghosted = ghosted(vector);
+            block.push_data(format!("LA::MPI::Vector {ghosted}"));
+            block.setup.push(format!(
+                "{ghosted}.reinit({}, {}, MPI_COMM_WORLD)",
+                owned_dofs(dof_handler),
+                relevant_dofs(dof_handler)
+            ));
+            block.output.push(format!("{ghosted} = {vector};"));
+            ghosted
+        } else {
+            vector.to_string()
+        };
+
+        if *n_components == 1 {
+            block
+                .output
+                .push(format!(r#"data_out.add_data_vector({data}, "{vector}");"#));
+        } else {
+            // The components are written as a single vector field
+            block.add_includes(&["deal.II/numerics/data_component_interpretation.h"]);
+            block.output.extend(lines!(
+                r#"data_out.add_data_vector(
+    {data}, std::vector<std::string>({n_components}, "{vector}"),
+    DataOut<dim>::type_dof_data,
+    std::vector<DataComponentInterpretation::DataComponentInterpretation>(
+        {n_components}, DataComponentInterpretation::component_is_part_of_vector));"#
+            ));
+        }
+
+        Ok(block)
+    });
+
+    factory.set_monitor(&|name,
+                          MonitorConfig {
+                              quantity,
+                              fields,
+                              file,
+                              mesh,
+                              dof_handler,
+                              element,
+                              n_components,
+                          },
+                          GenConfig {
+                              mpi, matrix_free, ..
+                          }| {
+        let mut block = BuildingBlock::new();
+        block.add_includes(&["fstream"]);
+        let class = vector_class(*mpi, *matrix_free);
+        let mapping = mapping(element);
+        let method = format!("monitor_{name}");
+        let csv = format!("{method}_csv");
+        let nc = *n_components;
+
+        // The fields are read on the locally relevant cells
+        let mut body = Vec::new();
+        let mut read = Vec::new();
+        for (vector, time_derivative_of) in fields.iter() {
+            let source = match time_derivative_of {
+                Some(unknown) => {
+                    let difference = format!("{vector}_difference");
+                    body.push(format!(
+                        r"  // Backward difference of {unknown}, vanishing at the steady state
+  {class} {difference}({unknown});
+  {difference}.sadd(1. / time_step, -1. / time_step, {unknown}_prev);
+  if (timestep_number == 0)
+    {difference} = 0;"
+                    ));
+                    difference
+                }
+                None => vector.clone(),
+            };
+            read.push(relevant_vector_code(
+                &mut body,
+                vector,
+                source,
+                dof_handler,
+                *mpi,
+                *matrix_free,
+            ));
+        }
+        body.push(format!(
+            "  const std::vector<const {class} *> fields = {{{}}};",
+            read.iter().map(|v| format!("&{v}")).join(", ")
+        ));
+
+        let (columns, record) = match quantity {
+            MonitorQuantity::Points(points) => {
+                body.push(format!(
+                    "  const std::vector<Point<dim>> points = {{\n      {}}};",
+                    points
+                        .iter()
+                        .map(|point| point_code(padded_coordinates(point)))
+                        .join(",\n      ")
+                ));
+                body.push(point_values_code(
+                    &mut block,
+                    &mapping,
+                    dof_handler,
+                    nc,
+                    *mpi,
+                ));
+                let columns = fields
+                    .iter()
+                    .flat_map(|(vector, _)| {
+                        (0..points.len()).flat_map(move |p| {
+                            (0..nc).map(move |c| match nc {
+                                1 => format!("{vector}@{p}"),
+                                _ => format!("{vector}_{c}@{p}"),
+                            })
+                        })
+                    })
+                    .collect_vec();
+                (columns, record_values_code(&csv))
+            }
+            MonitorQuantity::Line { from, to, samples } => {
+                let coordinates = ["x", "y", "z"].into_iter().take(from.len());
+                let [from, to] = [from, to].map(|point| point_code(padded_coordinates(point)));
+                body.push(format!(
+                    r"
+  // Evenly spaced samples of the segment
+  const Point<dim> from = {from}, to = {to};
+  std::vector<Point<dim>> points;
+  for (unsigned int s = 0; s < {samples}; ++s)
+    points.push_back(from + (to - from) * (s / data_type({})));",
+                    samples - 1
+                ));
+                body.push(point_values_code(
+                    &mut block,
+                    &mapping,
+                    dof_handler,
+                    nc,
+                    *mpi,
+                ));
+                let columns = coordinates
+                    .map(String::from)
+                    .chain(fields.iter().flat_map(|(vector, _)| {
+                        (0..nc).map(move |c| match nc {
+                            1 => vector.clone(),
+                            _ => format!("{vector}_{c}"),
+                        })
+                    }))
+                    .collect_vec();
+                // A row per sample
+                let record = format!(
+                    r#"  for (unsigned int p = 0; p < points.size(); ++p) {{
+    {csv} << time;
+    for (unsigned int d = 0; d < dim; ++d)
+      {csv} << "," << points[p][d];
+    for (unsigned int f = 0; f < fields.size(); ++f)
+      for (unsigned int c = 0; c < {nc}; ++c)
+        {csv} << "," << values[(f * points.size() + p) * {nc} + c];
+    {csv} << std::endl;
+  }}"#
+                );
+                (columns, record)
+            }
+            MonitorQuantity::Norms { norms, squared } => {
+                block.add_includes(&[
+                    "deal.II/base/function.h",
+                    "deal.II/numerics/vector_tools_integrate_difference.h",
+                ]);
+                let quadrature = quadrature(element);
+                let norm_types = norms
+                    .iter()
+                    .map(|(norm, _)| format!("VectorTools::{}", norm.norm_type()))
+                    .join(", ");
+                let coefficients = norms.iter().map(|(_, c)| format!("{c:e}")).join(", ");
+                let power = if *squared { " * norm" } else { "" };
+                body.push(format!(
+                    r"  const std::vector<VectorTools::NormType> norm_types = {{{norm_types}}};
+  const std::vector<data_type> coefficients = {{{coefficients}}};
+
+  // Weighted sum of the norms of the fields over the whole mesh
+  Vector<float> cellwise_norms({mesh}.n_active_cells());
+  std::vector<data_type> values = {{0.}};
+  for (unsigned int f = 0; f < fields.size(); ++f) {{
+    VectorTools::integrate_difference({mapping}, {dof_handler}, *fields[f],
+                                      Functions::ZeroFunction<dim>({nc}), cellwise_norms,
+                                      {quadrature}, norm_types[f]);
+    const data_type norm =
+        VectorTools::compute_global_error({mesh}, cellwise_norms, norm_types[f]);
+    values[0] += coefficients[f] * norm{power};
+  }}"
+                ));
+                (vec![name.to_string()], record_values_code(&csv))
+            }
+        };
+        let header = std::iter::once("time".to_string()).chain(columns).join(",");
+
+        block.push_data(format!("std::ofstream {csv}"));
+        block.methods_defs.push(format!("void {method}()"));
+        block.methods_impls.push(format!(
+            r#"
+void Sim::{method}() {{
+{}
+
+  // The first process appends a row to the file, opened at the first record
+  if (mpi_rank != 0)
+    return;
+  if (!{csv}.is_open()) {{
+    {csv}.open("{file}");
+    {csv}.precision(12);
+    {csv} << "{header}" << std::endl;
+  }}
+{record}
+}}"#,
+            body.join("\n")
+        ));
+        block.main.push(format!("{method}();"));
+
+        Ok(block)
+    });
+
+    factory.set_errors(&|_name,
+                         ErrorsConfig {
+                             errors,
+                             file,
+                             mesh,
+                             dof_handler,
+                             element,
+                         },
+                         GenConfig {
+                             mpi, matrix_free, ..
+                         }| {
+        let mut block = BuildingBlock::new();
+        block.add_includes(&[
+            "fstream",
+            "deal.II/base/auto_derivative_function.h",
+            "deal.II/grid/grid_tools.h",
+            "deal.II/numerics/vector_tools_integrate_difference.h",
+        ]);
+        let mapping = mapping(element);
+        let quadrature = quadrature(element);
+
+        // The H1 seminorm needs the gradients of the exact solutions
+        block.add_global(
+            r"
+class DifferentiatedFunction : public AutoDerivativeFunction<dim> {
+public:
+  DifferentiatedFunction(const Function<dim> &function, const data_type step)
+      : AutoDerivativeFunction<dim>(step, function.n_components), function(function) {
+    set_formula(AutoDerivativeFunction<dim>::FourthOrder);
+  }
+
+  virtual double value(const Point<dim> &point,
+                       const unsigned int component = 0) const override {
+    return function.value(point, component);
+  }
+
+private:
+  const Function<dim> &function;
+};"
+            .trim(),
+        );
+
+        let mut body = Vec::new();
+        for (vector, function) in errors.iter() {
+            let read = relevant_vector_code(
+                &mut body,
+                vector,
+                vector.clone(),
+                dof_handler,
+                *mpi,
+                *matrix_free,
+            );
+            body.push(format!(
+                r"  {function}.set_time(time);
+  const DifferentiatedFunction {function}_differentiated({function}, step);
+  for (const VectorTools::NormType norm_type : norm_types) {{
+    VectorTools::integrate_difference({mapping}, {dof_handler}, {read},
+                                      {function}_differentiated, cellwise_errors,
+                                      {quadrature}, norm_type);
+    errors.push_back(VectorTools::compute_global_error({mesh}, cellwise_errors, norm_type));
+  }}"
+            ));
+        }
+        let header = ["time", "n_dofs", "h"]
+            .into_iter()
+            .map(String::from)
+            .chain(errors.iter().flat_map(|(vector, _)| {
+                ["L2", "H1_seminorm", "Linfty"].map(|norm| format!("{vector}_{norm}"))
+            }))
+            .join(",");
+
+        block.push_data("std::ofstream errors_csv".to_string());
+        block.methods_defs.push("void write_errors()".to_string());
+        block.methods_impls.push(format!(
+            r#"
+void Sim::write_errors() {{
+  // The L2, H1 seminorm and Linfty norms of the differences with the exact solutions
+  const std::vector<VectorTools::NormType> norm_types = {{
+      VectorTools::L2_norm, VectorTools::H1_seminorm, VectorTools::Linfty_norm}};
+  const data_type h = GridTools::maximal_cell_diameter({mesh});
+  // Finite difference step of the gradients of the exact solutions, well below the cell size
+  const data_type step = 1e-4 * GridTools::minimal_cell_diameter({mesh});
+  Vector<float> cellwise_errors({mesh}.n_active_cells());
+  std::vector<data_type> errors;
+{}
+
+  // The first process appends a row to the file, opened at the first record
+  if (mpi_rank != 0)
+    return;
+  if (!errors_csv.is_open()) {{
+    errors_csv.open("{file}");
+    errors_csv.precision(12);
+    errors_csv << "{header}" << std::endl;
+  }}
+  errors_csv << time << "," << {dof_handler}.n_dofs() << "," << h;
+  for (const data_type error : errors)
+    errors_csv << "," << error;
+  errors_csv << std::endl;
+}}"#,
+            body.join("\n")
+        ));
+        block.output.push("write_errors();".to_string());
+
+        Ok(block)
+    });
+
+    factory.set_checkpoint(&|_name,
+                             CheckpointConfig {
+                                 mesh,
+                                 dof_handler,
+                                 element,
+                                 saved,
+                                 prefix,
+                                 time_start,
+                                 marked,
+                                 checkpoint: Checkpoint { every, keep },
+                             },
+                             GenConfig {
+                                 mpi, matrix_free, ..
+                             }| {
+        if *matrix_free {
+            Err(BuildingBlockError::Unsupported(
+                "checkpoints with matrix free code".to_string(),
+            ))?
+        }
+        let mut block = BuildingBlock::new();
+        block.add_includes(&["deque", "filesystem", "fstream", "iomanip"]);
+
+        let targets = saved.iter().map(|vector| format!("&{vector}")).join(", ");
+        let mark = if *marked {
+            format!("\n  mark_{mesh}();")
+        } else {
+            String::new()
+        };
+        let (save, load_mesh, load) = if *mpi {
+            block.add_includes(&["deal.II/distributed/solution_transfer.h"]);
+            let ghosted = saved
+                .iter()
+                .enumerate()
+                .map(|(i, vector)| format!("  previous[{i}] = {vector};"))
+                .join("\n");
+            let previous = (0..saved.len())
+                .map(|i| format!("&previous[{i}]"))
+                .join(", ");
+            let distribute = saved
+                .iter()
+                .map(|vector| format!("  constraints.distribute({vector});"))
+                .join("\n");
+            (
+                format!(
+                    r#"  if (mpi_rank == 0)
+    std::filesystem::create_directories(directory);
+  MPI_Barrier(MPI_COMM_WORLD);
+
+  // The previous values are attached to the mesh, each process writing its own cells
+  std::vector<LA::MPI::Vector> previous(
+      {count}, LA::MPI::Vector({owned_dofs}, {relevant_dofs}, MPI_COMM_WORLD));
+{ghosted}
+  parallel::distributed::SolutionTransfer<dim, LA::MPI::Vector> solution_transfer({dof_handler});
+  solution_transfer.prepare_for_serialization(
+      std::vector<const LA::MPI::Vector *>{{{previous}}});
+  {mesh}.save(directory + "/mesh");"#,
+                    count = saved.len(),
+                    owned_dofs = owned_dofs(dof_handler),
+                    relevant_dofs = relevant_dofs(dof_handler),
+                ),
+                format!(
+                    r#"  // The saved refinement is loaded onto the coarse mesh
+  while ({mesh}.n_global_levels() > 1) {{
+    for (const auto &cell : {mesh}.active_cell_iterators())
+      if (cell->is_locally_owned())
+        cell->set_coarsen_flag();
+    {mesh}.execute_coarsening_and_refinement();
+  }}
+  {mesh}.load(directory + "/mesh");"#
+                ),
+                format!(
+                    r"  // The values attached to the loaded mesh are distributed to the dofs
+  parallel::distributed::SolutionTransfer<dim, LA::MPI::Vector> solution_transfer({dof_handler});
+  std::vector<LA::MPI::Vector *> loaded = {{{targets}}};
+  solution_transfer.deserialize(loaded);
+{distribute}"
+                ),
+            )
+        } else {
+            block.add_includes(&[
+                "set",
+                "boost/archive/binary_iarchive.hpp",
+                "boost/archive/binary_oarchive.hpp",
+                "deal.II/grid/cell_id.h",
+                "deal.II/numerics/vector_tools.h",
+            ]);
+            (
+                format!(
+                    r#"  std::filesystem::create_directories(directory);
+
+  // The mesh followed by the previous values, written before the checkpoint is pointed to
+  {{
+    std::ofstream file(directory + "/data", std::ios::binary);
+    boost::archive::binary_oarchive archive(file);
+    archive << {mesh};
+    for (const Vector<data_type> *vector : {{{targets}}})
+      archive << *vector;
+  }}"#
+                ),
+                format!(
+                    r#"  // The mesh is in use by the dofs, so the saved mesh is read apart and the cells of the
+  // mesh are refined or coarsened until they are the saved ones
+  Triangulation<dim> saved_mesh;
+  std::ifstream file(directory + "/data", std::ios::binary);
+  boost::archive::binary_iarchive archive(file);
+  archive >> saved_mesh;
+  std::set<CellId> saved_cells, saved_active_cells;
+  for (const auto &cell : saved_mesh.cell_iterators()) {{
+    saved_cells.insert(cell->id());
+    if (cell->is_active())
+      saved_active_cells.insert(cell->id());
+  }}
+  for (bool changed = true; changed;) {{
+    changed = false;
+    for (const auto &cell : {mesh}.active_cell_iterators())
+      if (saved_active_cells.count(cell->id()) == 0) {{
+        if (saved_cells.count(cell->id()) != 0)
+          cell->set_refine_flag();
+        else
+          cell->set_coarsen_flag();
+        changed = true;
+      }}
+    if (changed)
+      {mesh}.execute_coarsening_and_refinement();
+  }}"#
+                ),
+                format!(
+                    r#"  // The saved values are interpolated from the dofs of the saved mesh, which are numbered
+  // in another order
+  Triangulation<dim> saved_mesh;
+  std::vector<Vector<data_type>> saved({count});
+  std::ifstream file(directory + "/data", std::ios::binary);
+  boost::archive::binary_iarchive archive(file);
+  archive >> saved_mesh;
+  for (Vector<data_type> &vector : saved)
+    archive >> vector;
+  DoFHandler<dim> saved_dof_handler(saved_mesh);
+  saved_dof_handler.distribute_dofs({element});
+  const std::vector<Vector<data_type> *> loaded = {{{targets}}};
+  for (unsigned int i = 0; i < saved.size(); ++i)
+    VectorTools::interpolate_to_different_mesh(saved_dof_handler, saved[i], {dof_handler},
+                                               constraints, *loaded[i]);"#,
+                    count = saved.len(),
+                ),
+            )
+        };
+        let (first_process, remove) = if *mpi {
+            ("if (mpi_rank == 0) {", "if (mpi_rank == 0)\n      ")
+        } else {
+            ("{", "")
+        };
+
+        block.push_data("std::deque<std::string> checkpoints".to_string());
+        block.methods_defs.extend([
+            "void save_checkpoint()".to_string(),
+            "void load_checkpoint_mesh(const std::string &directory)".to_string(),
+            "void load_checkpoint(const std::string &directory)".to_string(),
+        ]);
+        block.methods_impls.push(format!(
+            r#"
+void Sim::save_checkpoint() {{
+  // Each checkpoint is a directory holding the mesh and the previous values
+  const std::string directory =
+      "{prefix}checkpoint-" + Utilities::int_to_string(timestep_number, 6);
+{save}
+
+  // The time and the step of the checkpoint, and the path of the latest checkpoint
+  {first_process}
+    std::ofstream state(directory + "/state");
+    state << std::setprecision(17) << time << " " << timestep_number << std::endl;
+    std::ofstream latest("{prefix}{CHECKPOINT_LATEST_FILE}");
+    latest << directory << std::endl;
+  }}
+
+  // Only the latest {keep} checkpoints are kept
+  checkpoints.push_back(directory);
+  if (checkpoints.size() > {keep}) {{
+    {remove}std::filesystem::remove_all(checkpoints.front());
+    checkpoints.pop_front();
+  }}
+  pcout << "Saved checkpoint " << directory << std::endl;
+}}
+
+void Sim::load_checkpoint_mesh(const std::string &directory) {{
+{load_mesh}{mark}
+}}
+
+void Sim::load_checkpoint(const std::string &directory) {{
+{load}
+
+  // The time stepping resumes at the step following the checkpoint
+  std::ifstream state(directory + "/state");
+  state >> time >> timestep_number;
+  time += time_step;
+  ++timestep_number;
+  checkpoints.push_back(directory);
+  pcout << "Restarted from " << directory << " at t=" << time << std::endl;
+}}"#
+        ));
+
+        block.main.extend(match every {
+            OutputInterval::Steps(steps) => lines!(
+                r"
+// Save a checkpoint every {steps} steps
+if (timestep_number % {steps} == 0)
+  save_checkpoint();
+                "
+            ),
+            OutputInterval::Time(interval) => {
+                let interval = interval.seconds();
+                lines!(
+                    r"
+// Save a checkpoint every {interval} s of simulated time
+if (std::floor((time - {time_start}) / {interval} + 1e-8) >
+    std::floor((time - time_step - {time_start}) / {interval} + 1e-8))
+  save_checkpoint();
+                    "
+                )
+            }
+        });
+
+        Ok(block)
+    });
+
+    factory
+}
+
+/// Makes the values of `source` on the locally relevant cells readable, returning the vector
+/// to read from for `vector`.
+fn relevant_vector_code(
+    body: &mut Vec<String>,
+    vector: &str,
+    source: String,
+    dof_handler: &str,
+    mpi: bool,
+    matrix_free: bool,
+) -> String {
+    if mpi && matrix_free {
+        body.push(format!("  {source}.update_ghost_values();"));
+        source
+    } else if mpi {
+        let relevant = format!("{vector}_relevant");
+        body.push(format!(
+            "  LA::MPI::Vector {relevant}({}, {}, MPI_COMM_WORLD);\n  {relevant} = {source};",
+            owned_dofs(dof_handler),
+            relevant_dofs(dof_handler)
+        ));
+        relevant
+    } else {
+        source
+    }
+}
+
+/// The class of the vectors of the dofs.
+fn vector_class(mpi: bool, matrix_free: bool) -> &'static str {
+    if matrix_free {
+        MATRIX_FREE_VECTOR
+    } else if mpi {
+        "LA::MPI::Vector"
+    } else {
+        "Vector<data_type>"
+    }
+}
+
+/// Evaluates the `fields` with `n_components` components at the `points`, averaging the values
+/// found by several processes and leaving NaN where no process found the point.
+fn point_values_code(
+    block: &mut BuildingBlock,
+    mapping: &str,
+    dof_handler: &str,
+    n_components: usize,
+    mpi: bool,
+) -> String {
+    block.add_includes(&["deal.II/numerics/vector_tools_point_value.h", "limits"]);
+    let gather = if mpi {
+        "\n  values = Utilities::MPI::sum(values, MPI_COMM_WORLD);\n  found = Utilities::MPI::sum(found, MPI_COMM_WORLD);"
+    } else {
+        ""
+    };
+    format!(
+        r"
+  // The points are only found on the cells of the process
+  std::vector<data_type> values(fields.size() * points.size() * {n_components}, 0.);
+  std::vector<data_type> found(points.size(), 0.);
+  Vector<data_type> value({n_components});
+  for (unsigned int p = 0; p < points.size(); ++p)
+    try {{
+      for (unsigned int f = 0; f < fields.size(); ++f) {{
+        VectorTools::point_value({mapping}, {dof_handler}, *fields[f], points[p], value);
+        for (unsigned int c = 0; c < {n_components}; ++c)
+          values[(f * points.size() + p) * {n_components} + c] = value[c];
+      }}
+      found[p] = 1.;
+    }} catch (const ExceptionBase &) {{
+    }}{gather}
+  for (unsigned int p = 0; p < points.size(); ++p)
+    for (unsigned int f = 0; f < fields.size(); ++f)
+      for (unsigned int c = 0; c < {n_components}; ++c) {{
+        data_type &v = values[(f * points.size() + p) * {n_components} + c];
+        v = found[p] > 0. ? v / found[p] : std::numeric_limits<data_type>::quiet_NaN();
+      }}"
+    )
+}
+
+/// Appends a row of the time followed by the `values` to the file `csv`.
+fn record_values_code(csv: &str) -> String {
+    format!(
+        r#"  {csv} << time;
+  for (const data_type v : values)
+    {csv} << "," << v;
+  {csv} << std::endl;"#
+    )
+}
+
+/// Pads the coordinates of a point to three, with zeros beyond its dimension.
+fn padded_coordinates(coordinates: &[f64]) -> [f64; 3] {
+    std::array::from_fn(|d| coordinates.get(d).copied().unwrap_or(0.))
+}
+
+/// Declares the triangulation `name`, left for a grid generator to fill.
+fn triangulation_block(name: &str, mpi: bool) -> BuildingBlock {
+    let mut block = BuildingBlock::new();
+    if mpi {
+        block.add_includes(&[
+            "deal.II/distributed/tria.h",
+            "deal.II/distributed/grid_refinement.h",
+        ]);
+
+        block.constructor.push(format!(
+            "{name}(MPI_COMM_WORLD,
+        typename Triangulation<dim>::MeshSmoothing(
+            Triangulation<dim>::smoothing_on_refinement |
+            Triangulation<dim>::smoothing_on_coarsening))"
+        ))
+    } else {
+        block.add_includes(&["deal.II/grid/tria.h"]);
+    }
+
+    block.add_includes(&["deal.II/grid/grid_generator.h"]);
+
+    block.data.push(format!(
+        "{}Triangulation<dim> {name}",
+        if mpi { "parallel::distributed::" } else { "" }
+    ));
+    block
+}
+
+/// Marks the named boundaries and regions of the generated mesh `name`, and prints its
+/// number of cells when `show_info` is set.
+fn mark_mesh(
+    block: &mut BuildingBlock,
+    name: &str,
+    mesh: &dyn Mesh,
+    MeshParts {
+        boundaries,
+        regions,
+    }: &MeshParts,
+    show_info: bool,
+) {
+    // Named boundaries and regions are marked on the refined cells
+    if !boundaries.is_empty() || !regions.is_empty() {
+        let boundary_ids = mesh.boundaries(3);
+        let mut marks = Vec::new();
+        for (region, id) in mesh.regions() {
+            let CoordinateConditions { x, y, z } = &regions[&region];
+            marks.push(format!(
+                "    if ({})\n      cell->set_material_id({id}); // {region}",
+                condition_code(coordinate_conditions(x, y, z, "cell->center()"))
+            ));
+        }
+        let mut face_marks = Vec::new();
+        for (boundary, CoordinateConditions { x, y, z }) in boundaries.iter() {
+            let id = boundary_ids[boundary];
+            face_marks.push(format!(
+                "      if ({})\n        face->set_boundary_id({id}); // {boundary}",
+                condition_code(coordinate_conditions(x, y, z, "face->center()"))
+            ));
+        }
+        if !face_marks.is_empty() {
+            marks.push(format!(
+                r"    for (const auto &face : cell->face_iterators()) {{
+      if (!face->at_boundary())
+        continue;
+{}
+    }}",
+                face_marks.join("\n")
+            ));
+        }
+
+        block.methods_defs.push(format!("void mark_{name}()"));
+        block.methods_impls.push(format!(
+            r"
+void Sim::mark_{name}() {{
+  for (const auto &cell : {name}.active_cell_iterators()) {{
+{}
+  }}
+}}",
+            marks.join("\n")
+        ));
+        block.mesh_setup.push(format!("mark_{name}()"));
+    }
+
+    if show_info {
+        block.mesh_setup.push(format!(
+            r#"pcout << "Number of active cells: " << {name}.n_active_cells() << std::endl"#
+        ));
+    }
+}
+
+/// Prints the range of the cell diameters of the mesh `name`, whose cells are not all of
+/// the same size.
+fn report_cell_diameters(block: &mut BuildingBlock, name: &str) {
+    block.add_includes(&["deal.II/grid/grid_tools.h"]);
+    block.mesh_setup.push(format!(
+        r#"pcout << "Cell diameter: " << GridTools::minimal_cell_diameter({name}) << " m to " << GridTools::maximal_cell_diameter({name}) << " m" << std::endl"#
+    ));
+}
+
+/// Generates the point of the given coordinates, the ones beyond the dimension being ignored.
+fn point_code([x, y, z]: [f64; 3]) -> String {
+    format!("make_point({x}, {y}, {z})")
+}
+
+/// Joins the checks of coordinate conditions, matching everywhere when there are none.
+fn condition_code(conditions: Vec<String>) -> String {
+    if conditions.is_empty() {
+        "true".to_string()
+    } else {
+        conditions.join(" && ")
+    }
+}
+
+/// The deal.II class of `element` and the arguments of its constructor.
+fn element_code(block: &mut BuildingBlock, element: &FiniteElement) -> (&'static str, String) {
+    match element {
+        FiniteElement::Q1 | FiniteElement::Q2 | FiniteElement::Q3 | FiniteElement::Q(_) => {
+            block.add_includes(&["deal.II/fe/fe_q.h"]);
+            ("FE_Q<dim>", element.degree().to_string())
+        }
+        FiniteElement::DGQ(degree) => {
+            block.add_includes(&["deal.II/fe/fe_dgq.h"]);
+            ("FE_DGQ<dim>", degree.to_string())
+        }
+        FiniteElement::P(degree) => {
+            block.add_includes(&["deal.II/fe/fe_simplex_p.h"]);
+            ("FE_SimplexP<dim>", degree.to_string())
+        }
+        FiniteElement::FESystem(components) => {
+            block.add_includes(&["deal.II/fe/fe_system.h"]);
+            let args = components
+                .iter()
+                .map(
+                    |ElementComponent {
+                         element,
+                         multiplicity,
+                     }| {
+                        let (class, args) = element_code(block, element);
+                        format!("{class}({args}), {multiplicity}")
+                    },
+                )
+                .join(", ");
+            ("FESystem<dim>", args)
+        }
+    }
+}
 
-        let function_code = function_def_to_deal_ii_code(function_def)
-            .trim()
-            .split("\n")
-            .map(|line| format!("    {line}"))
-            .collect_vec()
-            .join("\n");
+/// Adds a method adding the face terms of the symmetric interior penalty method to the Laplace
+/// matrix `target` of a discontinuous element, whose cell terms are assembled beforehand.
+///
+/// The faces are assembled once the constraints are closed.
+fn add_interior_penalty_assembly(
+    block: &mut BuildingBlock,
+    target: &str,
+    element: &str,
+    dof_handler: &str,
+) {
+    block.add_includes(&[
+        "deal.II/fe/fe_interface_values.h",
+        "deal.II/lac/full_matrix.h",
+        "deal.II/meshworker/mesh_loop.h",
+        "deal.II/meshworker/scratch_data.h",
+    ]);
+    let mapping = mapping(element);
+    let quadrature = quadrature(element);
+    let face_quadrature = face_quadrature(element);
+
+    block
+        .methods_defs
+        .push(format!("void assemble_{target}_faces()"));
+    block.methods_impls.push(format!(
+        r"
+void Sim::assemble_{target}_faces() {{
+  struct CopyDataFace {{
+    FullMatrix<data_type> cell_matrix;
+    std::vector<types::global_dof_index> joint_dof_indices;
+  }};
+  struct CopyData {{
+    std::vector<CopyDataFace> face_data;
+  }};
+  using Iterator = typename DoFHandler<dim>::active_cell_iterator;
+
+  // Piecewise constant elements are penalized like linear ones
+  const data_type degree = std::max(1u, {element}.degree);
+
+  const auto cell_worker = [](const Iterator &, MeshWorker::ScratchData<dim> &,
+                              CopyData &copy_data) {{ copy_data.face_data.clear(); }};
+  const auto face_worker = [&](const Iterator &cell, const unsigned int &f,
+                               const unsigned int &sf, const Iterator &ncell,
+                               const unsigned int &nf, const unsigned int &nsf,
+                               MeshWorker::ScratchData<dim> &scratch_data,
+                               CopyData &copy_data) {{
+    const FEInterfaceValues<dim> &fe_iv =
+        scratch_data.reinit(cell, f, sf, ncell, nf, nsf);
+    const auto &normals = fe_iv.get_normal_vectors();
+    const unsigned int n_dofs = fe_iv.n_current_interface_dofs();
+
+    // The penalty grows with the degree and the inverse of the cell size
+    const data_type h = std::min(cell->measure() / cell->face(f)->measure(),
+                                 ncell->measure() / ncell->face(nf)->measure());
+    const data_type penalty = 2. * degree * (degree + 1.) / h;
+
+    CopyDataFace &copy_data_face = copy_data.face_data.emplace_back();
+    copy_data_face.joint_dof_indices = fe_iv.get_interface_dof_indices();
+    copy_data_face.cell_matrix.reinit(n_dofs, n_dofs);
+    for (const unsigned int q : fe_iv.quadrature_point_indices())
+      for (unsigned int i = 0; i < n_dofs; ++i)
+        for (unsigned int j = 0; j < n_dofs; ++j)
+          copy_data_face.cell_matrix(i, j) +=
+              (-fe_iv.jump_in_shape_values(i, q) *
+                   (fe_iv.average_of_shape_gradients(j, q) * normals[q]) -
+               (fe_iv.average_of_shape_gradients(i, q) * normals[q]) *
+                   fe_iv.jump_in_shape_values(j, q) +
+               penalty * fe_iv.jump_in_shape_values(i, q) *
+                   fe_iv.jump_in_shape_values(j, q)) *
+              fe_iv.JxW(q);
+  }};
+  const auto copier = [&](const CopyData &copy_data) {{
+    for (const auto &face : copy_data.face_data)
+      constraints.distribute_local_to_global(face.cell_matrix,
+                                             face.joint_dof_indices, {target});
+  }};
+
+  MeshWorker::ScratchData<dim> scratch_data(
+      {mapping}, {element}, {quadrature}, update_default, {face_quadrature},
+      update_values | update_gradients | update_quadrature_points |
+          update_JxW_values | update_normal_vectors);
+  MeshWorker::mesh_loop({dof_handler}.begin_active(), {dof_handler}.end(), cell_worker,
+                        copier, scratch_data, CopyData(),
+                        MeshWorker::assemble_own_cells |
+                            MeshWorker::assemble_own_interior_faces_once |
+                            MeshWorker::assemble_ghost_faces_once,
+                        {{}}, face_worker);
+  {target}.compress(VectorOperation::add);
+}}"
+    ));
+    block.assembly.push(format!("assemble_{target}_faces()"));
+}
 
-        block.data.push(format!("{class_name} {name}"));
+/// Integrals over the cells of the shape functions, assembled into matrices or vectors.
+#[derive(Clone, Copy)]
+enum CellIntegral<'a> {
+    /// ∫ φ_i φ_j
+    Mass,
+    /// ∫ c ∇φ_i·∇φ_j, weighted by the function c if any
+    Laplace(Option<&'a str>),
+    /// ∫ f φ_i, from the source function f
+    Source(&'a str),
+}
 
-        block.add_global(
+/// Adds a method assembling `integral` into `target` with `WorkStream`, the cells being
+/// integrated on all the threads while the copies into `target` are serialized.
+///
+/// The integral is assembled once the constraints are closed.
+fn add_cell_assembly(
+    block: &mut BuildingBlock,
+    target: &str,
+    integral: CellIntegral,
+    element: &str,
+    dof_handler: &str,
+    constrained: bool,
+    n_components: usize,
+) {
+    block.add_includes(&[
+        "deal.II/base/work_stream.h",
+        "deal.II/base/quadrature_lib.h",
+        "deal.II/fe/fe_values.h",
+        "deal.II/meshworker/copy_data.h",
+        "deal.II/meshworker/scratch_data.h",
+    ]);
+    let mapping = mapping(element);
+    let quadrature = quadrature(element);
+    let is_matrix = !matches!(integral, CellIntegral::Source(_));
+    if is_matrix {
+        block.add_includes(&["deal.II/lac/full_matrix.h"]);
+    }
+    let (local, local_type, copied) = match is_matrix {
+        true => ("cell_matrix", "FullMatrix<data_type>", "matrices"),
+        false => ("cell_vector", "Vector<data_type>", "vectors"),
+    };
+    let function = match integral {
+        CellIntegral::Mass | CellIntegral::Laplace(None) => None,
+        CellIntegral::Laplace(Some(function)) | CellIntegral::Source(function) => Some(function),
+    };
+    let flags = match integral {
+        CellIntegral::Mass => "update_values | update_JxW_values",
+        CellIntegral::Laplace(None) => "update_gradients | update_JxW_values",
+        CellIntegral::Laplace(Some(_)) => {
+            "update_gradients | update_quadrature_points | update_JxW_values"
+        }
+        CellIntegral::Source(_) => "update_values | update_quadrature_points | update_JxW_values",
+    };
+
+    // Shape functions of different components are not coupled
+    let component = |i: &str| format!("fe.system_to_component_index({i}).first");
+    let (fe_declaration, coupled, pad) = match n_components {
+        1 => (String::new(), String::new(), ""),
+        _ => (
+            "\n    const FiniteElement<dim> &fe = fe_values.get_fe();".to_string(),
             format!(
-                r"
-class {class_name} : public Function<dim> {{
-public:
-  virtual double value(const Point<dim> &point,
-                       const unsigned int component = 0) const override {{
-{function_code}
-  }}
-}};
-"
-            )
-            .trim(),
-        );
+                "if ({} == {})\n            ",
+                component("i"),
+                component("j")
+            ),
+            "  ",
+        ),
+    };
+    let (weight_declaration, weight, factor) = match (function, n_components, is_matrix) {
+        (None, _, _) => (String::new(), String::new(), String::new()),
+        (Some(function), 1, _) | (Some(function), _, true) => (
+            String::new(),
+            format!(
+                "\n      const data_type weight = {function}.value(fe_values.quadrature_point(q));"
+            ),
+            "weight * ".to_string(),
+        ),
+        (Some(function), _, false) => (
+            format!("\n    Vector<data_type> weight({n_components});"),
+            format!("\n      {function}.vector_value(fe_values.quadrature_point(q), weight);"),
+            format!("weight({}) * ", component("i")),
+        ),
+    };
+    let integrand = match integral {
+        CellIntegral::Mass => format!(
+            "for (const unsigned int j : fe_values.dof_indices())
+          {coupled}cell_matrix(i, j) += fe_values.shape_value(i, q) *
+                               {pad}fe_values.shape_value(j, q) * fe_values.JxW(q);"
+        ),
+        CellIntegral::Laplace(_) => format!(
+            "for (const unsigned int j : fe_values.dof_indices())
+          {coupled}cell_matrix(i, j) += {factor}fe_values.shape_grad(i, q) *
+                               {pad}fe_values.shape_grad(j, q) * fe_values.JxW(q);"
+        ),
+        CellIntegral::Source(_) => format!(
+            "cell_vector(i) += {factor}fe_values.shape_value(i, q) *
+                          fe_values.JxW(q);"
+        ),
+    };
+    let distribute = match constrained {
+        true => format!(
+            "constraints.distribute_local_to_global(copy_data.{copied}[0],
+                                           copy_data.local_dof_indices[0], {target});"
+        ),
+        false => format!("{target}.add(copy_data.local_dof_indices[0], copy_data.{copied}[0]);"),
+    };
 
-        Ok(block)
-    });
+    block.methods_defs.push(format!("void assemble_{target}()"));
+    block.methods_impls.push(format!(
+        r"
+void Sim::assemble_{target}() {{
+  using Iterator = typename DoFHandler<dim>::active_cell_iterator;
+  using CopyData = MeshWorker::CopyData<1, 1, 1>;
+
+  const auto cell_worker = [&](const Iterator &cell,
+                               MeshWorker::ScratchData<dim> &scratch_data,
+                               CopyData &copy_data) {{
+    const FEValues<dim> &fe_values = scratch_data.reinit(cell);{fe_declaration}{weight_declaration}
+    {local_type} &{local} = copy_data.{copied}[0];
+    {local} = 0;
+    for (const unsigned int q : fe_values.quadrature_point_indices()) {{{weight}
+      for (const unsigned int i : fe_values.dof_indices())
+        {integrand}
+    }}
+    cell->get_dof_indices(copy_data.local_dof_indices[0]);
+  }};
+  const auto copier = [&](const CopyData &copy_data) {{
+    {distribute}
+  }};
+
+  {target} = 0;
+  WorkStream::run({dof_handler}.begin_active(), {dof_handler}.end(), cell_worker, copier,
+                  MeshWorker::ScratchData<dim>({mapping}, {element}, {quadrature},
+                                               {flags}),
+                  CopyData({element}.n_dofs_per_cell()));
+}}"
+    ));
+    block.assembly.push(format!("assemble_{target}()"));
+}
 
-    factory.set_apply_boundary_condition(&|_name,
-                                           ApplyBoundaryConditionConfig {
-                                               function,
-                                               dof_handler,
-                                               matrix,
-                                               solution,
-                                               rhs,
-                                           },
-                                           _| {
-        let mut block = BuildingBlock::new();
+/// Adds a method assembling the operator `kind` of vector unknowns into the matrix `target`,
+/// from the divergence or the symmetric gradient of the shape functions.
+///
+/// The operator is assembled once the constraints are closed.
+fn add_vector_operator_assembly(
+    block: &mut BuildingBlock,
+    target: &str,
+    kind: ShapeMatrix,
+    element: &str,
+    dof_handler: &str,
+) {
+    block.add_includes(&[
+        "deal.II/fe/fe_values.h",
+        "deal.II/fe/fe_values_extractors.h",
+        "deal.II/lac/full_matrix.h",
+    ]);
+    let mapping = mapping(element);
+    let quadrature = quadrature(element);
+    let integrand = match kind {
+        ShapeMatrix::GradDiv => {
+            "fe_values[unknown].divergence(i, q) * fe_values[unknown].divergence(j, q)"
+        }
+        _ => {
+            "scalar_product(fe_values[unknown].symmetric_gradient(i, q),
+                             fe_values[unknown].symmetric_gradient(j, q))"
+        }
+    };
 
-        block.add_includes(&[
-            "deal.II/numerics/vector_tools_boundary.h",
-            "deal.II/numerics/matrix_tools.h",
-        ]);
+    block.methods_defs.push(format!("void assemble_{target}()"));
+    block.methods_impls.push(format!(
+        r"
+void Sim::assemble_{target}() {{
+  FEValues<dim> fe_values({mapping}, {element}, {quadrature},
+                          update_gradients | update_JxW_values);
+  const FEValuesExtractors::Vector unknown(0);
+
+  const unsigned int dofs_per_cell = {element}.n_dofs_per_cell();
+  FullMatrix<data_type> cell_matrix(dofs_per_cell, dofs_per_cell);
+  std::vector<types::global_dof_index> local_dof_indices(dofs_per_cell);
+
+  {target} = 0;
+  for (const auto &cell : {dof_handler}.active_cell_iterators()) {{
+    if (!cell->is_locally_owned())
+      continue;
+
+    fe_values.reinit(cell);
+    cell_matrix = 0;
+    for (const unsigned int q : fe_values.quadrature_point_indices())
+      for (const unsigned int i : fe_values.dof_indices())
+        for (const unsigned int j : fe_values.dof_indices())
+          cell_matrix(i, j) +=
+              {integrand} *
+              fe_values.JxW(q);
+
+    cell->get_dof_indices(local_dof_indices);
+    constraints.distribute_local_to_global(cell_matrix, local_dof_indices, {target});
+  }}
+  {target}.compress(VectorOperation::add);
+}}"
+    ));
+    block.assembly.push(format!("assemble_{target}()"));
+}
+
+/// Adds a method assembling the advection of the unknowns along the velocity weighting
+/// `config` into `target`, or the SUPG test of their time derivative.
+///
+/// Stabilized advections get the streamline diffusion τ (v·∇φ_i)(v·∇φ_j) too, τ being
+/// delta h / |v| on each cell of diameter h.
+/// Discontinuous elements integrate the advection by parts instead, taking the upwind values
+/// on the faces.
+fn add_advection_assembly(
+    block: &mut BuildingBlock,
+    target: &str,
+    config: &ShapeMatrixConfig,
+) -> Result<(), BuildingBlockError> {
+    let ShapeMatrixConfig {
+        dof_handler,
+        element,
+        kind,
+        discontinuous,
+        coefficient,
+        stabilization,
+        ..
+    } = config;
+    let Some(ShapeMatrixCoefficient { function, previous }) = coefficient else {
+        Err(BuildingBlockError::Unsupported(format!(
+            "{kind} matrix without velocity"
+        )))?
+    };
+    if *discontinuous && (stabilization.is_some() || *kind == ShapeMatrix::Supg) {
+        Err(BuildingBlockError::Unsupported(
+            "stabilization with discontinuous elements".to_string(),
+        ))?
+    }
+    block.add_includes(&[
+        "deal.II/fe/fe_values.h",
+        "deal.II/lac/full_matrix.h",
+        "deal.II/base/tensor.h",
+    ]);
+    let mapping = mapping(element);
+    let quadrature = quadrature(element);
+    let integrand = match (kind, discontinuous) {
+        (ShapeMatrix::Supg, _) => {
+            "tau * (velocity * fe_values.shape_grad(i, q)) * fe_values.shape_value(j, q)"
+        }
+        (_, true) => "-(velocity * fe_values.shape_grad(i, q)) * fe_values.shape_value(j, q)",
+        _ if stabilization.is_some() => {
+            "(fe_values.shape_value(i, q) +
+               tau * (velocity * fe_values.shape_grad(i, q))) *
+              (velocity * fe_values.shape_grad(j, q))"
+        }
+        _ => "fe_values.shape_value(i, q) * (velocity * fe_values.shape_grad(j, q))",
+    };
+    let tau = match stabilization {
+        Some(Stabilization { delta, .. }) => format!(
+            "
+      // The stabilization vanishes where the fluid is at rest
+      const data_type speed = velocity.norm();
+      const data_type tau = speed > 0. ? {delta:?} * cell->diameter() / speed : 0.;"
+        ),
+        None => String::new(),
+    };
+    let faces = match discontinuous {
+        true => {
+            add_upwind_assembly(block, target, element, dof_handler, function);
+            format!("\n  assemble_{target}_faces();")
+        }
+        false => String::new(),
+    };
 
+    block.methods_defs.push(format!("void assemble_{target}()"));
+    block.methods_impls.push(format!(
+        r"
+void Sim::assemble_{target}() {{
+  FEValues<dim> fe_values({mapping}, {element}, {quadrature},
+                          update_values | update_gradients |
+                              update_quadrature_points | update_JxW_values);
+
+  const unsigned int dofs_per_cell = {element}.n_dofs_per_cell();
+  FullMatrix<data_type> cell_matrix(dofs_per_cell, dofs_per_cell);
+  std::vector<types::global_dof_index> local_dof_indices(dofs_per_cell);
+
+  {target} = 0;
+  for (const auto &cell : {dof_handler}.active_cell_iterators()) {{
+    if (!cell->is_locally_owned())
+      continue;
+
+    fe_values.reinit(cell);
+    cell_matrix = 0;
+    for (const unsigned int q : fe_values.quadrature_point_indices()) {{
+      Tensor<1, dim> velocity;
+      for (unsigned int d = 0; d < dim; ++d)
+        velocity[d] = {function}.value(fe_values.quadrature_point(q), d);{tau}
+
+      for (const unsigned int i : fe_values.dof_indices())
+        for (const unsigned int j : fe_values.dof_indices())
+          cell_matrix(i, j) +=
+              {integrand} *
+              fe_values.JxW(q);
+    }}
+
+    cell->get_dof_indices(local_dof_indices);
+    constraints.distribute_local_to_global(cell_matrix, local_dof_indices, {target});
+  }}
+  {target}.compress(VectorOperation::add);{faces}
+}}"
+    ));
+    if let Some(previous) = previous {
+        // Velocities depending on time are reassembled at each time step
+        block.additional_matrixes.insert(previous.to_string());
         block.main.extend(lines!(
             r"
-// Apply boundary condition to the equation for solving {solution}
-{{
-  {function}.set_time(time);
-
-  std::map<types::global_dof_index, double> boundary_values;
-  VectorTools::interpolate_boundary_values(
-      {dof_handler}, 0, {function}, boundary_values);
-  MatrixTools::apply_boundary_values(boundary_values, {matrix}, {solution},
-                                     {rhs});
-}}
-               "
+// Reassemble {target} at the current time
+{previous}.copy_from({target});
+{function}.set_time(time);
+assemble_{target}();
+"
         ));
+    }
+    block.assembly.push(format!("assemble_{target}()"));
+    Ok(())
+}
 
-        Ok(block)
-    });
+/// Sets up `object` of the solver `name` at its first call only, its matrix being the same at
+/// every call. The object is kept as a member of the simulation, along with a flag marking it
+/// set up, and `setup` is the code initializing it.
+fn add_initialize_once(
+    block: &mut BuildingBlock,
+    name: &str,
+    class: &str,
+    object: &str,
+    setup: &str,
+) -> String {
+    block.push_data(format!("{class} {object}"));
+    block.push_data(format!("bool {name}_initialized = false"));
+    let setup = setup
+        .lines()
+        .map(|line| match line.is_empty() || line.starts_with('#') {
+            true => line.to_string(),
+            false => format!("  {line}"),
+        })
+        .join("\n");
+    format!(
+        "
+  if (!{name}_initialized) {{
+{setup}
+    {name}_initialized = true;
+  }}"
+    )
+}
 
-    factory.set_initial_condition(&|_name,
-                                    InitialConditionConfig {
-                                        dof_handler,
-                                        function,
-                                        element,
-                                        target,
-                                    },
-                                    _| {
-        let mut block = BuildingBlock::new();
+/// Sets up the matrix free data of `dof_handler`, from which the operators and the vectors
+/// are initialized, and declares the class of the operators.
+fn add_matrix_free_data(block: &mut BuildingBlock, dof_handler: &str, element: &str) {
+    let matrix_free = matrix_free(dof_handler);
+    let mapping = mapping(element);
+    block.add_includes(&[
+        "deal.II/base/subscriptor.h",
+        "deal.II/lac/la_parallel_vector.h",
+        "deal.II/lac/diagonal_matrix.h",
+        "deal.II/matrix_free/matrix_free.h",
+        "deal.II/matrix_free/fe_evaluation.h",
+        "deal.II/matrix_free/tools.h",
+    ]);
+    block.push_data(format!(
+        "std::shared_ptr<MatrixFree<dim, data_type>> {matrix_free}"
+    ));
+    // The data is built on the closed constraints, the operators being evaluated on the
+    // mapped quadrature points of each cell
+    block.push_setup([
+        "\n    // Matrix free data".to_string(),
+        "constraints.close()".to_string(),
+        format!("MatrixFree<dim, data_type>::AdditionalData {matrix_free}_data"),
+        format!(
+            "{matrix_free}_data.mapping_update_flags = update_values | update_gradients | update_JxW_values"
+        ),
+        format!("{matrix_free} = std::make_shared<MatrixFree<dim, data_type>>()"),
+        format!(
+            "{matrix_free}->reinit({mapping}, {dof_handler}, constraints, QGauss<1>({element}.degree + 1), {matrix_free}_data)"
+        ),
+    ]);
+    block.add_global(MATRIX_FREE_OPERATOR.trim());
+    block.additional_names.insert(matrix_free);
+}
+
+/// The code setting up the preconditioner of the matrix free operator `matrix` for the solver
+/// `name`, with the argument passed to the solver. The preconditioners are built from the
+/// diagonal of the operator, computed cell by cell.
+fn matrix_free_preconditioner(
+    block: &mut BuildingBlock,
+    name: &str,
+    matrix: &str,
+    preconditioner: Preconditioner,
+    constant: bool,
+) -> (String, String) {
+    let object = match constant {
+        true => format!("{name}_preconditioner"),
+        false => "preconditioner".to_string(),
+    };
+    let (class, setup, arg) = match preconditioner {
+        Preconditioner::Jacobi => (
+            format!("std::shared_ptr<DiagonalMatrix<{MATRIX_FREE_VECTOR}>>"),
+            format!("{object} = {matrix}.inverse_diagonal();"),
+            format!("*{object}"),
+        ),
+        Preconditioner::Chebyshev => {
+            let class = format!("PreconditionChebyshev<MatrixFreeOperator, {MATRIX_FREE_VECTOR}>");
+            let setup = format!(
+                "{class}::AdditionalData chebyshev_data;
+chebyshev_data.preconditioner = {matrix}.inverse_diagonal();
+chebyshev_data.degree = 4;
+chebyshev_data.smoothing_range = 20;
+{object}.initialize({matrix}, chebyshev_data);"
+            );
+            (class, setup, object.clone())
+        }
+        _ => return (String::new(), "PreconditionIdentity()".to_string()),
+    };
+    let setup = setup.lines().map(|line| format!("  {line}")).join("\n");
+    let code = match constant {
+        true => {
+            let initialization = add_initialize_once(block, name, &class, &object, &setup);
+            format!(
+                "\n  // The operator is the same at every step, the preconditioner is only initialized once{initialization}\n"
+            )
+        }
+        false => format!("\n  {class} {object};\n{setup}\n"),
+    };
+    (code, arg)
+}
 
-        block.add_includes(&["deal.II/numerics/vector_tools_project.h"]);
+/// Updates `unknown` from the lumped mass matrix instead of solving, the system matrix being
+/// the mass matrix scaled row by row. Rows left with their diagonal only are the ones of the
+/// boundary values and constraints, which are kept unlumped.
+fn add_lumped_update(
+    block: &mut BuildingBlock,
+    name: &str,
+    unknown: &str,
+    matrix: &str,
+    rhs: &str,
+    mass: &str,
+    mpi: bool,
+) {
+    block.methods_defs.push(format!("void {name}()"));
+    let (rows, compress) = if mpi {
+        (
+            format!("const auto row : {unknown}.locally_owned_elements()"),
+            format!("\n  {unknown}.compress(VectorOperation::insert);"),
+        )
+    } else {
+        (
+            format!("types::global_dof_index row = 0; row < {matrix}.m(); ++row"),
+            String::new(),
+        )
+    };
+    block.methods_impls.push(format!(
+        r#"
+void Sim::{name}() {{
+  for ({rows}) {{
+    data_type lumped_mass = 0;
+    for (auto entry = {mass}.begin(row); entry != {mass}.end(row); ++entry)
+      lumped_mass += entry->value();
 
-        let unknown = target.split("_").next().unwrap();
-        let owned_dofs = owned_dofs(dof_handler);
-        let distributed = distributed(dof_handler);
-
-        block.main_setup.extend(lines!(
-            r"// Apply Intial Condition for {unknown} 
-VectorTools::project({dof_handler}, constraints, QGauss<dim>({element}.degree + 1),
-                     {function}, {target});
-// {target} = {distributed};"
-        ));
+    bool diagonal_only = true;
+    for (auto entry = {matrix}.begin(row); entry != {matrix}.end(row); ++entry)
+      if (entry->column() != row && entry->value() != 0)
+        diagonal_only = false;
 
-        Ok(block)
-    });
+    const data_type diagonal = {matrix}.diag_element(row);
+    {unknown}(row) = {rhs}(row) / (diagonal_only ? diagonal : diagonal * lumped_mass / {mass}.diag_element(row));
+  }}{compress}
 
-    factory.set_add_vector_output(&|_name, vector, _| {
-        let mut block = BuildingBlock::new();
+  pcout << "    {name}: lumped mass update." << std::endl;
 
-        block.output.push(format!(
-            r#"data_out.add_data_vector({vector}, "{vector}");"#
-        ));
+  constraints.distribute({unknown});
+}}"#
+    ));
+}
 
-        Ok(block)
-    });
+/// Adds a method assembling the upwind fluxes of the advection along `velocity` over the faces
+/// into `target`, the unknowns only leaving the domain through the boundary.
+fn add_upwind_assembly(
+    block: &mut BuildingBlock,
+    target: &str,
+    element: &str,
+    dof_handler: &str,
+    velocity: &str,
+) {
+    block.add_includes(&[
+        "deal.II/fe/fe_interface_values.h",
+        "deal.II/meshworker/mesh_loop.h",
+        "deal.II/meshworker/scratch_data.h",
+    ]);
+    let mapping = mapping(element);
+    let quadrature = quadrature(element);
+    let face_quadrature = face_quadrature(element);
+
+    block
+        .methods_defs
+        .push(format!("void assemble_{target}_faces()"));
+    block.methods_impls.push(format!(
+        r"
+void Sim::assemble_{target}_faces() {{
+  struct CopyDataFace {{
+    FullMatrix<data_type> cell_matrix;
+    std::vector<types::global_dof_index> joint_dof_indices;
+  }};
+  struct CopyData {{
+    std::vector<CopyDataFace> face_data;
+  }};
+  using Iterator = typename DoFHandler<dim>::active_cell_iterator;
+
+  const auto velocity_at = [&](const Point<dim> &point) {{
+    Tensor<1, dim> velocity;
+    for (unsigned int d = 0; d < dim; ++d)
+      velocity[d] = {velocity}.value(point, d);
+    return velocity;
+  }};
+
+  const auto cell_worker = [](const Iterator &, MeshWorker::ScratchData<dim> &,
+                              CopyData &copy_data) {{ copy_data.face_data.clear(); }};
+  const auto boundary_worker = [&](const Iterator &cell, const unsigned int &f,
+                                   MeshWorker::ScratchData<dim> &scratch_data,
+                                   CopyData &copy_data) {{
+    const FEFaceValuesBase<dim> &fe_face = scratch_data.reinit(cell, f);
+    const auto &points = fe_face.get_quadrature_points();
+    const auto &normals = fe_face.get_normal_vectors();
+    const unsigned int n_dofs = fe_face.dofs_per_cell;
+
+    CopyDataFace &copy_data_face = copy_data.face_data.emplace_back();
+    copy_data_face.joint_dof_indices.resize(n_dofs);
+    cell->get_dof_indices(copy_data_face.joint_dof_indices);
+    copy_data_face.cell_matrix.reinit(n_dofs, n_dofs);
+    // The inflow is given by the boundary conditions
+    for (const unsigned int q : fe_face.quadrature_point_indices()) {{
+      const data_type velocity_dot_n = velocity_at(points[q]) * normals[q];
+      if (velocity_dot_n <= 0.)
+        continue;
+      for (unsigned int i = 0; i < n_dofs; ++i)
+        for (unsigned int j = 0; j < n_dofs; ++j)
+          copy_data_face.cell_matrix(i, j) += fe_face.shape_value(i, q) *
+                                              fe_face.shape_value(j, q) *
+                                              velocity_dot_n * fe_face.JxW(q);
+    }}
+  }};
+  const auto face_worker = [&](const Iterator &cell, const unsigned int &f,
+                               const unsigned int &sf, const Iterator &ncell,
+                               const unsigned int &nf, const unsigned int &nsf,
+                               MeshWorker::ScratchData<dim> &scratch_data,
+                               CopyData &copy_data) {{
+    const FEInterfaceValues<dim> &fe_iv =
+        scratch_data.reinit(cell, f, sf, ncell, nf, nsf);
+    const auto &points = fe_iv.get_quadrature_points();
+    const auto &normals = fe_iv.get_normal_vectors();
+    const unsigned int n_dofs = fe_iv.n_current_interface_dofs();
+
+    CopyDataFace &copy_data_face = copy_data.face_data.emplace_back();
+    copy_data_face.joint_dof_indices = fe_iv.get_interface_dof_indices();
+    copy_data_face.cell_matrix.reinit(n_dofs, n_dofs);
+    for (const unsigned int q : fe_iv.quadrature_point_indices()) {{
+      const data_type velocity_dot_n = velocity_at(points[q]) * normals[q];
+      for (unsigned int i = 0; i < n_dofs; ++i)
+        for (unsigned int j = 0; j < n_dofs; ++j)
+          copy_data_face.cell_matrix(i, j) +=
+              fe_iv.jump_in_shape_values(i, q) *
+              fe_iv.shape_value(velocity_dot_n > 0., j, q) * velocity_dot_n *
+              fe_iv.JxW(q);
+    }}
+  }};
+  const auto copier = [&](const CopyData &copy_data) {{
+    for (const auto &face : copy_data.face_data)
+      constraints.distribute_local_to_global(face.cell_matrix,
+                                             face.joint_dof_indices, {target});
+  }};
+
+  MeshWorker::ScratchData<dim> scratch_data(
+      {mapping}, {element}, {quadrature}, update_default, {face_quadrature},
+      update_values | update_quadrature_points | update_JxW_values |
+          update_normal_vectors);
+  MeshWorker::mesh_loop({dof_handler}.begin_active(), {dof_handler}.end(), cell_worker,
+                        copier, scratch_data, CopyData(),
+                        MeshWorker::assemble_own_cells |
+                            MeshWorker::assemble_boundary_faces |
+                            MeshWorker::assemble_own_interior_faces_once |
+                            MeshWorker::assemble_ghost_faces_once,
+                        boundary_worker, face_worker);
+  {target}.compress(VectorOperation::add);
+}}"
+    ));
+}
 
-    factory
+/// Adds a method assembling `target` from integrals over the boundary faces of the mesh on
+/// which the condition of `config` is imposed.
+///
+/// `function` weights the integrals, producing the boundary mass matrix ∫ f φ_i φ_j when
+/// `is_matrix` is set, and the boundary load vector ∫ f φ_i otherwise.
+/// For vector unknowns, each shape function is weighted by the component of `function` it
+/// belongs to, and the mass only couples the same components.
+/// The integrals are distributed with `constraints`, which must be closed by then.
+fn add_boundary_assembly(
+    block: &mut BuildingBlock,
+    target: &str,
+    function: &str,
+    is_matrix: bool,
+    config: &ApplyBoundaryConditionConfig,
+) {
+    let &ApplyBoundaryConditionConfig {
+        boundary,
+        boundary_ids,
+        dof_handler,
+        element,
+        n_components,
+        ..
+    } = config;
+    // The faces of the whole boundary are all integrated over
+    let boundary_id = boundary.and(boundary_ids.first().copied());
+    let mapping = mapping(element);
+    let face_quadrature = face_quadrature(element);
+    let skipped_face = match boundary_id {
+        Some(id) => format!("!face->at_boundary() || face->boundary_id() != {id}"),
+        None => "!face->at_boundary()".to_string(),
+    };
+    let (local, local_type, local_size, integrand) = match (is_matrix, n_components) {
+        (true, 1) => (
+            "cell_matrix",
+            "FullMatrix<data_type>",
+            "dofs_per_cell, dofs_per_cell",
+            r"for (const unsigned int j : fe_face_values.dof_indices())
+            cell_matrix(i, j) += weight * fe_face_values.shape_value(i, q) *
+                                 fe_face_values.shape_value(j, q) *
+                                 fe_face_values.JxW(q);",
+        ),
+        (false, 1) => (
+            "cell_vector",
+            "Vector<data_type>",
+            "dofs_per_cell",
+            r"cell_vector(i) += weight * fe_face_values.shape_value(i, q) *
+                            fe_face_values.JxW(q);",
+        ),
+        (true, _) => (
+            "cell_matrix",
+            "FullMatrix<data_type>",
+            "dofs_per_cell, dofs_per_cell",
+            r"for (const unsigned int j : fe_face_values.dof_indices())
+            if (fe.system_to_component_index(i).first ==
+                fe.system_to_component_index(j).first)
+              cell_matrix(i, j) += weight(fe.system_to_component_index(i).first) *
+                                   fe_face_values.shape_value(i, q) *
+                                   fe_face_values.shape_value(j, q) *
+                                   fe_face_values.JxW(q);",
+        ),
+        (false, _) => (
+            "cell_vector",
+            "Vector<data_type>",
+            "dofs_per_cell",
+            r"cell_vector(i) += weight(fe.system_to_component_index(i).first) *
+                            fe_face_values.shape_value(i, q) *
+                            fe_face_values.JxW(q);",
+        ),
+    };
+    let (weight_declaration, weight) = match n_components {
+        1 => (
+            String::new(),
+            format!(
+                "const data_type weight = {function}.value(fe_face_values.quadrature_point(q));"
+            ),
+        ),
+        _ => (
+            format!(
+                "
+  const FiniteElement<dim> &fe = {element};
+  Vector<data_type> weight({n_components});"
+            ),
+            format!("{function}.vector_value(fe_face_values.quadrature_point(q), weight);"),
+        ),
+    };
+
+    if is_matrix {
+        block.additional_matrixes.insert(target.to_string());
+        block.add_includes(&["deal.II/lac/full_matrix.h"]);
+    } else {
+        block.additional_vectors.insert(target.to_string());
+    }
+    block.methods_defs.push(format!("void assemble_{target}()"));
+    block.methods_impls.push(format!(
+        r"
+void Sim::assemble_{target}() {{
+  FEFaceValues<dim> fe_face_values({mapping}, {element}, {face_quadrature},
+                                   update_values | update_quadrature_points |
+                                       update_JxW_values);
+
+  const unsigned int dofs_per_cell = {element}.n_dofs_per_cell();
+  {local_type} {local}({local_size});
+  std::vector<types::global_dof_index> local_dof_indices(dofs_per_cell);{weight_declaration}
+
+  {target} = 0;
+  for (const auto &cell : {dof_handler}.active_cell_iterators()) {{
+    if (!cell->is_locally_owned())
+      continue;
+
+    for (const auto &face : cell->face_iterators()) {{
+      if ({skipped_face})
+        continue;
+
+      fe_face_values.reinit(cell, face);
+      {local} = 0;
+      for (const unsigned int q : fe_face_values.quadrature_point_indices()) {{
+        {weight}
+        for (const unsigned int i : fe_face_values.dof_indices())
+          {integrand}
+      }}
+
+      cell->get_dof_indices(local_dof_indices);
+      constraints.distribute_local_to_global({local}, local_dof_indices, {target});
+    }}
+  }}
+  {target}.compress(VectorOperation::add);
+}}"
+    ));
 }
 
 fn equation_to_deall_ii_setup_code(
     equation: &Equation,
     unknown: &dyn Expr,
+    system_mat: &str,
     vectors: &[&dyn Expr],
     matrixes: &[&dyn Expr],
+    hoist: &mut dyn FnMut(&dyn Expr) -> Box<dyn Expr>,
     GenConfig {
         mpi, matrix_free, ..
     }: &GenConfig,
-) -> Result<String, ExprCodeGenError> {
+) -> Result<[String; 2], ExprCodeGenError> {
     let lhs = equation.lhs.expand().factor(&[unknown]);
     let lhs = lhs.as_mul();
     if lhs.is_none() {
@@ -532,39 +3299,179 @@ fn equation_to_deall_ii_setup_code(
         })?;
     }
     let lhs = lhs.unwrap();
-    if lhs.operands.len() != 2 {
+    // The operator applied to the unknown is the product of the other factors, such as a
+    // parameter scaling a single matrix
+    let (unknowns, operator): (Vec<_>, Vec<_>) =
+        lhs.operands.iter().partition(|op| op.get_ref() == unknown);
+    if unknowns.len() != 1 {
         Err(ExprCodeGenError::UnsupportedEquation {
-            reason: "internal error: lhs is not a multiplication with two operands".into(),
+            reason: "internal error: lhs is not an operator applied to the unknown".into(),
             equation: equation.clone(),
         })?;
     }
-    let system_mat = format!("matrix_{}", unknown.to_cpp());
-    let system = mat_code_gen(&system_mat, lhs.operands[0].get_ref(), vectors, matrixes)?;
+    let operator = match operator.as_slice() {
+        [operator] => operator.clone_box(),
+        _ => Box::new(Mul::new(operator.iter().map(|op| op.get_ref()))),
+    };
+    let system = hoist(operator.get_ref());
+    let system = mat_code_gen(system_mat, system.get_ref(), vectors, matrixes)?;
 
-    let rhs = equation.rhs.get_ref();
-    let rhs = rhs_code_gen(rhs, vectors, matrixes)?;
+    // Matrix-vector products only read the owned values, the vectors need no ghost dofs
+    let rhs = hoist(equation.rhs.get_ref());
+    let rhs = rhs_code_gen(rhs.get_ref(), vectors, matrixes)?;
 
-    Ok(if *mpi {
-        format!(
-            "// ## Compute system for {unknown}
+    // Matrix free operators and vectors are not assembled, nothing needs to be compressed
+    Ok(if *mpi && !*matrix_free {
+        [
+            format!(
+                "// ## Compute system for {unknown}
 {system}
 
-{system_mat}.compress(VectorOperation::add);
-
-
-// ## Compute rhs for {unknown}
+{system_mat}.compress(VectorOperation::add);"
+            ),
+            format!(
+                "// ## Compute rhs for {unknown}
 {rhs}
 
 rhs.compress(VectorOperation::add);
 "
-        )
+            ),
+        ]
     } else {
-        format!(
-            "// ## Compute system for {unknown}\n{system}\n\n\n// ## Compute rhs for {unknown}\n{rhs}"
-        )
+        [
+            format!("// ## Compute system for {unknown}\n{system}"),
+            format!("// ## Compute rhs for {unknown}\n{rhs}"),
+        ]
     })
 }
 
+/// Whether `expr` only combines numbers, the `parameters`, the time step and the mathematical
+/// constants, its value being the same for the whole simulation.
+fn is_parameter_only(expr: &dyn Expr, parameters: &[&str]) -> bool {
+    match expr.known_expr() {
+        KnownExpr::Integer(_) | KnownExpr::Rational(_) => true,
+        KnownExpr::Symbol(Symbol { name }) => {
+            ["k", "pi", "e"].contains(&name.as_str()) || parameters.contains(&name.as_str())
+        }
+        KnownExpr::Add(Add { operands }) | KnownExpr::Mul(Mul { operands }) => operands
+            .iter()
+            .all(|op| is_parameter_only(op.get_ref(), parameters)),
+        KnownExpr::Pow(pow) => {
+            is_parameter_only(pow.base(), parameters)
+                && is_parameter_only(pow.exponent(), parameters)
+        }
+        _ => false,
+    }
+}
+
+/// Replaces the parameter-only factors of the products found in `expr` by named constants.
+///
+/// The constants are registered in `constants`, indexed by their C++ value so that identical
+/// coefficients share the same constant.
+fn hoist_constants(
+    expr: &dyn Expr,
+    prefix: &str,
+    parameters: &[&str],
+    constants: &mut IndexMap<String, (Symbol, Box<dyn Expr>)>,
+) -> Box<dyn Expr> {
+    let is_constant = |op: &dyn Expr| -> bool { is_parameter_only(op, parameters) };
+    let is_atom = |op: &dyn Expr| -> bool {
+        matches!(
+            op.known_expr(),
+            KnownExpr::Symbol(_) | KnownExpr::Integer(_) | KnownExpr::Rational(_)
+        )
+    };
+
+    match expr.known_expr() {
+        KnownExpr::Add(Add { operands }) => Box::new(Add::new_v2(
+            operands
+                .iter()
+                .map(|op| hoist_constants(op.get_ref(), prefix, parameters, constants))
+                .collect(),
+        )),
+        KnownExpr::Mul(Mul { operands }) => {
+            let (coeff, rest): (Vec<_>, Vec<_>) =
+                operands.iter().partition(|op| is_constant(op.get_ref()));
+            let mut operands: Vec<Box<dyn Expr>> = rest
+                .into_iter()
+                .map(|op| hoist_constants(op.get_ref(), prefix, parameters, constants))
+                .collect();
+
+            match coeff.as_slice() {
+                [] => (),
+                [op] if is_atom(op.get_ref()) => operands.insert(0, op.clone_box()),
+                _ => {
+                    let value = Mul::new_move(coeff.iter().map(|op| op.clone_box()).collect());
+                    let value_cpp = value.to_cpp();
+                    let index = constants.len();
+                    let (constant, _) = constants.entry(value_cpp).or_insert_with(|| {
+                        (
+                            Symbol::new(&format!("{prefix}_c{index}")),
+                            value.clone_box(),
+                        )
+                    });
+                    operands.insert(0, constant.clone_box());
+                }
+            }
+
+            if operands.len() == 1 {
+                operands.pop().unwrap()
+            } else {
+                Box::new(Mul::new_move(operands))
+            }
+        }
+        _ => expr.clone_box(),
+    }
+}
+
+/// Replaces the operators combining several matrices applied to vectors in the products found in
+/// `expr` by named matrices, so that they can be assembled once.
+///
+/// The operators are registered in `operators`, indexed by their C++ value so that identical
+/// operators share the same matrix.
+fn hoist_operators(
+    expr: &dyn Expr,
+    prefix: &str,
+    vectors: &[&dyn Expr],
+    matrixes: &[&dyn Expr],
+    operators: &mut IndexMap<String, (Symbol, Box<dyn Expr>)>,
+) -> Box<dyn Expr> {
+    let is_operator = |op: &dyn Expr| -> bool {
+        !matrixes.contains(&op)
+            && matrixes.iter().any(|m| op.has(*m))
+            && vectors.iter().all(|v| !op.has(*v))
+    };
+
+    match expr.known_expr() {
+        KnownExpr::Add(Add { operands }) => Box::new(Add::new_v2(
+            operands
+                .iter()
+                .map(|op| hoist_operators(op.get_ref(), prefix, vectors, matrixes, operators))
+                .collect(),
+        )),
+        KnownExpr::Mul(Mul { operands })
+            if operands.iter().any(|op| vectors.contains(&op.get_ref())) =>
+        {
+            Box::new(Mul::new_move(
+                operands
+                    .iter()
+                    .map(|op| {
+                        if !is_operator(op.get_ref()) {
+                            return op.clone_box();
+                        }
+                        let index = operators.len();
+                        let (matrix, _) = operators.entry(op.to_cpp()).or_insert_with(|| {
+                            (Symbol::new(&format!("{prefix}_mat{index}")), op.clone_box())
+                        });
+                        matrix.clone_box()
+                    })
+                    .collect(),
+            ))
+        }
+        _ => expr.clone_box(),
+    }
+}
+
 type ExprCodeGenRes = Result<StringWKind, ExprCodeGenError>;
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -1039,6 +3946,7 @@ mod tests {
     use symrs::{symbol, symbols};
 
     use super::*;
+    use crate::codegen::building_block::BlockRes;
 
     #[test]
     fn test_rhs_zero() {
@@ -1250,6 +4158,28 @@ system.add((-1./4.) * (c * c), laplace_mat);
         .trim();
         assert_eq!(res, expected)
     }
+    #[test]
+    fn test_scaled_operator_system() {
+        // The steady wave equation (c^2)A.U = F
+        let [c, laplace_mat, u, f] = symbols!("c", "laplace_mat", "u", "f");
+        let equation = Equation {
+            lhs: c.ipow(2) * laplace_mat * u,
+            rhs: f.clone_box(),
+        };
+        let [system, rhs] = equation_to_deall_ii_setup_code(
+            &equation,
+            u,
+            "matrix_u",
+            &[u, f],
+            &[laplace_mat],
+            &mut |e| e.clone_box(),
+            &GenConfig::default(),
+        )
+        .unwrap();
+        assert!(system.contains("matrix_u.copy_from(laplace_mat); matrix_u *= c * c;"));
+        assert!(rhs.contains("rhs = f;"));
+    }
+
     #[test]
     fn test_symbol() {
         // let eq = Equation::new(&Symbol::new("u"), &Integer::new(1));
@@ -1277,4 +4207,327 @@ rhs += (-1./2.) * time_step;
         .trim();
         assert_eq!(res, expected)
     }
+
+    #[test]
+    fn test_hoist_constants() {
+        let [k, mass_mat, c, laplace_mat, u_prev] =
+            symbols!("k", "mass_mat", "c", "laplace_mat", "u_prev");
+
+        let expr = (-(Integer::one_box() / k) * mass_mat
+            + Rational::new_box(1, 4) * c.ipow(2) * k.ipow(2) * laplace_mat)
+            * u_prev
+            + Rational::new_box(1, 4) * c.ipow(2) * k.ipow(2) * mass_mat * u_prev;
+        let mut constants = IndexMap::new();
+        let res = hoist_constants(expr.get_ref(), "equation_0", &["c"], &mut constants);
+
+        assert_eq!(
+            constants.keys().collect_vec(),
+            [
+                "-(1 / time_step)",
+                "(1./4.) * (c * c) * (time_step * time_step)"
+            ]
+        );
+        assert_eq!(
+            res.to_cpp(),
+            "(equation_0_c0 * mass_mat + equation_0_c1 * laplace_mat) * u_prev + equation_0_c1 * mass_mat * u_prev"
+        );
+    }
+
+    #[test]
+    fn test_hoist_constants_keeps_time_dependent_coefficients() {
+        let [k, c, t, mass_mat, u_prev] = symbols!("k", "c", "t", "mass_mat", "u_prev");
+        let sin_t = Func::new_move_box("sin".to_string(), vec![t.clone_box()]);
+
+        let expr = Rational::new_box(1, 2) * c * k * t * mass_mat * u_prev
+            + Rational::new_box(1, 2) * k * sin_t * mass_mat * u_prev;
+        let mut constants = IndexMap::new();
+        let res = hoist_constants(expr.get_ref(), "equation_0", &["c"], &mut constants);
+
+        // Only the parameter-only part of the coefficients is computed once
+        assert_eq!(
+            constants.keys().collect_vec(),
+            ["(1./2.) * c * time_step", "(1./2.) * time_step"]
+        );
+        assert_eq!(
+            res.to_cpp(),
+            "equation_0_c0 * t * mass_mat * u_prev + equation_0_c1 * std::sin(t) * mass_mat * u_prev"
+        );
+    }
+
+    fn apply_boundary_condition(
+        condition: BoundaryConditionKind,
+        gen_config: &GenConfig,
+    ) -> BlockRes {
+        // Crank-Nicolson weights of the Laplacian, the boundary integrals being weighted by k
+        let k = symbol!("k");
+        let laplace_coefficients = LaplaceCoefficients {
+            current: Rational::new_box(1, 2) * k,
+            previous: Rational::new_box(-1, 2) * k,
+        };
+        deal_ii_factory().apply_boundary_condition(
+            "apply_boundary_condition_u",
+            &ApplyBoundaryConditionConfig {
+                condition,
+                boundary: Some("left"),
+                boundary_ids: &[1],
+                dof_handler: "dof_handler",
+                element: "fe",
+                matrix: "system_matrix_u",
+                solution: "u",
+                previous: "u_prev",
+                rhs: "system_rhs_u",
+                laplace_coefficients: &laplace_coefficients,
+                n_components: 1,
+            },
+            gen_config,
+        )
+    }
+
+    #[test]
+    fn test_neumann_boundary_condition() {
+        let block = apply_boundary_condition(
+            BoundaryConditionKind::Neumann { flux: "g" },
+            &GenConfig::default(),
+        )
+        .unwrap();
+
+        // The flux is added to the right hand side, weighted like the Laplacian
+        let main = block.main.join("\n");
+        assert!(main.contains("assemble_boundary_flux_u_left();"));
+        assert!(main.contains("system_rhs_u.add(time_step, boundary_flux_u_left);"));
+        assert!(!main.contains("system_matrix_u"));
+        let assembly = block.methods_impls.join("\n");
+        assert!(assembly.contains("face->boundary_id() != 1"));
+        assert!(assembly.contains("g.value(fe_face_values.quadrature_point(q))"));
+        assert!(block.additional_vectors.contains("boundary_flux_u_left"));
+    }
+
+    #[test]
+    fn test_robin_boundary_condition() {
+        let block = apply_boundary_condition(
+            BoundaryConditionKind::Robin {
+                alpha: "alpha",
+                value: "g",
+            },
+            &GenConfig::default(),
+        )
+        .unwrap();
+
+        // The alpha-weighted boundary mass is added to the matrix at the current step, and taken
+        // out of the right hand side at the previous step
+        let main = block.main.join("\n");
+        assert!(main.contains("system_matrix_u.add((1./2.) * time_step, boundary_mass_u_left);"));
+        assert!(main.contains("boundary_mass_u_left.vmult(boundary_tmp_u_left, u_prev);"));
+        assert!(main.contains(
+            "system_rhs_u.add((-1./2.) * time_step, boundary_tmp_u_left, time_step, boundary_flux_u_left);"
+        ));
+        // The boundary mass does not depend on time
+        assert_eq!(block.assembly, ["assemble_boundary_mass_u_left()"]);
+        assert!(block.additional_matrixes.contains("boundary_mass_u_left"));
+        let assembly = block.methods_impls.join("\n");
+        assert!(assembly.contains("alpha.value(fe_face_values.quadrature_point(q))"));
+        assert!(assembly.contains("cell_matrix(i, j) += weight"));
+    }
+
+    #[test]
+    fn test_natural_boundary_condition_matrix_free_unsupported() {
+        let gen_config = GenConfig {
+            matrix_free: true,
+            ..Default::default()
+        };
+        let res =
+            apply_boundary_condition(BoundaryConditionKind::Neumann { flux: "g" }, &gen_config);
+        assert!(matches!(res, Err(BuildingBlockError::Unsupported(_))));
+    }
+
+    #[test]
+    fn test_periodicity_constraints() {
+        let config = PeriodicityConstraintsConfig {
+            dof_handler: "dof_handler",
+            direction: 0,
+            boundary_ids: [0, 1],
+        };
+        let factory = deal_ii_factory();
+
+        let block = factory
+            .periodicity_constraints("periodic", &config, &GenConfig::default())
+            .unwrap();
+        assert_eq!(
+            block.setup,
+            ["DoFTools::make_periodicity_constraints(dof_handler, 0, 1, 0, constraints)"]
+        );
+
+        for gen_config in [
+            GenConfig {
+                mpi: true,
+                ..Default::default()
+            },
+            GenConfig {
+                matrix_free: true,
+                ..Default::default()
+            },
+        ] {
+            let res = factory.periodicity_constraints("periodic", &config, &gen_config);
+            assert!(matches!(res, Err(BuildingBlockError::Unsupported(_))));
+        }
+    }
+
+    fn serial_shape_matrix(
+        kind: ShapeMatrix,
+        coefficient: Option<&str>,
+        n_components: usize,
+    ) -> BuildingBlock {
+        let matrix_config = MatrixConfig {
+            sparsity_pattern: "sparsity_pattern",
+            dof_handler: "dof_handler",
+        };
+        deal_ii_factory()
+            .shape_matrix(
+                "operator_mat",
+                &ShapeMatrixConfig {
+                    dof_handler: "dof_handler",
+                    element: "fe",
+                    matrix_config: &matrix_config,
+                    kind,
+                    constrained: true,
+                    discontinuous: false,
+                    coefficient: coefficient.map(|function| ShapeMatrixCoefficient {
+                        function,
+                        previous: None,
+                    }),
+                    stabilization: None,
+                    n_components,
+                },
+                &GenConfig::default(),
+            )
+            .unwrap()
+    }
+
+    fn serial_source(n_components: usize, constrained: bool) -> BuildingBlock {
+        let vector_config = VectorConfig {
+            dof_handler: "dof_handler",
+            is_unknown: false,
+        };
+        deal_ii_factory()
+            .vector_from_function(
+                "f_vec",
+                &VectorFromFnConfig {
+                    function: "fn_f",
+                    dof_handler: "dof_handler",
+                    element: "fe",
+                    vector_config: &vector_config,
+                    iteration: TargetIteration::Current,
+                    time_dependent: true,
+                    constrained,
+                    n_components,
+                },
+                &GenConfig::default(),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn test_mass_matrix_assembly() {
+        let block = serial_shape_matrix(ShapeMatrix::Mass, None, 1);
+
+        assert_eq!(block.assembly, ["assemble_operator_mat()"]);
+        let assembly = block.methods_impls.join("\n");
+        assert!(assembly.contains("void Sim::assemble_operator_mat() {"));
+        assert!(assembly.contains(
+            "cell_matrix(i, j) += fe_values.shape_value(i, q) *
+                               fe_values.shape_value(j, q) * fe_values.JxW(q);"
+        ));
+        assert!(assembly.contains(
+            "WorkStream::run(dof_handler.begin_active(), dof_handler.end(), cell_worker, copier,"
+        ));
+        assert!(assembly.contains("update_values | update_JxW_values"));
+        assert!(assembly.contains(
+            "constraints.distribute_local_to_global(copy_data.matrices[0],
+                                           copy_data.local_dof_indices[0], operator_mat);"
+        ));
+        assert!(!assembly.contains("weight"));
+    }
+
+    #[test]
+    fn test_laplace_matrix_assembly() {
+        let block = serial_shape_matrix(ShapeMatrix::Laplace, None, 1);
+
+        let assembly = block.methods_impls.join("\n");
+        assert!(assembly.contains(
+            "cell_matrix(i, j) += fe_values.shape_grad(i, q) *
+                               fe_values.shape_grad(j, q) * fe_values.JxW(q);"
+        ));
+        assert!(assembly.contains("update_gradients | update_JxW_values"));
+    }
+
+    #[test]
+    fn test_weighted_laplace_matrix_assembly() {
+        let block = serial_shape_matrix(ShapeMatrix::Laplace, Some("fn_kappa"), 1);
+
+        // The coefficient is evaluated at the quadrature points
+        let assembly = block.methods_impls.join("\n");
+        assert!(
+            assembly.contains(
+                "const data_type weight = fn_kappa.value(fe_values.quadrature_point(q));"
+            )
+        );
+        assert!(assembly.contains("cell_matrix(i, j) += weight * fe_values.shape_grad(i, q) *"));
+        assert!(
+            assembly.contains("update_gradients | update_quadrature_points | update_JxW_values")
+        );
+        // The matrix is only reassembled when the coefficient depends on time
+        assert!(block.main.is_empty());
+    }
+
+    #[test]
+    fn test_vector_laplace_matrix_assembly() {
+        let block = serial_shape_matrix(ShapeMatrix::Laplace, None, 2);
+
+        // Only the shape functions of the same component are coupled
+        let assembly = block.methods_impls.join("\n");
+        assert!(assembly.contains("const FiniteElement<dim> &fe = fe_values.get_fe();"));
+        assert!(assembly.contains(
+            "if (fe.system_to_component_index(i).first == fe.system_to_component_index(j).first)
+            cell_matrix(i, j) += fe_values.shape_grad(i, q) *
+                                 fe_values.shape_grad(j, q) * fe_values.JxW(q);"
+        ));
+    }
+
+    #[test]
+    fn test_source_assembly() {
+        let block = serial_source(1, true);
+
+        assert_eq!(block.assembly, ["assemble_f_vec()"]);
+        let assembly = block.methods_impls.join("\n");
+        assert!(assembly.contains("Vector<data_type> &cell_vector = copy_data.vectors[0];"));
+        assert!(
+            assembly
+                .contains("const data_type weight = fn_f.value(fe_values.quadrature_point(q));")
+        );
+        assert!(assembly.contains(
+            "cell_vector(i) += weight * fe_values.shape_value(i, q) *
+                          fe_values.JxW(q);"
+        ));
+        assert!(assembly.contains("update_values | update_quadrature_points | update_JxW_values"));
+        assert!(assembly.contains("constraints.distribute_local_to_global(copy_data.vectors[0],"));
+        // The source depends on time, it is reassembled at each step
+        let main = block.main.join("\n");
+        assert!(main.contains("fn_f.set_time(time);\nassemble_f_vec();"));
+    }
+
+    #[test]
+    fn test_vector_source_assembly() {
+        let block = serial_source(2, false);
+
+        // Each shape function is weighted by the component of the function it belongs to
+        let assembly = block.methods_impls.join("\n");
+        assert!(assembly.contains("Vector<data_type> weight(2);"));
+        assert!(assembly.contains("fn_f.vector_value(fe_values.quadrature_point(q), weight);"));
+        assert!(assembly.contains(
+            "cell_vector(i) += weight(fe.system_to_component_index(i).first) * fe_values.shape_value(i, q) *"
+        ));
+        assert!(
+            assembly.contains("f_vec.add(copy_data.local_dof_indices[0], copy_data.vectors[0]);")
+        );
+    }
 }
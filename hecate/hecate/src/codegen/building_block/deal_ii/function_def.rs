@@ -1,5 +1,8 @@
-use crate::codegen::input_schema::{Condition, ConditionedFunctionExpression, FunctionDef};
-use symrs::Symbol;
+use crate::codegen::input_schema::quantity::Length;
+use crate::codegen::input_schema::{
+    Condition, ConditionedFunctionExpression, FunctionDef, VectorFunction,
+};
+use symrs::{Expr, Symbol, ops::cse};
 
 pub(super) fn function_def_to_deal_ii_code(function_def: &FunctionDef) -> String {
     let substs = &[
@@ -9,10 +12,7 @@ pub(super) fn function_def_to_deal_ii_code(function_def: &FunctionDef) -> String
         [Symbol::new_box("z"), Symbol::new_box("point[2]")],
     ];
     match function_def {
-        FunctionDef::Expr(expr) => {
-            let expr_cpp = expr.subs(substs).to_cpp();
-            format!("return {expr_cpp};")
-        }
+        FunctionDef::Expr(expr) => return_code(expr.subs(substs).get_ref()),
         FunctionDef::Conditioned(conditionedFunctions) => {
             let mut res: Vec<String> = Vec::with_capacity(conditionedFunctions.len());
 
@@ -30,33 +30,21 @@ pub(super) fn function_def_to_deal_ii_code(function_def: &FunctionDef) -> String
                         )),
                     }
                 }
-                for (condition, accesser) in [(x, "point[0]"), (y, "point[1]"), (z, "point[2]")] {
-                    if let Some(condition) = condition {
-                        match condition {
-                            Condition::Value(value) => conditions
-                                .push(format!("float_equals({accesser}, {})", value.meters())),
-                            Condition::Range(range) => conditions.push(format!(
-                                "{accesser} >= {} && {accesser} <= {}",
-                                range.start.meters(),
-                                range.end.meters()
-                            )),
-                        }
-                    }
-                }
-                let expr_cpp = expr.subs(substs).to_cpp();
+                conditions.extend(coordinate_conditions(x, y, z, "point"));
+                let body = return_code(expr.subs(substs).get_ref())
+                    .lines()
+                    .map(|line| format!("  {line}"))
+                    .collect::<Vec<_>>()
+                    .join("\n");
                 if conditions.is_empty() {
-                    res.push(format!("else {{\n  return {expr_cpp};\n}}"));
+                    res.push(format!("else {{\n{body}\n}}"));
                     return res.join("\n");
                 }
                 let final_condition = conditions.join(" && ");
                 if i == 0 {
-                    res.push(format!(
-                        "if ({final_condition}) {{\n  return {expr_cpp};\n}}"
-                    ));
+                    res.push(format!("if ({final_condition}) {{\n{body}\n}}"));
                 } else {
-                    res.push(format!(
-                        "else if ({final_condition}) {{\n  return {expr_cpp};\n}}"
-                    ));
+                    res.push(format!("else if ({final_condition}) {{\n{body}\n}}"));
                 }
             }
 
@@ -64,7 +52,61 @@ pub(super) fn function_def_to_deal_ii_code(function_def: &FunctionDef) -> String
 
             res.join("\n")
         }
+        FunctionDef::Vector(VectorFunction { components }) => {
+            let mut res = vec!["switch (component) {".to_string()];
+            for (i, component) in components.iter().enumerate() {
+                res.push(format!("case {i}: {{"));
+                res.extend(
+                    function_def_to_deal_ii_code(component)
+                        .trim()
+                        .lines()
+                        .map(|line| format!("  {line}")),
+                );
+                res.push("}".to_string());
+            }
+            res.push("default:\n  return 0;\n}".to_string());
+            res.join("\n")
+        }
+    }
+}
+
+/// Generates the checks of the coordinate conditions on the C++ point `point`.
+pub(super) fn coordinate_conditions(
+    x: &Option<Condition<Length>>,
+    y: &Option<Condition<Length>>,
+    z: &Option<Condition<Length>>,
+    point: &str,
+) -> Vec<String> {
+    let mut conditions = Vec::new();
+    for (i, condition) in [x, y, z].into_iter().enumerate() {
+        let accesser = format!("{point}[{i}]");
+        if let Some(condition) = condition {
+            match condition {
+                Condition::Value(value) => {
+                    conditions.push(format!("float_equals({accesser}, {})", value.meters()))
+                }
+                Condition::Range(range) => conditions.push(format!(
+                    "{accesser} >= {} && {accesser} <= {}",
+                    range.start.meters(),
+                    range.end.meters()
+                )),
+            }
+        }
     }
+    conditions
+}
+
+/// Generates the code returning the expression, with repeated subexpressions
+/// hoisted into temporaries so they are only evaluated once per point.
+fn return_code(expr: &dyn Expr) -> String {
+    let cse = cse([expr], "tmp");
+    let mut res: Vec<String> = cse
+        .temporaries
+        .iter()
+        .map(|(symbol, value)| format!("const data_type {} = {};", symbol.to_cpp(), value.to_cpp()))
+        .collect();
+    res.push(format!("return {};", cse.exprs[0].to_cpp()));
+    res.join("\n")
 }
 
 #[cfg(test)]
@@ -87,4 +129,35 @@ mod tests {
         let res = function_def_to_deal_ii_code(&function_def);
         assert_eq!(res, "return 1;")
     }
+
+    #[test]
+    fn test_vector_function() {
+        let function_def = FunctionDef::Vector(VectorFunction {
+            components: vec![
+                FunctionDef::Expr("0".parse().unwrap()),
+                FunctionDef::Expr("-x".parse().unwrap()),
+            ],
+        });
+
+        let res = function_def_to_deal_ii_code(&function_def);
+        assert_eq!(
+            res,
+            "switch (component) {\ncase 0: {\n  return 0;\n}\ncase 1: {\n  return -point[0];\n}\ndefault:\n  return 0;\n}"
+        )
+    }
+
+    #[test]
+    fn test_function_repeated_subexpression() {
+        let function_def = FunctionDef::Expr(
+            "sin(4 * pi * t) + cos(4 * pi * t) * 4 * pi"
+                .parse()
+                .unwrap(),
+        );
+
+        let res = function_def_to_deal_ii_code(&function_def);
+        assert_eq!(
+            res,
+            "const data_type tmp0 = 4 * pi * get_time();\nreturn std::sin(tmp0) + std::cos(tmp0) * 4 * pi;"
+        )
+    }
 }
@@ -0,0 +1,131 @@
+// Linear combination of the mass and Laplace operators, evaluated cell by cell instead of
+// being assembled into a sparse matrix. It follows the interface of the sparse matrices the
+// equations are set up with, the operators being combined through their coefficients.
+class MatrixFreeOperator : public Subscriptor {
+public:
+  using VectorType = LinearAlgebra::distributed::Vector<data_type>;
+
+  void initialize(std::shared_ptr<const MatrixFree<dim, data_type>> matrix_free) {
+    data = matrix_free;
+  }
+
+  void set_coefficients(const data_type mass, const data_type laplace) {
+    mass_coefficient = mass;
+    laplace_coefficient = laplace;
+    constrained_values.clear();
+  }
+
+  void copy_from(const MatrixFreeOperator &other) {
+    data = other.data;
+    mass_coefficient = other.mass_coefficient;
+    laplace_coefficient = other.laplace_coefficient;
+    constrained_values = other.constrained_values;
+  }
+
+  MatrixFreeOperator &operator*=(const data_type factor) {
+    mass_coefficient *= factor;
+    laplace_coefficient *= factor;
+    return *this;
+  }
+
+  void add(const data_type factor, const MatrixFreeOperator &other) {
+    mass_coefficient += factor * other.mass_coefficient;
+    laplace_coefficient += factor * other.laplace_coefficient;
+  }
+
+  void add(const data_type a, const MatrixFreeOperator &A, const data_type b,
+           const MatrixFreeOperator &B) {
+    add(a, A);
+    add(b, B);
+  }
+
+  types::global_dof_index m() const {
+    return data->get_vector_partitioner()->size();
+  }
+
+  // The rows of the dofs with imposed values are the identity, of the sign of the operator so
+  // that it stays definite
+  void vmult(VectorType &dst, const VectorType &src) const {
+    if (constrained_values.empty()) {
+      data->cell_loop(&MatrixFreeOperator::apply_cells, this, dst, src, true);
+      return;
+    }
+    unconstrained_src = src;
+    unconstrained_src.zero_out_ghost_values();
+    for (const auto &[dof, value] : constrained_values)
+      unconstrained_src(dof) = 0;
+    data->cell_loop(&MatrixFreeOperator::apply_cells, this, dst, unconstrained_src, true);
+    for (const auto &[dof, value] : constrained_values)
+      dst(dof) = constrained_diagonal() * src(dof);
+  }
+
+  // Imposes boundary_values on the solution, their contribution to the other rows being moved
+  // to the right-hand side. Values imposed again replace the previous ones.
+  void apply_boundary_values(
+      const std::map<types::global_dof_index, data_type> &boundary_values,
+      VectorType &solution, VectorType &rhs) {
+    VectorType lifting, lifted;
+    data->initialize_dof_vector(lifting);
+    data->initialize_dof_vector(lifted);
+    for (const auto &[dof, value] : boundary_values) {
+      if (!lifting.in_local_range(dof))
+        continue;
+      const auto previous = constrained_values.find(dof);
+      lifting(dof) = previous == constrained_values.end() ? value : value - previous->second;
+      constrained_values[dof] = value;
+    }
+    data->cell_loop(&MatrixFreeOperator::apply_cells, this, lifted, lifting, true);
+    rhs -= lifted;
+    for (const auto &[dof, value] : constrained_values) {
+      rhs(dof) = constrained_diagonal() * value;
+      solution(dof) = value;
+    }
+  }
+
+  // The inverse of the diagonal, computed cell by cell, for the Jacobi and Chebyshev
+  // preconditioners
+  std::shared_ptr<DiagonalMatrix<VectorType>> inverse_diagonal() const {
+    auto inverse_diagonal = std::make_shared<DiagonalMatrix<VectorType>>();
+    VectorType &diagonal = inverse_diagonal->get_vector();
+    data->initialize_dof_vector(diagonal);
+    MatrixFreeTools::compute_diagonal(*data, diagonal, &MatrixFreeOperator::integrate, this);
+    for (const auto &[dof, value] : constrained_values)
+      diagonal(dof) = constrained_diagonal();
+    for (auto &entry : diagonal)
+      entry = 1. / entry;
+    return inverse_diagonal;
+  }
+
+private:
+  data_type constrained_diagonal() const {
+    return (mass_coefficient != 0 ? mass_coefficient : laplace_coefficient) < 0 ? -1 : 1;
+  }
+
+  void integrate(FEEvaluation<dim, -1> &phi) const {
+    phi.evaluate(EvaluationFlags::values | EvaluationFlags::gradients);
+    for (unsigned int q = 0; q < phi.n_q_points; ++q) {
+      phi.submit_value(mass_coefficient * phi.get_value(q), q);
+      phi.submit_gradient(laplace_coefficient * phi.get_gradient(q), q);
+    }
+    phi.integrate(EvaluationFlags::values | EvaluationFlags::gradients);
+  }
+
+  void apply_cells(const MatrixFree<dim, data_type> &matrix_free, VectorType &dst,
+                   const VectorType &src,
+                   const std::pair<unsigned int, unsigned int> &cell_range) const {
+    FEEvaluation<dim, -1> phi(matrix_free);
+    for (unsigned int cell = cell_range.first; cell < cell_range.second; ++cell) {
+      phi.reinit(cell);
+      phi.read_dof_values(src);
+      integrate(phi);
+      phi.distribute_local_to_global(dst);
+    }
+  }
+
+  std::shared_ptr<const MatrixFree<dim, data_type>> data;
+  data_type mass_coefficient = 0;
+  data_type laplace_coefficient = 0;
+  // Values imposed on locally owned dofs, such as the Dirichlet boundary values
+  std::map<types::global_dof_index, data_type> constrained_values;
+  mutable VectorType unconstrained_src;
+};
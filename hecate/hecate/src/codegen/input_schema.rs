@@ -3,8 +3,8 @@ use crate::codegen::building_block::{ApplyBoundaryConditionConfig, InitialCondit
 use crate::codegen::input_schema::quantity::{NO_REF_QUANTITY_PATTERN, QuantityEnum};
 use crate::codegen::input_schema::unit::format_unit;
 use derive_more::{Deref, DerefMut, From, FromStr, IntoIterator};
-use indexmap::IndexMap as BaseIndexMap;
-use itertools::Itertools;
+use indexmap::{IndexMap as BaseIndexMap, IndexSet};
+use itertools::{Itertools, iproduct};
 use lazy_static::lazy_static;
 use log::debug;
 use regex::Regex;
@@ -21,27 +21,52 @@ use std::path::Path;
 use std::str::FromStr;
 use std::sync::LazyLock;
 use symrs::ops::ParseExprError;
-use symrs::system::SystemError;
+use symrs::system::{Coefficient, SystemError, WeightedOperator};
 pub trait RawRepr {
     fn raw(&self) -> &str;
 }
 
+pub mod boundary;
+pub mod checkpoint;
+pub mod dimension;
+pub mod element;
 pub mod mesh;
+pub mod monitor;
+pub mod output;
 pub mod quantity;
 pub mod range;
 mod reference;
+pub mod refinement;
+pub mod solver;
+pub mod stabilization;
+pub mod time_scheme;
 mod unit;
 
+use boundary::{
+    BoundaryCondition, BoundaryConditions, PartialCondition, PeriodicCondition,
+    RESERVED_BOUNDARY_NAMES, operator_coefficient,
+};
+use checkpoint::{Checkpoint, CheckpointError};
+use dimension::{Dimension, DimensionChecker, DimensionError};
+pub use element::FiniteElement;
+use element::{ElementComponent, ElementError};
 use quantity::{Length, RANGE_PATTERN, Time};
 
 use mesh::MeshEnum;
+use mesh::file::{CellShape, MeshFileError, MeshSource};
+use monitor::{Monitor, MonitorError};
+use output::{Output, OutputError, OutputFormat, OutputInterval};
 use range::Range;
+use refinement::{Refinement, RefinementError};
 use serde::{Deserialize, Serialize};
+use solver::{LinearSolver, SolverError};
+use stabilization::{Stabilization, StabilizationError, StabilizationMethod};
 use tera::Tera;
 use thiserror::Error;
+use time_scheme::{TimeScheme, TimeSchemeError};
 
 use crate::codegen::building_block::deal_ii_factory;
-use symrs::{Equation, Expr, Func, Symbol, System, symbol};
+use symrs::{Add, Equation, Expr, Func, Integer, KnownExpr, Mul, Pow, Symbol, System, symbol};
 
 #[derive(Deref, DerefMut, Deserialize, Serialize, Clone, Debug, IntoIterator, From)]
 #[from(forward)]
@@ -61,6 +86,15 @@ where
     }
 }
 
+impl<K, V> Default for IndexMap<K, V>
+where
+    K: Eq + Hash,
+{
+    fn default() -> Self {
+        IndexMap::new()
+    }
+}
+
 impl<K, V> JsonSchema for IndexMap<K, V>
 where
     K: Eq + Hash + JsonSchema,
@@ -76,8 +110,10 @@ where
 }
 
 use super::building_block::{
-    Block, BlockRes, BuildingBlockFactory, EquationSetupConfig, ShapeMatrix, ShapeMatrixConfig,
-    SolveUnknownConfig,
+    Block, BlockRes, BoundaryConditionKind, BuildingBlockFactory, CheckpointConfig,
+    EquationSetupConfig, ErrorsConfig, LaplaceCoefficients, MonitorConfig, MonitorQuantity,
+    PeriodicityConstraintsConfig, RefinementConfig, ShapeMatrix, ShapeMatrixCoefficient,
+    ShapeMatrixConfig, SolveUnknownConfig, TargetIteration, VectorFromFnConfig, VectorOutputConfig,
 };
 use super::{
     BuildingBlock,
@@ -86,16 +122,6 @@ use super::{
     },
 };
 
-/// # Finite Element
-/// The finite element to use for the mesh.
-#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, Default)]
-pub enum FiniteElement {
-    Q1,
-    #[default]
-    Q2,
-    Q3,
-}
-
 /// # Solve
 /// The equation(s) to solve and the mesh to use.
 #[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
@@ -108,6 +134,9 @@ pub struct Solve {
     /// The mesh to use
     pub mesh: String,
 
+    // Element families with a degree are written as single key maps, such as `DGQ: 1`
+    #[serde(with = "serde_yaml::with::singleton_map_recursive")]
+    #[schemars(with = "FiniteElement")]
     pub element: FiniteElement,
 
     /// # Dimension
@@ -116,14 +145,58 @@ pub struct Solve {
     #[serde(default = "default_dimension")]
     pub dimension: usize,
 
+    /// # Kind
+    /// Whether the problem evolves in time or is solved for its steady state.
+    #[serde(default)]
+    pub kind: SolveKind,
+
     /// # Time
     /// The time range to solve.
+    /// The steady state is solved at the start of the range.
     #[serde(default = "default_solving_range")]
     pub time: Range<Time>,
 
     /// # Time Step
     /// The time step to use.
-    pub time_step: Time,
+    /// Required unless the problem is steady.
+    #[serde(default)]
+    pub time_step: Option<Time>,
+
+    /// # Time Scheme
+    /// The discretization of the time derivatives, Crank-Nicolson by default.
+    /// Written as a single key map for the θ-scheme, such as `theta: 0.6`
+    #[serde(default, with = "serde_yaml::with::singleton_map")]
+    #[schemars(with = "TimeScheme")]
+    pub time_scheme: TimeScheme,
+}
+
+/// # Solve Kind
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SolveKind {
+    /// # Transient
+    /// Steps through the time range from the initial conditions of the unknowns.
+    #[default]
+    Transient,
+
+    /// # Steady
+    /// Drops the time derivatives of the equations, solving them once for the steady state.
+    Steady,
+
+    /// # Steady Then Transient
+    /// Solves the steady state first, then steps through the time range from it instead of
+    /// the initial conditions of the unknowns.
+    SteadyThenTransient,
+}
+
+impl SolveKind {
+    pub fn is_steady(&self) -> bool {
+        matches!(self, SolveKind::Steady | SolveKind::SteadyThenTransient)
+    }
+
+    pub fn is_transient(&self) -> bool {
+        matches!(self, SolveKind::Transient | SolveKind::SteadyThenTransient)
+    }
 }
 
 fn default_dimension() -> usize {
@@ -144,10 +217,19 @@ pub struct GenConfig {
     pub mpi: bool,
 
     /// # Matrix Free
-    /// Whether to generate matrix free code.
+    /// Whether to generate matrix free code, evaluating the operators cell by cell instead of
+    /// assembling their matrices.
+    /// Only the mass and Laplace operators of scalar unknowns discretized with continuous
+    /// Lagrange elements are supported, with Dirichlet boundary conditions.
     #[serde(default)]
     pub matrix_free: bool,
 
+    /// # Threads
+    /// The number of threads assembling the operators on each process, all the cores being
+    /// used when left out.
+    #[serde(default)]
+    pub threads: Option<usize>,
+
     /// # Debug
     /// Whether to generate debug code.
     #[serde(default)]
@@ -160,6 +242,10 @@ pub enum QuantityKind {
     Length,
     Time,
     Speed,
+    Area,
+    Volume,
+    Mass,
+    Temperature,
     DiffusionCoefficient,
     Custom,
 }
@@ -170,10 +256,31 @@ impl QuantityKind {
             QuantityKind::Length => Some("m"),
             QuantityKind::Time => Some("s"),
             QuantityKind::Speed => Some("m/s"),
+            QuantityKind::Area => Some("m²"),
+            QuantityKind::Volume => Some("m³"),
+            QuantityKind::Mass => Some("kg"),
+            QuantityKind::Temperature => Some("K"),
             QuantityKind::DiffusionCoefficient => Some("m²/s"),
             QuantityKind::Custom => None,
         }
     }
+
+    /// The dimension of the quantity kind, if it is known.
+    pub fn dimension(&self) -> Option<Dimension> {
+        let length = Dimension::LENGTH;
+        let time = Dimension::TIME;
+        match self {
+            QuantityKind::Length => Some(length),
+            QuantityKind::Time => Some(time),
+            QuantityKind::Speed => Some(length / time),
+            QuantityKind::Area => Some(length.powi(2)),
+            QuantityKind::Volume => Some(length.powi(3)),
+            QuantityKind::Mass => Some(Dimension::MASS),
+            QuantityKind::Temperature => Some(Dimension::TEMPERATURE),
+            QuantityKind::DiffusionCoefficient => Some(length.powi(2) / time),
+            QuantityKind::Custom => None,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -329,6 +436,11 @@ pub struct InputSchema {
 
     /// # Equations
     /// The available equations.
+    /// Variable coefficients are written in divergence form, `div(kappa * grad(u))`, kappa being
+    /// a parameter or a function of space and time.
+    /// The advection along a vector function v is written `dot(v, grad(u))`.
+    /// The divergence of the linear elastic stress is written `div(sigma(u, lambda, mu))`, the
+    /// Lamé parameters being the parameters `lambda` and `mu` when left out, `div(sigma(u))`.
     pub equations: IndexMap<String, Equation>,
 
     /// # Parameters
@@ -345,6 +457,43 @@ pub struct InputSchema {
     pub functions: IndexMap<String, FunctionDef>,
 
     pub solve: Solve,
+
+    /// # Refinement
+    /// The adaptive refinement of the mesh during the simulation.
+    /// The mesh is kept as generated when left out.
+    #[serde(default)]
+    pub refinement: Option<Refinement>,
+
+    /// # Stabilization
+    /// The stabilization of the advection terms, `dot(v, grad(u))` or first order spatial
+    /// derivatives such as `dx(u)`.
+    /// The advection is not stabilized when left out.
+    #[serde(default)]
+    pub stabilization: Option<Stabilization>,
+
+    /// # Solver
+    /// The linear solver of the unknowns, each unknown completing it with its own settings.
+    #[serde(default)]
+    pub solver: LinearSolver,
+
+    /// # Output
+    /// The writing of the results, every unknown being written at every step as VTU files
+    /// when left out.
+    #[serde(default)]
+    pub output: Output,
+
+    /// # Monitors
+    /// The quantities recorded at every time step, such as values at points or energies, each
+    /// one in a CSV file of the output directory named after the monitor.
+    #[serde(default)]
+    pub monitors: IndexMap<String, Monitor>,
+
+    /// # Checkpoint
+    /// The periodic saving of the state of the time stepping, for long simulations to resume
+    /// from their latest checkpoint with `--restart <checkpoint>` instead of starting over.
+    /// No checkpoint is saved when left out.
+    #[serde(default)]
+    pub checkpoint: Option<Checkpoint>,
 }
 
 // TODO: ensure this is fine
@@ -450,6 +599,40 @@ pub enum FunctionDef {
     /// The function expressions are checked in order. Therefore, in case of an overlap, the first one will be used.
     /// If no function expressions without conditions are specified, a default value of 0 will be assumed.
     Conditioned(Vec<ConditionedFunctionExpression>),
+    /// # Vector Function
+    /// A function with several components, such as the initial value of a vector unknown.
+    Vector(VectorFunction),
+}
+
+/// # Vector Function
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct VectorFunction {
+    /// # Components
+    /// The definition of each component of the function, in order (x, y, z).
+    pub components: Vec<FunctionDef>,
+}
+
+impl FunctionDef {
+    pub fn n_components(&self) -> usize {
+        match self {
+            FunctionDef::Vector(VectorFunction { components }) => components.len(),
+            _ => 1,
+        }
+    }
+
+    /// Whether the function depends on time, through its expressions or its conditions
+    pub fn depends_on_time(&self) -> bool {
+        let t = Symbol::new("t");
+        match self {
+            FunctionDef::Expr(FunctionExpression(expr)) => expr.has(&t),
+            FunctionDef::Conditioned(items) => items
+                .iter()
+                .any(|item| item.t.is_some() || item.expr.0.has(&t)),
+            FunctionDef::Vector(VectorFunction { components }) => {
+                components.iter().any(|c| c.depends_on_time())
+            }
+        }
+    }
 }
 
 struct FunctionDefVisitor;
@@ -458,7 +641,7 @@ impl<'de> Visitor<'de> for FunctionDefVisitor {
     type Value = FunctionDef;
 
     fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-        formatter.write_str("a function expression or a conditioned function")
+        formatter.write_str("a function expression, a conditioned function or a vector function")
     }
 
     fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
@@ -498,6 +681,15 @@ impl<'de> Visitor<'de> for FunctionDefVisitor {
 
         Ok(FunctionDef::Conditioned(res))
     }
+
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::MapAccess<'de>,
+    {
+        Ok(FunctionDef::Vector(VectorFunction::deserialize(
+            serde::de::value::MapAccessDeserializer::new(map),
+        )?))
+    }
 }
 
 impl<'de> Deserialize<'de> for FunctionDef {
@@ -550,13 +742,40 @@ pub struct Unknown {
     /// # Boundary Condition
     /// The boundary condition of the unknown.
     #[serde(default)]
-    pub boundary: UnknownProperty,
+    pub boundary: BoundaryConditions,
+
+    /// # Exact Solution
+    /// The function of the exact solution of the unknown, for convergence studies.
+    /// The L², H¹ seminorm and L∞ norms of the errors are written to `errors.csv` in the output
+    /// directory at every output step.
+    #[serde(default)]
+    pub exact_solution: Option<String>,
 
     /// # Time Derivative Conditions
     /// The time derivative's conditions of the unknown.
     /// The number of derivative specified should match the max time order of the equations - 1.
     /// (ie. an equation of order 2 in time needs one derivative specified)
     pub derivative: Option<Box<Unknown>>,
+
+    /// # Quantity
+    /// The kind of quantity represented by the unknown (temperature, length...).
+    /// When specified, it is used to check the dimensional homogeneity of the equations.
+    pub quantity: Option<QuantityKind>,
+
+    /// # Rank
+    /// 0 for a scalar unknown, 1 for a vector unknown with one component per dimension, such
+    /// as a displacement.
+    /// The functions of the conditions of a vector unknown must be vector functions.
+    /// All the unknowns must have the same rank: coupled scalar and vector unknowns, such as the
+    /// velocity and pressure of Stokes, need block systems and block preconditioners, which are
+    /// not generated yet.
+    #[serde(default)]
+    pub rank: u32,
+
+    /// # Solver
+    /// The linear solver of the unknown, overriding the settings of the global solver.
+    #[serde(default)]
+    pub solver: LinearSolver,
 }
 
 pub struct ConstantFunction {
@@ -569,8 +788,13 @@ impl Unknown {
         if let UnknownProperty::FunctionName(initial) = &self.initial {
             f(initial)?;
         }
-        if let UnknownProperty::FunctionName(boundary) = &self.boundary {
-            f(boundary)?
+        for boundary in self.boundary.properties() {
+            if let UnknownProperty::FunctionName(boundary) = boundary {
+                f(boundary)?
+            }
+        }
+        if let Some(exact_solution) = &self.exact_solution {
+            f(exact_solution)?;
         }
 
         if let Some(derivative) = &self.derivative {
@@ -584,8 +808,9 @@ impl Unknown {
             && initial == s
         {
             true
-        } else if let UnknownProperty::FunctionName(boundary) = &self.boundary
-            && boundary == s
+        } else if self.boundary.properties().into_iter().any(
+            |boundary| matches!(boundary, UnknownProperty::FunctionName(boundary) if boundary == s),
+        ) || self.exact_solution.as_deref() == Some(s)
         {
             true
         } else if let Some(derivative) = &self.derivative {
@@ -600,15 +825,21 @@ impl Unknown {
         mut f: F,
     ) -> Result<(), E> {
         f(&self.initial)?;
-        // if let Some(boundary) = &self.boundary {
-        f(&self.boundary)?;
-        // }
+        for boundary in self.boundary.properties() {
+            f(boundary)?;
+        }
         if let Some(derivative) = &self.derivative {
             derivative.visit_props(f)?;
         }
         Ok(())
     }
 
+    /// The boundary conditions of the unknown and of its time derivatives.
+    pub fn boundaries(&self) -> impl Iterator<Item = (Option<&str>, &BoundaryCondition)> {
+        std::iter::successors(Some(self), |u| u.derivative.as_deref())
+            .flat_map(|u| u.boundary.conditions())
+    }
+
     pub fn visit_constants<E: StdError, F: FnMut(ConstantFunction) -> Result<(), E>>(
         &self,
         mut f: F,
@@ -645,10 +876,16 @@ pub enum CodeGenError {
     UnknownUnknown(String),
     #[error("missing boundary condition for unknown: {0}")]
     MissingBoundary(String),
+    #[error("natural boundary condition of unknown {0} requires a Laplacian of it in its equation")]
+    MissingLaplacian(String),
     #[error("missing derivative for unknown: {0}")]
     MissingDerivative(String),
     #[error("invalid expression for function expression: {0}")]
     InvalidFunctionExpression(ParseExprError),
+    #[error("invalid mesh file: {0}")]
+    MeshFile(#[from] MeshFileError),
+    #[error("invalid solver for unknown {0}: {1}")]
+    InvalidSolver(String, SolverError),
     #[error("failed to simplify system before code generation")]
     SystemSimplificationFailed(#[from] SystemError),
 }
@@ -690,6 +927,125 @@ pub enum SchemaValidationError {
     EquationNotFound(String),
     #[error("function name not found: {0}")]
     FunctionNotFound(String),
+    #[error("boundary {boundary} not found on mesh {mesh}")]
+    BoundaryNotFound { boundary: String, mesh: String },
+    #[error("invalid file of mesh {mesh}: {source}")]
+    MeshFile { mesh: String, source: MeshFileError },
+    #[error("mesh {mesh} is of dimension {found} instead of {expected}")]
+    MeshDimensionMismatch {
+        mesh: String,
+        expected: usize,
+        found: usize,
+    },
+    #[error("boundary name {0} is reserved for boundary conditions")]
+    ReservedBoundaryName(String),
+    #[error("periodic condition of unknown {0} on a named boundary needs the opposite boundary")]
+    MissingPeriodicPair(String),
+    #[error("invalid element: {0}")]
+    Element(#[from] ElementError),
+    #[error("simplex elements need a mesh read from a file, mesh {0} is made of hypercubes")]
+    SimplexElementOnHypercubes(String),
+    #[error("cells of mesh {mesh} are {found}, the element needs {expected}")]
+    ElementCellMismatch {
+        mesh: String,
+        expected: CellShape,
+        found: CellShape,
+    },
+    #[error("simplex elements do not support adaptive refinement")]
+    SimplexRefinement,
+    #[error("transient problems need a time step")]
+    MissingTimeStep,
+    #[error("adaptive refinement happens between time steps, steady problems have none")]
+    SteadyRefinement,
+    #[error("checkpoints save the time stepping, steady problems have none")]
+    SteadyCheckpoint,
+    #[error("invalid checkpoint: {0}")]
+    Checkpoint(#[from] CheckpointError),
+    #[error("invalid time scheme: {0}")]
+    TimeScheme(#[from] TimeSchemeError),
+    #[error(
+        "discontinuous elements only support natural boundary conditions, unknown {0} has a Dirichlet condition"
+    )]
+    DiscontinuousDirichlet(String),
+    #[error("element has {found} components but the unknowns have {expected}")]
+    ElementComponents { found: u32, expected: u32 },
+    #[error("unknown {unknown} is of rank {rank}, only scalar and vector unknowns are supported")]
+    UnsupportedRank { unknown: String, rank: u32 },
+    #[error(
+        "unknowns {0} are of different ranks, coupled scalar and vector unknowns (such as Stokes) need block systems, which are not supported"
+    )]
+    MixedRanks(String),
+    #[error("discontinuous elements do not support vector unknowns")]
+    DiscontinuousVector,
+    #[error(
+        "stress of equation {equation} is weighted by {coefficient}, the Lamé parameters of sigma(u, lambda, mu) must be numbers or expressions of the parameters (lambda and mu by default)"
+    )]
+    InvalidLameParameters {
+        equation: String,
+        coefficient: String,
+    },
+    #[error(
+        "function {function} has {found} components but is used for an unknown with {expected}"
+    )]
+    FunctionComponents {
+        function: String,
+        found: usize,
+        expected: usize,
+    },
+    #[error("partial condition of unknown {0} must impose existing components of a vector unknown")]
+    InvalidComponents(String),
+    #[error("invalid refinement: {0}")]
+    Refinement(#[from] RefinementError),
+    #[error("invalid output: {0}")]
+    Output(#[from] OutputError),
+    #[error("output field {0} is neither an unknown nor the time derivative of one")]
+    OutputFieldNotFound(String),
+    #[error("output field {0} is a time derivative, steady problems have none")]
+    SteadyOutputDerivative(String),
+    #[error("invalid monitor: {0}")]
+    Monitor(#[from] MonitorError),
+    #[error(
+        "field {field} of monitor {monitor} is neither an unknown nor the time derivative of one"
+    )]
+    MonitorFieldNotFound { monitor: String, field: String },
+    #[error("field {field} of monitor {monitor} is a time derivative, steady problems have none")]
+    SteadyMonitorDerivative { monitor: String, field: String },
+    #[error(
+        "coefficient {coefficient} of monitor {monitor} must be a number or an expression of the parameters"
+    )]
+    InvalidMonitorCoefficient {
+        monitor: String,
+        coefficient: String,
+    },
+    #[error("monitor name {0} is taken by the errors against the exact solutions")]
+    ErrorsMonitorName(String),
+    #[error("unknown {0} of the refinement not found")]
+    RefinedUnknownNotFound(String),
+    #[error("invalid solver: {0}")]
+    Solver(#[from] SolverError),
+    #[error("invalid stabilization: {0}")]
+    Stabilization(#[from] StabilizationError),
+    #[error("discontinuous elements are stabilized by upwind fluxes, without stabilization")]
+    DiscontinuousStabilization,
+    #[error("velocity {function} has {found} components instead of one per dimension")]
+    VelocityComponents { function: String, found: usize },
+    #[error("advection of vector unknowns is not supported")]
+    VectorAdvection,
+    #[error("matrix free code needs continuous Lagrange elements")]
+    MatrixFreeElement,
+    #[error(
+        "matrix free code only evaluates mass and Laplace operators, equation {equation} uses {operator}"
+    )]
+    MatrixFreeOperator { equation: String, operator: String },
+    #[error("matrix free code does not support {0}")]
+    MatrixFreeUnsupported(&'static str),
+    #[error("generation.threads must be at least one")]
+    NoThreads,
+    #[error("equation {equation} is not dimensionally homogeneous: {source}")]
+    DimensionMismatch {
+        equation: String,
+        source: DimensionError,
+    },
 }
 
 pub struct CodeGenRes {
@@ -697,6 +1053,8 @@ pub struct CodeGenRes {
     pub schema: String,
     pub file_name: String,
     pub cmakelists: Option<String>,
+    /// The mesh files read by the generated code, with their name next to it.
+    pub mesh_files: Vec<(String, MeshSource)>,
 }
 
 impl CodeGenRes {
@@ -712,6 +1070,17 @@ impl CodeGenRes {
         }
         let mut schema_file = File::create(dir.join("schema.hecate.yaml"))?;
         schema_file.write_all(self.schema.as_bytes())?;
+        for (file_name, source) in &self.mesh_files {
+            match source {
+                MeshSource::Path(path) => {
+                    fs::copy(path, dir.join(file_name))?;
+                }
+                MeshSource::Registered { id } => eprintln!(
+                    "Registered mesh file {id} must be copied to {}.",
+                    dir.join(file_name).display()
+                ),
+            }
+        }
 
         eprintln!("Wrote project files to {}.", dir.display());
         Ok(())
@@ -723,16 +1092,96 @@ impl InputSchema {
         let Solve {
             equations,
             mesh,
-            element: _,
+            element,
+            kind,
             time: _,
-            time_step: _,
-            dimension: _,
+            time_step,
+            time_scheme,
+            dimension,
         } = &self.solve;
 
+        if kind.is_transient() && time_step.is_none() {
+            return Err(SchemaValidationError::MissingTimeStep);
+        }
+        time_scheme.validate()?;
+        if *kind == SolveKind::Steady && self.refinement.is_some() {
+            return Err(SchemaValidationError::SteadyRefinement);
+        }
+        if let Some(checkpoint) = &self.checkpoint {
+            if *kind == SolveKind::Steady {
+                return Err(SchemaValidationError::SteadyCheckpoint);
+            }
+            checkpoint.validate()?;
+        }
         self.meshes
             .contains_key(mesh)
             .then_some(())
             .ok_or(SchemaValidationError::MeshNotFound(mesh.to_string()))?;
+        element.validate()?;
+        if element.is_simplex() {
+            if !matches!(self.meshes[mesh], MeshEnum::File(_)) {
+                return Err(SchemaValidationError::SimplexElementOnHypercubes(
+                    mesh.to_string(),
+                ));
+            }
+            if self.refinement.is_some() {
+                return Err(SchemaValidationError::SimplexRefinement);
+            }
+        }
+        if let Some((unknown, u)) = self.unknowns.iter().find(|(_, u)| u.rank > 1) {
+            return Err(SchemaValidationError::UnsupportedRank {
+                unknown: unknown.to_string(),
+                rank: u.rank,
+            });
+        }
+        if self.unknowns.values().map(|u| u.rank).unique().count() > 1 {
+            return Err(SchemaValidationError::MixedRanks(
+                self.unknowns.keys().join(", "),
+            ));
+        }
+        let n_components = self.n_components() as u32;
+        if element.n_components() != 1 && element.n_components() != n_components {
+            return Err(SchemaValidationError::ElementComponents {
+                found: element.n_components(),
+                expected: n_components,
+            });
+        }
+        if n_components > 1 && element.is_discontinuous() {
+            return Err(SchemaValidationError::DiscontinuousVector);
+        }
+        if let MeshEnum::File(file_mesh) = &self.meshes[mesh] {
+            let mesh_file_error = |source| SchemaValidationError::MeshFile {
+                mesh: mesh.to_string(),
+                source,
+            };
+            file_mesh.file_name().map_err(mesh_file_error)?;
+            let header = file_mesh.header().map_err(mesh_file_error)?;
+            if let Some(header) = &header
+                && header.dimension != *dimension
+            {
+                return Err(SchemaValidationError::MeshDimensionMismatch {
+                    mesh: mesh.to_string(),
+                    expected: *dimension,
+                    found: header.dimension,
+                });
+            }
+            let expected = if element.is_simplex() {
+                CellShape::Simplex
+            } else {
+                CellShape::Hypercube
+            };
+            if let Some(found) = header
+                .iter()
+                .flat_map(|header| header.cell_shapes())
+                .find(|shape| *shape != expected)
+            {
+                return Err(SchemaValidationError::ElementCellMismatch {
+                    mesh: mesh.to_string(),
+                    expected,
+                    found,
+                });
+            }
+        }
         let missing_eqs = equations
             .iter()
             .filter_map(|e| (!self.equations.contains_key(e)).then_some(e))
@@ -740,22 +1189,312 @@ impl InputSchema {
 
         for unknown in self.unknowns.values() {
             unknown.visit_symbols(|f_name| -> Result<(), SchemaValidationError> {
-                if !self.functions.contains_key(f_name) {
+                let Some(function) = self.functions.get(f_name) else {
                     return Err(SchemaValidationError::FunctionNotFound(f_name.to_string()));
+                };
+                if function.n_components() != self.n_components() {
+                    return Err(SchemaValidationError::FunctionComponents {
+                        function: f_name.to_string(),
+                        found: function.n_components(),
+                        expected: self.n_components(),
+                    });
                 }
                 Ok(())
             })?;
         }
 
+        let boundaries = self.meshes[mesh].get_ref().boundaries(*dimension);
+        if let Some(name) = boundaries
+            .keys()
+            .find(|name| RESERVED_BOUNDARY_NAMES.contains(&name.as_str()))
+        {
+            return Err(SchemaValidationError::ReservedBoundaryName(
+                name.to_string(),
+            ));
+        }
+        for (name, unknown) in &self.unknowns {
+            for (part, condition) in unknown.boundaries() {
+                let with = match condition {
+                    BoundaryCondition::Periodic(PeriodicCondition { with, .. }) => with.as_deref(),
+                    _ => None,
+                };
+                if part.is_some() && with.is_none() && condition.is_periodic() {
+                    return Err(SchemaValidationError::MissingPeriodicPair(name.to_string()));
+                }
+                if let BoundaryCondition::Partial(PartialCondition { components, .. }) = condition
+                    && (unknown.rank == 0
+                        || components.is_empty()
+                        || components.iter().any(|c| *c as usize >= *dimension))
+                {
+                    return Err(SchemaValidationError::InvalidComponents(name.to_string()));
+                }
+                if element.is_discontinuous()
+                    && matches!(
+                        condition,
+                        BoundaryCondition::Dirichlet(_) | BoundaryCondition::Partial(_)
+                    )
+                {
+                    return Err(SchemaValidationError::DiscontinuousDirichlet(
+                        name.to_string(),
+                    ));
+                }
+                if let Some(boundary) = part
+                    .into_iter()
+                    .chain(with)
+                    .find(|b| !boundaries.contains_key(*b))
+                {
+                    return Err(SchemaValidationError::BoundaryNotFound {
+                        boundary: boundary.to_string(),
+                        mesh: mesh.to_string(),
+                    });
+                }
+            }
+        }
+
         if missing_eqs.len() > 0 {
             return Err(SchemaValidationError::EquationNotFound(
                 missing_eqs.into_iter().join(", "),
             ));
         }
+        // The Lamé parameters weigh matrices assembled once, they must be constant
+        for name in equations {
+            for coefficient in self.stress_coefficients(&self.equations[name]) {
+                if !coefficient.is_zero()
+                    && evaluate(coefficient.get_ref(), &self.parameters).is_none()
+                {
+                    return Err(SchemaValidationError::InvalidLameParameters {
+                        equation: name.to_string(),
+                        coefficient: coefficient.str(),
+                    });
+                }
+            }
+        }
+
+        if let Some(refinement) = &self.refinement {
+            refinement.validate()?;
+            if let Some(unknown) = &refinement.unknown
+                && !self.unknowns.contains_key(unknown)
+            {
+                return Err(SchemaValidationError::RefinedUnknownNotFound(
+                    unknown.to_string(),
+                ));
+            }
+        }
+
+        self.output.validate(*dimension)?;
+        for field in self.output.fields.iter().flatten() {
+            let unknown = field.strip_prefix("dt_").unwrap_or(field);
+            if !self.unknowns.contains_key(field) && !self.unknowns.contains_key(unknown) {
+                return Err(SchemaValidationError::OutputFieldNotFound(
+                    field.to_string(),
+                ));
+            }
+            if *kind == SolveKind::Steady && !self.unknowns.contains_key(field) {
+                return Err(SchemaValidationError::SteadyOutputDerivative(
+                    field.to_string(),
+                ));
+            }
+        }
+
+        for (name, monitor) in &self.monitors {
+            monitor.validate(name, *dimension)?;
+            if Monitor::file_name(name) == ERRORS_FILE && self.has_exact_solutions() {
+                return Err(SchemaValidationError::ErrorsMonitorName(name.to_string()));
+            }
+            for field in monitor.fields().into_iter().flatten() {
+                let unknown = field.strip_prefix("dt_").unwrap_or(field);
+                if !self.unknowns.contains_key(field) && !self.unknowns.contains_key(unknown) {
+                    return Err(SchemaValidationError::MonitorFieldNotFound {
+                        monitor: name.to_string(),
+                        field: field.to_string(),
+                    });
+                }
+                if *kind == SolveKind::Steady && !self.unknowns.contains_key(field) {
+                    return Err(SchemaValidationError::SteadyMonitorDerivative {
+                        monitor: name.to_string(),
+                        field: field.to_string(),
+                    });
+                }
+            }
+            if let Monitor::Energy { terms } = monitor {
+                for term in terms {
+                    self.monitor_coefficient(name, &term.coefficient)?;
+                }
+            }
+        }
+
+        let GenConfig {
+            mpi,
+            matrix_free,
+            threads,
+            ..
+        } = self.gen_conf;
+        if threads == Some(0) {
+            return Err(SchemaValidationError::NoThreads);
+        }
+        self.solver.validate(mpi, matrix_free)?;
+        for unknown in self.unknowns.values() {
+            for unknown in std::iter::successors(Some(unknown), |u| u.derivative.as_deref()) {
+                unknown.solver.or(&self.solver).validate(mpi, matrix_free)?;
+            }
+        }
+        if matrix_free {
+            self.validate_matrix_free()?;
+        }
+
+        if let Some(stabilization) = &self.stabilization {
+            stabilization.validate()?;
+            if self.solve.element.is_discontinuous() {
+                return Err(SchemaValidationError::DiscontinuousStabilization);
+            }
+        }
+
+        // Velocities advecting the unknowns have one component per dimension
+        for (name, function) in &self.functions {
+            let advection = Symbol::new(&format!("advection_{name}"));
+            if !self
+                .solve
+                .equations
+                .iter()
+                .any(|e| self.equations[e].has(&advection))
+            {
+                continue;
+            }
+            if function.n_components() != self.solve.dimension {
+                return Err(SchemaValidationError::VelocityComponents {
+                    function: name.to_string(),
+                    found: function.n_components(),
+                });
+            }
+            if self.n_components() > 1 {
+                return Err(SchemaValidationError::VectorAdvection);
+            }
+        }
 
         // TODO validate all needed symbols by the equations are present
         // either as parameter of function
 
+        self.check_dimensions()?;
+
+        Ok(())
+    }
+
+    /// Checks that the matrix free operators can evaluate the equations to solve, which are
+    /// combinations of the mass and Laplace operators of scalar unknowns.
+    fn validate_matrix_free(&self) -> Result<(), SchemaValidationError> {
+        if self.n_components() > 1 {
+            return Err(SchemaValidationError::MatrixFreeUnsupported(
+                "vector unknowns",
+            ));
+        }
+        if !matches!(
+            self.unknowns_element(),
+            FiniteElement::Q1 | FiniteElement::Q2 | FiniteElement::Q3 | FiniteElement::Q(_)
+        ) {
+            return Err(SchemaValidationError::MatrixFreeElement);
+        }
+        if self.refinement.is_some() {
+            return Err(SchemaValidationError::MatrixFreeUnsupported(
+                "adaptive refinement",
+            ));
+        }
+        if self.checkpoint.is_some() {
+            return Err(SchemaValidationError::MatrixFreeUnsupported("checkpoints"));
+        }
+        for (_, condition) in self.unknowns.values().flat_map(|u| u.boundaries()) {
+            if condition.is_natural() {
+                return Err(SchemaValidationError::MatrixFreeUnsupported(
+                    "natural boundary conditions",
+                ));
+            }
+            if condition.is_periodic() {
+                return Err(SchemaValidationError::MatrixFreeUnsupported(
+                    "periodic boundary conditions",
+                ));
+            }
+        }
+
+        // Operators of vector unknowns, weighted by functions, and advections
+        let axes = &["x", "y", "z"][..self.solve.dimension];
+        let operators = ["grad_div", "div_eps"]
+            .into_iter()
+            .map(|operator| (Symbol::new_box(operator), operator.to_string()))
+            .chain(self.functions.keys().flat_map(|name| {
+                ["laplacian", "advection"].map(|operator| {
+                    let operator = format!("{operator}_{name}");
+                    (Symbol::new_box(&operator), operator)
+                })
+            }))
+            .chain(
+                iproduct!(self.unknowns.keys(), axes).map(|(unknown, axis)| {
+                    (
+                        Symbol::new_box(unknown).diff(axis, 1),
+                        format!("d{axis}({unknown})"),
+                    )
+                }),
+            )
+            .collect_vec();
+        for name in &self.solve.equations {
+            if let Some((_, operator)) = operators
+                .iter()
+                .find(|(operator, _)| self.equations[name].has(operator.get_ref()))
+            {
+                return Err(SchemaValidationError::MatrixFreeOperator {
+                    equation: name.to_string(),
+                    operator: operator.to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// The number of components of the unknowns, one per dimension for vector unknowns.
+    pub fn n_components(&self) -> usize {
+        match self.unknowns.values().map(|u| u.rank).max() {
+            Some(1) => self.solve.dimension,
+            _ => 1,
+        }
+    }
+
+    /// The element the unknowns are discretized with, a scalar element being repeated for each
+    /// component of vector unknowns.
+    pub fn unknowns_element(&self) -> FiniteElement {
+        let element = &self.solve.element;
+        match self.n_components() as u32 {
+            1 => element.clone(),
+            n_components if element.n_components() == n_components => element.clone(),
+            multiplicity => FiniteElement::FESystem(vec![ElementComponent {
+                element: element.clone(),
+                multiplicity,
+            }]),
+        }
+    }
+
+    /// Checks that the equations to solve are dimensionally homogeneous, using the
+    /// units of the parameters and the declared quantity of the unknowns.
+    pub fn check_dimensions(&self) -> Result<(), SchemaValidationError> {
+        let mut checker = DimensionChecker::new();
+        for (name, parameter) in &self.parameters {
+            checker.insert(name, parameter.dimension());
+        }
+        for (name, unknown) in &self.unknowns {
+            if let Some(dimension) = unknown.quantity.as_ref().and_then(|q| q.dimension()) {
+                checker.insert(name, dimension);
+            }
+        }
+
+        for name in &self.solve.equations {
+            let equation = self
+                .equations
+                .get(name)
+                .ok_or_else(|| SchemaValidationError::EquationNotFound(name.to_string()))?;
+            checker.check_equation(equation).map_err(|source| {
+                SchemaValidationError::DimensionMismatch {
+                    equation: name.to_string(),
+                    source,
+                }
+            })?;
+        }
         Ok(())
     }
 
@@ -775,48 +1514,446 @@ impl InputSchema {
             code,
             file_name: "main.cpp".to_string(),
             cmakelists,
+            mesh_files: self
+                .mesh_files()?
+                .into_iter()
+                .map(|(name, source)| (name, source.clone()))
+                .collect(),
         })
     }
 
-    pub fn generate_cpp_sources(&self) -> Result<String, CodeGenError> {
-        self.validate()?;
-        let gen_conf = &self.gen_conf;
-        let factory = deal_ii_factory();
-        let mut blocks = BuildingBlockCollector::new(&factory, gen_conf);
-        // blocks.newline();
-        // blocks.comment("Parameters");
-        // for (name, quantity) in &self.parameters {
-        //     blocks.create(name, Block::Parameter(quantity.si_value()))?;
-        // }
-        blocks.newline();
+    /// The mesh files read by the generated code, with the name they must have next to it.
+    pub fn mesh_files(&self) -> Result<Vec<(String, &MeshSource)>, MeshFileError> {
+        Ok(match self.meshes.get(&self.solve.mesh) {
+            Some(MeshEnum::File(file_mesh)) => vec![(file_mesh.file_name()?, &file_mesh.file)],
+            _ => vec![],
+        })
+    }
 
-        let Solve {
-            equations,
-            mesh,
-            element,
-            time,
-            time_step,
-            dimension,
-        } = &self.solve;
+    /// Retrieves the configuration of an unknown (or of one of its time derivatives) from its
+    /// C++ name.
+    fn unknown_config(&self, unknown_cpp: &str) -> Result<&Unknown, CodeGenError> {
+        Ok(
+            if let Some(captures) = UNKNOWN_DT_RE.captures(unknown_cpp) {
+                self.unknowns
+                    .get(&captures[1])
+                    .ok_or_else(|| CodeGenError::UnknownUnknown(captures[1].to_string()))?
+                    .derivative
+                    .as_ref()
+                    .ok_or_else(|| CodeGenError::MissingDerivative(captures[1].to_string()))?
+            } else {
+                self.unknowns
+                    .get(unknown_cpp)
+                    .or_else(|| self.unknowns.get(&unknown_cpp.to_uppercase()))
+                    .ok_or_else(|| CodeGenError::UnknownUnknown(unknown_cpp.to_string()))?
+            },
+        )
+    }
 
-        let mesh = &self.meshes[mesh];
+    /// Retrieves the linear solver of an unknown from its C++ name, checked against the symmetry
+    /// of the system it solves.
+    fn unknown_solver(
+        &self,
+        unknown_cpp: &str,
+        symmetric: bool,
+    ) -> Result<LinearSolver, CodeGenError> {
+        let solver = self.unknown_config(unknown_cpp)?.solver.or(&self.solver);
+        solver
+            .validate_symmetry(symmetric)
+            .map_err(|e| CodeGenError::InvalidSolver(unknown_cpp.to_string(), e))?;
+        Ok(solver)
+    }
 
-        let equations = equations
-            .iter()
-            .map(|e| self.equations[e].simplify_with_dimension(*dimension))
-            .collect_vec();
+    /// Adds to `blocks` the code solving the equations of `system` for its unknowns, whose
+    /// matrices, vectors and solvers are already created.
+    fn solve_system(
+        &self,
+        blocks: &mut BuildingBlockCollector,
+        system: &System,
+        phase: &SolvePhase,
+        constant_functions: &mut HashSet<String>,
+    ) -> Result<(), CodeGenError> {
+        let SolvePhase {
+            prefix,
+            dof_handler,
+            element,
+            rhs,
+            n_components,
+            mesh_boundaries,
+            mesh_boundary_ids,
+            unknown_solvers,
+            steady_unknowns,
+            constant_unknowns,
+            constant_rhs_unknowns,
+        } = *phase;
+        let mut solved_unknowns: HashSet<&dyn Expr> = HashSet::new();
+        let vectors: &Vec<_> = &system.vectors().map(|(v, _is_unknown)| v).collect();
+        let matrixes: &Vec<_> = &system.matrixes().collect();
+        let parameters = self.parameters.keys().map(String::as_str).collect_vec();
 
-        let unknowns = self.unknowns.keys().map(|s| &s[..]).collect_vec();
-        let mut knowns = Vec::with_capacity(self.functions.len());
-        let mut substitutions = Vec::with_capacity(self.functions.len() + self.unknowns.len());
+        // Solve equations
+        for (i, equation) in system.eqs_in_solving_order().enumerate() {
+            // Iterate through unknowns in the equation to find the unknown to solve for (TODO: for now only one unknown per equation is supported)
+            for unknown in system.equation_lhs_unknowns(equation) {
+                // Solve unknowns only once
+                if solved_unknowns.contains(&unknown) {
+                    continue;
+                }
 
-        // Identify functions used for the current problem
-        let mut used_functions: IndexMap<&str, &FunctionDef> =
-            IndexMap::with_capacity(self.functions.len());
+                // Setup equation for solving the unknown
+                let unknown_cpp = unknown.to_cpp();
+                let mat_name = format!("matrix_{unknown_cpp}");
+                let pristine_matrix = format!("pristine_{mat_name}");
+                blocks.create(
+                    &format!("{prefix}equation_{i}"),
+                    Block::EquationSetup(&EquationSetupConfig {
+                        equation,
+                        unknown,
+                        vectors,
+                        matrixes,
+                        parameters: &parameters,
+                        pristine_matrix: constant_unknowns
+                            .contains(&unknown)
+                            .then_some(&pristine_matrix),
+                        constant_rhs_operators: constant_rhs_unknowns.contains(&unknown),
+                    }),
+                )?;
 
-        for (name, f) in &self.functions {
-            if equations.iter().any(|eq| eq.has(&Symbol::new(name)))
-                || self.unknowns.iter().any(|(_, u)| u.has_symbol(name))
+                // Begin boundary condition
+                // Retrive initial and boundary conditions for the unknown
+                let unknown_config = self.unknown_config(&unknown_cpp)?;
+
+                // Setup initial values, unless the unknown starts from its steady state
+                if !steady_unknowns.contains(&unknown_cpp) {
+                    let initial = &unknown_config.initial;
+                    blocks.create(
+                        &format!("initial_condition_{unknown_cpp}"),
+                        Block::InitialCondition(&InitialConditionConfig {
+                            dof_handler,
+                            element,
+                            function: &initial.to_function_name(),
+                            target: &format!("{unknown_cpp}_prev"),
+                        }),
+                    )?;
+                }
+
+                // Create constant functions associated needed by the unknown
+                unknown_config.visit_constants(
+                    |ConstantFunction { ref name, value }| -> Result<(), CodeGenError> {
+                        if constant_functions.contains(name) {
+                            return Ok(());
+                        }
+                        constant_functions.insert(name.to_string());
+                        let value = FunctionDef::Expr(
+                            value
+                                .parse()
+                                .map_err(CodeGenError::InvalidFunctionExpression)?,
+                        );
+                        // Constants of vector unknowns are the same for all the components
+                        let function = match n_components {
+                            1 => value,
+                            _ => FunctionDef::Vector(VectorFunction {
+                                components: vec![value; n_components],
+                            }),
+                        };
+                        blocks.create(name, Block::Function(&function))?;
+                        Ok(())
+                    },
+                )?;
+
+                // Weights of the boundary integrals of natural conditions
+                let previous = format!("{unknown_cpp}_prev");
+                let previous_vec = system
+                    .known_unknowns
+                    .iter()
+                    .find(|v| v.to_cpp() == previous);
+                let find_matrix = |name: &str| system.matrixes().find(|m| m.to_cpp() == name);
+                let coefficients_of = |current_mat: &str, previous_mat: &str| {
+                    let current = find_matrix(current_mat).map_or_else(
+                        || Integer::new_box(0),
+                        |m| operator_coefficient(equation.lhs.get_ref(), m, unknown),
+                    );
+                    let previous = match (find_matrix(previous_mat), previous_vec) {
+                        (Some(m), Some(v)) => operator_coefficient(equation.rhs.get_ref(), m, v),
+                        _ => Integer::new_box(0),
+                    };
+                    LaplaceCoefficients { current, previous }
+                };
+                // The stress of vector unknowns is integrated by parts instead of their Laplacian,
+                // the strain matrix being weighted by 2μ on top of the boundary integrals
+                let strain_coefficients = coefficients_of("strain_mat", "strain_mat");
+                let [_, shear] = self
+                    .solve
+                    .equations
+                    .iter()
+                    .map(|name| self.stress_coefficients(&self.equations[name]))
+                    .find(|[_, shear]| !shear.is_zero())
+                    .unwrap_or_else(|| [Integer::new_box(1), Integer::new_box(1)]);
+                let inverse_shear = shear.ipow(-1);
+                let strain_coefficients = LaplaceCoefficients {
+                    current: (strain_coefficients.current * inverse_shear.clone_box()).simplify(),
+                    previous: (strain_coefficients.previous * inverse_shear).simplify(),
+                };
+                // Weighted Laplacians are integrated by parts with their coefficient, natural
+                // conditions then imposing the conormal derivative c ∂u/∂n
+                let weighted_coefficients = system
+                    .coefficients
+                    .iter()
+                    .filter(|c| c.operator == WeightedOperator::Laplacian)
+                    .map(|coefficient| {
+                        let [current, previous] = coefficient.matrixes().map(|m| m.to_cpp());
+                        match find_matrix(&previous) {
+                            Some(_) => coefficients_of(&current, &previous),
+                            None => coefficients_of(&current, &current),
+                        }
+                    });
+                let laplace_coefficients = [strain_coefficients]
+                    .into_iter()
+                    .chain([coefficients_of("laplace_mat", "laplace_mat")])
+                    .chain(weighted_coefficients)
+                    .find(|c| !c.current.is_zero() || !c.previous.is_zero())
+                    .unwrap_or_else(|| LaplaceCoefficients {
+                        current: Integer::new_box(0),
+                        previous: Integer::new_box(0),
+                    });
+
+                let mut conditions = unknown_config.boundary.conditions();
+                conditions.sort_by_key(|(_, condition)| condition.application_rank());
+                if conditions.iter().any(|(_, c)| c.is_natural())
+                    && laplace_coefficients.current.is_zero()
+                    && laplace_coefficients.previous.is_zero()
+                {
+                    Err(CodeGenError::MissingLaplacian(unknown_cpp.to_string()))?
+                }
+
+                for (part, boundary) in conditions {
+                    // Periodic conditions are imposed by the constraints of the system
+                    if boundary.is_periodic() {
+                        continue;
+                    }
+
+                    // Retrive boundary condition functions
+                    let functions = boundary
+                        .properties()
+                        .into_iter()
+                        .map(|p| p.to_function_name())
+                        .collect_vec();
+                    let condition = match (boundary, functions.as_slice()) {
+                        (BoundaryCondition::Dirichlet(_), [function]) => {
+                            BoundaryConditionKind::Dirichlet {
+                                function,
+                                components: None,
+                            }
+                        }
+                        (
+                            BoundaryCondition::Partial(PartialCondition { components, .. }),
+                            [function],
+                        ) => BoundaryConditionKind::Dirichlet {
+                            function,
+                            components: Some(components),
+                        },
+                        (BoundaryCondition::Neumann(_), [flux]) => {
+                            BoundaryConditionKind::Neumann { flux }
+                        }
+                        (BoundaryCondition::Robin { .. }, [alpha, value]) => {
+                            BoundaryConditionKind::Robin { alpha, value }
+                        }
+                        _ => unreachable!("boundary functions match the condition properties"),
+                    };
+                    let (name, boundary_ids) = match part {
+                        Some(part) => (
+                            format!("apply_boundary_condition_{unknown_cpp}_{part}"),
+                            vec![mesh_boundaries[part]],
+                        ),
+                        None => (
+                            format!("apply_boundary_condition_{unknown_cpp}"),
+                            mesh_boundary_ids.clone(),
+                        ),
+                    };
+
+                    // Apply boundary condition
+                    blocks.newline();
+                    blocks.create(
+                        &name,
+                        Block::AppyBoundaryCondition(&ApplyBoundaryConditionConfig {
+                            condition,
+                            boundary: part,
+                            boundary_ids: &boundary_ids,
+                            dof_handler,
+                            element,
+                            matrix: &mat_name,
+                            solution: &unknown_cpp,
+                            previous: &previous,
+                            rhs,
+                            laplace_coefficients: &laplace_coefficients,
+                            n_components,
+                        }),
+                    )?;
+                }
+
+                // Solve equation for unknown
+                blocks.newline();
+                blocks.call(
+                    unknown_solvers
+                        .get(&unknown)
+                        .ok_or_else(|| CodeGenError::MissingSolver(unknown.str()))?,
+                    &[],
+                )?;
+                blocks.newline();
+                solved_unknowns.insert(unknown);
+                // End boundary condition
+            }
+        }
+        Ok(())
+    }
+
+    pub fn generate_cpp_sources(&self) -> Result<String, CodeGenError> {
+        self.validate()?;
+        let gen_conf = &self.gen_conf;
+        let factory = deal_ii_factory();
+        let mut blocks = BuildingBlockCollector::new(&factory, gen_conf);
+        // blocks.newline();
+        // blocks.comment("Parameters");
+        // for (name, quantity) in &self.parameters {
+        //     blocks.create(name, Block::Parameter(quantity.si_value()))?;
+        // }
+        blocks.newline();
+
+        let Solve {
+            equations,
+            mesh,
+            element: _,
+            kind,
+            time,
+            time_step,
+            time_scheme,
+            dimension,
+        } = &self.solve;
+
+        let mesh = &self.meshes[mesh];
+        let mesh_boundaries = mesh.get_ref().boundaries(*dimension);
+        let mesh_boundary_ids = mesh.get_ref().boundary_ids(*dimension);
+
+        let equations = equations
+            .iter()
+            .map(|e| self.equations[e].simplify_with_dimension(*dimension))
+            .collect_vec();
+
+        let unknowns = self.unknowns.keys().map(|s| &s[..]).collect_vec();
+        let mut knowns = Vec::with_capacity(self.functions.len());
+        let mut substitutions = Vec::with_capacity(self.functions.len() + self.unknowns.len());
+
+        // Laplacians weighted by a parameter are plain Laplacians scaled by the parameter, and
+        // first order spatial derivatives are advections along the axes
+        let axes = &["x", "y", "z"][..*dimension];
+        let mut operator_substitutions = self
+            .parameters
+            .keys()
+            .map(|name| {
+                [
+                    Symbol::new_box(&format!("laplacian_{name}")),
+                    Symbol::new_box(name) * Symbol::new_box("laplacian"),
+                ]
+            })
+            .collect_vec();
+        for (unknown, axis) in iproduct!(self.unknowns.keys(), axes) {
+            operator_substitutions.push([
+                Symbol::new_box(unknown).diff(axis, 1),
+                Symbol::new_box(&format!("advection_e_{axis}")) * Symbol::new_box(unknown),
+            ]);
+        }
+        let equations = equations
+            .iter()
+            .map(|e| e.subs(&operator_substitutions).as_eq().unwrap())
+            .collect_vec();
+
+        // Unit velocities along the axes
+        let axis_velocities = axes
+            .iter()
+            .enumerate()
+            .map(|(d, axis)| {
+                let components = (0..*dimension)
+                    .map(|c| {
+                        FunctionDef::Expr(FunctionExpression(Integer::new_box((c == d) as isize)))
+                    })
+                    .collect();
+                (
+                    format!("e_{axis}"),
+                    FunctionDef::Vector(VectorFunction { components }),
+                )
+            })
+            .filter(|(name, _)| {
+                let advection = Symbol::new(&format!("advection_{name}"));
+                equations.iter().any(|eq| eq.has(&advection))
+            })
+            .collect_vec();
+
+        // Operators weighted by a function are assembled into their own matrices
+        let mut coefficients = vec![];
+        let all_functions = self
+            .functions
+            .iter()
+            .chain(axis_velocities.iter().map(|(name, f)| (name, f)));
+        for (name, f) in all_functions.clone() {
+            for operator in [WeightedOperator::Laplacian, WeightedOperator::Advection] {
+                let coefficient = Coefficient::new(name, operator, f.depends_on_time());
+                if equations
+                    .iter()
+                    .any(|eq| eq.has(coefficient.symbol().get_ref()))
+                {
+                    coefficients.push(coefficient);
+                }
+            }
+        }
+
+        // SUPG tests the time derivative with the derivative of the shape functions along the
+        // velocity too
+        let supg = self
+            .stabilization
+            .is_some_and(|s| s.method == StabilizationMethod::Supg);
+        let equations = match supg {
+            true => {
+                let supg_coefficients = coefficients
+                    .iter()
+                    .filter(|c| c.operator == WeightedOperator::Advection)
+                    .map(|c| Coefficient::new(&c.name, WeightedOperator::Supg, c.time_dependent))
+                    .collect_vec();
+                let equations = equations
+                    .iter()
+                    .map(|e| {
+                        let weight = supg_coefficients
+                            .iter()
+                            .filter(|c| {
+                                let [advection, _] = [WeightedOperator::Advection, c.operator]
+                                    .map(|o| Coefficient::new(&c.name, o, false).symbol());
+                                e.has(advection.get_ref())
+                            })
+                            .fold(Integer::new_box(1), |acc, c| acc + c.symbol());
+                        let time_derivatives = self
+                            .unknowns
+                            .keys()
+                            .map(|u| {
+                                let dt = Symbol::new_box(u).diff("t", 1);
+                                [dt.clone_box(), weight.clone_box() * dt]
+                            })
+                            .collect_vec();
+                        e.subs(&time_derivatives).as_eq().unwrap()
+                    })
+                    .collect_vec();
+                coefficients.extend(supg_coefficients);
+                equations
+            }
+            false => equations,
+        };
+
+        // Identify functions used for the current problem
+        let mut used_functions: IndexMap<&str, &FunctionDef> =
+            IndexMap::with_capacity(self.functions.len());
+
+        for (name, f) in all_functions {
+            if equations.iter().any(|eq| eq.has(&Symbol::new(name)))
+                || coefficients.iter().any(|c| c.name == *name)
+                || self.unknowns.iter().any(|(_, u)| u.has_symbol(name))
             {
                 used_functions.insert(name, f);
             }
@@ -853,28 +1990,78 @@ impl InputSchema {
             .map(|e| e.subs(&substitutions).as_eq().unwrap())
             .collect_vec();
 
-        let system = System::new(unknowns, knowns, equations.iter())
-            .to_first_order_in_time()
-            .time_discretized()
-            .simplified()?
-            .matrixify()
-            .to_crank_nikolson()
-            .to_constant_mesh()
-            .simplify();
+        let base_system =
+            System::new(unknowns, knowns, equations.iter()).with_coefficients(coefficients);
+        let transient_system = || -> Result<System, SystemError> {
+            let system = base_system
+                .to_first_order_in_time()
+                .time_discretized()
+                .simplified()?
+                .matrixify();
+            let system = match time_scheme {
+                TimeScheme::ExplicitEuler => system.to_explicit_euler(),
+                TimeScheme::ImplicitEuler => system.to_implicit_euler(),
+                TimeScheme::CrankNicolson => system.to_crank_nikolson(),
+                TimeScheme::Theta(theta) => system.to_theta_scheme(*theta),
+            };
+            Ok(system.to_constant_mesh().simplify())
+        };
+        // The steady state is a single implicit step without the time derivatives
+        let steady_system = || -> System {
+            base_system
+                .to_steady()
+                .time_discretized()
+                .to_implicit_euler()
+                .separated()
+                .matrixify()
+                .to_constant_mesh()
+                .simplify()
+        };
+        let (system, steady_system) = match kind {
+            SolveKind::Transient => (transient_system()?, None),
+            SolveKind::Steady => (steady_system(), None),
+            SolveKind::SteadyThenTransient => (transient_system()?, Some(steady_system())),
+        };
+        // Unknowns solved for their steady state need no initial condition
+        let steady_unknowns: HashSet<String> = match kind {
+            SolveKind::Transient => HashSet::new(),
+            _ => steady_system
+                .as_ref()
+                .unwrap_or(&system)
+                .unknowns
+                .iter()
+                .map(|u| u.to_cpp())
+                .collect(),
+        };
 
         // blocks.comment(&format!("/*\n{system}\n*/\n"));
         debug!("System:\n{system}");
 
-        let mesh = blocks.insert("mesh", factory.mesh("mesh", mesh.get_ref(), gen_conf)?)?;
+        let mesh_block = factory.mesh("mesh", mesh.get_ref(), gen_conf)?;
+        // Named parts of the mesh are marked again on the meshes loaded from checkpoints
+        let marked = mesh_block
+            .methods_defs
+            .iter()
+            .any(|method| method == "void mark_mesh()");
+        let mesh = blocks.insert("mesh", mesh_block)?;
 
+        let n_components = self.n_components();
         let element = blocks.insert(
             "element",
-            factory.finite_element("element", element, gen_conf)?,
+            factory.finite_element("element", &self.unknowns_element(), gen_conf)?,
         )?;
 
         let dof_handler = blocks.insert(
             "dof_handler",
-            factory.dof_handler("dof_handler", &DofHandlerConfig { mesh, element }, gen_conf)?,
+            factory.dof_handler(
+                "dof_handler",
+                &DofHandlerConfig {
+                    mesh,
+                    element,
+                    adaptive: self.refinement.is_some(),
+                },
+                gen_conf,
+            )?,
         )?;
 
         let vector_config = VectorConfig {
@@ -888,28 +2075,95 @@ impl InputSchema {
 
         let mut vectors: HashMap<&dyn Expr, String> = HashMap::with_capacity(system.num_vectors());
 
+        // Vectors of the functions of the equations, at the current and the previous step
+        let function_vectors: HashMap<String, (&FunctionDef, &str, TargetIteration)> = self
+            .functions
+            .iter()
+            .filter_map(|(name, f)| Some((name, f, functions.get(&name[..])?)))
+            .flat_map(|(name, f, function)| {
+                let [previous, current] = Func::new(name, []).time_discretize();
+                [
+                    (previous, TargetIteration::Previous),
+                    (current, TargetIteration::Current),
+                ]
+                .map(|(vector, iteration)| {
+                    (vector.to_vector().to_cpp(), (f, &function[..], iteration))
+                })
+            })
+            .collect();
+        // The constraints of periodic boundaries and hanging nodes are applied when assembling
+        let constrained = self.refinement.is_some()
+            || self
+                .unknowns
+                .values()
+                .flat_map(|u| u.boundaries())
+                .any(|(_, condition)| condition.is_periodic());
+
         for (vector, is_unknown) in system.vectors() {
             let vector_cpp = vector.to_cpp();
-            blocks.insert(
-                &vector_cpp,
-                factory.vector(
+            let config = if is_unknown {
+                &unknown_config
+            } else {
+                &vector_config
+            };
+            let block = match function_vectors.get(&vector_cpp) {
+                Some(&(f, function, iteration)) if !is_unknown => factory.vector_from_function(
                     &vector_cpp,
-                    if is_unknown {
-                        &unknown_config
-                    } else {
-                        &vector_config
+                    &VectorFromFnConfig {
+                        function,
+                        dof_handler,
+                        element,
+                        vector_config: config,
+                        iteration,
+                        time_dependent: f.depends_on_time(),
+                        constrained,
+                        n_components,
                     },
                     gen_conf,
                 )?,
-            )?;
+                _ => factory.vector(&vector_cpp, config, gen_conf)?,
+            };
+            blocks.insert(&vector_cpp, block)?;
             vectors.insert(vector, vector_cpp.clone());
         }
 
+        // Periodicity constraints must be known before building the sparsity pattern
+        let mut periodic_pairs: IndexSet<(u32, [u32; 2])> = IndexSet::new();
+        for (part, condition) in self.unknowns.values().flat_map(|u| u.boundaries()) {
+            if let BoundaryCondition::Periodic(PeriodicCondition { direction, with }) = condition {
+                let boundary_ids = match (part, with) {
+                    (Some(part), Some(with)) => [mesh_boundaries[part], mesh_boundaries[with]],
+                    _ => [0, 0],
+                };
+                periodic_pairs.insert((*direction, boundary_ids));
+            }
+        }
+        for (direction, boundary_ids) in &periodic_pairs {
+            let [first, second] = boundary_ids;
+            let name = format!("periodicity_constraints_{first}_{second}_{direction}");
+            blocks.insert(
+                &name,
+                factory.periodicity_constraints(
+                    &name,
+                    &PeriodicityConstraintsConfig {
+                        dof_handler,
+                        direction: *direction,
+                        boundary_ids: *boundary_ids,
+                    },
+                    gen_conf,
+                )?,
+            )?;
+        }
+
         let sparsity_pattern = blocks.insert(
             "sparsity_pattern",
             factory.sparsity_pattern(
                 "sparsity_pattern",
-                &SparsityPatternConfig { dof_handler },
+                &SparsityPatternConfig {
+                    dof_handler,
+                    constrained,
+                    flux: self.solve.element.is_discontinuous(),
+                },
                 gen_conf,
             )?,
         )?;
@@ -923,6 +2177,46 @@ impl InputSchema {
         };
         let rhs = blocks.create("rhs", Block::Vector(&vector_config))?;
         let mut unknown_solvers: HashMap<&dyn Expr, String> = HashMap::new();
+        // Explicit steps only invert the mass matrix, which is lumped into a diagonal unless
+        // the operators are matrix free
+        let lumped_unknowns: HashSet<&dyn Expr> = system
+            .unknowns
+            .iter()
+            .map(|unknown| unknown.get_ref())
+            .filter(|unknown| {
+                kind.is_transient()
+                    && time_scheme.is_explicit()
+                    && !gen_conf.matrix_free
+                    && system
+                        .lhs_matrixes(*unknown)
+                        .map(|matrix| matrix.to_cpp())
+                        .all_equal_value()
+                        .is_ok_and(|matrix| matrix == "mass_mat")
+            })
+            .collect();
+        // Left-hand sides that are the same at every step are assembled once, unless the mesh
+        // is refined between the steps
+        let constant_unknowns: HashSet<&dyn Expr> = system
+            .unknowns
+            .iter()
+            .map(|unknown| unknown.get_ref())
+            .filter(|unknown| {
+                kind.is_transient()
+                    && self.refinement.is_none()
+                    && system.has_constant_lhs(*unknown)
+            })
+            .collect();
+        // So are the operators applied to the previous values in the right-hand sides
+        let constant_rhs_unknowns: HashSet<&dyn Expr> = system
+            .unknowns
+            .iter()
+            .map(|unknown| unknown.get_ref())
+            .filter(|unknown| {
+                kind.is_transient()
+                    && self.refinement.is_none()
+                    && system.has_constant_rhs_operators(*unknown)
+            })
+            .collect();
         for unknown in &system.unknowns {
             let unknown = unknown.get_ref();
             let unknown_cpp = unknown.to_cpp();
@@ -935,7 +2229,14 @@ impl InputSchema {
                     .create(&mat_name, Block::Matrix(&matrix_config))?
                     .to_string(),
             );
+            if constant_unknowns.contains(&unknown) {
+                blocks.create(
+                    &format!("pristine_{mat_name}"),
+                    Block::Matrix(&matrix_config),
+                )?;
+            }
 
+            let symmetric = system.is_symmetric(unknown);
             unknown_solvers.insert(
                 unknown,
                 blocks
@@ -946,6 +2247,12 @@ impl InputSchema {
                             rhs,
                             unknown_vec,
                             unknown_mat: &mat_name,
+                            constrained,
+                            vector_valued: n_components > 1,
+                            symmetric,
+                            lumped_mass: lumped_unknowns.contains(&unknown).then_some("mass_mat"),
+                            solver: self.unknown_solver(&unknown_cpp, symmetric)?,
+                            constant: constant_unknowns.contains(&unknown),
                         }),
                     )?
                     .to_string(),
@@ -961,6 +2268,11 @@ impl InputSchema {
                     dof_handler,
                     element,
                     matrix_config: &matrix_config,
+                    constrained,
+                    discontinuous: self.solve.element.is_discontinuous(),
+                    coefficient: None,
+                    stabilization: None,
+                    n_components,
                 },
                 gen_conf,
             )?,
@@ -974,128 +2286,215 @@ impl InputSchema {
                     dof_handler,
                     element,
                     matrix_config: &matrix_config,
+                    constrained,
+                    discontinuous: self.solve.element.is_discontinuous(),
+                    coefficient: None,
+                    stabilization: None,
+                    n_components,
                 },
                 gen_conf,
             )?,
         )?;
 
-        let mut solved_unknowns: HashSet<&dyn Expr> = HashSet::new();
-        let vectors: &Vec<_> = &system.vectors().map(|(v, _is_unknown)| v).collect();
-        let matrixes: &Vec<_> = &system.matrixes().collect();
+        // Operators of vector unknowns, only assembled when the equations use them
+        let uses_matrix = |name: &str| {
+            system
+                .matrixes()
+                .chain(steady_system.iter().flat_map(|s| s.matrixes()))
+                .any(|m| m.to_cpp() == name)
+        };
+        for (name, kind) in [
+            ("grad_div_mat", ShapeMatrix::GradDiv),
+            ("strain_mat", ShapeMatrix::Strain),
+        ] {
+            if uses_matrix(name) {
+                blocks.insert(
+                    name,
+                    factory.shape_matrix(
+                        name,
+                        &ShapeMatrixConfig {
+                            kind,
+                            dof_handler,
+                            element,
+                            matrix_config: &matrix_config,
+                            constrained,
+                            discontinuous: false,
+                            coefficient: None,
+                            stabilization: None,
+                            n_components,
+                        },
+                        gen_conf,
+                    )?,
+                )?;
+            }
+        }
+
+        // Operators weighted by coefficient functions
+        for coefficient in &system.coefficients {
+            let [current, previous] = coefficient.matrixes().map(|m| m.to_cpp());
+            if !uses_matrix(&current) {
+                continue;
+            }
+            let time_dependent = system.matrixes().any(|m| m.to_cpp() == previous);
+            blocks.insert(
+                &current,
+                factory.shape_matrix(
+                    &current,
+                    &ShapeMatrixConfig {
+                        kind: match coefficient.operator {
+                            WeightedOperator::Laplacian => ShapeMatrix::Laplace,
+                            WeightedOperator::Advection => ShapeMatrix::Advection,
+                            WeightedOperator::Supg => ShapeMatrix::Supg,
+                        },
+                        dof_handler,
+                        element,
+                        matrix_config: &matrix_config,
+                        constrained,
+                        discontinuous: self.solve.element.is_discontinuous(),
+                        coefficient: Some(ShapeMatrixCoefficient {
+                            function: &functions[&coefficient.name[..]],
+                            previous: time_dependent.then_some(&previous[..]),
+                        }),
+                        stabilization: self.stabilization,
+                        n_components,
+                    },
+                    gen_conf,
+                )?,
+            )?;
+        }
 
         /* Create a set to create identic constant functions only once */
         let mut constant_functions: HashSet<String> = HashSet::new();
 
-        // Solve equations
-        for (i, equation) in system.eqs_in_solving_order().enumerate() {
-            // Iterate through unknowns in the equation to find the unknown to solve for (TODO: for now only one unknown per equation is supported)
-            for unknown in system.equation_lhs_unknowns(equation) {
-                // Solve unknowns only once
-                if solved_unknowns.contains(&unknown) {
-                    continue;
-                }
+        let phase = SolvePhase {
+            prefix: "",
+            dof_handler,
+            element,
+            rhs,
+            n_components,
+            mesh_boundaries: &mesh_boundaries,
+            mesh_boundary_ids: &mesh_boundary_ids,
+            unknown_solvers: &unknown_solvers,
+            steady_unknowns: &steady_unknowns,
+            constant_unknowns: &constant_unknowns,
+            constant_rhs_unknowns: &constant_rhs_unknowns,
+        };
+        self.solve_system(&mut blocks, &system, &phase, &mut constant_functions)?;
 
-                // Setup equation for solving the unknown
-                blocks.create(
-                    &format!("equation_{i}"),
-                    Block::EquationSetup(&EquationSetupConfig {
-                        equation,
-                        unknown,
-                        vectors,
-                        matrixes,
-                    }),
-                )?;
+        // Output results
+        blocks.call("output_results", &[])?;
+        let unknowns = system.unknowns.iter().map(|u| u.to_cpp()).collect_vec();
+        let fields = match &self.output.fields {
+            Some(fields) => fields
+                .iter()
+                .map(|field| output_field(&unknowns, field))
+                .collect::<Result<Vec<_>, _>>()?,
+            None => unknowns.iter().map(|u| (u.clone(), None)).collect(),
+        };
+        for (vector, time_derivative_of) in &fields {
+            if time_derivative_of.is_some() {
+                blocks.create(vector, Block::Vector(&vector_config))?;
+            }
+            blocks.add_vector_output(&VectorOutputConfig {
+                vector,
+                dof_handler,
+                n_components,
+                time_derivative_of: time_derivative_of.as_deref(),
+            })?;
+        }
 
-                // Begin boundary condition
-                // Retrive initial and boundary conditions for the unknown
-                let unknown_cpp = unknown.to_cpp();
-                let mat_name = format!("matrix_{unknown_cpp}");
-                let unknown_config = if let Some(captures) = UNKNOWN_DT_RE.captures(&unknown_cpp) {
-                    self.unknowns
-                        .get(&captures[1])
-                        .ok_or_else(|| CodeGenError::UnknownUnknown(captures[1].to_string()))?
-                        .derivative
-                        .as_ref()
-                        .ok_or_else(|| CodeGenError::MissingDerivative(captures[1].to_string()))?
-                } else {
-                    self.unknowns
-                        .get(&unknown_cpp)
-                        .or_else(|| self.unknowns.get(&unknown_cpp.to_uppercase()))
-                        .ok_or_else(|| CodeGenError::UnknownUnknown(unknown_cpp.to_string()))?
+        // Errors against the exact solutions at every output step
+        let mut errors = Vec::new();
+        for (name, unknown) in &self.unknowns {
+            let derivatives = std::iter::successors(Some(unknown), |u| u.derivative.as_deref());
+            for (order, unknown) in derivatives.enumerate() {
+                let Some(exact_solution) = &unknown.exact_solution else {
+                    continue;
                 };
-
-                // Setup initial values
-                let initial = &unknown_config.initial;
-                blocks.create(
-                    &format!("initial_condition_{unknown_cpp}"),
-                    Block::InitialCondition(&InitialConditionConfig {
+                let field = format!("{}{name}", "dt_".repeat(order));
+                let (vector, None) = output_field(&unknowns, &field)? else {
+                    return Err(CodeGenError::UnknownUnknown(field));
+                };
+                errors.push((vector, functions[&exact_solution[..]].clone()));
+            }
+        }
+        if !errors.is_empty() {
+            let file = format!("{}{ERRORS_FILE}", self.output.path_prefix());
+            blocks.insert(
+                "write_errors",
+                factory.errors(
+                    "write_errors",
+                    &ErrorsConfig {
+                        errors: &errors,
+                        file: &file,
+                        mesh,
                         dof_handler,
                         element,
-                        function: &initial.to_function_name(),
-                        target: &format!("{unknown_cpp}_prev"),
-                    }),
-                )?;
-
-                // Create constant functions associated needed by the unknown
-                unknown_config.visit_constants(
-                    |ConstantFunction { ref name, value }| -> Result<(), CodeGenError> {
-                        if constant_functions.contains(name) {
-                            return Ok(());
-                        }
-                        constant_functions.insert(name.to_string());
-                        blocks.create(
-                            name,
-                            Block::Function(&FunctionDef::Expr(
-                                value
-                                    .parse()
-                                    .map_err(|e| CodeGenError::InvalidFunctionExpression(e))?,
-                            )),
-                        )?;
-                        Ok(())
                     },
-                )?;
-
-                // Retrive boundary condition function
-                let function = &unknown_config
-                    .boundary
-                    // .as_ref()
-                    // .or_else(|| )
-                    // .ok_or_else(|| CodeGenError::MissingBoundary(unknown_cpp.to_string()))?
-                    .to_function_name();
-
-                // Apply boundary condition
-                blocks.newline();
-                blocks.create(
-                    &format!("apply_boundary_condition_{unknown_cpp}"),
-                    Block::AppyBoundaryCondition(&ApplyBoundaryConditionConfig {
-                        dof_handler,
-                        function,
-                        matrix: &mat_name,
-                        solution: &unknown_cpp,
-                        rhs,
-                    }),
-                )?;
-
-                // Solve equation for unknown
-                blocks.newline();
-                blocks.call(
-                    unknown_solvers
-                        .get(&unknown)
-                        .ok_or_else(|| CodeGenError::MissingSolver(unknown.str()))?,
-                    &[],
-                )?;
-                blocks.newline();
-                solved_unknowns.insert(unknown);
-                // End boundary condition
-            }
+                    gen_conf,
+                )?,
+            )?;
         }
-        // End Solve Equations
 
-        // Output results
-        blocks.call("output_results", &[])?;
-        for unknown in &system.unknowns {
-            let unknown = unknown.to_cpp();
-            blocks.add_vector_output(&unknown)?;
+        // Record the monitors at every step
+        for (name, monitor) in &self.monitors {
+            let fields = match monitor.fields() {
+                Some(fields) => fields
+                    .into_iter()
+                    .map(|field| output_field(&unknowns, field))
+                    .collect::<Result<Vec<_>, _>>()?,
+                None => unknowns.iter().map(|u| (u.clone(), None)).collect(),
+            };
+            let norms;
+            let quantity = match monitor {
+                Monitor::Points { points, .. } => MonitorQuantity::Points(points),
+                Monitor::Line {
+                    from, to, samples, ..
+                } => MonitorQuantity::Line {
+                    from,
+                    to,
+                    samples: *samples,
+                },
+                Monitor::Norm { norm, .. } => {
+                    norms = vec![(*norm, 1.)];
+                    MonitorQuantity::Norms {
+                        norms: &norms,
+                        squared: false,
+                    }
+                }
+                Monitor::Energy { terms } => {
+                    norms = terms
+                        .iter()
+                        .map(|term| {
+                            Ok((
+                                term.norm,
+                                self.monitor_coefficient(name, &term.coefficient)?,
+                            ))
+                        })
+                        .collect::<Result<Vec<_>, SchemaValidationError>>()?;
+                    MonitorQuantity::Norms {
+                        norms: &norms,
+                        squared: true,
+                    }
+                }
+            };
+            let file = format!("{}{}", self.output.path_prefix(), Monitor::file_name(name));
+            blocks.insert(
+                &format!("monitor_{name}"),
+                factory.monitor(
+                    name,
+                    &MonitorConfig {
+                        quantity,
+                        fields: &fields,
+                        file: &file,
+                        mesh,
+                        dof_handler,
+                        element,
+                        n_components,
+                    },
+                    gen_conf,
+                )?,
+            )?;
         }
 
         // Swap new values with previous values to move on to the next step
@@ -1107,13 +2506,179 @@ impl InputSchema {
             blocks.call("swap", &[&unknown, &unknown_prev])?;
         }
 
+        // Adapt the mesh to the latest solution
+        if let Some(refinement) = &self.refinement {
+            let estimated = match &refinement.unknown {
+                Some(unknown) => system
+                    .unknowns
+                    .iter()
+                    .map(|u| u.to_cpp())
+                    .find(|u| u == unknown || *u == unknown.to_lowercase())
+                    .ok_or_else(|| CodeGenError::UnknownUnknown(unknown.to_string()))?,
+                None => system.unknowns[0].to_cpp(),
+            };
+            let transferred = system
+                .unknowns
+                .iter()
+                .map(|u| format!("{}_prev", u.to_cpp()))
+                .collect_vec();
+
+            blocks.newline();
+            blocks.insert(
+                "refine_mesh",
+                factory.refine_mesh(
+                    "refine_mesh",
+                    &RefinementConfig {
+                        mesh,
+                        dof_handler,
+                        element,
+                        estimated: &format!("{estimated}_prev"),
+                        transferred: &transferred,
+                        refinement,
+                    },
+                    gen_conf,
+                )?,
+            )?;
+        }
+
+        // Save the state of the time stepping to resume from
+        if let Some(checkpoint) = &self.checkpoint {
+            let saved = system
+                .unknowns
+                .iter()
+                .map(|u| format!("{}_prev", u.to_cpp()))
+                .collect_vec();
+
+            blocks.newline();
+            blocks.insert(
+                "checkpoint",
+                factory.checkpoint(
+                    "checkpoint",
+                    &CheckpointConfig {
+                        mesh,
+                        dof_handler,
+                        element,
+                        saved: &saved,
+                        prefix: &self.output.path_prefix(),
+                        time_start: time.start.seconds(),
+                        marked,
+                        checkpoint,
+                    },
+                    gen_conf,
+                )?,
+            )?;
+        }
+
+        let mut block = blocks.collect(dof_handler, sparsity_pattern)?;
+        let steady = match &steady_system {
+            // The steady state is solved before stepping through time
+            Some(steady_system) => {
+                let mut steady_blocks = BuildingBlockCollector::new(&factory, gen_conf);
+                // The steady state is solved without the mass matrix, which lumped solvers need,
+                // and with another matrix than the one constant solvers set up once
+                let mut steady_solvers = unknown_solvers.clone();
+                for &unknown in lumped_unknowns.union(&constant_unknowns) {
+                    let unknown_cpp = unknown.to_cpp();
+                    let symmetric = steady_system.is_symmetric(unknown);
+                    steady_solvers.insert(
+                        unknown,
+                        steady_blocks
+                            .create(
+                                &format!("steady_solve_{unknown_cpp}"),
+                                Block::SolveUnknown(&SolveUnknownConfig {
+                                    dof_handler,
+                                    rhs,
+                                    unknown_vec: &vectors[&unknown],
+                                    unknown_mat: &format!("matrix_{unknown_cpp}"),
+                                    constrained,
+                                    vector_valued: n_components > 1,
+                                    symmetric,
+                                    lumped_mass: None,
+                                    solver: self.unknown_solver(&unknown_cpp, symmetric)?,
+                                    constant: false,
+                                }),
+                            )?
+                            .to_string(),
+                    );
+                }
+                let steady_phase = SolvePhase {
+                    prefix: "steady_",
+                    unknown_solvers: &steady_solvers,
+                    constant_unknowns: &HashSet::new(),
+                    constant_rhs_unknowns: &HashSet::new(),
+                    ..phase
+                };
+                self.solve_system(
+                    &mut steady_blocks,
+                    steady_system,
+                    &steady_phase,
+                    &mut constant_functions,
+                )?;
+                steady_blocks.call("output_results", &[])?;
+                for name in self.monitors.keys() {
+                    steady_blocks.call(&format!("monitor_{name}"), &[])?;
+                }
+                steady_blocks.newline();
+                steady_blocks.comment("The steady state is the initial state of the time stepping");
+                for unknown in &steady_system.unknowns {
+                    let unknown = unknown.to_cpp();
+                    let unknown_prev = format!("{unknown}_prev");
+                    steady_blocks.call("swap", &[&unknown, &unknown_prev])?;
+                }
+                merge_phase(
+                    &mut block,
+                    steady_blocks.collect(dof_handler, sparsity_pattern)?,
+                )
+            }
+            None if *kind == SolveKind::Steady => std::mem::take(&mut block.main),
+            None => vec![],
+        };
+
         // Setup context for filling the template
-        let mut context: tera::Context = blocks.collect(dof_handler, sparsity_pattern)?.into();
+        let mut context: tera::Context = block.into();
+        context.insert(
+            "steady",
+            &steady
+                .into_iter()
+                .map(|line| format!("  {line}"))
+                .join("\n"),
+        );
+        context.insert("transient", &kind.is_transient());
+        context.insert("checkpoint", &self.checkpoint.is_some());
+        context.insert(
+            "time_scheme",
+            &if kind.is_transient() {
+                time_scheme.to_string()
+            } else {
+                String::new()
+            },
+        );
         context.insert("time_start", &time.start.seconds());
         context.insert("time_end", &time.end.seconds());
-        context.insert("time_step", &time_step.seconds());
+        context.insert(
+            "time_step",
+            &time_step
+                .as_ref()
+                .map_or(0., |time_step| time_step.seconds()),
+        );
         context.insert("dimension", &dimension);
         context.insert("mpi", &self.gen_conf.mpi);
+        context.insert("threads", &self.gen_conf.threads);
+
+        let output = &self.output;
+        let (output_steps, output_time) = match &output.every {
+            Some(OutputInterval::Steps(steps)) => (Some(*steps), None),
+            Some(OutputInterval::Time(time)) => (None, Some(time.seconds())),
+            None => (None, None),
+        };
+        context.insert("output_steps", &output_steps);
+        context.insert("output_time", &output_time);
+        context.insert("output_directory", &output.directory);
+        context.insert("output_prefix", &output.path_prefix());
+        context.insert("vtu", &output.has_format(OutputFormat::Vtu));
+        context.insert("hdf5", &output.has_format(OutputFormat::Hdf5));
+        context.insert("gnuplot", &output.has_format(OutputFormat::Gnuplot));
+        context.insert("compression", output.compression.level());
 
         let parameters: indexmap::IndexMap<&String, String> = self
             .parameters
@@ -1127,6 +2692,161 @@ impl InputSchema {
     }
 }
 
+/// Name of the file of the errors against the exact solutions, in the output directory.
+pub const ERRORS_FILE: &str = "errors.csv";
+
+/// Name of the file holding the path of the latest checkpoint, in the output directory.
+pub const CHECKPOINT_LATEST_FILE: &str = "checkpoint.latest";
+
+impl InputSchema {
+    /// Whether an unknown or one of its time derivatives has an exact solution.
+    pub fn has_exact_solutions(&self) -> bool {
+        self.unknowns.values().any(|unknown| {
+            std::iter::successors(Some(unknown), |u| u.derivative.as_deref())
+                .any(|u| u.exact_solution.is_some())
+        })
+    }
+
+    /// The weights of the operators of the divergence of the stress applied to the unknowns in
+    /// `equation`, λ for `grad_div` and 2μ for `div_eps` with σ(u) = λ div(u) I + 2 μ ε(u), as
+    /// they are on its right hand side.
+    fn stress_coefficients(&self, equation: &Equation) -> [Box<dyn Expr>; 2] {
+        ["grad_div", "div_eps"].map(|operator| {
+            let operator = Symbol::new(operator);
+            let mut coefficient = Integer::new_box(0);
+            for unknown in self.unknowns.keys() {
+                let unknown = Symbol::new(unknown);
+                coefficient += operator_coefficient(equation.rhs.get_ref(), &operator, &unknown)
+                    - operator_coefficient(equation.lhs.get_ref(), &operator, &unknown);
+            }
+            coefficient.simplify()
+        })
+    }
+
+    /// The weight of a term of the energy monitor `monitor`, evaluated with the values of the
+    /// parameters.
+    fn monitor_coefficient(
+        &self,
+        monitor: &str,
+        coefficient: &monitor::Coefficient,
+    ) -> Result<f64, SchemaValidationError> {
+        let expression = match coefficient {
+            monitor::Coefficient::Number(value) => return Ok(*value),
+            monitor::Coefficient::Expression(expression) => expression,
+        };
+        expression
+            .parse::<Box<dyn Expr>>()
+            .ok()
+            .and_then(|expr| evaluate(expr.get_ref(), &self.parameters))
+            .ok_or_else(|| SchemaValidationError::InvalidMonitorCoefficient {
+                monitor: monitor.to_string(),
+                coefficient: expression.to_string(),
+            })
+    }
+}
+
+/// The value of an expression of numbers and parameters, `None` when it involves anything else.
+fn evaluate(expr: &dyn Expr, parameters: &IndexMap<String, QuantityEnum>) -> Option<f64> {
+    match KnownExpr::from_expr(expr) {
+        KnownExpr::Add(Add { operands }) => operands
+            .iter()
+            .map(|operand| evaluate(operand.get_ref(), parameters))
+            .sum(),
+        KnownExpr::Mul(Mul { operands }) => operands
+            .iter()
+            .map(|operand| evaluate(operand.get_ref(), parameters))
+            .product(),
+        KnownExpr::Pow(Pow { base, exponent }) => Some(
+            evaluate(base.get_ref(), parameters)?.powf(evaluate(exponent.get_ref(), parameters)?),
+        ),
+        KnownExpr::Symbol(Symbol { name }) => parameters.get(name).map(|value| value.si_value()),
+        _ => expr.as_f64(),
+    }
+}
+
+/// The vector written for the output `field`, with the unknown it is the time derivative of when
+/// it is not an unknown of the system itself.
+fn output_field(
+    unknowns: &[String],
+    field: &str,
+) -> Result<(String, Option<String>), CodeGenError> {
+    let find = |name: &str| {
+        unknowns
+            .iter()
+            .find(|u| *u == name || **u == name.to_lowercase())
+            .cloned()
+    };
+    if let Some(unknown) = find(field) {
+        return Ok((unknown, None));
+    }
+    field
+        .strip_prefix("dt_")
+        .and_then(find)
+        .map(|unknown| (format!("dt_{unknown}"), Some(unknown)))
+        .ok_or_else(|| CodeGenError::UnknownUnknown(field.to_string()))
+}
+
+/// Merges the code of another phase of the simulation into `block`, skipping what `block`
+/// already declares, and returns the lines running the phase.
+fn merge_phase(block: &mut BuildingBlock, phase: BuildingBlock) -> Vec<String> {
+    fn extend_unique(target: &mut Vec<String>, lines: Vec<String>) {
+        for line in lines {
+            if !target.contains(&line) {
+                target.push(line);
+            }
+        }
+    }
+    let BuildingBlock {
+        includes,
+        data,
+        mesh_setup,
+        setup,
+        assembly,
+        additional_names: _,
+        constructor,
+        methods_defs,
+        methods_impls,
+        additional_vectors: _,
+        additional_matrixes: _,
+        main,
+        main_setup,
+        global,
+        output,
+    } = phase;
+    block.includes.extend(includes);
+    extend_unique(&mut block.data, data);
+    extend_unique(&mut block.mesh_setup, mesh_setup);
+    extend_unique(&mut block.setup, setup);
+    extend_unique(&mut block.assembly, assembly);
+    extend_unique(&mut block.constructor, constructor);
+    extend_unique(&mut block.methods_defs, methods_defs);
+    extend_unique(&mut block.methods_impls, methods_impls);
+    extend_unique(&mut block.main_setup, main_setup);
+    extend_unique(&mut block.global, global);
+    extend_unique(&mut block.output, output);
+    main
+}
+
+/// The objects shared by the phases of a simulation solving the equations of a system.
+#[derive(Clone, Copy)]
+struct SolvePhase<'a> {
+    /// Prefix of the names of the blocks setting up the equations of the phase
+    prefix: &'a str,
+    dof_handler: &'a str,
+    element: &'a str,
+    rhs: &'a str,
+    n_components: usize,
+    mesh_boundaries: &'a BaseIndexMap<String, u32>,
+    mesh_boundary_ids: &'a Vec<u32>,
+    unknown_solvers: &'a HashMap<&'a dyn Expr, String>,
+    /// Unknowns starting from their steady state instead of an initial condition
+    steady_unknowns: &'a HashSet<String>,
+    /// Unknowns whose system matrix is assembled once, before the time stepping
+    constant_unknowns: &'a HashSet<&'a dyn Expr>,
+    /// Unknowns whose right-hand side operators are assembled once, before the time stepping
+    constant_rhs_unknowns: &'a HashSet<&'a dyn Expr>,
+}
+
 static UNKNOWN_DT_RE: LazyLock<Regex> =
     LazyLock::new(|| Regex::new("^dt_(.+)").expect("valid regex"));
 
@@ -1144,7 +2864,9 @@ impl From<BuildingBlock> for tera::Context {
         let BuildingBlock {
             includes,
             data,
+            mesh_setup,
             setup,
+            assembly,
             additional_names: _,
             constructor,
             methods_defs,
@@ -1177,7 +2899,9 @@ impl From<BuildingBlock> for tera::Context {
         }
 
         context.insert("data", &to_cpp_lines("  ", data));
+        context.insert("mesh_setup", &to_cpp_lines("    ", mesh_setup));
         context.insert("setup", &to_cpp_lines("    ", setup));
+        context.insert("assembly", &to_cpp_lines("    ", assembly));
         context.insert("constructors", &constructor);
         context.insert("methods_defs", &to_cpp_lines("  ", methods_defs));
         context.insert("methods_impls", &methods_impls.into_iter().join("\n"));
@@ -1276,7 +3000,9 @@ impl<'fa> BuildingBlockCollector<'fa> {
             BuildingBlock {
                 includes,
                 data,
+                mesh_setup,
                 setup,
+                assembly,
                 additional_names: _,
                 constructor,
                 methods_defs,
@@ -1291,7 +3017,9 @@ impl<'fa> BuildingBlockCollector<'fa> {
         ) in self.blocks
         {
             res.includes.extend(includes);
+            res.mesh_setup.extend(mesh_setup);
             res.setup.extend(setup);
+            res.assembly.extend(assembly);
             res.data.extend(data);
             res.constructor.extend(constructor);
             res.methods_defs.extend(methods_defs);
@@ -1357,13 +3085,298 @@ impl<'fa> BuildingBlockCollector<'fa> {
         self.blocks.insert(name, self.factory.comment(content));
     }
 
-    fn add_vector_output(&mut self, unknown: &str) -> Result<(), BuildingBlockError> {
-        let name = format!("add_vector_output_{unknown}");
+    fn add_vector_output(&mut self, config: &VectorOutputConfig) -> Result<(), BuildingBlockError> {
+        let name = format!("add_vector_output_{}", config.vector);
         self.insert(
             &name,
             self.factory
-                .add_vector_output(&name, unknown, self.gen_conf)?,
+                .add_vector_output(&name, config, self.gen_conf)?,
         )?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ELASTIC_WAVE: &str = include_str!("../../input-schemas/elastic-wave.hecate.yml");
+    const HEAT_EQ: &str = include_str!("../../input-schemas/heat-eq.hecate.yml");
+    const REFINEMENT: &str = include_str!("../../tests/fixtures/refinement.hecate.yml");
+    const DG: &str = include_str!("../../tests/fixtures/dg.hecate.yml");
+    const WEIGHTED_LAPLACIAN: &str =
+        include_str!("../../tests/fixtures/weighted-laplacian.hecate.yml");
+    const ADVECTION_SUPG: &str = include_str!("../../tests/fixtures/advection-supg.hecate.yml");
+
+    fn elastic_wave() -> InputSchema {
+        InputSchema::from_yaml(ELASTIC_WAVE).unwrap()
+    }
+
+    fn matrix_free(yaml: &str) -> InputSchema {
+        let mut schema = InputSchema::from_yaml(yaml).unwrap();
+        schema.gen_conf.matrix_free = true;
+        schema
+    }
+
+    fn assert_matrix_free_unsupported(schema: &InputSchema, feature: &str) {
+        match schema.validate() {
+            Err(SchemaValidationError::MatrixFreeUnsupported(unsupported)) => {
+                assert_eq!(unsupported, feature)
+            }
+            res => panic!("expected {feature} to be unsupported, got {res:?}"),
+        }
+    }
+
+    fn assert_matrix_free_operator(schema: &InputSchema, expected: &str) {
+        match schema.validate() {
+            Err(SchemaValidationError::MatrixFreeOperator { operator, .. }) => {
+                assert_eq!(operator, expected)
+            }
+            res => panic!("expected {expected} to be unsupported, got {res:?}"),
+        }
+    }
+
+    #[test]
+    fn test_check_dimensions_missing_equation() {
+        let mut schema = InputSchema::from_yaml(HEAT_EQ).unwrap();
+        schema.check_dimensions().unwrap();
+        schema.solve.equations.push("missing".to_string());
+        assert!(matches!(
+            schema.check_dimensions(),
+            Err(SchemaValidationError::EquationNotFound(name)) if name == "missing"
+        ));
+    }
+
+    #[test]
+    fn test_element_cell_shapes() {
+        let path = std::env::temp_dir().join("hecate-test-triangles.inp");
+        std::fs::write(
+            &path,
+            "3 1 0 0 0\n1 0 0 0\n2 1 0 0\n3 0 1 0\n1 0 tri 1 2 3\n",
+        )
+        .unwrap();
+        let mut schema = InputSchema::from_yaml(HEAT_EQ).unwrap();
+        schema.solve.dimension = 2;
+        schema.meshes["domain"] =
+            serde_yaml::from_str(&format!("type: file\nfile: {}", path.display())).unwrap();
+
+        schema.solve.element = FiniteElement::P(1);
+        schema.validate().unwrap();
+        schema.solve.element = FiniteElement::Q(1);
+        assert!(matches!(
+            schema.validate(),
+            Err(SchemaValidationError::ElementCellMismatch {
+                expected: CellShape::Hypercube,
+                found: CellShape::Simplex,
+                ..
+            })
+        ));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_lame_parameters() {
+        let mut schema = elastic_wave();
+        assert!(schema.validate().is_ok());
+
+        schema.parameters.shift_remove("mu");
+        assert!(matches!(
+            schema.validate(),
+            Err(SchemaValidationError::InvalidLameParameters { equation, coefficient })
+                if equation == "elastic_wave" && coefficient == "2mu"
+        ));
+
+        // Named Lamé parameters weigh the stress and the traction of the natural conditions
+        let mut schema = elastic_wave();
+        let mu = schema.parameters.shift_remove("mu").unwrap();
+        schema.parameters.insert("shear".to_string(), mu);
+        schema.equations["elastic_wave"] = "rho * diff(u, t, 2) = div(sigma(u, lambda, shear)) + f"
+            .parse()
+            .unwrap();
+        assert!(schema.validate().is_ok());
+        let code = schema.generate_cpp_sources().unwrap();
+        assert!(
+            code.contains(
+                "const data_type equation_0_c2 = (-1./2.) * shear * (1 / rho) * time_step;"
+            )
+        );
+        assert!(code.contains("rhs.add((-1./2.) * (1 / rho) * time_step, boundary_flux_u_right);"));
+    }
+
+    #[test]
+    fn test_mixed_ranks_unsupported() {
+        // A velocity-pressure system such as Stokes would need block systems
+        let mut schema = elastic_wave();
+        let mut pressure = schema.unknowns["u"].clone();
+        pressure.rank = 0;
+        schema.unknowns.insert("p".to_string(), pressure);
+        assert!(matches!(
+            schema.validate(),
+            Err(SchemaValidationError::MixedRanks(unknowns)) if unknowns == "u, p"
+        ));
+    }
+
+    #[test]
+    fn test_constant_operators_assembled_once() {
+        let code = InputSchema::from_yaml(HEAT_EQ)
+            .unwrap()
+            .generate_cpp_sources()
+            .unwrap();
+        assert!(code.contains("matrix_t.copy_from(pristine_matrix_t);"));
+        assert!(code.contains("equation_0_mat0.vmult(rhs, t_prev);"));
+        assert!(!code.contains("mtmp"));
+
+        // The operators are reassembled after each refinement of the mesh
+        let code = InputSchema::from_yaml(REFINEMENT)
+            .unwrap()
+            .generate_cpp_sources()
+            .unwrap();
+        assert!(!code.contains("pristine_matrix_t"));
+        assert!(!code.contains("equation_0_mat0"));
+        assert!(code.contains("mtmp.vmult(rhs, t_prev);"));
+    }
+
+    #[test]
+    fn test_matrix_free() {
+        assert!(matrix_free(HEAT_EQ).validate().is_ok());
+    }
+
+    #[test]
+    fn test_matrix_free_vector_unknowns() {
+        assert_matrix_free_unsupported(&matrix_free(ELASTIC_WAVE), "vector unknowns");
+    }
+
+    #[test]
+    fn test_matrix_free_element() {
+        assert!(matches!(
+            matrix_free(DG).validate(),
+            Err(SchemaValidationError::MatrixFreeElement)
+        ));
+    }
+
+    #[test]
+    fn test_matrix_free_refinement() {
+        assert_matrix_free_unsupported(&matrix_free(REFINEMENT), "adaptive refinement");
+    }
+
+    #[test]
+    fn test_matrix_free_checkpoints() {
+        let mut schema = matrix_free(HEAT_EQ);
+        schema.checkpoint = Some(serde_yaml::from_str("every: 100").unwrap());
+        assert_matrix_free_unsupported(&schema, "checkpoints");
+    }
+
+    #[test]
+    fn test_matrix_free_natural_conditions() {
+        let mut schema = matrix_free(HEAT_EQ);
+        schema.unknowns["T"].boundary = serde_yaml::from_str("neumann: 0").unwrap();
+        assert_matrix_free_unsupported(&schema, "natural boundary conditions");
+    }
+
+    #[test]
+    fn test_matrix_free_periodic_conditions() {
+        let mut schema = matrix_free(HEAT_EQ);
+        schema.unknowns["T"].boundary = serde_yaml::from_str("periodic:\n  direction: 0").unwrap();
+        assert_matrix_free_unsupported(&schema, "periodic boundary conditions");
+    }
+
+    #[test]
+    fn test_matrix_free_vector_operators() {
+        for (equation, operator) in [
+            ("dt(T) = grad(div(T))", "grad_div"),
+            ("dt(T) = div(sigma(T, 0, thermal_diffusivity))", "div_eps"),
+        ] {
+            let mut schema = matrix_free(HEAT_EQ);
+            schema.equations["heat_eq"] = equation.parse().unwrap();
+            assert_matrix_free_operator(&schema, operator);
+        }
+    }
+
+    #[test]
+    fn test_matrix_free_weighted_operator() {
+        let mut schema = matrix_free(WEIGHTED_LAPLACIAN);
+        schema.unknowns["T"].boundary = serde_yaml::from_str("0").unwrap();
+        assert_matrix_free_operator(&schema, "laplacian_kappa");
+    }
+
+    #[test]
+    fn test_matrix_free_advection() {
+        assert_matrix_free_operator(&matrix_free(ADVECTION_SUPG), "advection_v");
+    }
+
+    #[test]
+    fn test_matrix_free_derivative() {
+        let mut schema = matrix_free(HEAT_EQ);
+        schema.equations["heat_eq"] = "dt(T) = dx(T)".parse().unwrap();
+        assert_matrix_free_operator(&schema, "dx(T)");
+    }
+
+    #[test]
+    fn test_threads() {
+        let mut schema = InputSchema::from_yaml(HEAT_EQ).unwrap();
+        schema.gen_conf.threads = Some(4);
+        let code = schema.generate_cpp_sources().unwrap();
+        assert!(code.contains("#include <deal.II/base/multithread_info.h>"));
+        assert!(code.contains("MultithreadInfo::set_thread_limit(4);"));
+
+        // Each MPI process is limited to the threads while initializing MPI
+        schema.gen_conf.mpi = true;
+        let code = schema.generate_cpp_sources().unwrap();
+        assert!(
+            code.contains("Utilities::MPI::MPI_InitFinalize mpi_initialization(argc, argv, 4);")
+        );
+        assert!(!code.contains("set_thread_limit"));
+
+        schema.gen_conf.threads = Some(0);
+        assert!(matches!(
+            schema.validate(),
+            Err(SchemaValidationError::NoThreads)
+        ));
+    }
+
+    #[test]
+    fn test_ghost_values() {
+        let mut schema = elastic_wave();
+        schema.gen_conf.mpi = true;
+        let code = schema.generate_cpp_sources().unwrap();
+
+        // The output evaluates the unknowns on the locally relevant cells
+        assert!(code.contains("u_ghosted = u;"));
+        // The right-hand side and the boundary flux never evaluate the unknowns on cells
+        assert!(code.contains("boundary_flux_u_right"));
+        assert!(!code.contains("get_function_values"));
+        assert!(!code.contains("get_function_gradients"));
+    }
+
+    #[test]
+    fn test_checkpoints() {
+        let mut schema = InputSchema::from_yaml(HEAT_EQ).unwrap();
+        schema.checkpoint = Some(serde_yaml::from_str("every: 100\nkeep: 3").unwrap());
+        let code = schema.generate_cpp_sources().unwrap();
+        assert!(code.contains("if (timestep_number % 100 == 0)\n      save_checkpoint();"));
+        assert!(code.contains("if (checkpoints.size() > 3) {"));
+        assert!(code.contains("latest(\"./checkpoint.latest\")"));
+        assert!(code.contains("restart = argv[i + 1];"));
+        assert!(code.contains("if (!restart.empty())\n    load_checkpoint_mesh(restart);"));
+        assert!(code.contains("if (!restart.empty())\n    load_checkpoint(restart);"));
+        // Serial checkpoints archive the mesh followed by the previous values
+        assert!(code.contains("archive << mesh;"));
+        assert!(code.contains("for (const Vector<data_type> *vector : {&t_prev})"));
+        assert!(code.contains("archive >> saved_mesh;"));
+        assert!(code.contains("interpolate_to_different_mesh"));
+        assert!(!code.contains("SolutionTransfer"));
+
+        // MPI checkpoints attach the previous values to the distributed mesh
+        schema.gen_conf.mpi = true;
+        let code = schema.generate_cpp_sources().unwrap();
+        assert!(code.contains("#include <deal.II/distributed/solution_transfer.h>"));
+        assert!(code.contains("previous[0] = t_prev;"));
+        assert!(code.contains("solution_transfer.prepare_for_serialization("));
+        assert!(code.contains("mesh.save(directory + \"/mesh\");"));
+        assert!(code.contains("mesh.load(directory + \"/mesh\");"));
+        assert!(code.contains("std::vector<LA::MPI::Vector *> loaded = {&t_prev};"));
+        assert!(code.contains("solution_transfer.deserialize(loaded);"));
+        assert!(code.contains("if (mpi_rank == 0)\n      std::filesystem::remove_all"));
+        assert!(!code.contains("boost::archive"));
+    }
+}
@@ -0,0 +1,309 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use symrs::{Expr, Integer, KnownExpr, Mul};
+
+use super::{IndexMap, UnknownProperty};
+
+/// Keys of the tagged boundary conditions, which cannot be used to name boundaries.
+pub const RESERVED_BOUNDARY_NAMES: [&str; 4] = ["neumann", "robin", "periodic", "partial"];
+
+/// # Boundary Conditions
+/// The conditions imposed on the boundary of the domain for an unknown.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum BoundaryConditions {
+    /// # Uniform Boundary Condition
+    /// A condition imposed on the whole boundary.
+    Uniform(BoundaryCondition),
+
+    /// # Boundary Conditions Per Part
+    /// Conditions imposed on named parts of the boundary of the mesh.
+    /// Parts left out are insulated (zero normal derivative).
+    Parts(IndexMap<String, BoundaryCondition>),
+}
+
+/// # Boundary Condition
+/// The condition imposed on the boundary of the domain for an unknown.
+/// A plain value or function name imposes the value of the unknown (Dirichlet condition).
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BoundaryCondition {
+    /// # Neumann Condition
+    /// Imposes the normal derivative of the unknown: ∂u/∂n = flux.
+    /// A zero flux describes an insulated wall.
+    /// With a weighted Laplacian `div(kappa * grad(u))`, the flux is kappa ∂u/∂n.
+    Neumann(UnknownProperty),
+
+    /// # Robin Condition
+    /// Imposes a linear relation between the unknown and its normal derivative:
+    /// ∂u/∂n + alpha * u = value.
+    /// A convective wall with a transfer coefficient h, a conductivity κ and an exterior
+    /// temperature T_ext is described by alpha = h / κ and value = h * T_ext / κ.
+    Robin {
+        alpha: UnknownProperty,
+        value: UnknownProperty,
+    },
+
+    /// # Periodic Condition
+    /// Identifies the faces of the boundary with their translation along a direction.
+    Periodic(PeriodicCondition),
+
+    /// # Partial Dirichlet Condition
+    /// Imposes the value of some components of a vector unknown, the other components being
+    /// free of traction (a roller support, for instance).
+    Partial(PartialCondition),
+
+    /// # Dirichlet Condition
+    /// Imposes the value of the unknown.
+    #[serde(untagged)]
+    Dirichlet(UnknownProperty),
+}
+
+/// # Periodic Condition
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct PeriodicCondition {
+    /// # Direction
+    /// The axis along which the domain is periodic (0 for x, 1 for y, 2 for z).
+    pub direction: u32,
+
+    /// # Opposite Boundary
+    /// The named boundary matched with the one the condition is imposed on.
+    /// Required when the condition is imposed on a named part of the boundary.
+    /// On the whole boundary, the faces of the boundary id 0 are matched with each other.
+    #[serde(default)]
+    pub with: Option<String>,
+}
+
+/// # Partial Dirichlet Condition
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct PartialCondition {
+    /// # Components
+    /// The imposed components (0 for x, 1 for y, 2 for z).
+    pub components: Vec<u32>,
+
+    /// # Value
+    /// The value of the unknown, only the imposed components being read.
+    pub value: UnknownProperty,
+}
+
+impl Default for BoundaryConditions {
+    fn default() -> Self {
+        BoundaryConditions::Uniform(BoundaryCondition::default())
+    }
+}
+
+impl BoundaryConditions {
+    /// The conditions with the name of the part of the boundary they are imposed on, `None`
+    /// standing for the whole boundary.
+    pub fn conditions(&self) -> Vec<(Option<&str>, &BoundaryCondition)> {
+        match self {
+            BoundaryConditions::Uniform(condition) => vec![(None, condition)],
+            BoundaryConditions::Parts(parts) => parts
+                .iter()
+                .map(|(part, condition)| (Some(part.as_str()), condition))
+                .collect(),
+        }
+    }
+
+    pub fn properties(&self) -> Vec<&UnknownProperty> {
+        self.conditions()
+            .into_iter()
+            .flat_map(|(_, condition)| condition.properties())
+            .collect()
+    }
+}
+
+impl Default for BoundaryCondition {
+    fn default() -> Self {
+        BoundaryCondition::Dirichlet(UnknownProperty::default())
+    }
+}
+
+impl BoundaryCondition {
+    /// The values and functions the condition depends on.
+    pub fn properties(&self) -> Vec<&UnknownProperty> {
+        match self {
+            BoundaryCondition::Dirichlet(value)
+            | BoundaryCondition::Neumann(value)
+            | BoundaryCondition::Partial(PartialCondition { value, .. }) => vec![value],
+            BoundaryCondition::Robin { alpha, value } => vec![alpha, value],
+            BoundaryCondition::Periodic(_) => vec![],
+        }
+    }
+
+    pub fn is_periodic(&self) -> bool {
+        matches!(self, BoundaryCondition::Periodic(_))
+    }
+
+    /// Rank of the condition when applying them in turn: natural conditions add boundary
+    /// integrals to the system, periodic conditions are part of its constraints, and Dirichlet
+    /// conditions finally override the rows of their dofs.
+    pub fn application_rank(&self) -> u8 {
+        match self {
+            BoundaryCondition::Neumann(_) | BoundaryCondition::Robin { .. } => 0,
+            BoundaryCondition::Periodic(_) => 1,
+            BoundaryCondition::Dirichlet(_) | BoundaryCondition::Partial(_) => 2,
+        }
+    }
+
+    /// Whether the condition is weakly imposed through boundary integrals.
+    pub fn is_natural(&self) -> bool {
+        matches!(
+            self,
+            BoundaryCondition::Neumann(_) | BoundaryCondition::Robin { .. }
+        )
+    }
+}
+
+/// Sums the coefficients of the terms of `expr` where `operator` is applied to `operand`.
+///
+/// Used to retrieve how the Laplace matrix applied to an unknown is weighted in a discretized
+/// equation, so that the boundary terms of its integration by parts are weighted the same way.
+pub fn operator_coefficient(
+    expr: &dyn Expr,
+    operator: &dyn Expr,
+    operand: &dyn Expr,
+) -> Box<dyn Expr> {
+    let expr = expr.expand();
+    let terms = match expr.known_expr() {
+        KnownExpr::Add(add) => add.operands.iter().map(|op| op.get_ref()).collect(),
+        _ => vec![expr.get_ref()],
+    };
+
+    let mut coefficient = Integer::new_box(0);
+    for term in terms {
+        let KnownExpr::Mul(Mul { operands }) = term.known_expr() else {
+            continue;
+        };
+        if !operands.iter().any(|op| op.get_ref() == operator)
+            || !operands.iter().any(|op| op.get_ref() == operand)
+        {
+            continue;
+        }
+        let mut factors: Vec<Box<dyn Expr>> = operands
+            .iter()
+            .filter(|op| op.get_ref() != operator && op.get_ref() != operand)
+            .map(|op| op.clone_box())
+            .collect();
+        coefficient += match factors.len() {
+            0 => Integer::new_box(1),
+            1 => factors.pop().unwrap(),
+            _ => Box::new(Mul::new_move(factors)),
+        };
+    }
+    coefficient.simplify()
+}
+
+#[cfg(test)]
+mod tests {
+    use symrs::{Matrix, Symbol, symbols};
+
+    use super::*;
+
+    #[test]
+    fn test_deserialize_boundary_conditions() {
+        let conditions: Vec<BoundaryCondition> = serde_yaml::from_str(
+            r"
+- 0
+- T_hot
+- neumann: 0
+- robin:
+    alpha: 2
+    value: T_ext
+- periodic:
+    direction: 1
+- partial:
+    components: [0]
+    value: 0
+",
+        )
+        .unwrap();
+
+        assert!(matches!(
+            conditions[0],
+            BoundaryCondition::Dirichlet(UnknownProperty::Constant(0))
+        ));
+        assert!(matches!(
+            &conditions[1],
+            BoundaryCondition::Dirichlet(UnknownProperty::FunctionName(f)) if f == "T_hot"
+        ));
+        assert!(matches!(
+            conditions[2],
+            BoundaryCondition::Neumann(UnknownProperty::Constant(0))
+        ));
+        assert!(matches!(
+            &conditions[3],
+            BoundaryCondition::Robin {
+                alpha: UnknownProperty::Constant(2),
+                value: UnknownProperty::FunctionName(value),
+            } if value == "T_ext"
+        ));
+        assert!(matches!(
+            conditions[4],
+            BoundaryCondition::Periodic(PeriodicCondition {
+                direction: 1,
+                with: None
+            })
+        ));
+        assert!(matches!(
+            &conditions[5],
+            BoundaryCondition::Partial(PartialCondition { components, .. }) if components == &[0]
+        ));
+        assert_eq!(conditions[5].application_rank(), 2);
+        assert_eq!(conditions[3].properties().len(), 2);
+        assert!(conditions[4].properties().is_empty());
+    }
+
+    #[test]
+    fn test_deserialize_boundary_parts() {
+        let conditions: Vec<BoundaryConditions> = serde_yaml::from_str(
+            r"
+- neumann: 0
+- left: T_hot
+  right:
+    neumann: 0
+  top:
+    periodic:
+      direction: 1
+      with: bottom
+",
+        )
+        .unwrap();
+
+        assert!(matches!(
+            conditions[0],
+            BoundaryConditions::Uniform(BoundaryCondition::Neumann(_))
+        ));
+        let parts = conditions[1].conditions();
+        assert_eq!(
+            parts.iter().map(|(part, _)| *part).collect::<Vec<_>>(),
+            [Some("left"), Some("right"), Some("top")]
+        );
+        assert!(matches!(parts[0].1, BoundaryCondition::Dirichlet(_)));
+        assert!(matches!(
+            parts[2].1,
+            BoundaryCondition::Periodic(PeriodicCondition { direction: 1, with: Some(with) }) if with == "bottom"
+        ));
+        assert_eq!(conditions[1].properties().len(), 2);
+    }
+
+    #[test]
+    fn test_operator_coefficient() {
+        let [c0, c1, c2, u, u_prev] = symbols!("c0", "c1", "c2", "u", "u_prev");
+        let m = Matrix::square("M", "n");
+        let a = Matrix::square("A", "n");
+
+        let lhs = (c0 * m.get_ref() + c1 * a.get_ref()) * u;
+        assert_eq!(operator_coefficient(lhs.get_ref(), &a, u), c1.clone_box());
+        assert_eq!(
+            operator_coefficient(lhs.get_ref(), &a, u_prev),
+            Integer::new_box(0)
+        );
+
+        let rhs = (c0 * m.get_ref() + c2 * a.get_ref()) * u_prev + c1 * a.get_ref() * u_prev;
+        assert_eq!(
+            operator_coefficient(rhs.get_ref(), &a, u_prev),
+            (c2 + c1).simplify()
+        );
+    }
+}
@@ -0,0 +1,83 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::output::OutputInterval;
+
+/// # Checkpoint
+/// The periodic saving of the state of the time stepping, the mesh with the previous values of
+/// the unknowns, the time and the step, for the simulation to resume from it when run with
+/// `--restart <checkpoint>`.
+/// Each checkpoint is a directory of the output directory, the path of the latest one being
+/// written to `checkpoint.latest`.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Checkpoint {
+    /// # Every
+    /// The interval between two checkpoints, either a number of time steps such as `100`, or a
+    /// simulated time such as `"10 s"`.
+    pub every: OutputInterval,
+
+    /// # Keep
+    /// The number of latest checkpoints kept, older ones being removed.
+    #[serde(default = "default_keep")]
+    pub keep: u32,
+}
+
+#[derive(Error, Debug)]
+pub enum CheckpointError {
+    #[error("checkpoint interval must be at least one time step")]
+    ZeroSteps,
+    #[error("checkpoint interval must be a positive time, found {0}")]
+    NonPositiveTime(f64),
+    #[error("at least one checkpoint must be kept")]
+    ZeroKeep,
+}
+
+fn default_keep() -> u32 {
+    2
+}
+
+impl Checkpoint {
+    pub fn validate(&self) -> Result<(), CheckpointError> {
+        match &self.every {
+            OutputInterval::Steps(0) => return Err(CheckpointError::ZeroSteps),
+            OutputInterval::Time(time) if time.seconds() <= 0.0 => {
+                return Err(CheckpointError::NonPositiveTime(time.seconds()));
+            }
+            _ => {}
+        }
+        if self.keep == 0 {
+            return Err(CheckpointError::ZeroKeep);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_checkpoint() {
+        let checkpoint: Checkpoint = serde_yaml::from_str("every: 100").unwrap();
+        assert!(matches!(checkpoint.every, OutputInterval::Steps(100)));
+        assert_eq!(checkpoint.keep, 2);
+        assert!(checkpoint.validate().is_ok());
+
+        let checkpoint: Checkpoint = serde_yaml::from_str("every: 10 s\nkeep: 3").unwrap();
+        assert!(matches!(checkpoint.every, OutputInterval::Time(_)));
+        assert_eq!(checkpoint.keep, 3);
+
+        let checkpoint: Checkpoint = serde_yaml::from_str("every: 0").unwrap();
+        assert!(matches!(
+            checkpoint.validate(),
+            Err(CheckpointError::ZeroSteps)
+        ));
+
+        let checkpoint: Checkpoint = serde_yaml::from_str("every: 5\nkeep: 0").unwrap();
+        assert!(matches!(
+            checkpoint.validate(),
+            Err(CheckpointError::ZeroKeep)
+        ));
+    }
+}
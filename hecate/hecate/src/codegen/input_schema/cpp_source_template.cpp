@@ -1,11 +1,20 @@
+{% if time_scheme -%}
+// Time scheme: {{ time_scheme }}
+{% endif -%}
 #include <iostream>
-{% if not mpi -%}
+{% if not mpi or vtu or gnuplot -%}
 #include <fstream>
 {% endif -%}
+{% if output_directory -%}
+#include <filesystem>
+{% endif -%}
 #include <cmath>
 #include <deal.II/lac/affine_constraints.h>
 #include <deal.II/numerics/data_out.h>
 #include <deal.II/base/conditional_ostream.h>
+{% if threads and not mpi -%}
+#include <deal.II/base/multithread_info.h>
+{% endif -%}
 {{ includes }}
 {% if mpi -%}
 #include <deal.II/lac/generic_linear_algebra.h>
@@ -57,6 +66,14 @@ bool float_equals(data_type a, data_type b) {
   return std::fabs(b - a) < epsilon;
 }
 
+Point<dim> make_point(data_type x, data_type y, data_type z) {
+  const data_type coordinates[] = {x, y, z};
+  Point<dim> point;
+  for (int d = 0; d < dim; ++d)
+    point[d] = coordinates[d];
+  return point;
+}
+
 
 {{ global }}
 
@@ -74,23 +91,46 @@ public:
     {%- endfor %}
   {}
 
+  {% if checkpoint -%}
+  void run(const std::string &restart);
+  {%- else -%}
   void run();
+  {%- endif %}
   void output_results();
 
+  void setup_mesh() {
+    {{ mesh_setup | trim }}
+  }
+
   void setup_system() {
     constraints.clear();
     {{ setup | trim }}
     constraints.close();
+    {%- if assembly %}
+
+    // Assemble operators constant in time
+    {{ assembly | trim }}
+    {%- endif %}
   }
 
   AffineConstraints<data_type> constraints;
   data_type time;
   unsigned long timestep_number = 1;
+  const data_type time_step = {{ time_step }};
   {%if mpi -%}
   const unsigned int mpi_rank = 0;
   const unsigned int n_mpi_processes = 1;
   {%- endif %}
   ConditionalOStream pcout;
+  {%- if output_time %}
+  data_type next_output_time = {{ time_start }};
+  {%- endif %}
+  {%- if vtu %}
+  std::vector<std::pair<double, std::string>> times_and_names;
+  {%- endif %}
+  {%- if hdf5 %}
+  std::vector<XDMFEntry> xdmf_entries;
+  {%- endif %}
 
   {{ data | trim }}
 
@@ -99,7 +139,11 @@ public:
 
 {{ methods_impls | trim }}
 
+{% if checkpoint -%}
+void Sim::run(const std::string &restart) {
+{%- else -%}
 void Sim::run() {
+{%- endif %}
   {% if mpi -%}
   pcout << "Running with "
 #ifdef USE_PETSC_LA
@@ -111,12 +155,51 @@ void Sim::run() {
   << " MPI rank(s)..." << std::endl;
 
   {% endif -%}
+  {%- if output_directory -%}
+  // Create the output directory
+  {% if mpi -%}
+  if (mpi_rank == 0)
+    std::filesystem::create_directories("{{ output_directory }}");
+  MPI_Barrier(MPI_COMM_WORLD);
+  {%- else -%}
+  std::filesystem::create_directories("{{ output_directory }}");
+  {%- endif %}
+
+  {% endif -%}
+  setup_mesh();
+  {%- if checkpoint %}
+  if (!restart.empty())
+    load_checkpoint_mesh(restart);
+  {%- endif %}
   setup_system();
-  data_type time_step = {{ time_step }};
+  {%- if steady %}
+
+  // Solve the steady state
+  {%- if checkpoint %}, unless resuming from a checkpoint
+  if (restart.empty()) {
+  {%- endif %}
+  pcout << "Steady state at t=" << {{ time_start }} << std::endl;
+  time = {{ time_start }};
+  timestep_number = 0;
+  {{ steady | trim }}
+  {%- if checkpoint %}
+  }
+  {%- endif %}
+  {%- endif %}
+  {%- if transient %}
+  {%- if steady %}
+  timestep_number = 1;
+  {%- endif %}
   time = {{ time_start }} + time_step;
 
   // Prepare time stepping
   {{ main_setup | trim }}
+  {%- if checkpoint %}
+
+  // Resume the time stepping from the checkpoint
+  if (!restart.empty())
+    load_checkpoint(restart);
+  {%- endif %}
 
   // Run time stepping
   for (; time <= {{ time_end }}; time += time_step, ++timestep_number) {
@@ -124,42 +207,106 @@ void Sim::run() {
 
     {{ main | trim }}
   }
+  {%- endif %}
 
 }
 
 void Sim::output_results() {
+  {%- if output_steps %}
+  // The results are written every {{ output_steps }} steps
+  if (timestep_number % {{ output_steps }} != 0)
+    return;
+  {%- elif output_time %}
+  // The results are written every {{ output_time }} s of simulated time
+  if (time < next_output_time - 1e-8 * time_step)
+    return;
+  while (next_output_time <= time + 1e-8 * time_step)
+    next_output_time += {{ output_time }};
+  {%- endif %}
+  {%- if output_steps or output_time %}
+{% endif %}
   DataOut<dim> data_out;
 
   data_out.attach_dof_handler(dof_handler);
   {{output | trim}}
-  data_out.build_patches();
-
+  data_out.build_patches(element_mapping);
+  {%- if vtu %}
 
   DataOutBase::VtkFlags vtk_flags;
-  vtk_flags.compression_level = DataOutBase::CompressionLevel::best_speed;
+  vtk_flags.compression_level = DataOutBase::CompressionLevel::{{ compression }};
   data_out.set_flags(vtk_flags);
-  {%if mpi -%}
-  data_out.write_vtu_with_pvtu_record("./", "solution", timestep_number, MPI_COMM_WORLD, 2, 8);
-  {% else %}
+  {%- if mpi %}
+  const std::string filename = data_out.write_vtu_with_pvtu_record(
+      "{{ output_prefix }}", "solution", timestep_number, MPI_COMM_WORLD, 2, 8);
+  {%- else %}
   const std::string filename =
       "solution-" + Utilities::int_to_string(timestep_number, 3) + ".vtu";
-  std::ofstream output(filename);
+  std::ofstream output("{{ output_prefix }}" + filename);
   data_out.write_vtu(output);
-  {%- endif -%}
+  {%- endif %}
+
+  // The files are indexed by time, for ParaView to open them as a series
+  times_and_names.emplace_back(time, filename);
+  {%- if mpi %}
+  if (mpi_rank == 0) {
+    std::ofstream pvd_output("{{ output_prefix }}solution.pvd");
+    DataOutBase::write_pvd_record(pvd_output, times_and_names);
+  }
+  {%- else %}
+  std::ofstream pvd_output("{{ output_prefix }}solution.pvd");
+  DataOutBase::write_pvd_record(pvd_output, times_and_names);
+  {%- endif %}
+  {%- endif %}
+  {%- if hdf5 %}
+
+  // Each step is written to an HDF5 file, indexed by time in the XDMF file
+  DataOutBase::DataOutFilter data_filter(DataOutBase::DataOutFilterFlags(true, true));
+  data_out.write_filtered_data(data_filter);
+  const std::string h5_filename =
+      "solution-" + Utilities::int_to_string(timestep_number, 3) + ".h5";
+  data_out.write_hdf5_parallel(data_filter, "{{ output_prefix }}" + h5_filename, MPI_COMM_WORLD);
+  xdmf_entries.push_back(
+      data_out.create_xdmf_entry(data_filter, h5_filename, time, MPI_COMM_WORLD));
+  data_out.write_xdmf_file(xdmf_entries, "{{ output_prefix }}solution.xdmf", MPI_COMM_WORLD);
+  {%- endif %}
+  {%- if gnuplot %}
+
+  std::ofstream gnuplot_output("{{ output_prefix }}solution-" +
+                               Utilities::int_to_string(timestep_number, 3) +
+                               {%- if mpi %}
+                               "." + Utilities::int_to_string(mpi_rank, 4) +
+                               {%- endif %}
+                               ".gpl");
+  data_out.write_gnuplot(gnuplot_output);
+  {%- endif %}
 }
 
 int main(int argc, char *argv[]) {
   try {
     {% if mpi -%}
-    Utilities::MPI::MPI_InitFinalize mpi_initialization(argc, argv, numbers::invalid_unsigned_int);
+    Utilities::MPI::MPI_InitFinalize mpi_initialization(argc, argv, {% if threads %}{{ threads }}{% else %}numbers::invalid_unsigned_int{% endif %});
     {%- else -%}
     #ifdef DEAL_II_WITH_KOKKOS
     Kokkos::initialize(argc, argv);
     #endif
+    {%- if threads %}
+    MultithreadInfo::set_thread_limit({{ threads }});
     {%- endif %}
+    {%- endif %}
+
+    {% if checkpoint -%}
+    // The checkpoint to resume from, given by --restart <checkpoint>
+    std::string restart;
+    for (int i = 1; i + 1 < argc; ++i)
+      if (std::string(argv[i]) == "--restart")
+        restart = argv[i + 1];
 
+    Sim sim;
+    sim.run(restart);
+    {%- else -%}
     Sim sim;
     sim.run();
+    {%- endif %}
 
     {% if not mpi -%}
     #ifdef DEAL_II_WITH_KOKKOS
@@ -0,0 +1,430 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::ops::{Div, Mul};
+
+use symrs::{Add, Diff, Equation, Expr, Func, Integer, Pow, Rational, Symbol};
+use thiserror::Error;
+
+use super::unit::CharUtils;
+
+/// # Dimension
+/// The dimension of a quantity, expressed as the exponents of
+/// the seven base quantities of the international system.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
+pub struct Dimension {
+    pub length: i32,
+    pub time: i32,
+    pub mass: i32,
+    pub current: i32,
+    pub temperature: i32,
+    pub amount: i32,
+    pub luminous_intensity: i32,
+}
+
+impl Dimension {
+    pub const DIMENSIONLESS: Dimension = Dimension::new(0, 0, 0, 0, 0, 0, 0);
+    pub const LENGTH: Dimension = Dimension::new(1, 0, 0, 0, 0, 0, 0);
+    pub const TIME: Dimension = Dimension::new(0, 1, 0, 0, 0, 0, 0);
+    pub const MASS: Dimension = Dimension::new(0, 0, 1, 0, 0, 0, 0);
+    pub const TEMPERATURE: Dimension = Dimension::new(0, 0, 0, 0, 1, 0, 0);
+
+    pub const fn new(
+        length: i32,
+        time: i32,
+        mass: i32,
+        current: i32,
+        temperature: i32,
+        amount: i32,
+        luminous_intensity: i32,
+    ) -> Self {
+        Dimension {
+            length,
+            time,
+            mass,
+            current,
+            temperature,
+            amount,
+            luminous_intensity,
+        }
+    }
+
+    fn exponents(&self) -> [i32; 7] {
+        [
+            self.length,
+            self.time,
+            self.mass,
+            self.current,
+            self.temperature,
+            self.amount,
+            self.luminous_intensity,
+        ]
+    }
+
+    fn from_exponents(e: [i32; 7]) -> Self {
+        Dimension::new(e[0], e[1], e[2], e[3], e[4], e[5], e[6])
+    }
+
+    pub fn is_dimensionless(&self) -> bool {
+        *self == Dimension::DIMENSIONLESS
+    }
+
+    pub fn powi(&self, n: i32) -> Self {
+        Dimension::from_exponents(self.exponents().map(|e| e * n))
+    }
+
+    /// Raises the dimension to a rational power.
+    /// Returns `None` if the result would have fractional exponents.
+    pub fn pow_rational(&self, num: i32, denom: i32) -> Option<Self> {
+        let exponents = self.exponents().map(|e| e * num);
+        exponents
+            .iter()
+            .all(|e| e % denom == 0)
+            .then(|| Dimension::from_exponents(exponents.map(|e| e / denom)))
+    }
+}
+
+impl Mul for Dimension {
+    type Output = Dimension;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        let (a, b) = (self.exponents(), rhs.exponents());
+        Dimension::from_exponents(std::array::from_fn(|i| a[i] + b[i]))
+    }
+}
+
+impl Div for Dimension {
+    type Output = Dimension;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        self * rhs.powi(-1)
+    }
+}
+
+impl Display for Dimension {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        const SYMBOLS: [&str; 7] = ["L", "T", "M", "I", "Θ", "N", "J"];
+        if self.is_dimensionless() {
+            return write!(f, "1");
+        }
+        let mut factors = Vec::new();
+        for (symbol, exponent) in SYMBOLS.iter().zip(self.exponents()) {
+            match exponent {
+                0 => continue,
+                1 => factors.push(symbol.to_string()),
+                e => {
+                    let sign = if e < 0 { "⁻" } else { "" };
+                    let digits: String = e
+                        .abs()
+                        .to_string()
+                        .chars()
+                        .map(|c| c.to_superscript_digit().unwrap_or(c))
+                        .collect();
+                    factors.push(format!("{symbol}{sign}{digits}"))
+                }
+            }
+        }
+        write!(f, "{}", factors.join("·"))
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum DimensionError {
+    #[error("term '{term}' has dimension {found}, expected {expected}")]
+    Inhomogeneous {
+        term: String,
+        expected: Dimension,
+        found: Dimension,
+    },
+    #[error(
+        "argument '{argument}' of {function} has dimension {found}, expected it to be dimensionless"
+    )]
+    NonDimensionlessArgument {
+        function: String,
+        argument: String,
+        found: Dimension,
+    },
+}
+
+/// Functions taking dimensionless arguments and returning a dimensionless value.
+const TRANSCENDENTAL_FUNCTIONS: [&str; 16] = [
+    "sin", "cos", "tan", "asin", "acos", "atan", "sinh", "cosh", "tanh", "asinh", "acosh", "atanh",
+    "exp", "log", "ln", "log10",
+];
+
+/// Assigns dimensions to the symbols of equations and checks their homogeneity.
+///
+/// Symbols without a known dimension (functions, undeclared unknowns...) are left unconstrained
+/// and are therefore compatible with any dimension.
+#[derive(Debug, Clone)]
+pub struct DimensionChecker<'a> {
+    symbols: HashMap<&'a str, Dimension>,
+}
+
+impl Default for DimensionChecker<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> DimensionChecker<'a> {
+    pub fn new() -> Self {
+        let mut symbols = HashMap::new();
+        symbols.insert("t", Dimension::TIME);
+        for space in ["x", "y", "z"] {
+            symbols.insert(space, Dimension::LENGTH);
+        }
+        // Second order spatial operators
+        for operator in ["laplacian", "Δ", "grad_div", "div_eps"] {
+            symbols.insert(operator, Dimension::LENGTH.powi(-2));
+        }
+        for nabla in ["nabla", "∇"] {
+            symbols.insert(nabla, Dimension::LENGTH.powi(-1));
+        }
+        for constant in ["pi", "e"] {
+            symbols.insert(constant, Dimension::DIMENSIONLESS);
+        }
+        DimensionChecker { symbols }
+    }
+
+    pub fn insert(&mut self, symbol: &'a str, dimension: Dimension) {
+        self.symbols.insert(symbol, dimension);
+    }
+
+    pub fn check_equation(&self, equation: &Equation) -> Result<(), DimensionError> {
+        let lhs = self.dimension(equation.lhs.get_ref())?;
+        let rhs = self.dimension(equation.rhs.get_ref())?;
+        if let (Some(lhs), Some(rhs)) = (lhs, rhs)
+            && lhs != rhs
+        {
+            return Err(DimensionError::Inhomogeneous {
+                term: equation.rhs.str(),
+                expected: lhs,
+                found: rhs,
+            });
+        }
+        Ok(())
+    }
+
+    /// Computes the dimension of an expression.
+    /// Returns `None` if the dimension is unconstrained.
+    pub fn dimension(&self, expr: &dyn Expr) -> Result<Option<Dimension>, DimensionError> {
+        let any = expr as &dyn Any;
+        if let Some(symbol) = any.downcast_ref::<Symbol>() {
+            Ok(self.symbols.get(symbol.name.as_str()).copied())
+        } else if any.is::<Integer>() || any.is::<Rational>() {
+            // Zero can be added to anything
+            Ok((!expr.is_zero()).then_some(Dimension::DIMENSIONLESS))
+        } else if let Some(add) = any.downcast_ref::<Add>() {
+            let mut expected: Option<Dimension> = None;
+            for term in &add.operands {
+                let Some(found) = self.dimension(term.get_ref())? else {
+                    continue;
+                };
+                match expected {
+                    None => expected = Some(found),
+                    Some(expected) if expected != found => {
+                        return Err(DimensionError::Inhomogeneous {
+                            term: term.str(),
+                            expected,
+                            found,
+                        });
+                    }
+                    _ => (),
+                }
+            }
+            Ok(expected)
+        } else if let Some(mul) = expr.as_mul() {
+            let mut res = Dimension::DIMENSIONLESS;
+            let mut constrained = true;
+            for factor in &mul.operands {
+                match self.dimension(factor.get_ref())? {
+                    Some(dimension) => res = res * dimension,
+                    None => constrained = false,
+                }
+            }
+            Ok(constrained.then_some(res))
+        } else if let Some(pow) = any.downcast_ref::<Pow>() {
+            let base = self.dimension(pow.base())?;
+            self.check_dimensionless("pow", pow.exponent())?;
+            let exponent = pow.exponent() as &dyn Any;
+            Ok(match (base, exponent.downcast_ref::<Integer>()) {
+                (None, _) => None,
+                (Some(base), _) if base.is_dimensionless() => Some(base),
+                (Some(base), Some(Integer { value })) => Some(base.powi(*value as i32)),
+                (Some(base), None) => exponent
+                    .downcast_ref::<Rational>()
+                    .and_then(|r| base.pow_rational(r.num as i32, r.denom as i32)),
+            })
+        } else if let Some(diff) = any.downcast_ref::<Diff>() {
+            let Some(mut res) = self.dimension(diff.f.get_ref())? else {
+                return Ok(None);
+            };
+            for (var, order) in &diff.vars {
+                let Some(var) = self.symbols.get(var.name.as_str()) else {
+                    return Ok(None);
+                };
+                res = res / var.powi(*order as i32);
+            }
+            Ok(Some(res))
+        } else if let Some(Func { name, args }) = expr.as_function() {
+            if args.is_empty() {
+                return Ok(self.symbols.get(name.as_str()).copied());
+            }
+            if TRANSCENDENTAL_FUNCTIONS.contains(&name.as_str()) {
+                for arg in args {
+                    self.check_dimensionless(name, arg.get_ref())?;
+                }
+                return Ok(Some(Dimension::DIMENSIONLESS));
+            }
+            match (name.as_str(), &args[..]) {
+                // The angle of a point only needs both coordinates to share a dimension
+                ("atan2", [y, x]) => {
+                    if let (Some(expected), Some(found)) =
+                        (self.dimension(y.get_ref())?, self.dimension(x.get_ref())?)
+                        && expected != found
+                    {
+                        return Err(DimensionError::Inhomogeneous {
+                            term: x.str(),
+                            expected,
+                            found,
+                        });
+                    }
+                    Ok(Some(Dimension::DIMENSIONLESS))
+                }
+                ("abs", [arg]) => self.dimension(arg.get_ref()),
+                ("sqrt", [arg]) => Ok(self
+                    .dimension(arg.get_ref())?
+                    .and_then(|d| d.pow_rational(1, 2))),
+                // User functions keep their own dimension whatever they are applied to
+                _ => Ok(self.symbols.get(name.as_str()).copied()),
+            }
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn check_dimensionless(&self, function: &str, expr: &dyn Expr) -> Result<(), DimensionError> {
+        match self.dimension(expr)? {
+            Some(found) if !found.is_dimensionless() => {
+                Err(DimensionError::NonDimensionlessArgument {
+                    function: function.to_string(),
+                    argument: expr.str(),
+                    found,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heat_checker() -> DimensionChecker<'static> {
+        let mut checker = DimensionChecker::new();
+        checker.insert("T", Dimension::TEMPERATURE);
+        checker.insert("k", Dimension::LENGTH.powi(2) / Dimension::TIME);
+        checker
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Dimension::DIMENSIONLESS.to_string(), "1");
+        assert_eq!(
+            (Dimension::LENGTH.powi(2) / Dimension::TIME).to_string(),
+            "L²·T⁻¹"
+        );
+        assert_eq!(
+            (Dimension::MASS * Dimension::LENGTH.powi(-12)).to_string(),
+            "L⁻¹²·M"
+        );
+    }
+
+    #[test]
+    fn test_heat_equation_homogeneous() {
+        let equation = Equation::from_str("dt(T) = k * laplacian(T)").unwrap();
+        heat_checker().check_equation(&equation).unwrap();
+    }
+
+    #[test]
+    fn test_heat_equation_inhomogeneous() {
+        let equation = Equation::from_str("dt(T) = k * T").unwrap();
+        let err = heat_checker().check_equation(&equation).unwrap_err();
+        assert!(matches!(
+            err,
+            DimensionError::Inhomogeneous { found, .. } if found == Dimension::TEMPERATURE * Dimension::LENGTH.powi(2) / Dimension::TIME
+        ));
+    }
+
+    #[test]
+    fn test_add_reports_offending_term() {
+        let equation = Equation::from_str("dt(T) - k * laplacian * T + T = 0").unwrap();
+        let err = heat_checker().check_equation(&equation).unwrap_err();
+        let DimensionError::Inhomogeneous { term, .. } = err else {
+            panic!("expected an inhomogeneous error, got {err}");
+        };
+        assert_eq!(term, "T");
+    }
+
+    #[test]
+    fn test_unconstrained_symbols() {
+        let equation = Equation::from_str("dt(T) = k * laplacian(T) + f").unwrap();
+        heat_checker().check_equation(&equation).unwrap();
+    }
+
+    #[test]
+    fn test_function_argument_dimensionless() {
+        let checker = heat_checker();
+        checker
+            .dimension(&*"sin(x^2 / (k * t))".parse::<Box<dyn Expr>>().unwrap())
+            .unwrap();
+        assert!(
+            checker
+                .dimension(&*"sin(T)".parse::<Box<dyn Expr>>().unwrap())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_atan2_dimension() {
+        let checker = heat_checker();
+        let dimension = checker
+            .dimension(&*"atan2(y, x)".parse::<Box<dyn Expr>>().unwrap())
+            .unwrap();
+        assert_eq!(dimension, Some(Dimension::DIMENSIONLESS));
+        assert!(matches!(
+            checker.dimension(&*"atan2(y, t)".parse::<Box<dyn Expr>>().unwrap()),
+            Err(DimensionError::Inhomogeneous { found, .. }) if found == Dimension::TIME
+        ));
+    }
+
+    #[test]
+    fn test_user_function_dimension() {
+        let mut checker = heat_checker();
+        // Applied user functions are unconstrained unless their dimension is known
+        checker
+            .check_equation(&Equation::from_str("dt(T) = k * laplacian(T) + f(x)").unwrap())
+            .unwrap();
+        checker
+            .dimension(&*"g(T)".parse::<Box<dyn Expr>>().unwrap())
+            .unwrap();
+        checker.insert("f", Dimension::TEMPERATURE / Dimension::TIME);
+        checker
+            .check_equation(&Equation::from_str("dt(T) = k * laplacian(T) + f(x, t)").unwrap())
+            .unwrap();
+        checker.insert("f", Dimension::TEMPERATURE);
+        assert!(
+            checker
+                .check_equation(&Equation::from_str("dt(T) = f(x)").unwrap())
+                .is_err()
+        );
+        assert_eq!(
+            checker
+                .dimension(&*"sqrt(k * t)".parse::<Box<dyn Expr>>().unwrap())
+                .unwrap(),
+            Some(Dimension::LENGTH)
+        );
+    }
+}
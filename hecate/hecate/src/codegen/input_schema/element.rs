@@ -0,0 +1,195 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// # Finite Element
+/// The finite element to use for the mesh.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, Default, PartialEq, Eq)]
+pub enum FiniteElement {
+    Q1,
+    #[default]
+    Q2,
+    Q3,
+
+    /// # Lagrange Element
+    /// Continuous Lagrange element of any degree on quadrilaterals and hexahedra.
+    Q(u32),
+
+    /// # Discontinuous Lagrange Element
+    /// Discontinuous Lagrange element on quadrilaterals and hexahedra.
+    /// The Laplacian is assembled with the symmetric interior penalty method, and the boundary
+    /// conditions must be natural (Neumann or Robin).
+    DGQ(u32),
+
+    /// # Simplex Lagrange Element
+    /// Continuous Lagrange element on triangles and tetrahedra, for meshes read from files.
+    /// Degrees 1 to 3 are supported.
+    P(u32),
+
+    /// # Element System
+    /// Combination of elements, each describing components of vector-valued unknowns.
+    FESystem(Vec<ElementComponent>),
+}
+
+/// # Element Component
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct ElementComponent {
+    pub element: FiniteElement,
+
+    /// # Multiplicity
+    /// The number of components described by the element.
+    #[serde(default = "default_multiplicity")]
+    pub multiplicity: u32,
+}
+
+fn default_multiplicity() -> u32 {
+    1
+}
+
+#[derive(Error, Debug)]
+pub enum ElementError {
+    #[error("degree {degree} is not supported by {family} elements")]
+    InvalidDegree { family: &'static str, degree: u32 },
+    #[error("element system mixes simplex and hypercube elements")]
+    MixedCellKinds,
+    #[error("element system is empty")]
+    EmptySystem,
+}
+
+impl FiniteElement {
+    /// The highest polynomial degree of the element, from which quadratures are sized.
+    pub fn degree(&self) -> u32 {
+        match self {
+            FiniteElement::Q1 => 1,
+            FiniteElement::Q2 => 2,
+            FiniteElement::Q3 => 3,
+            FiniteElement::Q(degree) | FiniteElement::DGQ(degree) | FiniteElement::P(degree) => {
+                *degree
+            }
+            FiniteElement::FESystem(components) => components
+                .iter()
+                .map(|c| c.element.degree())
+                .max()
+                .unwrap_or(0),
+        }
+    }
+
+    /// Whether the element lives on triangles and tetrahedra.
+    pub fn is_simplex(&self) -> bool {
+        match self {
+            FiniteElement::P(_) => true,
+            FiniteElement::FESystem(components) => {
+                components.iter().any(|c| c.element.is_simplex())
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether some of the components of the element are discontinuous across cells.
+    pub fn is_discontinuous(&self) -> bool {
+        match self {
+            FiniteElement::DGQ(_) => true,
+            FiniteElement::FESystem(components) => {
+                components.iter().any(|c| c.element.is_discontinuous())
+            }
+            _ => false,
+        }
+    }
+
+    pub fn n_components(&self) -> u32 {
+        match self {
+            FiniteElement::FESystem(components) => components
+                .iter()
+                .map(|c| c.multiplicity * c.element.n_components())
+                .sum(),
+            _ => 1,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ElementError> {
+        match self {
+            FiniteElement::Q(0) => Err(ElementError::InvalidDegree {
+                family: "Q",
+                degree: 0,
+            }),
+            FiniteElement::P(degree) if !(1..=3).contains(degree) => {
+                Err(ElementError::InvalidDegree {
+                    family: "P",
+                    degree: *degree,
+                })
+            }
+            FiniteElement::FESystem(components) => {
+                if components.is_empty() {
+                    return Err(ElementError::EmptySystem);
+                }
+                for component in components {
+                    component.element.validate()?;
+                }
+                if components.iter().any(|c| c.element.is_simplex())
+                    && components.iter().any(|c| !c.element.is_simplex())
+                {
+                    return Err(ElementError::MixedCellKinds);
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_elements() {
+        let elements: Vec<FiniteElement> = serde_yaml::with::singleton_map_recursive::deserialize(
+            serde_yaml::Deserializer::from_str(
+                r"
+- Q1
+- Q: 4
+- DGQ: 0
+- P: 2
+- FESystem:
+  - element: Q2
+    multiplicity: 2
+  - element: Q1
+",
+            ),
+        )
+        .unwrap();
+
+        assert_eq!(elements[0].degree(), 1);
+        assert_eq!(elements[1], FiniteElement::Q(4));
+        assert!(elements[2].is_discontinuous());
+        assert!(elements[3].is_simplex());
+        assert_eq!(elements[4].degree(), 2);
+        assert_eq!(elements[4].n_components(), 3);
+        assert!(elements.iter().all(|e| e.validate().is_ok()));
+    }
+
+    #[test]
+    fn test_validate_elements() {
+        assert!(matches!(
+            FiniteElement::P(4).validate(),
+            Err(ElementError::InvalidDegree {
+                family: "P",
+                degree: 4
+            })
+        ));
+        let mixed = FiniteElement::FESystem(vec![
+            ElementComponent {
+                element: FiniteElement::P(2),
+                multiplicity: 2,
+            },
+            ElementComponent {
+                element: FiniteElement::Q1,
+                multiplicity: 1,
+            },
+        ]);
+        assert!(matches!(
+            mixed.validate(),
+            Err(ElementError::MixedCellKinds)
+        ));
+    }
+}
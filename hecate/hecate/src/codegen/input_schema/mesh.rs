@@ -7,6 +7,13 @@ use serde::{Deserialize, Serialize};
 use crate::codegen::input_schema::quantity::Length;
 
 use super::range::Range;
+use super::{Condition, IndexMap};
+
+pub mod file;
+pub use file::FileMesh;
+
+/// Names of the faces of a colorized hyper rectangle, ordered by boundary id.
+const COLORIZED_FACES: [&str; 6] = ["left", "right", "bottom", "top", "back", "front"];
 
 #[typetag::serde(tag = "type")]
 pub trait Mesh: std::fmt::Debug + DynClone + Any {
@@ -15,6 +22,144 @@ pub trait Mesh: std::fmt::Debug + DynClone + Any {
     fn as_any(&self) -> &dyn Any {
         self.get_ref() as &dyn Any
     }
+
+    /// Named parts of the boundary, with their boundary id.
+    fn boundaries(&self, _dimension: usize) -> indexmap::IndexMap<String, u32> {
+        indexmap::IndexMap::new()
+    }
+
+    /// Every boundary id found on the mesh, in increasing order.
+    fn boundary_ids(&self, dimension: usize) -> Vec<u32> {
+        let mut ids = self.boundaries(dimension).into_values().collect::<Vec<_>>();
+        if !ids.contains(&0) {
+            ids.push(0);
+        }
+        ids.sort();
+        ids.dedup();
+        ids
+    }
+
+    /// Named regions of the domain, with their material id.
+    fn regions(&self) -> indexmap::IndexMap<String, u32> {
+        indexmap::IndexMap::new()
+    }
+}
+
+/// # Coordinate Conditions
+/// Selects the part of the domain matching all the given coordinate conditions.
+/// Each condition can be none, a value or a range.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct CoordinateConditions {
+    pub x: Option<Condition<Length>>,
+    pub y: Option<Condition<Length>>,
+    pub z: Option<Condition<Length>>,
+}
+
+/// # Mesh Parts
+/// Named parts of the boundary and regions of a mesh.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct MeshParts {
+    /// # Boundaries
+    /// Named parts of the boundary, made of the boundary faces whose center matches the
+    /// coordinate conditions.
+    pub boundaries: IndexMap<String, CoordinateConditions>,
+
+    /// # Regions
+    /// Named regions of the domain, made of the cells whose center matches the coordinate
+    /// conditions.
+    pub regions: IndexMap<String, CoordinateConditions>,
+}
+
+impl MeshParts {
+    /// Names the boundary ids set by the generator of the mesh, followed by the named parts
+    /// numbered from `first_id`.
+    fn boundaries(&self, generated: &[&str], first_id: u32) -> indexmap::IndexMap<String, u32> {
+        let mut boundaries: indexmap::IndexMap<String, u32> = generated
+            .iter()
+            .map(|name| name.to_string())
+            .zip(0..)
+            .collect();
+        boundaries.extend(self.boundaries.keys().cloned().zip(first_id..));
+        boundaries
+    }
+
+    fn regions(&self) -> indexmap::IndexMap<String, u32> {
+        // Cells outside of the named regions keep the material id 0
+        self.regions.keys().cloned().zip(1..).collect()
+    }
+}
+
+/// # Point
+/// A point of the domain, coordinates left out being zero.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct MeshPoint {
+    pub x: Length,
+    pub y: Length,
+    pub z: Length,
+}
+
+impl Default for MeshPoint {
+    fn default() -> Self {
+        MeshPoint {
+            x: length("0 m"),
+            y: length("0 m"),
+            z: length("0 m"),
+        }
+    }
+}
+
+impl MeshPoint {
+    pub fn coordinates(&self) -> [f64; 3] {
+        [self.x.meters(), self.y.meters(), self.z.meters()]
+    }
+}
+
+/// # Resolution
+/// The size of each cell, either the same along every axis or per axis.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum Resolution {
+    Uniform(Length),
+    PerAxis {
+        #[serde(default = "default_resolution")]
+        x: Length,
+        #[serde(default = "default_resolution")]
+        y: Length,
+        #[serde(default = "default_resolution")]
+        z: Length,
+    },
+}
+
+impl Default for Resolution {
+    fn default() -> Self {
+        Resolution::Uniform(default_resolution())
+    }
+}
+
+impl Resolution {
+    /// The requested size of the cells along the axis (0 for x, 1 for y, 2 for z).
+    pub fn along(&self, axis: usize) -> &Length {
+        match self {
+            Resolution::Uniform(resolution) => resolution,
+            Resolution::PerAxis { x, y, z } => [x, y, z][axis],
+        }
+    }
+}
+
+fn length(raw: &str) -> Length {
+    raw.parse().expect("valid length")
+}
+
+fn default_resolution() -> Length {
+    length("0.03125 m")
+}
+
+/// Number of global refinements of a coarse cell of size `size` needed to get cells no larger
+/// than `resolution`.
+pub fn refinements(size: f64, resolution: f64) -> u32 {
+    (size / resolution).abs().log2().ceil().max(0.) as u32
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
@@ -22,18 +167,52 @@ pub trait Mesh: std::fmt::Debug + DynClone + Any {
 #[serde(rename_all = "snake_case")]
 pub enum MeshEnum {
     HyperCube(HyperCubeMesh),
+    HyperRectangle(HyperRectangleMesh),
+    HyperBall(HyperBallMesh),
+    HyperShell(HyperShellMesh),
+    LShape(LShapeMesh),
+    Cylinder(CylinderMesh),
+    File(FileMesh),
 }
 
 impl MeshEnum {
     pub fn get_ref(&self) -> &dyn Mesh {
         match self {
             MeshEnum::HyperCube(m) => m.get_ref(),
+            MeshEnum::HyperRectangle(m) => m.get_ref(),
+            MeshEnum::HyperBall(m) => m.get_ref(),
+            MeshEnum::HyperShell(m) => m.get_ref(),
+            MeshEnum::LShape(m) => m.get_ref(),
+            MeshEnum::Cylinder(m) => m.get_ref(),
+            MeshEnum::File(m) => m.get_ref(),
+        }
+    }
+
+    /// Halves the size of the cells, refining meshes read from a file once more.
+    pub fn refine(&mut self) {
+        let halve = |resolution: &mut Length| {
+            *resolution = length(&format!("{} m", resolution.meters() / 2.));
+        };
+        match self {
+            MeshEnum::HyperCube(m) => halve(&mut m.resolution),
+            MeshEnum::HyperRectangle(m) => match &mut m.resolution {
+                Resolution::Uniform(resolution) => halve(resolution),
+                Resolution::PerAxis { x, y, z } => [x, y, z].into_iter().for_each(halve),
+            },
+            MeshEnum::HyperBall(m) => halve(&mut m.resolution),
+            MeshEnum::HyperShell(m) => halve(&mut m.resolution),
+            MeshEnum::LShape(m) => halve(&mut m.resolution),
+            MeshEnum::Cylinder(m) => halve(&mut m.resolution),
+            MeshEnum::File(m) => m.refinements += 1,
         }
     }
 }
 
 dyn_clone::clone_trait_object!(Mesh);
 
+/// # Hyper Cube Mesh
+/// A square (2D) or cube (3D) refined globally until the cells are no larger than the
+/// resolution, the number of cells along each axis thus being a power of two.
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(default)]
 pub struct HyperCubeMesh {
@@ -43,21 +222,410 @@ pub struct HyperCubeMesh {
     /// The size of each cell.
     pub resolution: Length,
     pub show_info: bool,
+
+    /// # Colorize
+    /// Whether to name the faces of the cube: left and right along x, bottom and top along y,
+    /// back and front along z.
+    pub colorize: bool,
+
+    #[serde(flatten)]
+    pub parts: MeshParts,
 }
 
 impl Default for HyperCubeMesh {
     fn default() -> Self {
         HyperCubeMesh {
             range: "0 m .. 1 m".parse().unwrap(),
-            resolution: "0.03125 m".parse().expect("valid length"),
+            resolution: default_resolution(),
             show_info: false,
+            colorize: false,
+            parts: MeshParts::default(),
         }
     }
 }
 
+/// Boundaries of a hyper rectangle. Named parts get ids after the ones of the faces of a
+/// colorized rectangle, whatever the dimension, and unnamed faces keep the boundary id 0 when
+/// the rectangle is not colorized.
+fn rectangle_boundaries(
+    parts: &MeshParts,
+    colorize: bool,
+    dimension: usize,
+) -> indexmap::IndexMap<String, u32> {
+    if colorize {
+        parts.boundaries(
+            &COLORIZED_FACES[..2 * dimension.min(3)],
+            COLORIZED_FACES.len() as u32,
+        )
+    } else {
+        parts.boundaries(&[], 1)
+    }
+}
+
 #[typetag::serde(name = "hyper_cube")]
 impl Mesh for HyperCubeMesh {
     fn get_ref(&self) -> &dyn Mesh {
         self as &dyn Mesh
     }
+
+    fn boundaries(&self, dimension: usize) -> indexmap::IndexMap<String, u32> {
+        rectangle_boundaries(&self.parts, self.colorize, dimension)
+    }
+
+    fn regions(&self) -> indexmap::IndexMap<String, u32> {
+        self.parts.regions()
+    }
+}
+
+/// # Hyper Rectangle Mesh
+/// A rectangle (2D) or box (3D) with its own extent and number of cells along each axis.
+/// The number of cells along an axis is the smallest one giving cells no larger than the
+/// resolution.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct HyperRectangleMesh {
+    pub x: Range<Length>,
+    pub y: Range<Length>,
+    pub z: Range<Length>,
+    pub resolution: Resolution,
+    pub show_info: bool,
+
+    /// # Colorize
+    /// Whether to name the faces of the rectangle: left and right along x, bottom and top
+    /// along y, back and front along z.
+    pub colorize: bool,
+
+    #[serde(flatten)]
+    pub parts: MeshParts,
+}
+
+impl Default for HyperRectangleMesh {
+    fn default() -> Self {
+        HyperRectangleMesh {
+            x: "0 m .. 1 m".parse().unwrap(),
+            y: "0 m .. 1 m".parse().unwrap(),
+            z: "0 m .. 1 m".parse().unwrap(),
+            resolution: Resolution::default(),
+            show_info: false,
+            colorize: false,
+            parts: MeshParts::default(),
+        }
+    }
+}
+
+impl HyperRectangleMesh {
+    pub fn ranges(&self) -> [&Range<Length>; 3] {
+        [&self.x, &self.y, &self.z]
+    }
+
+    /// The number of cells along each axis.
+    pub fn subdivisions(&self) -> [u64; 3] {
+        let mut subdivisions = [1; 3];
+        for (axis, range) in self.ranges().into_iter().enumerate() {
+            let extent = (range.end.meters() - range.start.meters()).abs();
+            let cells = extent / self.resolution.along(axis).meters();
+            // Tolerates the rounding errors of resolutions dividing the extent
+            subdivisions[axis] = ((cells - 1e-9).ceil() as u64).max(1);
+        }
+        subdivisions
+    }
+}
+
+#[typetag::serde(name = "hyper_rectangle")]
+impl Mesh for HyperRectangleMesh {
+    fn get_ref(&self) -> &dyn Mesh {
+        self as &dyn Mesh
+    }
+
+    fn boundaries(&self, dimension: usize) -> indexmap::IndexMap<String, u32> {
+        rectangle_boundaries(&self.parts, self.colorize, dimension)
+    }
+
+    fn regions(&self) -> indexmap::IndexMap<String, u32> {
+        self.parts.regions()
+    }
+}
+
+/// # Hyper Ball Mesh
+/// A disk (2D) or ball (3D) refined globally until the cells are about the size of the
+/// resolution.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct HyperBallMesh {
+    pub center: MeshPoint,
+    pub radius: Length,
+
+    /// # Resolution
+    /// The approximate size of each cell.
+    pub resolution: Length,
+    pub show_info: bool,
+
+    #[serde(flatten)]
+    pub parts: MeshParts,
+}
+
+impl Default for HyperBallMesh {
+    fn default() -> Self {
+        HyperBallMesh {
+            center: MeshPoint::default(),
+            radius: length("1 m"),
+            resolution: default_resolution(),
+            show_info: false,
+            parts: MeshParts::default(),
+        }
+    }
+}
+
+#[typetag::serde(name = "hyper_ball")]
+impl Mesh for HyperBallMesh {
+    fn get_ref(&self) -> &dyn Mesh {
+        self as &dyn Mesh
+    }
+
+    fn boundaries(&self, _dimension: usize) -> indexmap::IndexMap<String, u32> {
+        self.parts.boundaries(&[], 1)
+    }
+
+    fn regions(&self) -> indexmap::IndexMap<String, u32> {
+        self.parts.regions()
+    }
+}
+
+/// # Hyper Shell Mesh
+/// The space between two concentric circles (2D) or spheres (3D), refined globally until the
+/// cells are no thicker than the resolution.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct HyperShellMesh {
+    pub center: MeshPoint,
+    pub inner_radius: Length,
+    pub outer_radius: Length,
+
+    /// # Resolution
+    /// The size of each cell along the radius.
+    pub resolution: Length,
+    pub show_info: bool,
+
+    /// # Colorize
+    /// Whether to name the inner and outer boundaries.
+    pub colorize: bool,
+
+    #[serde(flatten)]
+    pub parts: MeshParts,
+}
+
+impl Default for HyperShellMesh {
+    fn default() -> Self {
+        HyperShellMesh {
+            center: MeshPoint::default(),
+            inner_radius: length("0.5 m"),
+            outer_radius: length("1 m"),
+            resolution: default_resolution(),
+            show_info: false,
+            colorize: false,
+            parts: MeshParts::default(),
+        }
+    }
+}
+
+#[typetag::serde(name = "hyper_shell")]
+impl Mesh for HyperShellMesh {
+    fn get_ref(&self) -> &dyn Mesh {
+        self as &dyn Mesh
+    }
+
+    fn boundaries(&self, _dimension: usize) -> indexmap::IndexMap<String, u32> {
+        if self.colorize {
+            self.parts.boundaries(&["inner", "outer"], 2)
+        } else {
+            self.parts.boundaries(&[], 1)
+        }
+    }
+
+    fn regions(&self) -> indexmap::IndexMap<String, u32> {
+        self.parts.regions()
+    }
+}
+
+/// # L-Shape Mesh
+/// The hyper cube over the range without its upper right quadrant (2D) or octant (3D),
+/// refined globally until the cells are no larger than the resolution.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct LShapeMesh {
+    pub range: Range<Length>,
+
+    /// # Resolution
+    /// The size of each cell.
+    pub resolution: Length,
+    pub show_info: bool,
+
+    #[serde(flatten)]
+    pub parts: MeshParts,
+}
+
+impl Default for LShapeMesh {
+    fn default() -> Self {
+        LShapeMesh {
+            range: "-1 m .. 1 m".parse().unwrap(),
+            resolution: default_resolution(),
+            show_info: false,
+            parts: MeshParts::default(),
+        }
+    }
+}
+
+#[typetag::serde(name = "l_shape")]
+impl Mesh for LShapeMesh {
+    fn get_ref(&self) -> &dyn Mesh {
+        self as &dyn Mesh
+    }
+
+    fn boundaries(&self, _dimension: usize) -> indexmap::IndexMap<String, u32> {
+        self.parts.boundaries(&[], 1)
+    }
+
+    fn regions(&self) -> indexmap::IndexMap<String, u32> {
+        self.parts.regions()
+    }
+}
+
+/// Names of the boundary ids set by the cylinder generator.
+const CYLINDER_FACES: [&str; 3] = ["hull", "left", "right"];
+
+/// # Cylinder Mesh
+/// A cylinder centered at the origin with its axis along x, refined globally until the cells
+/// are about the size of the resolution. Its hull and its left and right ends are named
+/// boundaries. Only meaningful in 3D.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct CylinderMesh {
+    pub radius: Length,
+    pub half_length: Length,
+
+    /// # Resolution
+    /// The approximate size of each cell.
+    pub resolution: Length,
+    pub show_info: bool,
+
+    #[serde(flatten)]
+    pub parts: MeshParts,
+}
+
+impl Default for CylinderMesh {
+    fn default() -> Self {
+        CylinderMesh {
+            radius: length("1 m"),
+            half_length: length("1 m"),
+            resolution: default_resolution(),
+            show_info: false,
+            parts: MeshParts::default(),
+        }
+    }
+}
+
+#[typetag::serde(name = "cylinder")]
+impl Mesh for CylinderMesh {
+    fn get_ref(&self) -> &dyn Mesh {
+        self as &dyn Mesh
+    }
+
+    fn boundaries(&self, _dimension: usize) -> indexmap::IndexMap<String, u32> {
+        self.parts
+            .boundaries(&CYLINDER_FACES, CYLINDER_FACES.len() as u32)
+    }
+
+    fn regions(&self) -> indexmap::IndexMap<String, u32> {
+        self.parts.regions()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hyper_cube_boundaries() {
+        let mesh: HyperCubeMesh = serde_yaml::from_str(
+            r"
+colorize: true
+boundaries:
+  inlet:
+    x: -1 m
+    y: -0.33 m .. 0.33 m
+regions:
+  core:
+    x: 0.2 m .. 0.4 m
+",
+        )
+        .unwrap();
+
+        let boundaries = mesh.boundaries(2);
+        assert_eq!(
+            boundaries.into_iter().collect::<Vec<_>>(),
+            [
+                ("left".to_string(), 0),
+                ("right".to_string(), 1),
+                ("bottom".to_string(), 2),
+                ("top".to_string(), 3),
+                ("inlet".to_string(), 6),
+            ]
+        );
+        assert_eq!(mesh.boundary_ids(1), [0, 1, 6]);
+        assert_eq!(mesh.regions()["core"], 1);
+
+        let mesh = HyperCubeMesh {
+            colorize: false,
+            ..mesh
+        };
+        assert_eq!(mesh.boundaries(2)["inlet"], 1);
+        assert_eq!(mesh.boundary_ids(2), [0, 1]);
+    }
+
+    #[test]
+    fn test_hyper_rectangle_subdivisions() {
+        let mesh: HyperRectangleMesh = serde_yaml::from_str(
+            r"
+x: 0 m .. 2 m
+y: -0.5 m .. 0.5 m
+resolution:
+  x: 0.3 m
+  y: 0.1 m
+",
+        )
+        .unwrap();
+        assert_eq!(mesh.subdivisions(), [7, 10, 32]);
+
+        let mesh: HyperRectangleMesh = serde_yaml::from_str("resolution: 0.4 m").unwrap();
+        assert_eq!(mesh.subdivisions(), [3, 3, 3]);
+        assert_eq!(mesh.boundary_ids(3), [0]);
+    }
+
+    #[test]
+    fn test_refinements() {
+        assert_eq!(refinements(1., 0.03125), 5);
+        assert_eq!(refinements(1., 0.03), 6);
+        assert_eq!(refinements(1., 2.), 0);
+    }
+
+    #[test]
+    fn test_hyper_shell_boundaries() {
+        let mesh: HyperShellMesh = serde_yaml::from_str(
+            r"
+colorize: true
+boundaries:
+  top:
+    y: 0 m .. 1 m
+",
+        )
+        .unwrap();
+        assert_eq!(
+            mesh.boundaries(2).into_iter().collect::<Vec<_>>(),
+            [
+                ("inner".to_string(), 0),
+                ("outer".to_string(), 1),
+                ("top".to_string(), 2),
+            ]
+        );
+    }
 }
@@ -0,0 +1,575 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::super::IndexMap;
+use super::Mesh;
+
+/// # File Mesh
+/// A mesh read from a file, for instance made with Gmsh.
+/// The physical groups of the mesh are kept: the tags of the groups of faces become boundary
+/// ids and the tags of the groups of cells become material ids.
+/// Cells are quadrilaterals (2D) or hexahedra (3D), or triangles and tetrahedra for the simplex
+/// `P` elements. The cells of binary and registered files are not checked against the element.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FileMesh {
+    pub file: MeshSource,
+
+    /// # Format
+    /// The format of the file, guessed from its extension when left out.
+    #[serde(default)]
+    pub format: Option<MeshFormat>,
+
+    /// # Refinements
+    /// The number of global refinements of the mesh read from the file.
+    #[serde(default)]
+    pub refinements: u32,
+
+    #[serde(default)]
+    pub show_info: bool,
+
+    /// # Boundaries
+    /// Names of boundary ids of the file. The physical names of a Gmsh file are used as well.
+    #[serde(default = "IndexMap::<String, u32>::new")]
+    pub boundaries: IndexMap<String, u32>,
+
+    /// # Regions
+    /// Names of material ids of the file. The physical names of a Gmsh file are used as well.
+    #[serde(default = "IndexMap::<String, u32>::new")]
+    pub regions: IndexMap<String, u32>,
+}
+
+/// # Mesh Source
+/// Where to find the mesh file.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum MeshSource {
+    /// # Path
+    /// The path of the file, relative to the working directory.
+    Path(String),
+
+    /// # Registered File
+    /// A file registered in the database of Hecate, copied from the host it is stored on.
+    Registered { id: i32 },
+}
+
+/// # Mesh Format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MeshFormat {
+    /// Gmsh mesh (.msh)
+    Msh,
+    /// AVS UCD mesh (.inp, .ucd)
+    Ucd,
+    /// Legacy VTK unstructured grid (.vtk)
+    Vtk,
+    /// ExodusII mesh (.e, .exo), needs deal.II built with ExodusII support
+    Exodusii,
+}
+
+impl MeshFormat {
+    pub fn from_extension(extension: &str) -> Option<MeshFormat> {
+        Some(match extension.to_lowercase().as_str() {
+            "msh" => MeshFormat::Msh,
+            "inp" | "ucd" => MeshFormat::Ucd,
+            "vtk" => MeshFormat::Vtk,
+            "e" | "exo" | "ex2" => MeshFormat::Exodusii,
+            _ => None?,
+        })
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            MeshFormat::Msh => "msh",
+            MeshFormat::Ucd => "inp",
+            MeshFormat::Vtk => "vtk",
+            MeshFormat::Exodusii => "exo",
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum MeshFileError {
+    #[error("failed to read mesh file {path}")]
+    Read {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("format of mesh file {0} cannot be guessed from its extension")]
+    UnknownFormat(String),
+    #[error("malformed mesh file: {0}")]
+    Malformed(String),
+}
+
+/// The shape of the cells of a mesh.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CellShape {
+    /// Triangles and tetrahedra
+    Simplex,
+    /// Quadrilaterals and hexahedra
+    Hypercube,
+}
+
+impl std::fmt::Display for CellShape {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            CellShape::Simplex => "triangles or tetrahedra",
+            CellShape::Hypercube => "quadrilaterals or hexahedra",
+        })
+    }
+}
+
+/// The header of a mesh file: its dimension, the tags of its physical groups and the shapes of
+/// its cells.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MeshHeader {
+    pub dimension: usize,
+    /// The tags found on the entities of each dimension.
+    pub tags: BTreeMap<usize, BTreeSet<u32>>,
+    /// The named tags with the dimension of their entities.
+    pub names: Vec<(usize, u32, String)>,
+    /// The shapes of the entities of each dimension, from two dimensions.
+    pub shapes: BTreeMap<usize, BTreeSet<CellShape>>,
+}
+
+impl MeshHeader {
+    /// The named tags of the entities of the dimension.
+    pub fn names_of(&self, dimension: usize) -> indexmap::IndexMap<String, u32> {
+        self.names
+            .iter()
+            .filter(|(dim, _, _)| *dim == dimension)
+            .map(|(_, tag, name)| (name.clone(), *tag))
+            .collect()
+    }
+
+    /// The shapes of the cells, the entities of the dimension of the mesh.
+    pub fn cell_shapes(&self) -> BTreeSet<CellShape> {
+        self.shapes
+            .get(&self.dimension)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn insert_shape(&mut self, dimension: usize, shape: Option<CellShape>) {
+        if let Some(shape) = shape {
+            self.shapes.entry(dimension).or_default().insert(shape);
+        }
+    }
+}
+
+impl FileMesh {
+    pub fn format(&self) -> Result<MeshFormat, MeshFileError> {
+        if let Some(format) = self.format {
+            return Ok(format);
+        }
+        match &self.file {
+            MeshSource::Path(path) => Path::new(path)
+                .extension()
+                .and_then(|extension| MeshFormat::from_extension(&extension.to_string_lossy()))
+                .ok_or_else(|| MeshFileError::UnknownFormat(path.clone())),
+            MeshSource::Registered { id } => Err(MeshFileError::UnknownFormat(format!("#{id}"))),
+        }
+    }
+
+    /// The name of the file once placed next to the generated sources.
+    pub fn file_name(&self) -> Result<String, MeshFileError> {
+        Ok(match &self.file {
+            MeshSource::Path(path) => Path::new(path)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .ok_or_else(|| MeshFileError::UnknownFormat(path.clone()))?,
+            MeshSource::Registered { id } => format!("mesh_{id}.{}", self.format()?.extension()),
+        })
+    }
+
+    /// Reads the header of the file, when it is available locally in a format Hecate can read.
+    pub fn header(&self) -> Result<Option<MeshHeader>, MeshFileError> {
+        let MeshSource::Path(path) = &self.file else {
+            return Ok(None);
+        };
+        let format = self.format()?;
+        if format == MeshFormat::Exodusii {
+            return Ok(None);
+        }
+        let content = std::fs::read(path).map_err(|source| MeshFileError::Read {
+            path: path.clone(),
+            source,
+        })?;
+        parse_header(&String::from_utf8_lossy(&content), format)
+    }
+}
+
+#[typetag::serde(name = "file")]
+impl Mesh for FileMesh {
+    fn get_ref(&self) -> &dyn Mesh {
+        self as &dyn Mesh
+    }
+
+    fn boundaries(&self, dimension: usize) -> indexmap::IndexMap<String, u32> {
+        let mut boundaries = match self.header() {
+            Ok(Some(header)) => header.names_of(dimension.saturating_sub(1)),
+            _ => indexmap::IndexMap::new(),
+        };
+        boundaries.extend(self.boundaries.iter().map(|(name, id)| (name.clone(), *id)));
+        boundaries
+    }
+
+    fn boundary_ids(&self, dimension: usize) -> Vec<u32> {
+        let mut ids = self.boundaries(dimension).into_values().collect::<Vec<_>>();
+        if let Ok(Some(header)) = self.header() {
+            ids.extend(
+                header
+                    .tags
+                    .get(&dimension.saturating_sub(1))
+                    .into_iter()
+                    .flatten(),
+            );
+        }
+        ids.push(0);
+        ids.sort();
+        ids.dedup();
+        ids
+    }
+
+    fn regions(&self) -> indexmap::IndexMap<String, u32> {
+        let mut regions = match self.header() {
+            Ok(Some(header)) => header.names_of(header.dimension),
+            _ => indexmap::IndexMap::new(),
+        };
+        regions.extend(self.regions.iter().map(|(name, id)| (name.clone(), *id)));
+        regions
+    }
+}
+
+/// Parses the header of a mesh file, returning `None` for files that cannot be read as text.
+pub fn parse_header(
+    content: &str,
+    format: MeshFormat,
+) -> Result<Option<MeshHeader>, MeshFileError> {
+    match format {
+        MeshFormat::Msh => parse_msh_header(content),
+        MeshFormat::Ucd => parse_ucd_header(content).map(Some),
+        MeshFormat::Vtk => parse_vtk_header(content).map(Some),
+        MeshFormat::Exodusii => Ok(None),
+    }
+}
+
+fn numbers<T: std::str::FromStr>(line: &str) -> Result<Vec<T>, MeshFileError> {
+    line.split_whitespace()
+        .map(|word| {
+            word.parse()
+                .map_err(|_| MeshFileError::Malformed(format!("expected numbers, found {line:?}")))
+        })
+        .collect()
+}
+
+/// The shape of the Gmsh element type, for triangles, quadrilaterals, tetrahedra and
+/// hexahedra of the first and second orders.
+fn msh_cell_shape(element_type: u32) -> Option<CellShape> {
+    match element_type {
+        2 | 4 | 9 | 11 => Some(CellShape::Simplex),
+        3 | 5 | 10 | 12 | 16 | 17 => Some(CellShape::Hypercube),
+        _ => None,
+    }
+}
+
+/// Returns the lines of the Gmsh section `name`, if present.
+fn msh_section<'a>(content: &'a str, name: &str) -> Option<Vec<&'a str>> {
+    let mut lines = content
+        .lines()
+        .skip_while(|line| line.trim() != format!("${name}"));
+    lines.next()?;
+    Some(
+        lines
+            .take_while(|line| line.trim() != format!("$End{name}"))
+            .collect(),
+    )
+}
+
+fn parse_msh_header(content: &str) -> Result<Option<MeshHeader>, MeshFileError> {
+    let format = msh_section(content, "MeshFormat")
+        .ok_or_else(|| MeshFileError::Malformed("missing $MeshFormat section".to_string()))?;
+    let [version, file_type, ..] = format
+        .first()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>())
+        .unwrap_or_default()[..]
+    else {
+        return Err(MeshFileError::Malformed(
+            "empty $MeshFormat section".to_string(),
+        ));
+    };
+    if file_type != "0" {
+        // Binary files only have their physical names readable as text
+        return Ok(None);
+    }
+
+    let mut header = MeshHeader::default();
+    for line in msh_section(content, "PhysicalNames")
+        .unwrap_or_default()
+        .iter()
+        .skip(1)
+    {
+        let (dimension, rest) = line.trim().split_once(' ').unwrap_or_default();
+        let (tag, name) = rest.trim().split_once(' ').unwrap_or_default();
+        let [dimension, tag] = [dimension, tag].map(|n| n.parse::<u32>());
+        let (Ok(dimension), Ok(tag)) = (dimension, tag) else {
+            return Err(MeshFileError::Malformed(format!(
+                "invalid physical name {line:?}"
+            )));
+        };
+        header.names.push((
+            dimension as usize,
+            tag,
+            name.trim().trim_matches('"').to_string(),
+        ));
+    }
+
+    if version.starts_with('4') {
+        // Entities are listed with their physical tags, per dimension
+        let entities = msh_section(content, "Entities")
+            .ok_or_else(|| MeshFileError::Malformed("missing $Entities section".to_string()))?;
+        let counts: Vec<usize> = numbers(entities.first().copied().unwrap_or_default())?;
+        let mut lines = entities.iter().skip(1);
+        for (dimension, count) in counts.into_iter().enumerate() {
+            if count > 0 {
+                header.dimension = dimension;
+            }
+            // Points have a position where other entities have a bounding box
+            let tags_start = if dimension == 0 { 4 } else { 7 };
+            for line in lines.by_ref().take(count) {
+                let fields: Vec<f64> = numbers(line)?;
+                let n_tags = fields.get(tags_start).copied().unwrap_or_default() as usize;
+                header.tags.entry(dimension).or_default().extend(
+                    fields
+                        .iter()
+                        .skip(tags_start + 1)
+                        .take(n_tags)
+                        .map(|tag| tag.abs() as u32),
+                );
+            }
+        }
+
+        // Elements are listed by blocks of a single type
+        if let Some(elements) = msh_section(content, "Elements") {
+            let mut lines = elements.iter().skip(1);
+            while let Some(line) = lines.next() {
+                let [dimension, _, element_type, count] = numbers::<usize>(line)?[..] else {
+                    return Err(MeshFileError::Malformed(format!(
+                        "invalid element block {line:?}"
+                    )));
+                };
+                header.insert_shape(dimension, msh_cell_shape(element_type as u32));
+                lines.by_ref().take(count).for_each(drop);
+            }
+        }
+    } else {
+        // Each element lists its type and then its tags, the physical one first
+        let elements = msh_section(content, "Elements")
+            .ok_or_else(|| MeshFileError::Malformed("missing $Elements section".to_string()))?;
+        for line in elements.iter().skip(1) {
+            let fields: Vec<u32> = numbers(line)?;
+            let [_, element_type, n_tags, ..] = fields[..] else {
+                return Err(MeshFileError::Malformed(format!(
+                    "invalid element {line:?}"
+                )));
+            };
+            let dimension = match element_type {
+                15 => 0,
+                1 | 8 | 26 | 27 | 28 => 1,
+                2 | 3 | 9 | 10 | 16 | 20 | 21 | 22 | 23 | 24 | 25 => 2,
+                _ => 3,
+            };
+            header.dimension = header.dimension.max(dimension);
+            header.insert_shape(dimension, msh_cell_shape(element_type));
+            if n_tags > 0 {
+                header.tags.entry(dimension).or_default().insert(fields[3]);
+            }
+        }
+    }
+    Ok(Some(header))
+}
+
+fn parse_ucd_header(content: &str) -> Result<MeshHeader, MeshFileError> {
+    let mut lines = content
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'));
+    let counts: Vec<usize> = numbers(lines.next().unwrap_or_default())?;
+    let [n_nodes, n_cells, ..] = counts[..] else {
+        return Err(MeshFileError::Malformed("invalid UCD header".to_string()));
+    };
+
+    let mut header = MeshHeader::default();
+    // Each cell is given as: id material type nodes...
+    for line in lines.skip(n_nodes).take(n_cells) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [_, material, cell_type, ..] = fields[..] else {
+            return Err(MeshFileError::Malformed(format!("invalid cell {line:?}")));
+        };
+        let dimension = match cell_type {
+            "pt" => 0,
+            "line" => 1,
+            "tri" | "quad" => 2,
+            _ => 3,
+        };
+        let material = material
+            .parse()
+            .map_err(|_| MeshFileError::Malformed(format!("invalid material of {line:?}")))?;
+        header.dimension = header.dimension.max(dimension);
+        header.insert_shape(
+            dimension,
+            match cell_type {
+                "tri" | "tet" => Some(CellShape::Simplex),
+                "quad" | "hex" => Some(CellShape::Hypercube),
+                _ => None,
+            },
+        );
+        header.tags.entry(dimension).or_default().insert(material);
+    }
+    Ok(header)
+}
+
+fn parse_vtk_header(content: &str) -> Result<MeshHeader, MeshFileError> {
+    let mut lines = content.lines();
+    lines
+        .by_ref()
+        .find(|line| line.starts_with("CELL_TYPES"))
+        .ok_or_else(|| MeshFileError::Malformed("missing CELL_TYPES section".to_string()))?;
+
+    let mut header = MeshHeader::default();
+    for line in lines.take_while(|line| !line.trim().is_empty() && !line.starts_with("CELL_DATA")) {
+        for cell_type in numbers::<u32>(line)? {
+            let dimension = match cell_type {
+                1 | 2 => 0,
+                3 | 4 => 1,
+                5..=9 => 2,
+                _ => 3,
+            };
+            header.dimension = header.dimension.max(dimension);
+            header.insert_shape(
+                dimension,
+                match cell_type {
+                    5 | 10 | 22 | 24 => Some(CellShape::Simplex),
+                    8 | 9 | 11 | 12 | 23 | 25 => Some(CellShape::Hypercube),
+                    _ => None,
+                },
+            );
+        }
+    }
+    Ok(header)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_msh2_header() {
+        let header = parse_header(
+            r#"$MeshFormat
+2.2 0 8
+$EndMeshFormat
+$PhysicalNames
+2
+1 1 "inlet"
+2 3 "rock"
+$EndPhysicalNames
+$Nodes
+4
+1 0 0 0
+2 1 0 0
+3 1 1 0
+4 0 1 0
+$EndNodes
+$Elements
+2
+1 1 2 1 1 1 2
+2 3 2 3 1 1 2 3 4
+$EndElements
+"#,
+            MeshFormat::Msh,
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(header.dimension, 2);
+        assert_eq!(header.names_of(1)["inlet"], 1);
+        assert_eq!(header.names_of(2)["rock"], 3);
+        assert_eq!(header.tags[&1], BTreeSet::from([1]));
+        assert_eq!(header.tags[&2], BTreeSet::from([3]));
+        assert_eq!(header.cell_shapes(), BTreeSet::from([CellShape::Hypercube]));
+    }
+
+    #[test]
+    fn test_parse_msh4_header() {
+        let header = parse_header(
+            r#"$MeshFormat
+4.1 0 8
+$EndMeshFormat
+$PhysicalNames
+1
+3 7 "basin"
+$EndPhysicalNames
+$Entities
+1 1 0 1
+1 0 0 0 0
+1 0 0 0 1 1 0 1 5 2 1 -1
+1 0 0 0 1 1 1 1 7 6 1 2 3 4 5 6
+$EndEntities
+$Elements
+2 2 1 2
+1 1 1 1
+1 1 2
+3 1 4 1
+2 1 2 3 4
+$EndElements
+"#,
+            MeshFormat::Msh,
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(header.dimension, 3);
+        assert_eq!(header.tags[&1], BTreeSet::from([5]));
+        assert_eq!(header.tags[&3], BTreeSet::from([7]));
+        assert_eq!(header.names_of(3)["basin"], 7);
+        assert_eq!(header.cell_shapes(), BTreeSet::from([CellShape::Simplex]));
+
+        let binary = parse_header("$MeshFormat\n4.1 1 8\n$EndMeshFormat\n", MeshFormat::Msh);
+        assert_eq!(binary.unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_ucd_header() {
+        let header = parse_header(
+            r"# two cells
+4 2 0 0 0
+1 0 0 0
+2 1 0 0
+3 1 1 0
+4 0 1 0
+1 2 quad 1 2 3 4
+2 4 line 1 2
+",
+            MeshFormat::Ucd,
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(header.dimension, 2);
+        assert_eq!(header.tags[&1], BTreeSet::from([4]));
+        assert_eq!(header.cell_shapes(), BTreeSet::from([CellShape::Hypercube]));
+    }
+
+    #[test]
+    fn test_file_name() {
+        let mesh: FileMesh = serde_yaml::from_str("file: meshes/basin.msh").unwrap();
+        assert_eq!(mesh.format().unwrap(), MeshFormat::Msh);
+        assert_eq!(mesh.file_name().unwrap(), "basin.msh");
+
+        let mesh: FileMesh = serde_yaml::from_str("file: {id: 3}\nformat: ucd").unwrap();
+        assert_eq!(mesh.file_name().unwrap(), "mesh_3.inp");
+    }
+}
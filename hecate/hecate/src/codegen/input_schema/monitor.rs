@@ -0,0 +1,333 @@
+use std::str::FromStr;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// # Monitor
+/// A quantity recorded at every time step, appended to a CSV file of the output directory
+/// named after the monitor.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Monitor {
+    /// # Points
+    /// The values of fields at points, such as seismic receivers, with a column per field and
+    /// point.
+    Points {
+        /// # Points
+        /// The coordinates of the points, one per dimension.
+        points: Vec<Vec<f64>>,
+
+        /// # Fields
+        /// The unknowns recorded, or their time derivatives such as `dt_u`.
+        /// All the unknowns of the system are recorded when left out.
+        #[serde(default)]
+        fields: Option<Vec<String>>,
+    },
+
+    /// # Line
+    /// The values of fields at evenly spaced samples of a segment, with a row per sample and
+    /// time step.
+    Line {
+        /// # From
+        /// The coordinates of the first end of the segment.
+        from: Vec<f64>,
+
+        /// # To
+        /// The coordinates of the second end of the segment.
+        to: Vec<f64>,
+
+        /// # Samples
+        /// The number of samples, including both ends of the segment.
+        #[serde(default = "default_samples")]
+        samples: u32,
+
+        /// # Fields
+        /// The unknowns recorded, or their time derivatives such as `dt_u`.
+        /// All the unknowns of the system are recorded when left out.
+        #[serde(default)]
+        fields: Option<Vec<String>>,
+    },
+
+    /// # Norm
+    /// The norm of a field over the whole mesh.
+    Norm {
+        /// # Field
+        /// The unknown, or the time derivative of an unknown such as `dt_u`.
+        field: String,
+
+        /// # Norm
+        #[serde(default)]
+        norm: Norm,
+    },
+
+    /// # Energy
+    /// A weighted sum of squared norms, such as ½‖dt_u‖² + ½c²|u|² with the H¹ seminorm for
+    /// the energy of a wave.
+    Energy {
+        /// # Terms
+        /// The squared norms summed up.
+        terms: Vec<EnergyTerm>,
+    },
+}
+
+/// # Energy Term
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct EnergyTerm {
+    /// # Field
+    /// The unknown, or the time derivative of an unknown such as `dt_u`.
+    pub field: String,
+
+    /// # Norm
+    #[serde(default)]
+    pub norm: Norm,
+
+    /// # Coefficient
+    /// The weight of the squared norm, a number or an expression of the parameters such as
+    /// `0.5 * c^2`.
+    #[serde(default = "default_coefficient")]
+    pub coefficient: Coefficient,
+}
+
+/// # Coefficient
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum Coefficient {
+    Number(f64),
+    Expression(String),
+}
+
+/// # Norm
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Norm {
+    /// # L² Norm
+    /// The square root of the integral of the squared values.
+    #[default]
+    L2,
+    /// # H¹ Seminorm
+    /// The L² norm of the gradient.
+    H1Seminorm,
+    /// # H¹ Norm
+    /// The square root of the sum of the squared L² norm and H¹ seminorm.
+    H1,
+    /// # L∞ Norm
+    /// The largest absolute value at the quadrature points.
+    Linfty,
+}
+
+impl Norm {
+    /// The `VectorTools::NormType` of the norm.
+    pub fn norm_type(&self) -> &'static str {
+        match self {
+            Norm::L2 => "L2_norm",
+            Norm::H1Seminorm => "H1_seminorm",
+            Norm::H1 => "H1_norm",
+            Norm::Linfty => "Linfty_norm",
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum MonitorError {
+    #[error("monitor name {0} must start with a letter and only hold letters, digits and _")]
+    InvalidName(String),
+    #[error("monitor {0} has no point")]
+    NoPoints(String),
+    #[error("point of monitor {monitor} has {found} coordinates instead of {expected}")]
+    PointDimension {
+        monitor: String,
+        found: usize,
+        expected: usize,
+    },
+    #[error("line of monitor {0} needs at least two samples")]
+    TooFewSamples(String),
+    #[error("energy of monitor {0} has no term")]
+    NoTerms(String),
+}
+
+fn default_samples() -> u32 {
+    101
+}
+
+fn default_coefficient() -> Coefficient {
+    Coefficient::Number(1.0)
+}
+
+impl Monitor {
+    pub fn validate(&self, name: &str, dimension: usize) -> Result<(), MonitorError> {
+        let mut chars = name.chars();
+        if !chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+            || !chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            return Err(MonitorError::InvalidName(name.to_string()));
+        }
+        let points: Vec<&Vec<f64>> = match self {
+            Monitor::Points { points, .. } if points.is_empty() => {
+                return Err(MonitorError::NoPoints(name.to_string()));
+            }
+            Monitor::Points { points, .. } => points.iter().collect(),
+            Monitor::Line { samples, .. } if *samples < 2 => {
+                return Err(MonitorError::TooFewSamples(name.to_string()));
+            }
+            Monitor::Line { from, to, .. } => vec![from, to],
+            Monitor::Energy { terms } if terms.is_empty() => {
+                return Err(MonitorError::NoTerms(name.to_string()));
+            }
+            Monitor::Norm { .. } | Monitor::Energy { .. } => vec![],
+        };
+        if let Some(point) = points.iter().find(|point| point.len() != dimension) {
+            return Err(MonitorError::PointDimension {
+                monitor: name.to_string(),
+                found: point.len(),
+                expected: dimension,
+            });
+        }
+        Ok(())
+    }
+
+    /// The fields recorded by the monitor, all the unknowns when `None`.
+    pub fn fields(&self) -> Option<Vec<&str>> {
+        match self {
+            Monitor::Points { fields, .. } | Monitor::Line { fields, .. } => fields
+                .as_ref()
+                .map(|fields| fields.iter().map(|f| &f[..]).collect()),
+            Monitor::Norm { field, .. } => Some(vec![field]),
+            Monitor::Energy { terms } => Some(terms.iter().map(|term| &term.field[..]).collect()),
+        }
+    }
+
+    /// The name of the CSV file written for the monitor `name`.
+    pub fn file_name(name: &str) -> String {
+        format!("{name}.csv")
+    }
+}
+
+/// The records of a monitor, read from its CSV file.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct MonitorSeries {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<f64>>,
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum MonitorSeriesError {
+    #[error("monitor file has no header")]
+    MissingHeader,
+    #[error("line {line} has {found} values instead of {expected}")]
+    RowLength {
+        line: usize,
+        found: usize,
+        expected: usize,
+    },
+    #[error("invalid value {value} on line {line}")]
+    InvalidValue { line: usize, value: String },
+}
+
+impl FromStr for MonitorSeries {
+    type Err = MonitorSeriesError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines();
+        // The last line is being written when it is not terminated yet
+        if !s.ends_with('\n') {
+            lines.next_back();
+        }
+        let columns: Vec<String> = lines
+            .next()
+            .ok_or(MonitorSeriesError::MissingHeader)?
+            .split(',')
+            .map(|column| column.trim().to_string())
+            .collect();
+        let rows = lines
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                let line_number = i + 2;
+                let row = line
+                    .split(',')
+                    .map(|value| {
+                        let value = value.trim();
+                        value.parse().map_err(|_| MonitorSeriesError::InvalidValue {
+                            line: line_number,
+                            value: value.to_string(),
+                        })
+                    })
+                    .collect::<Result<Vec<f64>, _>>()?;
+                if row.len() != columns.len() {
+                    return Err(MonitorSeriesError::RowLength {
+                        line: line_number,
+                        found: row.len(),
+                        expected: columns.len(),
+                    });
+                }
+                Ok(row)
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(MonitorSeries { columns, rows })
+    }
+}
+
+impl MonitorSeries {
+    /// The values of the column `name`, one per row.
+    pub fn column(&self, name: &str) -> Option<Vec<f64>> {
+        let index = self.columns.iter().position(|column| column == name)?;
+        Some(self.rows.iter().map(|row| row[index]).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_monitors() {
+        let monitor: Monitor =
+            serde_yaml::from_str("type: points\npoints: [[0.5, 0.5], [0.2, 0.8]]").unwrap();
+        assert!(monitor.validate("receivers", 2).is_ok());
+        assert!(matches!(
+            monitor.validate("receivers", 3),
+            Err(MonitorError::PointDimension {
+                found: 2,
+                expected: 3,
+                ..
+            })
+        ));
+        assert!(matches!(
+            monitor.validate("2receivers", 2),
+            Err(MonitorError::InvalidName(_))
+        ));
+
+        let monitor: Monitor = serde_yaml::from_str(
+            "type: energy\nterms:\n  - field: dt_u\n    coefficient: 0.5\n  - field: u\n    norm: h1_seminorm\n    coefficient: 0.5 * c^2",
+        )
+        .unwrap();
+        let Monitor::Energy { terms } = &monitor else {
+            panic!("energy monitor expected");
+        };
+        assert_eq!(terms[1].norm, Norm::H1Seminorm);
+        assert!(matches!(&terms[1].coefficient, Coefficient::Expression(e) if e == "0.5 * c^2"));
+        assert_eq!(monitor.fields(), Some(vec!["dt_u", "u"]));
+    }
+
+    #[test]
+    fn test_read_monitor_series() {
+        let series: MonitorSeries = "time,u@0,u@1\n0.1,1,2\n0.2,3,4\n0.3,5".parse().unwrap();
+        assert_eq!(series.columns, vec!["time", "u@0", "u@1"]);
+        assert_eq!(series.rows, vec![vec![0.1, 1., 2.], vec![0.2, 3., 4.]]);
+        assert_eq!(series.column("u@1"), Some(vec![2., 4.]));
+
+        assert_eq!(
+            "time,u\n0.1,1,2\n".parse::<MonitorSeries>(),
+            Err(MonitorSeriesError::RowLength {
+                line: 2,
+                found: 3,
+                expected: 2
+            })
+        );
+        assert_eq!(
+            "".parse::<MonitorSeries>(),
+            Err(MonitorSeriesError::MissingHeader)
+        );
+    }
+}
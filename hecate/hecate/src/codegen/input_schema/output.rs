@@ -0,0 +1,188 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::quantity::Time;
+
+/// # Output
+/// The writing of the results of the simulation.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Output {
+    /// # Every
+    /// The interval between two writings of the results, either a number of time steps such as
+    /// `10`, or a simulated time such as `"0.5 s"`.
+    /// Every time step is written when left out.
+    #[serde(default)]
+    pub every: Option<OutputInterval>,
+
+    /// # Fields
+    /// The unknowns written, or their time derivatives such as `dt_u`.
+    /// All the unknowns of the system are written when left out.
+    #[serde(default)]
+    pub fields: Option<Vec<String>>,
+
+    /// # Formats
+    /// The formats of the written files.
+    #[serde(default = "default_formats")]
+    pub formats: Vec<OutputFormat>,
+
+    /// # Directory
+    /// The directory the files are written to, created if needed.
+    /// The working directory of the simulation when left out.
+    #[serde(default)]
+    pub directory: Option<String>,
+
+    /// # Compression
+    /// The compression of the VTU files.
+    #[serde(default)]
+    pub compression: Compression,
+}
+
+impl Default for Output {
+    fn default() -> Self {
+        Output {
+            every: None,
+            fields: None,
+            formats: default_formats(),
+            directory: None,
+            compression: Compression::default(),
+        }
+    }
+}
+
+/// # Output Interval
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum OutputInterval {
+    /// # Steps
+    /// A number of time steps.
+    Steps(u32),
+    /// # Time
+    /// A simulated time.
+    Time(Time),
+}
+
+/// # Output Format
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    /// # VTU
+    /// A VTU file per step, indexed by time in a PVD collection that ParaView opens as a series.
+    /// With MPI, each process writes its own piece, gathered by a PVTU record.
+    Vtu,
+    /// # HDF5
+    /// An HDF5 file per step, indexed by time in an XDMF file.
+    /// Needs deal.II built with HDF5.
+    Hdf5,
+    /// # Gnuplot
+    /// A gnuplot data file per step, for one dimensional problems.
+    Gnuplot,
+}
+
+/// # Compression
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Compression {
+    /// # None
+    /// The data is written uncompressed.
+    None,
+    /// # Best Speed
+    /// The data is compressed as fast as possible.
+    #[default]
+    BestSpeed,
+    /// # Best Compression
+    /// The data is compressed as much as possible.
+    BestCompression,
+}
+
+impl Compression {
+    /// The `DataOutBase::CompressionLevel` of the compression.
+    pub fn level(&self) -> &'static str {
+        match self {
+            Compression::None => "no_compression",
+            Compression::BestSpeed => "best_speed",
+            Compression::BestCompression => "best_compression",
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum OutputError {
+    #[error("output interval must be at least one time step")]
+    ZeroSteps,
+    #[error("output interval must be a positive time, found {0}")]
+    NonPositiveTime(f64),
+    #[error("at least one output format is needed")]
+    NoFormat,
+    #[error("gnuplot output is only supported for one dimensional problems")]
+    GnuplotDimension,
+    #[error("output directory must not be empty")]
+    EmptyDirectory,
+}
+
+fn default_formats() -> Vec<OutputFormat> {
+    vec![OutputFormat::Vtu]
+}
+
+impl Output {
+    pub fn validate(&self, dimension: usize) -> Result<(), OutputError> {
+        match &self.every {
+            Some(OutputInterval::Steps(0)) => return Err(OutputError::ZeroSteps),
+            Some(OutputInterval::Time(time)) if time.seconds() <= 0.0 => {
+                return Err(OutputError::NonPositiveTime(time.seconds()));
+            }
+            _ => {}
+        }
+        if self.formats.is_empty() {
+            return Err(OutputError::NoFormat);
+        }
+        if self.formats.contains(&OutputFormat::Gnuplot) && dimension != 1 {
+            return Err(OutputError::GnuplotDimension);
+        }
+        if self.directory.as_ref().is_some_and(|d| d.trim().is_empty()) {
+            return Err(OutputError::EmptyDirectory);
+        }
+        Ok(())
+    }
+
+    pub fn has_format(&self, format: OutputFormat) -> bool {
+        self.formats.contains(&format)
+    }
+
+    /// The prefix of the paths of the written files.
+    pub fn path_prefix(&self) -> String {
+        match &self.directory {
+            Some(directory) => format!("{}/", directory.trim_end_matches('/')),
+            None => "./".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_output() {
+        let output: Output = serde_yaml::from_str("every: 10").unwrap();
+        assert!(matches!(output.every, Some(OutputInterval::Steps(10))));
+        assert_eq!(output.formats, vec![OutputFormat::Vtu]);
+        assert_eq!(output.compression, Compression::BestSpeed);
+        assert_eq!(output.path_prefix(), "./");
+        assert!(output.validate(2).is_ok());
+
+        let output: Output =
+            serde_yaml::from_str("every: 0.5 s\nformats: [vtu, gnuplot]\ndirectory: results/")
+                .unwrap();
+        assert!(matches!(&output.every, Some(OutputInterval::Time(time)) if time.seconds() == 0.5));
+        assert_eq!(output.path_prefix(), "results/");
+        assert!(output.validate(1).is_ok());
+        assert!(matches!(
+            output.validate(2),
+            Err(OutputError::GnuplotDimension)
+        ));
+
+        let output: Output = serde_yaml::from_str("every: 0").unwrap();
+        assert!(matches!(output.validate(2), Err(OutputError::ZeroSteps)));
+    }
+}
@@ -1,5 +1,6 @@
 #![allow(unused)]
 use super::RawRepr;
+use super::dimension::Dimension;
 use super::unit::{FormatUnitError, format_unit};
 use crate::StdError;
 use const_format::{concatcp, formatcp};
@@ -460,6 +461,29 @@ impl QuantityEnum {
             QuantityEnum::Custom(q) => q.value,
         }
     }
+
+    pub fn dimension(&self) -> Dimension {
+        let length = Dimension::LENGTH;
+        let time = Dimension::TIME;
+        match self {
+            QuantityEnum::Speed(_) => length / time,
+            QuantityEnum::Length(_) => length,
+            QuantityEnum::Area(_) => length.powi(2),
+            QuantityEnum::Volume(_) => length.powi(3),
+            QuantityEnum::Mass(_) => Dimension::MASS,
+            QuantityEnum::Temperature(_) => Dimension::TEMPERATURE,
+            QuantityEnum::DiffusionCoefficient(_) => length.powi(2) / time,
+            QuantityEnum::Custom(q) => Dimension::new(
+                q.length as i32,
+                q.time as i32,
+                q.mass as i32,
+                q.current as i32,
+                q.temperature as i32,
+                q.amount as i32,
+                q.luminous_intensity as i32,
+            ),
+        }
+    }
 }
 //
 // impl Deref for QuantityEnum {
@@ -0,0 +1,130 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// # Adaptive Mesh Refinement
+/// Refines the mesh where the estimated error is the largest, and coarsens it where it is the
+/// smallest, every few time steps.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Refinement {
+    /// # Estimator
+    /// The estimator of the error on each cell.
+    #[serde(default)]
+    pub estimator: ErrorEstimator,
+
+    /// # Unknown
+    /// The unknown whose error is estimated.
+    /// Defaults to the first unknown of the system.
+    #[serde(default)]
+    pub unknown: Option<String>,
+
+    /// # Refine Fraction
+    /// The fraction of the total estimated error held by the refined cells.
+    #[serde(default = "default_refine_fraction")]
+    pub refine_fraction: f64,
+
+    /// # Coarsen Fraction
+    /// The fraction of the total estimated error held by the coarsened cells.
+    #[serde(default = "default_coarsen_fraction")]
+    pub coarsen_fraction: f64,
+
+    /// # Max Level
+    /// The level of refinement of the mesh beyond which cells are not refined anymore.
+    /// Unlimited when left out.
+    #[serde(default)]
+    pub max_level: Option<u32>,
+
+    /// # Min Level
+    /// The level of refinement of the mesh below which cells are not coarsened anymore.
+    #[serde(default)]
+    pub min_level: u32,
+
+    /// # Frequency
+    /// The number of time steps between two refinements of the mesh.
+    #[serde(default = "default_frequency")]
+    pub frequency: u32,
+}
+
+/// # Error Estimator
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorEstimator {
+    /// # Kelly Error Estimator
+    /// Integrates the jump of the gradient of the solution across the faces of each cell.
+    #[default]
+    Kelly,
+}
+
+#[derive(Error, Debug)]
+pub enum RefinementError {
+    #[error("refine and coarsen fractions must be between 0 and 1 and sum up to at most 1")]
+    InvalidFractions,
+    #[error("frequency of the refinement must be at least one time step")]
+    ZeroFrequency,
+    #[error("max level {max} is below min level {min}")]
+    InvalidLevels { min: u32, max: u32 },
+}
+
+fn default_refine_fraction() -> f64 {
+    0.3
+}
+
+fn default_coarsen_fraction() -> f64 {
+    0.03
+}
+
+fn default_frequency() -> u32 {
+    5
+}
+
+impl Refinement {
+    pub fn validate(&self) -> Result<(), RefinementError> {
+        let fractions = [self.refine_fraction, self.coarsen_fraction];
+        if fractions.iter().any(|f| !(0.0..=1.0).contains(f)) || fractions.iter().sum::<f64>() > 1.0
+        {
+            return Err(RefinementError::InvalidFractions);
+        }
+        if self.frequency == 0 {
+            return Err(RefinementError::ZeroFrequency);
+        }
+        if let Some(max) = self.max_level
+            && max < self.min_level
+        {
+            return Err(RefinementError::InvalidLevels {
+                min: self.min_level,
+                max,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_refinement() {
+        let refinement: Refinement = serde_yaml::from_str("max_level: 7").unwrap();
+
+        assert_eq!(refinement.estimator, ErrorEstimator::Kelly);
+        assert_eq!(refinement.refine_fraction, 0.3);
+        assert_eq!(refinement.coarsen_fraction, 0.03);
+        assert_eq!(refinement.max_level, Some(7));
+        assert_eq!(refinement.frequency, 5);
+        assert!(refinement.validate().is_ok());
+
+        let refinement: Refinement =
+            serde_yaml::from_str("refine_fraction: 0.8\ncoarsen_fraction: 0.3").unwrap();
+        assert!(matches!(
+            refinement.validate(),
+            Err(RefinementError::InvalidFractions)
+        ));
+
+        let refinement: Refinement = serde_yaml::from_str("max_level: 2\nmin_level: 3").unwrap();
+        assert!(matches!(
+            refinement.validate(),
+            Err(RefinementError::InvalidLevels { min: 3, max: 2 })
+        ));
+    }
+}
@@ -0,0 +1,276 @@
+use std::fmt::Display;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// # Linear Solver
+/// The solver of the linear system of an unknown at each time step.
+/// Settings left out are taken from the global solver, then chosen from the system: CG for
+/// symmetric systems and GMRES otherwise, preconditioned by AMG with MPI, SSOR for vector
+/// unknowns and nothing otherwise.
+/// Matrix free code is preconditioned by a Chebyshev iteration instead, and has no direct
+/// solvers.
+/// Unknowns updated with a lumped mass matrix are not solved, their solver being unused.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct LinearSolver {
+    /// # Method
+    /// The Krylov method or direct solver.
+    pub method: Option<SolverMethod>,
+
+    /// # Preconditioner
+    /// The preconditioner of the Krylov methods, unused by direct solvers.
+    pub preconditioner: Option<Preconditioner>,
+
+    /// # Tolerance
+    /// The residual to reach relative to the norm of the right-hand side, 1e-8 by default
+    /// (1e-6 with MPI).
+    pub tolerance: Option<f64>,
+
+    /// # Max Iterations
+    /// The maximum number of iterations, 1000 by default (the number of dofs with MPI).
+    pub max_iterations: Option<u32>,
+}
+
+/// # Solver Method
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SolverMethod {
+    /// # Conjugate Gradient
+    /// For symmetric positive definite systems.
+    Cg,
+
+    /// # GMRES
+    /// For any system, such as the non-symmetric ones of advections.
+    Gmres,
+
+    /// # BiCGStab
+    /// For non-symmetric systems, with a lower memory footprint than GMRES.
+    Bicgstab,
+
+    /// # MINRES
+    /// For symmetric indefinite systems.
+    Minres,
+
+    /// # UMFPACK
+    /// Sparse direct solver, without MPI.
+    Umfpack,
+
+    /// # MUMPS
+    /// Parallel sparse direct solver, with MPI.
+    Mumps,
+}
+
+/// # Preconditioner
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Preconditioner {
+    /// # Identity
+    /// No preconditioning.
+    Identity,
+
+    /// # Jacobi
+    /// Scales by the inverse of the diagonal.
+    Jacobi,
+
+    /// # SSOR
+    /// Symmetric successive over-relaxation, with a relaxation factor of 1.2.
+    Ssor,
+
+    /// # ILU
+    /// Incomplete LU decomposition.
+    Ilu,
+
+    /// # Algebraic Multigrid
+    /// Scales to large systems, with MPI only.
+    Amg,
+
+    /// # Chebyshev
+    /// Chebyshev iteration scaled by the inverse of the diagonal, with matrix free code only.
+    Chebyshev,
+}
+
+#[derive(Error, Debug)]
+pub enum SolverError {
+    #[error("tolerance of the solver must be positive")]
+    InvalidTolerance,
+    #[error("max iterations of the solver must be positive")]
+    InvalidMaxIterations,
+    #[error("{0} needs MPI")]
+    MissingMpi(String),
+    #[error("{0} is not available with MPI")]
+    UnavailableWithMpi(String),
+    #[error("direct solver {0} takes no preconditioner")]
+    DirectPreconditioner(SolverMethod),
+    #[error("{0} needs a symmetric system, use GMRES or BiCGStab instead")]
+    NonSymmetric(SolverMethod),
+    #[error("{0} needs an assembled matrix, which matrix free code does not have")]
+    NeedsMatrix(String),
+    #[error("{0} needs matrix free code")]
+    MissingMatrixFree(String),
+}
+
+impl Display for SolverMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            SolverMethod::Cg => "CG",
+            SolverMethod::Gmres => "GMRES",
+            SolverMethod::Bicgstab => "BiCGStab",
+            SolverMethod::Minres => "MINRES",
+            SolverMethod::Umfpack => "UMFPACK",
+            SolverMethod::Mumps => "MUMPS",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl Display for Preconditioner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Preconditioner::Identity => "identity",
+            Preconditioner::Jacobi => "Jacobi",
+            Preconditioner::Ssor => "SSOR",
+            Preconditioner::Ilu => "ILU",
+            Preconditioner::Amg => "AMG",
+            Preconditioner::Chebyshev => "Chebyshev",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl SolverMethod {
+    pub fn is_direct(&self) -> bool {
+        matches!(self, SolverMethod::Umfpack | SolverMethod::Mumps)
+    }
+
+    pub fn needs_symmetry(&self) -> bool {
+        matches!(self, SolverMethod::Cg | SolverMethod::Minres)
+    }
+}
+
+impl Preconditioner {
+    /// Whether the preconditioner is built from the entries of an assembled matrix
+    pub fn needs_matrix(&self) -> bool {
+        matches!(
+            self,
+            Preconditioner::Ssor | Preconditioner::Ilu | Preconditioner::Amg
+        )
+    }
+}
+
+impl LinearSolver {
+    /// The settings of `self`, completed by the ones of `global`
+    pub fn or(&self, global: &LinearSolver) -> LinearSolver {
+        LinearSolver {
+            method: self.method.or(global.method),
+            preconditioner: self.preconditioner.or(global.preconditioner),
+            tolerance: self.tolerance.or(global.tolerance),
+            max_iterations: self.max_iterations.or(global.max_iterations),
+        }
+    }
+
+    pub fn validate(&self, mpi: bool, matrix_free: bool) -> Result<(), SolverError> {
+        if self.tolerance.is_some_and(|tolerance| tolerance <= 0.) {
+            return Err(SolverError::InvalidTolerance);
+        }
+        if self.max_iterations == Some(0) {
+            return Err(SolverError::InvalidMaxIterations);
+        }
+        match (self.method, self.preconditioner) {
+            (Some(method), _) if matrix_free && method.is_direct() => {
+                return Err(SolverError::NeedsMatrix(format!("{method} solver")));
+            }
+            (_, Some(preconditioner)) if matrix_free && preconditioner.needs_matrix() => {
+                return Err(SolverError::NeedsMatrix(format!(
+                    "{preconditioner} preconditioner"
+                )));
+            }
+            (_, Some(Preconditioner::Chebyshev)) if !matrix_free => {
+                return Err(SolverError::MissingMatrixFree(
+                    "Chebyshev preconditioner".to_string(),
+                ));
+            }
+            _ => (),
+        }
+        match (self.method, self.preconditioner, mpi) {
+            (Some(method), Some(_), _) if method.is_direct() => {
+                Err(SolverError::DirectPreconditioner(method))
+            }
+            (Some(SolverMethod::Umfpack), _, true) => Err(SolverError::UnavailableWithMpi(
+                "UMFPACK solver".to_string(),
+            )),
+            (Some(SolverMethod::Mumps), _, false) => {
+                Err(SolverError::MissingMpi("MUMPS solver".to_string()))
+            }
+            (_, Some(Preconditioner::Amg), false) => {
+                Err(SolverError::MissingMpi("AMG preconditioner".to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks the method against the symmetry of the system it solves
+    pub fn validate_symmetry(&self, symmetric: bool) -> Result<(), SolverError> {
+        match self.method {
+            Some(method) if method.needs_symmetry() && !symmetric => {
+                Err(SolverError::NonSymmetric(method))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_and_validate_solver() {
+        let global: LinearSolver =
+            serde_yaml::from_str("method: minres\ntolerance: 1e-10").unwrap();
+        let solver: LinearSolver = serde_yaml::from_str("preconditioner: amg").unwrap();
+        let solver = solver.or(&global);
+
+        assert_eq!(solver.method, Some(SolverMethod::Minres));
+        assert_eq!(solver.preconditioner, Some(Preconditioner::Amg));
+        assert_eq!(solver.tolerance, Some(1e-10));
+        assert!(solver.validate(true, false).is_ok());
+        assert!(matches!(
+            solver.validate(false, false),
+            Err(SolverError::MissingMpi(_))
+        ));
+        assert!(matches!(
+            solver.validate_symmetry(false),
+            Err(SolverError::NonSymmetric(SolverMethod::Minres))
+        ));
+
+        let direct: LinearSolver =
+            serde_yaml::from_str("method: umfpack\npreconditioner: ilu").unwrap();
+        assert!(matches!(
+            direct.validate(false, false),
+            Err(SolverError::DirectPreconditioner(SolverMethod::Umfpack))
+        ));
+    }
+
+    #[test]
+    fn test_validate_matrix_free_solver() {
+        let chebyshev: LinearSolver = serde_yaml::from_str("preconditioner: chebyshev").unwrap();
+        assert!(chebyshev.validate(false, true).is_ok());
+        assert!(chebyshev.validate(true, true).is_ok());
+        assert!(matches!(
+            chebyshev.validate(false, false),
+            Err(SolverError::MissingMatrixFree(_))
+        ));
+
+        let amg: LinearSolver = serde_yaml::from_str("preconditioner: amg").unwrap();
+        assert!(matches!(
+            amg.validate(true, true),
+            Err(SolverError::NeedsMatrix(_))
+        ));
+        let direct: LinearSolver = serde_yaml::from_str("method: umfpack").unwrap();
+        assert!(matches!(
+            direct.validate(false, true),
+            Err(SolverError::NeedsMatrix(_))
+        ));
+    }
+}
@@ -0,0 +1,81 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// # Stabilization
+/// Stabilizes the advection of the unknowns along a velocity field, which produces oscillations
+/// when it dominates the diffusion.
+/// The stabilization parameter of each cell is τ = delta * h / |v|, h being the diameter of the
+/// cell and v the velocity.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Stabilization {
+    /// # Method
+    /// The stabilization method.
+    #[serde(default)]
+    pub method: StabilizationMethod,
+
+    /// # Delta
+    /// The dimensionless factor of the stabilization parameter.
+    #[serde(default = "default_delta")]
+    pub delta: f64,
+}
+
+/// # Stabilization Method
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StabilizationMethod {
+    /// # Streamline Upwind Petrov-Galerkin
+    /// Tests the residual of the equation with τ v·∇φ on top of the shape functions φ.
+    /// The diffusion and the sources are left out of the residual, the former vanishing with
+    /// linear elements.
+    #[default]
+    Supg,
+
+    /// # Streamline Diffusion
+    /// Adds a diffusion τ (v·∇u, v·∇φ) along the streamlines only.
+    StreamlineDiffusion,
+}
+
+#[derive(Error, Debug)]
+pub enum StabilizationError {
+    #[error("delta of the stabilization must be positive")]
+    InvalidDelta,
+}
+
+fn default_delta() -> f64 {
+    0.5
+}
+
+impl Stabilization {
+    pub fn validate(&self) -> Result<(), StabilizationError> {
+        if self.delta <= 0.0 {
+            return Err(StabilizationError::InvalidDelta);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_stabilization() {
+        let stabilization: Stabilization = serde_yaml::from_str("delta: 0.3").unwrap();
+
+        assert_eq!(stabilization.method, StabilizationMethod::Supg);
+        assert_eq!(stabilization.delta, 0.3);
+        assert!(stabilization.validate().is_ok());
+
+        let stabilization: Stabilization =
+            serde_yaml::from_str("method: streamline_diffusion\ndelta: 0").unwrap();
+        assert_eq!(
+            stabilization.method,
+            StabilizationMethod::StreamlineDiffusion
+        );
+        assert!(matches!(
+            stabilization.validate(),
+            Err(StabilizationError::InvalidDelta)
+        ));
+    }
+}
@@ -0,0 +1,110 @@
+use std::fmt::Display;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use symrs::Rational;
+use thiserror::Error;
+
+/// # Time Scheme
+/// The discretization of the time derivatives, weighting the operators between the previous
+/// time step (θ = 0) and the current one (θ = 1).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeScheme {
+    /// # Explicit Euler
+    /// θ = 0, first order and conditionally stable.
+    /// The mass matrix is lumped, each step then being a direct update instead of a solve.
+    ExplicitEuler,
+
+    /// # Implicit Euler
+    /// θ = 1, first order and unconditionally stable.
+    ImplicitEuler,
+
+    /// # Crank-Nicolson
+    /// θ = 1/2, second order and unconditionally stable.
+    #[default]
+    CrankNicolson,
+
+    /// # θ-Scheme
+    /// An arbitrary weight between 0 and 1.
+    Theta(f64),
+}
+
+#[derive(Error, Debug)]
+pub enum TimeSchemeError {
+    #[error("θ of the time scheme must be between 0 and 1, got {0}")]
+    InvalidTheta(f64),
+    #[error("θ of the time scheme has too many decimals to be used as a rational, got {0}")]
+    UnrepresentableTheta(f64),
+}
+
+impl TimeScheme {
+    pub fn theta(&self) -> f64 {
+        match self {
+            TimeScheme::ExplicitEuler => 0.,
+            TimeScheme::ImplicitEuler => 1.,
+            TimeScheme::CrankNicolson => 0.5,
+            TimeScheme::Theta(theta) => *theta,
+        }
+    }
+
+    /// Whether the unknowns of the current time step only appear in the time derivatives
+    pub fn is_explicit(&self) -> bool {
+        self.theta() == 0.
+    }
+
+    pub fn validate(&self) -> Result<(), TimeSchemeError> {
+        match self.theta() {
+            theta if !(0. ..=1.).contains(&theta) => Err(TimeSchemeError::InvalidTheta(theta)),
+            // θ is substituted as a rational in the discretized equations
+            theta if Rational::try_from_float(theta).is_none() => {
+                Err(TimeSchemeError::UnrepresentableTheta(theta))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Display for TimeScheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            TimeScheme::ExplicitEuler => "explicit Euler",
+            TimeScheme::ImplicitEuler => "implicit Euler",
+            TimeScheme::CrankNicolson => "Crank-Nicolson",
+            TimeScheme::Theta(_) => "θ-scheme",
+        };
+        write!(f, "{name} (θ = {})", self.theta())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Solve {
+        #[serde(with = "serde_yaml::with::singleton_map")]
+        time_scheme: TimeScheme,
+    }
+
+    #[test]
+    fn test_deserialize_time_scheme() {
+        let solve: Solve = serde_yaml::from_str("time_scheme: implicit_euler").unwrap();
+        assert_eq!(solve.time_scheme, TimeScheme::ImplicitEuler);
+
+        let solve: Solve = serde_yaml::from_str("time_scheme: {theta: 0.6}").unwrap();
+        assert_eq!(solve.time_scheme, TimeScheme::Theta(0.6));
+        assert!(solve.time_scheme.validate().is_ok());
+        assert_eq!(solve.time_scheme.to_string(), "θ-scheme (θ = 0.6)");
+
+        assert!(matches!(
+            TimeScheme::Theta(1.5).validate(),
+            Err(TimeSchemeError::InvalidTheta(_))
+        ));
+        assert!(matches!(
+            TimeScheme::Theta(1e-19).validate(),
+            Err(TimeSchemeError::UnrepresentableTheta(_))
+        ));
+        assert!(TimeScheme::ExplicitEuler.is_explicit());
+    }
+}
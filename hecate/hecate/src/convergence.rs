@@ -0,0 +1,297 @@
+//! Convergence studies of the errors against the exact solutions of the unknowns, written by
+//! the generated code on a ladder of refined meshes and time steps.
+
+use std::path::Path;
+use std::process::Command;
+
+use indexmap::IndexMap;
+use log::info;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::CodeGenError;
+use crate::codegen::input_schema::monitor::{MonitorSeries, MonitorSeriesError};
+use crate::codegen::input_schema::{ERRORS_FILE, InputSchema};
+
+/// Columns of the errors file which are not errors.
+const LEVEL_COLUMNS: [&str; 3] = ["time", "n_dofs", "h"];
+
+#[derive(Error, Debug)]
+pub enum ConvergenceError {
+    #[error("a convergence study needs at least two levels")]
+    TooFewLevels,
+    #[error("no unknown has an exact solution to compute errors against")]
+    NoExactSolution,
+    #[error("failed to generate the sources of level {level}: {source}")]
+    CodeGen { level: usize, source: CodeGenError },
+    #[error("failed to write the sources of level {level}: {source}")]
+    Io {
+        level: usize,
+        source: std::io::Error,
+    },
+    #[error("command `{command}` of level {level} failed")]
+    CommandFailed { level: usize, command: String },
+    #[error("invalid errors file of level {level}: {source}")]
+    ErrorsFile {
+        level: usize,
+        source: MonitorSeriesError,
+    },
+    #[error("errors file of level {0} has no record")]
+    NoRecord(usize),
+    #[error("column {0} is missing from the errors files")]
+    MissingColumn(String),
+    #[error("last record of level {level} is at time {time}, not at time {expected} as level 0")]
+    TimeMismatch {
+        level: usize,
+        time: f64,
+        expected: f64,
+    },
+}
+
+/// The errors of the last record of each level, with the observed rates of convergence
+/// between consecutive levels. The last records of the levels must be at the same time.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ConvergenceTable {
+    /// The largest cell diameter of each level
+    pub h: Vec<f64>,
+    /// The errors of each level, by column of the errors file such as `u_L2`
+    pub errors: IndexMap<String, Vec<f64>>,
+    /// The rates between consecutive levels, by column of the errors file
+    pub rates: IndexMap<String, Vec<f64>>,
+}
+
+/// The schemas of a convergence study, the cells of each level being half the size of the
+/// ones of the previous level, and so is the time step when `refine_time` is set.
+pub fn refinement_ladder(
+    schema: &InputSchema,
+    levels: usize,
+    refine_time: bool,
+) -> Result<Vec<InputSchema>, ConvergenceError> {
+    if levels < 2 {
+        return Err(ConvergenceError::TooFewLevels);
+    }
+    if !schema.has_exact_solutions() {
+        return Err(ConvergenceError::NoExactSolution);
+    }
+    Ok(std::iter::successors(Some(schema.clone()), |previous| {
+        let mut schema = previous.clone();
+        for mesh in schema.meshes.values_mut() {
+            mesh.refine();
+        }
+        if refine_time && let Some(time_step) = &mut schema.solve.time_step {
+            *time_step = format!("{} s", time_step.seconds() / 2.)
+                .parse()
+                .expect("valid time");
+        }
+        Some(schema)
+    })
+    .take(levels)
+    .collect())
+}
+
+/// Generates, builds and runs the simulation of each schema in the directory `level_{i}` of
+/// `dir`, returning the errors written by each level.
+/// Needs CMake and deal.II on the machine.
+pub fn run_ladder(
+    schemas: &[InputSchema],
+    dir: &Path,
+) -> Result<Vec<MonitorSeries>, ConvergenceError> {
+    schemas
+        .iter()
+        .enumerate()
+        .map(|(level, schema)| {
+            let level_dir = dir.join(format!("level_{level}"));
+            info!("running level {level} in {}", level_dir.display());
+            schema
+                .generate_sources()
+                .map_err(|source| ConvergenceError::CodeGen { level, source })?
+                .write_to_dir(&level_dir)
+                .map_err(|source| ConvergenceError::Io { level, source })?;
+
+            for command in [
+                &["cmake", "-S", ".", "-B", "build"][..],
+                &["cmake", "--build", "build"],
+                &["./build/run_sim"],
+            ] {
+                let failed = || ConvergenceError::CommandFailed {
+                    level,
+                    command: command.join(" "),
+                };
+                let status = Command::new(command[0])
+                    .args(&command[1..])
+                    .current_dir(&level_dir)
+                    .status()
+                    .map_err(|_| failed())?;
+                if !status.success() {
+                    return Err(failed());
+                }
+            }
+
+            let errors_file = level_dir
+                .join(schema.output.path_prefix())
+                .join(ERRORS_FILE);
+            std::fs::read_to_string(errors_file)
+                .map_err(|source| ConvergenceError::Io { level, source })?
+                .parse()
+                .map_err(|source| ConvergenceError::ErrorsFile { level, source })
+        })
+        .collect()
+}
+
+/// Runs the convergence study of `schema` on `levels` levels in `dir`.
+pub fn convergence_study(
+    schema: &InputSchema,
+    levels: usize,
+    refine_time: bool,
+    dir: &Path,
+) -> Result<ConvergenceTable, ConvergenceError> {
+    let schemas = refinement_ladder(schema, levels, refine_time)?;
+    ConvergenceTable::new(&run_ladder(&schemas, dir)?)
+}
+
+impl ConvergenceTable {
+    /// Computes the rates of the errors of the last records of the levels, the observed order
+    /// being log(e_i / e_i+1) / log(h_i / h_i+1).
+    pub fn new(levels: &[MonitorSeries]) -> Result<Self, ConvergenceError> {
+        if levels.len() < 2 {
+            return Err(ConvergenceError::TooFewLevels);
+        }
+        let last_rows = levels
+            .iter()
+            .enumerate()
+            .map(|(level, series)| series.rows.last().ok_or(ConvergenceError::NoRecord(level)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let column = |name: &str| {
+            levels
+                .iter()
+                .zip(&last_rows)
+                .map(|(series, row)| {
+                    let index = series.columns.iter().position(|column| column == name);
+                    index
+                        .map(|index| row[index])
+                        .ok_or_else(|| ConvergenceError::MissingColumn(name.to_string()))
+                })
+                .collect::<Result<Vec<f64>, _>>()
+        };
+
+        // The errors of levels ending at different times are not comparable
+        let times = column("time")?;
+        for (level, &time) in times.iter().enumerate().skip(1) {
+            if (time - times[0]).abs() > 1e-9 * times[0].abs().max(1.) {
+                return Err(ConvergenceError::TimeMismatch {
+                    level,
+                    time,
+                    expected: times[0],
+                });
+            }
+        }
+        let h = column("h")?;
+        let mut errors = IndexMap::new();
+        let mut rates = IndexMap::new();
+        for name in &levels[0].columns {
+            if LEVEL_COLUMNS.contains(&&name[..]) {
+                continue;
+            }
+            let values = column(name)?;
+            let rate = values
+                .windows(2)
+                .zip(h.windows(2))
+                .map(|(e, h)| (e[0] / e[1]).ln() / (h[0] / h[1]).ln())
+                .collect();
+            errors.insert(name.clone(), values);
+            rates.insert(name.clone(), rate);
+        }
+        Ok(ConvergenceTable { h, errors, rates })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::input_schema::mesh::MeshEnum;
+
+    #[test]
+    fn test_convergence_rates() {
+        let levels = [
+            "time,n_dofs,h,u_L2,u_H1_seminorm\n0,25,0.5,1,2\n1,25,0.5,0.16,0.4\n",
+            "time,n_dofs,h,u_L2,u_H1_seminorm\n1,81,0.25,0.04,0.2\n",
+            "time,n_dofs,h,u_L2,u_H1_seminorm\n1,289,0.125,0.01,0.1\n",
+        ]
+        .map(|errors| errors.parse::<MonitorSeries>().unwrap());
+        let table = ConvergenceTable::new(&levels).unwrap();
+        assert_eq!(table.h, vec![0.5, 0.25, 0.125]);
+        assert_eq!(table.errors["u_L2"], vec![0.16, 0.04, 0.01]);
+        for (rate, expected) in table.rates["u_L2"].iter().zip([2., 2.]) {
+            assert!((rate - expected).abs() < 1e-12);
+        }
+        for rate in &table.rates["u_H1_seminorm"] {
+            assert!((rate - 1.).abs() < 1e-12);
+        }
+        assert!(matches!(
+            ConvergenceTable::new(&levels[..1]),
+            Err(ConvergenceError::TooFewLevels)
+        ));
+    }
+
+    #[test]
+    fn test_convergence_time_mismatch() {
+        let levels = [
+            "time,n_dofs,h,u_L2\n0,25,0.5,1\n1,25,0.5,0.16\n",
+            "time,n_dofs,h,u_L2\n0.5,81,0.25,0.04\n",
+        ]
+        .map(|errors| errors.parse::<MonitorSeries>().unwrap());
+        assert!(matches!(
+            ConvergenceTable::new(&levels),
+            Err(ConvergenceError::TimeMismatch { level: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_refinement_ladder() {
+        let schema = InputSchema::from_yaml(
+            r#"
+solve:
+  mesh: domain
+  element: Q1
+  dimension: 2
+  equations: [heat]
+  time: 0 s .. 1 s
+  time_step: 0.1 s
+meshes:
+  domain:
+    type: hyper_cube
+    range: 0 m .. 1 m
+    resolution: 0.25 m
+unknowns:
+  u:
+    initial: u_exact
+    boundary: u_exact
+    exact_solution: u_exact
+equations:
+  heat: dt(u) = laplacian(u)
+functions:
+  u_exact: exp(-2 * pi^2 * t) * sin(pi * x) * sin(pi * y)
+parameters: {}
+"#,
+        )
+        .unwrap();
+        let ladder = refinement_ladder(&schema, 3, true).unwrap();
+        let resolutions = ladder
+            .iter()
+            .map(|schema| match &schema.meshes["domain"] {
+                MeshEnum::HyperCube(mesh) => mesh.resolution.meters(),
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(resolutions, vec![0.25, 0.125, 0.0625]);
+        let time_step = ladder[2].solve.time_step.as_ref().unwrap().seconds();
+        assert!((time_step - 0.025).abs() < 1e-12);
+
+        let mut schema = schema;
+        schema.unknowns["u"].exact_solution = None;
+        assert!(matches!(
+            refinement_ladder(&schema, 3, true),
+            Err(ConvergenceError::NoExactSolution)
+        ));
+    }
+}
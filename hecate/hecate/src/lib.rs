@@ -9,6 +9,8 @@ use codegen::input_schema::InputSchema;
 pub use codegen::input_schema::SchemaValidationError;
 use schemars::schema_for;
 pub mod codegen;
+#[cfg(not(target_family = "wasm"))]
+pub mod convergence;
 #[cfg(all(feature = "js", not(target_arch = "wasm32")))]
 pub mod js;
 pub use codegen::BuildingBlock;
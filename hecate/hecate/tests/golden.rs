@@ -0,0 +1,156 @@
+//! Smoke test of the code generation: each input schema is generated for a serial and an MPI
+//! build and compared with its golden files in `tests/golden`.
+//!
+//! The example schemas of `input-schemas` are generated for both builds, and the fixtures of
+//! `tests/fixtures`, each exercising a feature of the generator, for the build it supports.
+//!
+//! Run with `UPDATE_GOLDEN=1` to write the golden files after an intended change of the
+//! generated code.
+
+use std::{env, fs, path::Path};
+
+use hecate::InputSchema;
+
+fn check_golden(dir: &str, schema_name: &str, mpi: bool) {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let yaml = fs::read_to_string(root.join(dir).join(schema_name))
+        .unwrap_or_else(|e| panic!("failed to read {schema_name}: {e}"));
+    let mut schema = InputSchema::from_yaml(&yaml).unwrap();
+    schema.gen_conf.mpi = mpi;
+    let code = schema
+        .generate_cpp_sources()
+        .unwrap_or_else(|e| panic!("failed to generate {schema_name}: {e}"));
+
+    let stem = schema_name.trim_end_matches(".hecate.yml");
+    let extension = if mpi { "mpi.cpp" } else { "cpp" };
+    let golden = root
+        .join("tests/golden")
+        .join(format!("{stem}.{extension}"));
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&golden, &code).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&golden)
+        .unwrap_or_else(|e| panic!("failed to read {}: {e}", golden.display()));
+    if let Some((i, (found, expected))) = code
+        .lines()
+        .zip(expected.lines())
+        .enumerate()
+        .find(|(_, (found, expected))| found != expected)
+    {
+        panic!(
+            "{stem} differs from {} at line {}:\n  expected: {expected}\n  found:    {found}",
+            golden.display(),
+            i + 1
+        );
+    }
+    assert_eq!(
+        code.lines().count(),
+        expected.lines().count(),
+        "{stem} and {} differ in length",
+        golden.display()
+    );
+}
+
+fn check_example(schema_name: &str, mpi: bool) {
+    check_golden("input-schemas", schema_name, mpi);
+}
+
+fn check_fixture(schema_name: &str, mpi: bool) {
+    check_golden("tests/fixtures", schema_name, mpi);
+}
+
+#[test]
+fn heat_eq_serial() {
+    check_example("heat-eq.hecate.yml", false);
+}
+
+#[test]
+fn heat_eq_mpi() {
+    check_example("heat-eq.hecate.yml", true);
+}
+
+#[test]
+fn wave_eq_serial() {
+    check_example("wave-eq.hecate.yml", false);
+}
+
+#[test]
+fn wave_eq_mpi() {
+    check_example("wave-eq.hecate.yml", true);
+}
+
+#[test]
+fn elastic_wave_serial() {
+    check_example("elastic-wave.hecate.yml", false);
+}
+
+#[test]
+fn elastic_wave_mpi() {
+    check_example("elastic-wave.hecate.yml", true);
+}
+
+#[test]
+fn poisson_serial() {
+    check_example("poisson.hecate.yml", false);
+}
+
+#[test]
+fn poisson_mpi() {
+    check_example("poisson.hecate.yml", true);
+}
+
+#[test]
+fn hyper_rectangle_mesh() {
+    check_fixture("meshes.hecate.yml", false);
+}
+
+#[test]
+fn adaptive_refinement_serial() {
+    check_fixture("refinement.hecate.yml", false);
+}
+
+#[test]
+fn adaptive_refinement_mpi() {
+    check_fixture("refinement.hecate.yml", true);
+}
+
+#[test]
+fn discontinuous_element() {
+    check_fixture("dg.hecate.yml", false);
+}
+
+#[test]
+fn weighted_laplacian() {
+    check_fixture("weighted-laplacian.hecate.yml", false);
+}
+
+#[test]
+fn advection_supg() {
+    check_fixture("advection-supg.hecate.yml", false);
+}
+
+#[test]
+fn advection_dg_upwind() {
+    check_fixture("advection-dg.hecate.yml", false);
+}
+
+#[test]
+fn explicit_lumped_mass() {
+    check_fixture("explicit.hecate.yml", false);
+}
+
+#[test]
+fn output_formats() {
+    check_fixture("output.hecate.yml", false);
+}
+
+#[test]
+fn matrix_free_serial() {
+    check_fixture("matrix-free.hecate.yml", false);
+}
+
+#[test]
+fn matrix_free_mpi() {
+    check_fixture("matrix-free.hecate.yml", true);
+}
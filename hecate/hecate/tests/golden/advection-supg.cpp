@@ -0,0 +1,461 @@
+// Time scheme: Crank-Nicolson (θ = 0.5)
+#include <iostream>
+#include <fstream>
+#include <cmath>
+#include <deal.II/lac/affine_constraints.h>
+#include <deal.II/numerics/data_out.h>
+#include <deal.II/base/conditional_ostream.h>
+#include <deal.II/base/function.h>
+#include <deal.II/grid/tria.h>
+#include <deal.II/grid/grid_generator.h>
+#include <deal.II/fe/fe_q.h>
+#include <deal.II/fe/mapping_q.h>
+#include <deal.II/base/quadrature_lib.h>
+#include <deal.II/dofs/dof_handler.h>
+#include <deal.II/lac/vector.h>
+#include <deal.II/lac/dynamic_sparsity_pattern.h>
+#include <deal.II/lac/sparsity_pattern.h>
+#include <deal.II/dofs/dof_tools.h>
+#include <deal.II/lac/sparse_matrix.h>
+#include <deal.II/lac/solver_cg.h>
+#include <deal.II/lac/precondition.h>
+#include <deal.II/lac/solver_control.h>
+#include <deal.II/lac/solver_gmres.h>
+#include <deal.II/base/work_stream.h>
+#include <deal.II/fe/fe_values.h>
+#include <deal.II/meshworker/copy_data.h>
+#include <deal.II/meshworker/scratch_data.h>
+#include <deal.II/lac/full_matrix.h>
+#include <deal.II/base/tensor.h>
+#include <deal.II/numerics/vector_tools_project.h>
+#include <deal.II/numerics/vector_tools_boundary.h>
+#include <deal.II/numerics/matrix_tools.h>
+
+#ifdef DEAL_II_WITH_KOKKOS
+#include <Kokkos_Core.hpp>
+#endif
+
+const int dim = 2;
+using data_type = double;
+
+
+// Parameters
+const data_type d = 1e-4;
+
+using namespace dealii;
+using namespace dealii::numbers;
+
+static constexpr data_type pi = PI;
+static constexpr data_type e = E;
+
+
+bool float_equals(data_type a, data_type b) {
+  const data_type base_epsilon = 1e-8;
+  const data_type epsilon = base_epsilon * std::max(1.0, std::max(std::abs(a), std::abs(b)));
+  return std::fabs(b - a) < epsilon;
+}
+
+Point<dim> make_point(data_type x, data_type y, data_type z) {
+  const data_type coordinates[] = {x, y, z};
+  Point<dim> point;
+  for (int d = 0; d < dim; ++d)
+    point[d] = coordinates[d];
+  return point;
+}
+
+
+class Fn_c0 : public Function<dim> {
+public:
+  virtual double value(const Point<dim> &point,
+                       const unsigned int component = 0) const override {
+    return std::exp(-100 * ((point[0] + -1./4.) * (point[0] + -1./4.) + (point[1] + -1./2.) * (point[1] + -1./2.)));
+  }
+};
+
+
+class Fn_v : public Function<dim> {
+public:
+  Fn_v() : Function<dim>(2) {}
+
+  virtual double value(const Point<dim> &point,
+                       const unsigned int component = 0) const override {
+    switch (component) {
+    case 0: {
+      return 1;
+    }
+    case 1: {
+      return 0;
+    }
+    default:
+      return 0;
+    }
+  }
+};
+
+
+class Fn_0 : public Function<dim> {
+public:
+  virtual double value(const Point<dim> &point,
+                       const unsigned int component = 0) const override {
+    return 0;
+  }
+};
+
+
+class Sim {
+public:
+  Sim()
+    : pcout(std::cout, true)
+    , element(1), element_mapping(1), element_quadrature(element.degree + 1), element_face_quadrature(element.degree + 1), dof_handler(mesh)
+  {}
+
+  void run();
+  void output_results();
+
+  void setup_mesh() {
+    GridGenerator::hyper_cube(mesh, 0, 1);
+    mesh.refine_global(4);
+    pcout << "Cell size: " << 0.0625 << " m" << std::endl;
+  }
+
+  void setup_system() {
+    constraints.clear();
+    dof_handler.distribute_dofs(element);
+    pcout << "Number of degrees of freedom: " << dof_handler.n_dofs() << "\n" << std::endl;
+    c.reinit(dof_handler.n_dofs());
+    c_prev.reinit(dof_handler.n_dofs());
+    DynamicSparsityPattern dof_handler_dsp(dof_handler.n_dofs(), dof_handler.n_dofs());
+    DoFTools::make_sparsity_pattern(dof_handler, dof_handler_dsp);
+    sparsity_pattern.copy_from(dof_handler_dsp);
+    rhs.reinit(dof_handler.n_dofs());
+    matrix_c.reinit(sparsity_pattern);
+    pristine_matrix_c.reinit(sparsity_pattern);
+    laplace_mat.reinit(sparsity_pattern);
+    mass_mat.reinit(sparsity_pattern);
+    advection_v_mat.reinit(sparsity_pattern);
+    supg_v_mat.reinit(sparsity_pattern);
+    equation_0_mat0.reinit(sparsity_pattern);
+    constraints.close();
+
+    // Assemble operators constant in time
+    assemble_laplace_mat();
+    assemble_mass_mat();
+    assemble_advection_v_mat();
+    assemble_supg_v_mat();
+  }
+
+  AffineConstraints<data_type> constraints;
+  data_type time;
+  unsigned long timestep_number = 1;
+  const data_type time_step = 0.01;
+  
+  ConditionalOStream pcout;
+  std::vector<std::pair<double, std::string>> times_and_names;
+
+  Fn_c0 fn_c0;
+  Fn_v fn_v;
+  Triangulation<dim> mesh;
+  const FE_Q<dim> element;
+  const MappingQ<dim> element_mapping;
+  const QGauss<dim> element_quadrature;
+  const QGauss<dim - 1> element_face_quadrature;
+  DoFHandler<dim> dof_handler;
+  Vector<data_type> c;
+  Vector<data_type> c_prev;
+  SparsityPattern sparsity_pattern;
+  Vector<data_type> rhs;
+  SparseMatrix<data_type> matrix_c;
+  SparseMatrix<data_type> pristine_matrix_c;
+  SparseMatrix<data_type> laplace_mat;
+  SparseMatrix<data_type> mass_mat;
+  SparseMatrix<data_type> advection_v_mat;
+  SparseMatrix<data_type> supg_v_mat;
+  const data_type equation_0_c0 = 1 / time_step;
+  const data_type equation_0_c1 = (1./2.) * d;
+  const data_type equation_0_c2 = (-1./2.) * d;
+  Fn_0 fn_0;
+  SparseMatrix<data_type> equation_0_mat0;
+
+  void solve_c();
+  void assemble_laplace_mat();
+  void assemble_mass_mat();
+  void assemble_advection_v_mat();
+  void assemble_supg_v_mat();
+};
+
+void Sim::solve_c() {
+  SolverControl solver_control(1000, 1e-8 * rhs.l2_norm());
+  SolverGMRES<Vector<data_type>> gmres(solver_control);
+
+  gmres.solve(matrix_c, c, rhs, PreconditionIdentity());
+
+  pcout << "    solve_c: " << solver_control.last_step()
+            << "  GMRES iterations." << std::endl;
+}
+            
+
+void Sim::assemble_laplace_mat() {
+  using Iterator = typename DoFHandler<dim>::active_cell_iterator;
+  using CopyData = MeshWorker::CopyData<1, 1, 1>;
+
+  const auto cell_worker = [&](const Iterator &cell,
+                               MeshWorker::ScratchData<dim> &scratch_data,
+                               CopyData &copy_data) {
+    const FEValues<dim> &fe_values = scratch_data.reinit(cell);
+    FullMatrix<data_type> &cell_matrix = copy_data.matrices[0];
+    cell_matrix = 0;
+    for (const unsigned int q : fe_values.quadrature_point_indices()) {
+      for (const unsigned int i : fe_values.dof_indices())
+        for (const unsigned int j : fe_values.dof_indices())
+          cell_matrix(i, j) += fe_values.shape_grad(i, q) *
+                               fe_values.shape_grad(j, q) * fe_values.JxW(q);
+    }
+    cell->get_dof_indices(copy_data.local_dof_indices[0]);
+  };
+  const auto copier = [&](const CopyData &copy_data) {
+    laplace_mat.add(copy_data.local_dof_indices[0], copy_data.matrices[0]);
+  };
+
+  laplace_mat = 0;
+  WorkStream::run(dof_handler.begin_active(), dof_handler.end(), cell_worker, copier,
+                  MeshWorker::ScratchData<dim>(element_mapping, element, element_quadrature,
+                                               update_gradients | update_JxW_values),
+                  CopyData(element.n_dofs_per_cell()));
+}
+
+void Sim::assemble_mass_mat() {
+  using Iterator = typename DoFHandler<dim>::active_cell_iterator;
+  using CopyData = MeshWorker::CopyData<1, 1, 1>;
+
+  const auto cell_worker = [&](const Iterator &cell,
+                               MeshWorker::ScratchData<dim> &scratch_data,
+                               CopyData &copy_data) {
+    const FEValues<dim> &fe_values = scratch_data.reinit(cell);
+    FullMatrix<data_type> &cell_matrix = copy_data.matrices[0];
+    cell_matrix = 0;
+    for (const unsigned int q : fe_values.quadrature_point_indices()) {
+      for (const unsigned int i : fe_values.dof_indices())
+        for (const unsigned int j : fe_values.dof_indices())
+          cell_matrix(i, j) += fe_values.shape_value(i, q) *
+                               fe_values.shape_value(j, q) * fe_values.JxW(q);
+    }
+    cell->get_dof_indices(copy_data.local_dof_indices[0]);
+  };
+  const auto copier = [&](const CopyData &copy_data) {
+    mass_mat.add(copy_data.local_dof_indices[0], copy_data.matrices[0]);
+  };
+
+  mass_mat = 0;
+  WorkStream::run(dof_handler.begin_active(), dof_handler.end(), cell_worker, copier,
+                  MeshWorker::ScratchData<dim>(element_mapping, element, element_quadrature,
+                                               update_values | update_JxW_values),
+                  CopyData(element.n_dofs_per_cell()));
+}
+
+void Sim::assemble_advection_v_mat() {
+  FEValues<dim> fe_values(element_mapping, element, element_quadrature,
+                          update_values | update_gradients |
+                              update_quadrature_points | update_JxW_values);
+
+  const unsigned int dofs_per_cell = element.n_dofs_per_cell();
+  FullMatrix<data_type> cell_matrix(dofs_per_cell, dofs_per_cell);
+  std::vector<types::global_dof_index> local_dof_indices(dofs_per_cell);
+
+  advection_v_mat = 0;
+  for (const auto &cell : dof_handler.active_cell_iterators()) {
+    if (!cell->is_locally_owned())
+      continue;
+
+    fe_values.reinit(cell);
+    cell_matrix = 0;
+    for (const unsigned int q : fe_values.quadrature_point_indices()) {
+      Tensor<1, dim> velocity;
+      for (unsigned int d = 0; d < dim; ++d)
+        velocity[d] = fn_v.value(fe_values.quadrature_point(q), d);
+      // The stabilization vanishes where the fluid is at rest
+      const data_type speed = velocity.norm();
+      const data_type tau = speed > 0. ? 0.5 * cell->diameter() / speed : 0.;
+
+      for (const unsigned int i : fe_values.dof_indices())
+        for (const unsigned int j : fe_values.dof_indices())
+          cell_matrix(i, j) +=
+              (fe_values.shape_value(i, q) +
+               tau * (velocity * fe_values.shape_grad(i, q))) *
+              (velocity * fe_values.shape_grad(j, q)) *
+              fe_values.JxW(q);
+    }
+
+    cell->get_dof_indices(local_dof_indices);
+    constraints.distribute_local_to_global(cell_matrix, local_dof_indices, advection_v_mat);
+  }
+  advection_v_mat.compress(VectorOperation::add);
+}
+
+void Sim::assemble_supg_v_mat() {
+  FEValues<dim> fe_values(element_mapping, element, element_quadrature,
+                          update_values | update_gradients |
+                              update_quadrature_points | update_JxW_values);
+
+  const unsigned int dofs_per_cell = element.n_dofs_per_cell();
+  FullMatrix<data_type> cell_matrix(dofs_per_cell, dofs_per_cell);
+  std::vector<types::global_dof_index> local_dof_indices(dofs_per_cell);
+
+  supg_v_mat = 0;
+  for (const auto &cell : dof_handler.active_cell_iterators()) {
+    if (!cell->is_locally_owned())
+      continue;
+
+    fe_values.reinit(cell);
+    cell_matrix = 0;
+    for (const unsigned int q : fe_values.quadrature_point_indices()) {
+      Tensor<1, dim> velocity;
+      for (unsigned int d = 0; d < dim; ++d)
+        velocity[d] = fn_v.value(fe_values.quadrature_point(q), d);
+      // The stabilization vanishes where the fluid is at rest
+      const data_type speed = velocity.norm();
+      const data_type tau = speed > 0. ? 0.5 * cell->diameter() / speed : 0.;
+
+      for (const unsigned int i : fe_values.dof_indices())
+        for (const unsigned int j : fe_values.dof_indices())
+          cell_matrix(i, j) +=
+              tau * (velocity * fe_values.shape_grad(i, q)) * fe_values.shape_value(j, q) *
+              fe_values.JxW(q);
+    }
+
+    cell->get_dof_indices(local_dof_indices);
+    constraints.distribute_local_to_global(cell_matrix, local_dof_indices, supg_v_mat);
+  }
+  supg_v_mat.compress(VectorOperation::add);
+}
+
+void Sim::run() {
+  setup_mesh();
+  setup_system();
+  time = 0 + time_step;
+
+  // Prepare time stepping
+  // # Assemble the time invariant system of equation ((1 / k)M^n + (1/2)DA^n + (1/2)C_v^n + (1 / k)P_v^n)(C^n) = ((1 / k)M^n + (-1/2)DA^n + (-1/2)C_v^n + (1 / k)P_v^n)(C^n-1)
+  // ## Compute system for C^n
+  // pristine_matrix_c = equation_0_c0.M^n + equation_0_c1.A^n + (1/2)C_v^n + equation_0_c0.P_v^n
+  // pristine_matrix_c = equation_0_c0 * M^n
+  pristine_matrix_c.copy_from(mass_mat); pristine_matrix_c *= equation_0_c0;
+  
+  // pristine_matrix_c += equation_0_c1.A^n + (1/2)C_v^n
+  pristine_matrix_c.add(equation_0_c1, laplace_mat, 1./2., advection_v_mat);
+  
+  // pristine_matrix_c += equation_0_c0.P_v^n
+  pristine_matrix_c.add(equation_0_c0, supg_v_mat);
+  
+  // # Assemble the time invariant right-hand side operators of equation ((1 / k)M^n + (1/2)DA^n + (1/2)C_v^n + (1 / k)P_v^n)(C^n) = ((1 / k)M^n + (-1/2)DA^n + (-1/2)C_v^n + (1 / k)P_v^n)(C^n-1)
+  // equation_0_mat0 = equation_0_c0.M^n + equation_0_c2.A^n + (-1/2)C_v^n + equation_0_c0.P_v^n
+  // equation_0_mat0 = equation_0_c0 * M^n
+  equation_0_mat0.copy_from(mass_mat); equation_0_mat0 *= equation_0_c0;
+  
+  // equation_0_mat0 += equation_0_c2.A^n + (-1/2)C_v^n
+  equation_0_mat0.add(equation_0_c2, laplace_mat, -1./2., advection_v_mat);
+  
+  // equation_0_mat0 += equation_0_c0.P_v^n
+  equation_0_mat0.add(equation_0_c0, supg_v_mat);
+  
+  // Apply Intial Condition for c 
+  VectorTools::project(element_mapping, dof_handler, constraints, element_quadrature,
+                       fn_c0, c_prev);
+
+  // Run time stepping
+  for (; time <= 1; time += time_step, ++timestep_number) {
+    pcout << "Time step " << timestep_number << " at t=" << time << std::endl;
+
+    // # Setup equation ((1 / k)M^n + (1/2)DA^n + (1/2)C_v^n + (1 / k)P_v^n)(C^n) = ((1 / k)M^n + (-1/2)DA^n + (-1/2)C_v^n + (1 / k)P_v^n)(C^n-1)
+    // ## Copy system for C^n, assembled once
+    matrix_c.copy_from(pristine_matrix_c);
+    
+    
+    // ## Compute rhs for C^n
+    // rhs = equation_0_mat0.(C^n-1)
+    equation_0_mat0.vmult(rhs, c_prev);
+    
+
+    // Apply boundary condition to the equation for solving c
+    {
+      fn_0.set_time(time);
+    
+      std::map<types::global_dof_index, double> boundary_values;
+      VectorTools::interpolate_boundary_values(
+          element_mapping, dof_handler, 0, fn_0, boundary_values);
+      MatrixTools::apply_boundary_values(boundary_values, matrix_c, c,
+                                         rhs);
+    }
+    
+
+    solve_c();
+    
+
+    output_results();
+    
+
+    // Swap new values with previous values for the next step
+    swap(c, c_prev);
+  }
+
+}
+
+void Sim::output_results() {
+  DataOut<dim> data_out;
+
+  data_out.attach_dof_handler(dof_handler);
+  data_out.add_data_vector(c, "c");
+  data_out.build_patches(element_mapping);
+
+  DataOutBase::VtkFlags vtk_flags;
+  vtk_flags.compression_level = DataOutBase::CompressionLevel::best_speed;
+  data_out.set_flags(vtk_flags);
+  const std::string filename =
+      "solution-" + Utilities::int_to_string(timestep_number, 3) + ".vtu";
+  std::ofstream output("./" + filename);
+  data_out.write_vtu(output);
+
+  // The files are indexed by time, for ParaView to open them as a series
+  times_and_names.emplace_back(time, filename);
+  std::ofstream pvd_output("./solution.pvd");
+  DataOutBase::write_pvd_record(pvd_output, times_and_names);
+}
+
+int main(int argc, char *argv[]) {
+  try {
+    #ifdef DEAL_II_WITH_KOKKOS
+    Kokkos::initialize(argc, argv);
+    #endif
+
+    Sim sim;
+    sim.run();
+
+    #ifdef DEAL_II_WITH_KOKKOS
+    Kokkos::finalize();
+    #endif
+    } catch (std::exception &exc) {
+    std::cerr << std::endl
+                << std::endl
+                << "----------------------------------------------------"
+                << std::endl;
+      std::cerr << "Exception on processing: " << std::endl
+                << exc.what() << std::endl
+                << "Aborting!" << std::endl
+                << "----------------------------------------------------"
+                << std::endl;
+
+      return 1;
+
+  } catch (...) {
+    std::cerr << std::endl
+                << std::endl
+                << "----------------------------------------------------"
+                << std::endl;
+      std::cerr << "Unknown exception!" << std::endl
+                << "Aborting!" << std::endl
+                << "----------------------------------------------------"
+                << std::endl;
+      return 1;
+  }
+
+  return 0;
+}
@@ -0,0 +1,692 @@
+// Time scheme: Crank-Nicolson (θ = 0.5)
+#include <iostream>
+#include <fstream>
+#include <cmath>
+#include <deal.II/lac/affine_constraints.h>
+#include <deal.II/numerics/data_out.h>
+#include <deal.II/base/conditional_ostream.h>
+#include <deal.II/base/function.h>
+#include <deal.II/distributed/tria.h>
+#include <deal.II/distributed/grid_refinement.h>
+#include <deal.II/grid/grid_generator.h>
+#include <deal.II/fe/fe_system.h>
+#include <deal.II/fe/fe_q.h>
+#include <deal.II/fe/mapping_q.h>
+#include <deal.II/base/quadrature_lib.h>
+#include <deal.II/dofs/dof_handler.h>
+#include <deal.II/dofs/dof_tools.h>
+#include <deal.II/lac/vector.h>
+#include <deal.II/lac/petsc_vector.h>
+#include <deal.II/numerics/vector_tools_rhs.h>
+#include <deal.II/lac/dynamic_sparsity_pattern.h>
+#include <deal.II/lac/sparsity_pattern.h>
+#include <deal.II/lac/sparsity_tools.h>
+#include <deal.II/lac/sparse_matrix.h>
+#include <deal.II/lac/petsc_sparse_matrix.h>
+#include <deal.II/lac/solver_cg.h>
+#include <deal.II/lac/precondition.h>
+#include <deal.II/lac/solver_control.h>
+#include <deal.II/fe/component_mask.h>
+#include <deal.II/numerics/matrix_creator.h>
+#include <deal.II/fe/fe_values.h>
+#include <deal.II/fe/fe_values_extractors.h>
+#include <deal.II/lac/full_matrix.h>
+#include <deal.II/numerics/vector_tools_interpolate.h>
+#include <deal.II/numerics/vector_tools_boundary.h>
+#include <deal.II/numerics/matrix_tools.h>
+#include <deal.II/numerics/data_component_interpretation.h>
+#include <deal.II/lac/generic_linear_algebra.h>
+#include <deal.II/base/mpi.h>
+#include <mpi.h>
+#ifdef DEAL_II_WITH_KOKKOS
+#include <Kokkos_Core.hpp>
+#endif
+
+const int dim = 2;
+using data_type = double;
+
+
+// Parameters
+const data_type lambda = 1e0;const data_type mu = 5e-1;const data_type rho = 1e0;
+
+using namespace dealii;
+using namespace dealii::numbers;
+
+// Parellel linear algebra library namespace (PETSc or Trilinos)
+namespace LA
+{
+  #if defined(DEAL_II_WITH_PETSC) && !defined(DEAL_II_PETSC_WITH_COMPLEX) && \
+    !(defined(DEAL_II_WITH_TRILINOS) && defined(FORCE_USE_OF_TRILINOS))
+    using namespace dealii::LinearAlgebraPETSc;
+  #  define USE_PETSC_LA
+  #elif defined(DEAL_II_WITH_TRILINOS)
+    using namespace dealii::LinearAlgebraTrilinos;
+  #else
+  #  error DEAL_II_WITH_PETSC or DEAL_II_WITH_TRILINOS required
+  #endif
+} // namespace LA
+
+static constexpr data_type pi = PI;
+static constexpr data_type e = E;
+
+
+bool float_equals(data_type a, data_type b) {
+  const data_type base_epsilon = 1e-8;
+  const data_type epsilon = base_epsilon * std::max(1.0, std::max(std::abs(a), std::abs(b)));
+  return std::fabs(b - a) < epsilon;
+}
+
+Point<dim> make_point(data_type x, data_type y, data_type z) {
+  const data_type coordinates[] = {x, y, z};
+  Point<dim> point;
+  for (int d = 0; d < dim; ++d)
+    point[d] = coordinates[d];
+  return point;
+}
+
+
+class Fn_zero : public Function<dim> {
+public:
+  Fn_zero() : Function<dim>(2) {}
+
+  virtual double value(const Point<dim> &point,
+                       const unsigned int component = 0) const override {
+    switch (component) {
+    case 0: {
+      return 0;
+    }
+    case 1: {
+      return 0;
+    }
+    default:
+      return 0;
+    }
+  }
+};
+
+
+class Fn_f : public Function<dim> {
+public:
+  Fn_f() : Function<dim>(2) {}
+
+  virtual double value(const Point<dim> &point,
+                       const unsigned int component = 0) const override {
+    switch (component) {
+    case 0: {
+      return 0;
+    }
+    case 1: {
+      return 0;
+    }
+    default:
+      return 0;
+    }
+  }
+};
+
+
+class Fn_load : public Function<dim> {
+public:
+  Fn_load() : Function<dim>(2) {}
+
+  virtual double value(const Point<dim> &point,
+                       const unsigned int component = 0) const override {
+    switch (component) {
+    case 0: {
+      return 0;
+    }
+    case 1: {
+      if (get_time() >= 0 && get_time() <= 0.1) {
+        return -1./100.;
+      }
+      else {
+        return 0;
+      }
+    }
+    default:
+      return 0;
+    }
+  }
+};
+
+
+class Sim {
+public:
+  Sim()
+    : mpi_rank(Utilities::MPI::this_mpi_process(MPI_COMM_WORLD))
+    , n_mpi_processes(Utilities::MPI::n_mpi_processes(MPI_COMM_WORLD))
+    , pcout(std::cout, mpi_rank == 0)
+    , mesh(MPI_COMM_WORLD,
+        typename Triangulation<dim>::MeshSmoothing(
+            Triangulation<dim>::smoothing_on_refinement |
+            Triangulation<dim>::smoothing_on_coarsening)), element(FE_Q<dim>(1), 2), element_mapping(1), element_quadrature(element.degree + 1), element_face_quadrature(element.degree + 1), dof_handler(mesh)
+  {}
+
+  void run();
+  void output_results();
+
+  void setup_mesh() {
+    std::vector<unsigned int> mesh_subdivisions = {32, 32, 32};
+    mesh_subdivisions.resize(dim);
+    GridGenerator::subdivided_hyper_rectangle(mesh, mesh_subdivisions, make_point(0, 0, 0), make_point(1, 1, 1), true);
+    const double mesh_cell_size[] = {0.03125, 0.03125, 0.03125};
+    pcout << "Cell size: " << mesh_cell_size[0] << " m";
+    for (int d = 1; d < dim; ++d) pcout << " x " << mesh_cell_size[d] << " m";
+    pcout << std::endl;
+  }
+
+  void setup_system() {
+    constraints.clear();
+    dof_handler.distribute_dofs(element);
+    pcout << "Number of degrees of freedom: " << dof_handler.n_dofs() << "\n" << std::endl;
+    dof_handler_owned_dofs = dof_handler.locally_owned_dofs();
+    dof_handler_relevant_dofs = DoFTools::extract_locally_relevant_dofs(dof_handler);
+    constraints.clear();
+    constraints.reinit(dof_handler_owned_dofs, dof_handler_relevant_dofs);
+    DoFTools::make_hanging_node_constraints(dof_handler, constraints);
+    u.reinit(dof_handler_owned_dofs, MPI_COMM_WORLD);
+    dt_u.reinit(dof_handler_owned_dofs, MPI_COMM_WORLD);
+    u_prev.reinit(dof_handler_owned_dofs, MPI_COMM_WORLD);
+    dt_u_prev.reinit(dof_handler_owned_dofs, MPI_COMM_WORLD);
+    f_prev.reinit(dof_handler_owned_dofs, MPI_COMM_WORLD);
+    f.reinit(dof_handler_owned_dofs, MPI_COMM_WORLD);
+    constraints.close();
+    
+    // Sparsity Pattern
+    DynamicSparsityPattern dof_handler_dsp(dof_handler_relevant_dofs);
+    DoFTools::make_sparsity_pattern(dof_handler, dof_handler_dsp, constraints, false);
+    SparsityTools::distribute_sparsity_pattern(dof_handler_dsp, dof_handler_owned_dofs, MPI_COMM_WORLD, dof_handler_relevant_dofs);
+    rhs.reinit(dof_handler_owned_dofs, MPI_COMM_WORLD);
+    matrix_u.reinit(dof_handler_owned_dofs, dof_handler_owned_dofs, dof_handler_dsp, MPI_COMM_WORLD);
+    pristine_matrix_u.reinit(dof_handler_owned_dofs, dof_handler_owned_dofs, dof_handler_dsp, MPI_COMM_WORLD);
+    matrix_dt_u.reinit(dof_handler_owned_dofs, dof_handler_owned_dofs, dof_handler_dsp, MPI_COMM_WORLD);
+    pristine_matrix_dt_u.reinit(dof_handler_owned_dofs, dof_handler_owned_dofs, dof_handler_dsp, MPI_COMM_WORLD);
+    laplace_mat.reinit(dof_handler_owned_dofs, dof_handler_owned_dofs, dof_handler_dsp, MPI_COMM_WORLD);
+    MatrixCreator::create_laplace_matrix(element_mapping, dof_handler, element_quadrature, laplace_mat);
+    mass_mat.reinit(dof_handler_owned_dofs, dof_handler_owned_dofs, dof_handler_dsp, MPI_COMM_WORLD);
+    MatrixCreator::create_mass_matrix(element_mapping, dof_handler, element_quadrature, mass_mat);
+    grad_div_mat.reinit(dof_handler_owned_dofs, dof_handler_owned_dofs, dof_handler_dsp, MPI_COMM_WORLD);
+    strain_mat.reinit(dof_handler_owned_dofs, dof_handler_owned_dofs, dof_handler_dsp, MPI_COMM_WORLD);
+    u_ghosted.reinit(dof_handler_owned_dofs, dof_handler_relevant_dofs, MPI_COMM_WORLD);
+    dt_u_ghosted.reinit(dof_handler_owned_dofs, dof_handler_relevant_dofs, MPI_COMM_WORLD);
+    vtmp.reinit(dof_handler_owned_dofs, MPI_COMM_WORLD);
+    equation_0_mat0.reinit(dof_handler_owned_dofs, dof_handler_owned_dofs, dof_handler_dsp, MPI_COMM_WORLD);
+    boundary_flux_u_right.reinit(dof_handler_owned_dofs, MPI_COMM_WORLD);
+    equation_1_mat0.reinit(dof_handler_owned_dofs, dof_handler_owned_dofs, dof_handler_dsp, MPI_COMM_WORLD);
+    constraints.close();
+
+    // Assemble operators constant in time
+    assemble_f_prev();
+    assemble_f();
+    assemble_grad_div_mat();
+    assemble_strain_mat();
+  }
+
+  AffineConstraints<data_type> constraints;
+  data_type time;
+  unsigned long timestep_number = 1;
+  const data_type time_step = 0.01;
+  const unsigned int mpi_rank = 0;
+  const unsigned int n_mpi_processes = 1;
+  ConditionalOStream pcout;
+  std::vector<std::pair<double, std::string>> times_and_names;
+
+  Fn_zero fn_zero;
+  Fn_f fn_f;
+  Fn_load fn_load;
+  parallel::distributed::Triangulation<dim> mesh;
+  const FESystem<dim> element;
+  const MappingQ<dim> element_mapping;
+  const QGauss<dim> element_quadrature;
+  const QGauss<dim - 1> element_face_quadrature;
+  DoFHandler<dim> dof_handler;
+  IndexSet dof_handler_owned_dofs;
+  IndexSet dof_handler_relevant_dofs;
+  LA::MPI::Vector u;
+  LA::MPI::Vector dt_u;
+  LA::MPI::Vector u_prev;
+  LA::MPI::Vector dt_u_prev;
+  LA::MPI::Vector f_prev;
+  LA::MPI::Vector f;
+  LA::MPI::Vector rhs;
+  LA::MPI::SparseMatrix matrix_u;
+  LA::MPI::SparseMatrix pristine_matrix_u;
+  LA::MPI::PreconditionAMG solve_u_preconditioner;
+  bool solve_u_initialized = false;
+  LA::MPI::SparseMatrix matrix_dt_u;
+  LA::MPI::SparseMatrix pristine_matrix_dt_u;
+  LA::MPI::PreconditionAMG solve_dt_u_preconditioner;
+  bool solve_dt_u_initialized = false;
+  LA::MPI::SparseMatrix laplace_mat;
+  LA::MPI::SparseMatrix mass_mat;
+  LA::MPI::SparseMatrix grad_div_mat;
+  LA::MPI::SparseMatrix strain_mat;
+  const data_type equation_0_c0 = -(1 / time_step);
+  const data_type equation_0_c1 = (-1./4.) * lambda * (1 / rho) * time_step;
+  const data_type equation_0_c2 = (-1./2.) * mu * (1 / rho) * time_step;
+  const data_type equation_0_c3 = (-1./4.) * (1 / rho) * time_step;
+  const data_type equation_0_c4 = (1./4.) * lambda * (1 / rho) * time_step;
+  const data_type equation_0_c5 = (1./2.) * mu * (1 / rho) * time_step;
+  const data_type equation_1_c0 = (1./2.) * (1 / rho) * time_step;
+  const data_type equation_1_c1 = (-1./2.) * lambda * (1 / rho) * time_step;
+  const data_type equation_1_c2 = -mu * (1 / rho) * time_step;
+  LA::MPI::Vector u_ghosted;
+  LA::MPI::Vector dt_u_ghosted;
+  LA::MPI::Vector vtmp;
+  LA::MPI::SparseMatrix equation_0_mat0;
+  LA::MPI::Vector boundary_flux_u_right;
+  LA::MPI::SparseMatrix equation_1_mat0;
+
+  void assemble_f_prev();
+  void assemble_f();
+  void solve_u();
+  void solve_dt_u();
+  void assemble_grad_div_mat();
+  void assemble_strain_mat();
+  void assemble_boundary_flux_u_right();
+};
+
+void Sim::assemble_f_prev() {
+  VectorTools::create_right_hand_side(element_mapping, dof_handler, element_quadrature, fn_f,
+                                      f_prev);
+}
+
+void Sim::assemble_f() {
+  VectorTools::create_right_hand_side(element_mapping, dof_handler, element_quadrature, fn_f,
+                                      f);
+}
+
+void Sim::solve_u() {
+  SolverControl solver_control(dof_handler.n_dofs(), 1e-6 * rhs.l2_norm());
+  LA::SolverCG solver(solver_control);
+
+  // The matrix is the same at every step, the preconditioner is only initialized once
+  if (!solve_u_initialized) {
+    // Preconditioner configuration
+    LA::MPI::PreconditionAMG::AdditionalData data;
+#ifdef USE_PETSC_LA
+    data.symmetric_operator = true;
+#else
+    // The coarse levels represent the translations of each component
+    DoFTools::extract_constant_modes(dof_handler, ComponentMask(), data.constant_modes);
+#endif
+
+    solve_u_preconditioner.initialize(matrix_u, data);
+    solve_u_initialized = true;
+  }
+
+  solver.solve(matrix_u, u, rhs, solve_u_preconditioner);
+
+  pcout << "    solve_u: " << solver_control.last_step()
+            << "  CG iterations." << std::endl;
+
+  constraints.distribute(u);
+}
+
+void Sim::solve_dt_u() {
+  SolverControl solver_control(dof_handler.n_dofs(), 1e-6 * rhs.l2_norm());
+  LA::SolverCG solver(solver_control);
+
+  // The matrix is the same at every step, the preconditioner is only initialized once
+  if (!solve_dt_u_initialized) {
+    // Preconditioner configuration
+    LA::MPI::PreconditionAMG::AdditionalData data;
+#ifdef USE_PETSC_LA
+    data.symmetric_operator = true;
+#else
+    // The coarse levels represent the translations of each component
+    DoFTools::extract_constant_modes(dof_handler, ComponentMask(), data.constant_modes);
+#endif
+
+    solve_dt_u_preconditioner.initialize(matrix_dt_u, data);
+    solve_dt_u_initialized = true;
+  }
+
+  solver.solve(matrix_dt_u, dt_u, rhs, solve_dt_u_preconditioner);
+
+  pcout << "    solve_dt_u: " << solver_control.last_step()
+            << "  CG iterations." << std::endl;
+
+  constraints.distribute(dt_u);
+}
+
+void Sim::assemble_grad_div_mat() {
+  FEValues<dim> fe_values(element_mapping, element, element_quadrature,
+                          update_gradients | update_JxW_values);
+  const FEValuesExtractors::Vector unknown(0);
+
+  const unsigned int dofs_per_cell = element.n_dofs_per_cell();
+  FullMatrix<data_type> cell_matrix(dofs_per_cell, dofs_per_cell);
+  std::vector<types::global_dof_index> local_dof_indices(dofs_per_cell);
+
+  grad_div_mat = 0;
+  for (const auto &cell : dof_handler.active_cell_iterators()) {
+    if (!cell->is_locally_owned())
+      continue;
+
+    fe_values.reinit(cell);
+    cell_matrix = 0;
+    for (const unsigned int q : fe_values.quadrature_point_indices())
+      for (const unsigned int i : fe_values.dof_indices())
+        for (const unsigned int j : fe_values.dof_indices())
+          cell_matrix(i, j) +=
+              fe_values[unknown].divergence(i, q) * fe_values[unknown].divergence(j, q) *
+              fe_values.JxW(q);
+
+    cell->get_dof_indices(local_dof_indices);
+    constraints.distribute_local_to_global(cell_matrix, local_dof_indices, grad_div_mat);
+  }
+  grad_div_mat.compress(VectorOperation::add);
+}
+
+void Sim::assemble_strain_mat() {
+  FEValues<dim> fe_values(element_mapping, element, element_quadrature,
+                          update_gradients | update_JxW_values);
+  const FEValuesExtractors::Vector unknown(0);
+
+  const unsigned int dofs_per_cell = element.n_dofs_per_cell();
+  FullMatrix<data_type> cell_matrix(dofs_per_cell, dofs_per_cell);
+  std::vector<types::global_dof_index> local_dof_indices(dofs_per_cell);
+
+  strain_mat = 0;
+  for (const auto &cell : dof_handler.active_cell_iterators()) {
+    if (!cell->is_locally_owned())
+      continue;
+
+    fe_values.reinit(cell);
+    cell_matrix = 0;
+    for (const unsigned int q : fe_values.quadrature_point_indices())
+      for (const unsigned int i : fe_values.dof_indices())
+        for (const unsigned int j : fe_values.dof_indices())
+          cell_matrix(i, j) +=
+              scalar_product(fe_values[unknown].symmetric_gradient(i, q),
+                             fe_values[unknown].symmetric_gradient(j, q)) *
+              fe_values.JxW(q);
+
+    cell->get_dof_indices(local_dof_indices);
+    constraints.distribute_local_to_global(cell_matrix, local_dof_indices, strain_mat);
+  }
+  strain_mat.compress(VectorOperation::add);
+}
+
+void Sim::assemble_boundary_flux_u_right() {
+  FEFaceValues<dim> fe_face_values(element_mapping, element, element_face_quadrature,
+                                   update_values | update_quadrature_points |
+                                       update_JxW_values);
+
+  const unsigned int dofs_per_cell = element.n_dofs_per_cell();
+  Vector<data_type> cell_vector(dofs_per_cell);
+  std::vector<types::global_dof_index> local_dof_indices(dofs_per_cell);
+  const FiniteElement<dim> &fe = element;
+  Vector<data_type> weight(2);
+
+  boundary_flux_u_right = 0;
+  for (const auto &cell : dof_handler.active_cell_iterators()) {
+    if (!cell->is_locally_owned())
+      continue;
+
+    for (const auto &face : cell->face_iterators()) {
+      if (!face->at_boundary() || face->boundary_id() != 1)
+        continue;
+
+      fe_face_values.reinit(cell, face);
+      cell_vector = 0;
+      for (const unsigned int q : fe_face_values.quadrature_point_indices()) {
+        fn_load.vector_value(fe_face_values.quadrature_point(q), weight);
+        for (const unsigned int i : fe_face_values.dof_indices())
+          cell_vector(i) += weight(fe.system_to_component_index(i).first) *
+                            fe_face_values.shape_value(i, q) *
+                            fe_face_values.JxW(q);
+      }
+
+      cell->get_dof_indices(local_dof_indices);
+      constraints.distribute_local_to_global(cell_vector, local_dof_indices, boundary_flux_u_right);
+    }
+  }
+  boundary_flux_u_right.compress(VectorOperation::add);
+}
+
+void Sim::run() {
+  pcout << "Running with "
+#ifdef USE_PETSC_LA
+  << "PETSc"
+#else
+  << "Trilinos"
+#endif
+  << " on " << Utilities::MPI::n_mpi_processes(MPI_COMM_WORLD)
+  << " MPI rank(s)..." << std::endl;
+
+  setup_mesh();
+  setup_system();
+  time = 0 + time_step;
+
+  // Prepare time stepping
+  // # Assemble the time invariant system of equation (-(1 / k)M^n + (-1/4)lambda.(1 / rho)kB^n + (-1/2)mu.(1 / rho)kS^n)(U^n) = (-(1 / k)M^n + (1/4)lambda.(1 / rho)kB^n + (1/2)mu.(1 / rho)kS^n)(U^n-1) - M^n.(DT_U^n-1) + (-1/4)(1 / rho)k(F^n-1) + (-1/4)(1 / rho)k(F^n)
+  // ## Compute system for U^n
+  // pristine_matrix_u = equation_0_c0.M^n + equation_0_c1.B^n + equation_0_c2.S^n
+  // pristine_matrix_u = equation_0_c0 * M^n
+  pristine_matrix_u.copy_from(mass_mat); pristine_matrix_u *= equation_0_c0;
+  
+  // pristine_matrix_u += equation_0_c1.B^n + equation_0_c2.S^n
+  pristine_matrix_u.add(equation_0_c1, grad_div_mat, equation_0_c2, strain_mat);
+  
+  pristine_matrix_u.compress(VectorOperation::add);
+  
+  // # Assemble the time invariant right-hand side operators of equation (-(1 / k)M^n + (-1/4)lambda.(1 / rho)kB^n + (-1/2)mu.(1 / rho)kS^n)(U^n) = (-(1 / k)M^n + (1/4)lambda.(1 / rho)kB^n + (1/2)mu.(1 / rho)kS^n)(U^n-1) - M^n.(DT_U^n-1) + (-1/4)(1 / rho)k(F^n-1) + (-1/4)(1 / rho)k(F^n)
+  // equation_0_mat0 = equation_0_c0.M^n + equation_0_c4.B^n + equation_0_c5.S^n
+  // equation_0_mat0 = equation_0_c0 * M^n
+  equation_0_mat0.copy_from(mass_mat); equation_0_mat0 *= equation_0_c0;
+  
+  // equation_0_mat0 += equation_0_c4.B^n + equation_0_c5.S^n
+  equation_0_mat0.add(equation_0_c4, grad_div_mat, equation_0_c5, strain_mat);
+  
+  equation_0_mat0.compress(VectorOperation::add);
+  
+  // Apply Intial Condition for u
+  VectorTools::interpolate(element_mapping, dof_handler, fn_zero, u_prev);
+  constraints.distribute(u_prev);
+  // # Assemble the time invariant system of equation M^n.(DT_U^n) = ((-1/2)lambda.(1 / rho)kB^n - mu.(1 / rho)kS^n)(U^n) + ((-1/2)lambda.(1 / rho)kB^n - mu.(1 / rho)kS^n)(U^n-1) + M^n.(DT_U^n-1) + (1/2)(1 / rho)k(F^n-1) + (1/2)(1 / rho)k(F^n)
+  // ## Compute system for DT_U^n
+  pristine_matrix_dt_u.copy_from(mass_mat);
+  
+  pristine_matrix_dt_u.compress(VectorOperation::add);
+  
+  // # Assemble the time invariant right-hand side operators of equation M^n.(DT_U^n) = ((-1/2)lambda.(1 / rho)kB^n - mu.(1 / rho)kS^n)(U^n) + ((-1/2)lambda.(1 / rho)kB^n - mu.(1 / rho)kS^n)(U^n-1) + M^n.(DT_U^n-1) + (1/2)(1 / rho)k(F^n-1) + (1/2)(1 / rho)k(F^n)
+  // equation_1_mat0 = equation_1_c1.B^n + equation_1_c2.S^n
+  // equation_1_mat0 = equation_1_c1 * B^n
+  equation_1_mat0.copy_from(grad_div_mat); equation_1_mat0 *= equation_1_c1;
+  
+  // equation_1_mat0 += equation_1_c2.S^n
+  equation_1_mat0.add(equation_1_c2, strain_mat);
+  
+  equation_1_mat0.compress(VectorOperation::add);
+  
+  // Apply Intial Condition for dt
+  VectorTools::interpolate(element_mapping, dof_handler, fn_zero, dt_u_prev);
+  constraints.distribute(dt_u_prev);
+
+  // Run time stepping
+  for (; time <= 1; time += time_step, ++timestep_number) {
+    pcout << "Time step " << timestep_number << " at t=" << time << std::endl;
+
+    // # Setup equation (-(1 / k)M^n + (-1/4)lambda.(1 / rho)kB^n + (-1/2)mu.(1 / rho)kS^n)(U^n) = (-(1 / k)M^n + (1/4)lambda.(1 / rho)kB^n + (1/2)mu.(1 / rho)kS^n)(U^n-1) - M^n.(DT_U^n-1) + (-1/4)(1 / rho)k(F^n-1) + (-1/4)(1 / rho)k(F^n)
+    // ## Copy system for U^n, assembled once
+    matrix_u.copy_from(pristine_matrix_u);
+    
+    
+    // ## Compute rhs for U^n
+    // rhs = equation_0_mat0.(U^n-1) - M^n.(DT_U^n-1) + equation_0_c3.(F^n-1) + equation_0_c3.(F^n)
+    // rhs = equation_0_mat0.(U^n-1)
+    equation_0_mat0.vmult(rhs, u_prev);
+    
+    // rhs += -M^n.(DT_U^n-1)
+    mass_mat.vmult(vtmp, dt_u_prev);
+    rhs -= vtmp;
+    
+    // rhs += equation_0_c3.(F^n-1) + equation_0_c3.(F^n)
+    rhs.add(equation_0_c3, f_prev, equation_0_c3, f);
+    
+    rhs.compress(VectorOperation::add);
+    
+    
+
+    // Apply Neumann boundary condition on right to the equation for solving u
+    {
+      fn_load.set_time(time);
+      assemble_boundary_flux_u_right();
+      rhs.add((-1./2.) * (1 / rho) * time_step, boundary_flux_u_right);
+    }
+    
+
+    // Apply boundary condition on left to the equation for solving u
+    {
+      fn_zero.set_time(time);
+    
+      std::map<types::global_dof_index, double> boundary_values;
+      VectorTools::interpolate_boundary_values(
+          element_mapping, dof_handler, 0, fn_zero, boundary_values);
+      MatrixTools::apply_boundary_values(boundary_values, matrix_u, u,
+                                         rhs);
+    }
+    
+
+    // Apply boundary condition on bottom to the equation for solving u
+    {
+      fn_zero.set_time(time);
+    
+      std::map<types::global_dof_index, double> boundary_values;
+      VectorTools::interpolate_boundary_values(
+          element_mapping, dof_handler, 2, fn_zero, boundary_values,
+          ComponentMask(std::vector<bool>{false, true}));
+      MatrixTools::apply_boundary_values(boundary_values, matrix_u, u,
+                                         rhs);
+    }
+    
+
+    solve_u();
+    
+
+    // # Setup equation M^n.(DT_U^n) = ((-1/2)lambda.(1 / rho)kB^n - mu.(1 / rho)kS^n)(U^n) + ((-1/2)lambda.(1 / rho)kB^n - mu.(1 / rho)kS^n)(U^n-1) + M^n.(DT_U^n-1) + (1/2)(1 / rho)k(F^n-1) + (1/2)(1 / rho)k(F^n)
+    // ## Copy system for DT_U^n, assembled once
+    matrix_dt_u.copy_from(pristine_matrix_dt_u);
+    
+    
+    // ## Compute rhs for DT_U^n
+    // rhs = equation_1_mat0.(U^n) + equation_1_mat0.(U^n-1) + M^n.(DT_U^n-1) + equation_1_c0.(F^n-1) + equation_1_c0.(F^n)
+    // rhs = equation_1_mat0.(U^n)
+    equation_1_mat0.vmult(rhs, u);
+    
+    // rhs += equation_1_mat0.(U^n-1)
+    // vtmp = equation_1_mat0.(U^n-1)
+    equation_1_mat0.vmult(vtmp, u_prev);
+    rhs += vtmp;
+    
+    // rhs += M^n.(DT_U^n-1)
+    // vtmp = M^n.(DT_U^n-1)
+    mass_mat.vmult(vtmp, dt_u_prev);
+    rhs += vtmp;
+    
+    // rhs += equation_1_c0.(F^n-1) + equation_1_c0.(F^n)
+    rhs.add(equation_1_c0, f_prev, equation_1_c0, f);
+    
+    rhs.compress(VectorOperation::add);
+    
+    
+
+    // Apply boundary condition to the equation for solving dt_u
+    {
+      fn_zero.set_time(time);
+    
+      std::map<types::global_dof_index, double> boundary_values;
+      VectorTools::interpolate_boundary_values(
+          element_mapping, dof_handler, 0, fn_zero, boundary_values);
+      VectorTools::interpolate_boundary_values(
+          element_mapping, dof_handler, 1, fn_zero, boundary_values);
+      VectorTools::interpolate_boundary_values(
+          element_mapping, dof_handler, 2, fn_zero, boundary_values);
+      VectorTools::interpolate_boundary_values(
+          element_mapping, dof_handler, 3, fn_zero, boundary_values);
+      MatrixTools::apply_boundary_values(boundary_values, matrix_dt_u, dt_u,
+                                         rhs);
+    }
+    
+
+    solve_dt_u();
+    
+
+    output_results();
+    
+
+    // Swap new values with previous values for the next step
+    swap(u, u_prev);
+    swap(dt_u, dt_u_prev);
+  }
+
+}
+
+void Sim::output_results() {
+  DataOut<dim> data_out;
+
+  data_out.attach_dof_handler(dof_handler);
+  u_ghosted = u;
+  data_out.add_data_vector(
+      u_ghosted, std::vector<std::string>(2, "u"),
+      DataOut<dim>::type_dof_data,
+      std::vector<DataComponentInterpretation::DataComponentInterpretation>(
+          2, DataComponentInterpretation::component_is_part_of_vector));
+  dt_u_ghosted = dt_u;
+  data_out.add_data_vector(
+      dt_u_ghosted, std::vector<std::string>(2, "dt_u"),
+      DataOut<dim>::type_dof_data,
+      std::vector<DataComponentInterpretation::DataComponentInterpretation>(
+          2, DataComponentInterpretation::component_is_part_of_vector));
+  data_out.build_patches(element_mapping);
+
+  DataOutBase::VtkFlags vtk_flags;
+  vtk_flags.compression_level = DataOutBase::CompressionLevel::best_speed;
+  data_out.set_flags(vtk_flags);
+  const std::string filename = data_out.write_vtu_with_pvtu_record(
+      "./", "solution", timestep_number, MPI_COMM_WORLD, 2, 8);
+
+  // The files are indexed by time, for ParaView to open them as a series
+  times_and_names.emplace_back(time, filename);
+  if (mpi_rank == 0) {
+    std::ofstream pvd_output("./solution.pvd");
+    DataOutBase::write_pvd_record(pvd_output, times_and_names);
+  }
+}
+
+int main(int argc, char *argv[]) {
+  try {
+    Utilities::MPI::MPI_InitFinalize mpi_initialization(argc, argv, numbers::invalid_unsigned_int);
+
+    Sim sim;
+    sim.run();
+
+    } catch (std::exception &exc) {
+    std::cerr << std::endl
+                << std::endl
+                << "----------------------------------------------------"
+                << std::endl;
+      std::cerr << "Exception on processing: " << std::endl
+                << exc.what() << std::endl
+                << "Aborting!" << std::endl
+                << "----------------------------------------------------"
+                << std::endl;
+
+      return 1;
+
+  } catch (...) {
+    std::cerr << std::endl
+                << std::endl
+                << "----------------------------------------------------"
+                << std::endl;
+      std::cerr << "Unknown exception!" << std::endl
+                << "Aborting!" << std::endl
+                << "----------------------------------------------------"
+                << std::endl;
+      return 1;
+  }
+
+  return 0;
+}
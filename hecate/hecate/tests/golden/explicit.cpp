@@ -0,0 +1,349 @@
+// Time scheme: explicit Euler (θ = 0)
+#include <iostream>
+#include <fstream>
+#include <cmath>
+#include <deal.II/lac/affine_constraints.h>
+#include <deal.II/numerics/data_out.h>
+#include <deal.II/base/conditional_ostream.h>
+#include <deal.II/base/function.h>
+#include <deal.II/grid/tria.h>
+#include <deal.II/grid/grid_generator.h>
+#include <deal.II/fe/fe_q.h>
+#include <deal.II/fe/mapping_q.h>
+#include <deal.II/base/quadrature_lib.h>
+#include <deal.II/dofs/dof_handler.h>
+#include <deal.II/lac/vector.h>
+#include <deal.II/lac/dynamic_sparsity_pattern.h>
+#include <deal.II/lac/sparsity_pattern.h>
+#include <deal.II/dofs/dof_tools.h>
+#include <deal.II/lac/sparse_matrix.h>
+#include <deal.II/base/work_stream.h>
+#include <deal.II/fe/fe_values.h>
+#include <deal.II/meshworker/copy_data.h>
+#include <deal.II/meshworker/scratch_data.h>
+#include <deal.II/lac/full_matrix.h>
+#include <deal.II/numerics/vector_tools_project.h>
+#include <deal.II/numerics/vector_tools_boundary.h>
+#include <deal.II/numerics/matrix_tools.h>
+
+#ifdef DEAL_II_WITH_KOKKOS
+#include <Kokkos_Core.hpp>
+#endif
+
+const int dim = 1;
+using data_type = double;
+
+
+// Parameters
+const data_type kappa = 1e-1;
+
+using namespace dealii;
+using namespace dealii::numbers;
+
+static constexpr data_type pi = PI;
+static constexpr data_type e = E;
+
+
+bool float_equals(data_type a, data_type b) {
+  const data_type base_epsilon = 1e-8;
+  const data_type epsilon = base_epsilon * std::max(1.0, std::max(std::abs(a), std::abs(b)));
+  return std::fabs(b - a) < epsilon;
+}
+
+Point<dim> make_point(data_type x, data_type y, data_type z) {
+  const data_type coordinates[] = {x, y, z};
+  Point<dim> point;
+  for (int d = 0; d < dim; ++d)
+    point[d] = coordinates[d];
+  return point;
+}
+
+
+class Fn_T0 : public Function<dim> {
+public:
+  virtual double value(const Point<dim> &point,
+                       const unsigned int component = 0) const override {
+    return std::sin(pi * point[0]);
+  }
+};
+
+
+class Fn_0 : public Function<dim> {
+public:
+  virtual double value(const Point<dim> &point,
+                       const unsigned int component = 0) const override {
+    return 0;
+  }
+};
+
+
+class Sim {
+public:
+  Sim()
+    : pcout(std::cout, true)
+    , element(1), element_mapping(1), element_quadrature(element.degree + 1), element_face_quadrature(element.degree + 1), dof_handler(mesh)
+  {}
+
+  void run();
+  void output_results();
+
+  void setup_mesh() {
+    GridGenerator::hyper_cube(mesh, 0, 1);
+    mesh.refine_global(4);
+    pcout << "Cell size: " << 0.0625 << " m" << std::endl;
+  }
+
+  void setup_system() {
+    constraints.clear();
+    dof_handler.distribute_dofs(element);
+    pcout << "Number of degrees of freedom: " << dof_handler.n_dofs() << "\n" << std::endl;
+    t.reinit(dof_handler.n_dofs());
+    t_prev.reinit(dof_handler.n_dofs());
+    DynamicSparsityPattern dof_handler_dsp(dof_handler.n_dofs(), dof_handler.n_dofs());
+    DoFTools::make_sparsity_pattern(dof_handler, dof_handler_dsp);
+    sparsity_pattern.copy_from(dof_handler_dsp);
+    rhs.reinit(dof_handler.n_dofs());
+    matrix_t.reinit(sparsity_pattern);
+    pristine_matrix_t.reinit(sparsity_pattern);
+    laplace_mat.reinit(sparsity_pattern);
+    mass_mat.reinit(sparsity_pattern);
+    equation_0_mat0.reinit(sparsity_pattern);
+    constraints.close();
+
+    // Assemble operators constant in time
+    assemble_laplace_mat();
+    assemble_mass_mat();
+  }
+
+  AffineConstraints<data_type> constraints;
+  data_type time;
+  unsigned long timestep_number = 1;
+  const data_type time_step = 0.001;
+  
+  ConditionalOStream pcout;
+  std::vector<std::pair<double, std::string>> times_and_names;
+
+  Fn_T0 fn_T0;
+  Triangulation<dim> mesh;
+  const FE_Q<dim> element;
+  const MappingQ<dim> element_mapping;
+  const QGauss<dim> element_quadrature;
+  const QGauss<dim - 1> element_face_quadrature;
+  DoFHandler<dim> dof_handler;
+  Vector<data_type> t;
+  Vector<data_type> t_prev;
+  SparsityPattern sparsity_pattern;
+  Vector<data_type> rhs;
+  SparseMatrix<data_type> matrix_t;
+  SparseMatrix<data_type> pristine_matrix_t;
+  SparseMatrix<data_type> laplace_mat;
+  SparseMatrix<data_type> mass_mat;
+  const data_type equation_0_c0 = 1 / time_step;
+  const data_type equation_0_c1 = -kappa;
+  Fn_0 fn_0;
+  SparseMatrix<data_type> equation_0_mat0;
+
+  void solve_t();
+  void assemble_laplace_mat();
+  void assemble_mass_mat();
+};
+
+void Sim::solve_t() {
+  for (types::global_dof_index row = 0; row < matrix_t.m(); ++row) {
+    data_type lumped_mass = 0;
+    for (auto entry = mass_mat.begin(row); entry != mass_mat.end(row); ++entry)
+      lumped_mass += entry->value();
+
+    bool diagonal_only = true;
+    for (auto entry = matrix_t.begin(row); entry != matrix_t.end(row); ++entry)
+      if (entry->column() != row && entry->value() != 0)
+        diagonal_only = false;
+
+    const data_type diagonal = matrix_t.diag_element(row);
+    t(row) = rhs(row) / (diagonal_only ? diagonal : diagonal * lumped_mass / mass_mat.diag_element(row));
+  }
+
+  pcout << "    solve_t: lumped mass update." << std::endl;
+
+  constraints.distribute(t);
+}
+
+void Sim::assemble_laplace_mat() {
+  using Iterator = typename DoFHandler<dim>::active_cell_iterator;
+  using CopyData = MeshWorker::CopyData<1, 1, 1>;
+
+  const auto cell_worker = [&](const Iterator &cell,
+                               MeshWorker::ScratchData<dim> &scratch_data,
+                               CopyData &copy_data) {
+    const FEValues<dim> &fe_values = scratch_data.reinit(cell);
+    FullMatrix<data_type> &cell_matrix = copy_data.matrices[0];
+    cell_matrix = 0;
+    for (const unsigned int q : fe_values.quadrature_point_indices()) {
+      for (const unsigned int i : fe_values.dof_indices())
+        for (const unsigned int j : fe_values.dof_indices())
+          cell_matrix(i, j) += fe_values.shape_grad(i, q) *
+                               fe_values.shape_grad(j, q) * fe_values.JxW(q);
+    }
+    cell->get_dof_indices(copy_data.local_dof_indices[0]);
+  };
+  const auto copier = [&](const CopyData &copy_data) {
+    laplace_mat.add(copy_data.local_dof_indices[0], copy_data.matrices[0]);
+  };
+
+  laplace_mat = 0;
+  WorkStream::run(dof_handler.begin_active(), dof_handler.end(), cell_worker, copier,
+                  MeshWorker::ScratchData<dim>(element_mapping, element, element_quadrature,
+                                               update_gradients | update_JxW_values),
+                  CopyData(element.n_dofs_per_cell()));
+}
+
+void Sim::assemble_mass_mat() {
+  using Iterator = typename DoFHandler<dim>::active_cell_iterator;
+  using CopyData = MeshWorker::CopyData<1, 1, 1>;
+
+  const auto cell_worker = [&](const Iterator &cell,
+                               MeshWorker::ScratchData<dim> &scratch_data,
+                               CopyData &copy_data) {
+    const FEValues<dim> &fe_values = scratch_data.reinit(cell);
+    FullMatrix<data_type> &cell_matrix = copy_data.matrices[0];
+    cell_matrix = 0;
+    for (const unsigned int q : fe_values.quadrature_point_indices()) {
+      for (const unsigned int i : fe_values.dof_indices())
+        for (const unsigned int j : fe_values.dof_indices())
+          cell_matrix(i, j) += fe_values.shape_value(i, q) *
+                               fe_values.shape_value(j, q) * fe_values.JxW(q);
+    }
+    cell->get_dof_indices(copy_data.local_dof_indices[0]);
+  };
+  const auto copier = [&](const CopyData &copy_data) {
+    mass_mat.add(copy_data.local_dof_indices[0], copy_data.matrices[0]);
+  };
+
+  mass_mat = 0;
+  WorkStream::run(dof_handler.begin_active(), dof_handler.end(), cell_worker, copier,
+                  MeshWorker::ScratchData<dim>(element_mapping, element, element_quadrature,
+                                               update_values | update_JxW_values),
+                  CopyData(element.n_dofs_per_cell()));
+}
+
+void Sim::run() {
+  setup_mesh();
+  setup_system();
+  time = 0 + time_step;
+
+  // Prepare time stepping
+  // # Assemble the time invariant system of equation (1 / k)M^n.(T^n) = ((1 / k)M^n - kappa.A^n)(T^n-1)
+  // ## Compute system for T^n
+  // pristine_matrix_t = equation_0_c0 * M^n
+  pristine_matrix_t.copy_from(mass_mat); pristine_matrix_t *= equation_0_c0;
+  
+  // # Assemble the time invariant right-hand side operators of equation (1 / k)M^n.(T^n) = ((1 / k)M^n - kappa.A^n)(T^n-1)
+  // equation_0_mat0 = equation_0_c0.M^n + equation_0_c1.A^n
+  // equation_0_mat0 = equation_0_c0 * M^n
+  equation_0_mat0.copy_from(mass_mat); equation_0_mat0 *= equation_0_c0;
+  
+  // equation_0_mat0 += equation_0_c1.A^n
+  equation_0_mat0.add(equation_0_c1, laplace_mat);
+  
+  // Apply Intial Condition for t 
+  VectorTools::project(element_mapping, dof_handler, constraints, element_quadrature,
+                       fn_T0, t_prev);
+
+  // Run time stepping
+  for (; time <= 1; time += time_step, ++timestep_number) {
+    pcout << "Time step " << timestep_number << " at t=" << time << std::endl;
+
+    // # Setup equation (1 / k)M^n.(T^n) = ((1 / k)M^n - kappa.A^n)(T^n-1)
+    // ## Copy system for T^n, assembled once
+    matrix_t.copy_from(pristine_matrix_t);
+    
+    
+    // ## Compute rhs for T^n
+    // rhs = equation_0_mat0.(T^n-1)
+    equation_0_mat0.vmult(rhs, t_prev);
+    
+
+    // Apply boundary condition to the equation for solving t
+    {
+      fn_0.set_time(time);
+    
+      std::map<types::global_dof_index, double> boundary_values;
+      VectorTools::interpolate_boundary_values(
+          element_mapping, dof_handler, 0, fn_0, boundary_values);
+      MatrixTools::apply_boundary_values(boundary_values, matrix_t, t,
+                                         rhs);
+    }
+    
+
+    solve_t();
+    
+
+    output_results();
+    
+
+    // Swap new values with previous values for the next step
+    swap(t, t_prev);
+  }
+
+}
+
+void Sim::output_results() {
+  DataOut<dim> data_out;
+
+  data_out.attach_dof_handler(dof_handler);
+  data_out.add_data_vector(t, "t");
+  data_out.build_patches(element_mapping);
+
+  DataOutBase::VtkFlags vtk_flags;
+  vtk_flags.compression_level = DataOutBase::CompressionLevel::best_speed;
+  data_out.set_flags(vtk_flags);
+  const std::string filename =
+      "solution-" + Utilities::int_to_string(timestep_number, 3) + ".vtu";
+  std::ofstream output("./" + filename);
+  data_out.write_vtu(output);
+
+  // The files are indexed by time, for ParaView to open them as a series
+  times_and_names.emplace_back(time, filename);
+  std::ofstream pvd_output("./solution.pvd");
+  DataOutBase::write_pvd_record(pvd_output, times_and_names);
+}
+
+int main(int argc, char *argv[]) {
+  try {
+    #ifdef DEAL_II_WITH_KOKKOS
+    Kokkos::initialize(argc, argv);
+    #endif
+
+    Sim sim;
+    sim.run();
+
+    #ifdef DEAL_II_WITH_KOKKOS
+    Kokkos::finalize();
+    #endif
+    } catch (std::exception &exc) {
+    std::cerr << std::endl
+                << std::endl
+                << "----------------------------------------------------"
+                << std::endl;
+      std::cerr << "Exception on processing: " << std::endl
+                << exc.what() << std::endl
+                << "Aborting!" << std::endl
+                << "----------------------------------------------------"
+                << std::endl;
+
+      return 1;
+
+  } catch (...) {
+    std::cerr << std::endl
+                << std::endl
+                << "----------------------------------------------------"
+                << std::endl;
+      std::cerr << "Unknown exception!" << std::endl
+                << "Aborting!" << std::endl
+                << "----------------------------------------------------"
+                << std::endl;
+      return 1;
+  }
+
+  return 0;
+}
@@ -0,0 +1,356 @@
+// Time scheme: Crank-Nicolson (θ = 0.5)
+#include <iostream>
+#include <fstream>
+#include <cmath>
+#include <deal.II/lac/affine_constraints.h>
+#include <deal.II/numerics/data_out.h>
+#include <deal.II/base/conditional_ostream.h>
+#include <deal.II/base/function.h>
+#include <deal.II/distributed/tria.h>
+#include <deal.II/distributed/grid_refinement.h>
+#include <deal.II/grid/grid_generator.h>
+#include <deal.II/fe/fe_q.h>
+#include <deal.II/fe/mapping_q.h>
+#include <deal.II/base/quadrature_lib.h>
+#include <deal.II/dofs/dof_handler.h>
+#include <deal.II/dofs/dof_tools.h>
+#include <deal.II/lac/vector.h>
+#include <deal.II/lac/petsc_vector.h>
+#include <deal.II/lac/dynamic_sparsity_pattern.h>
+#include <deal.II/lac/sparsity_pattern.h>
+#include <deal.II/lac/sparsity_tools.h>
+#include <deal.II/lac/sparse_matrix.h>
+#include <deal.II/lac/petsc_sparse_matrix.h>
+#include <deal.II/lac/solver_cg.h>
+#include <deal.II/lac/precondition.h>
+#include <deal.II/lac/solver_control.h>
+#include <deal.II/numerics/matrix_creator.h>
+#include <deal.II/numerics/vector_tools_interpolate.h>
+#include <deal.II/numerics/vector_tools_boundary.h>
+#include <deal.II/numerics/matrix_tools.h>
+#include <deal.II/lac/generic_linear_algebra.h>
+#include <deal.II/base/mpi.h>
+#include <mpi.h>
+#ifdef DEAL_II_WITH_KOKKOS
+#include <Kokkos_Core.hpp>
+#endif
+
+const int dim = 1;
+using data_type = double;
+
+
+// Parameters
+const data_type t_cold = 2.73e2;const data_type t_hot = 3.73e2;const data_type thermal_diffusivity = 1e-1;
+
+using namespace dealii;
+using namespace dealii::numbers;
+
+// Parellel linear algebra library namespace (PETSc or Trilinos)
+namespace LA
+{
+  #if defined(DEAL_II_WITH_PETSC) && !defined(DEAL_II_PETSC_WITH_COMPLEX) && \
+    !(defined(DEAL_II_WITH_TRILINOS) && defined(FORCE_USE_OF_TRILINOS))
+    using namespace dealii::LinearAlgebraPETSc;
+  #  define USE_PETSC_LA
+  #elif defined(DEAL_II_WITH_TRILINOS)
+    using namespace dealii::LinearAlgebraTrilinos;
+  #else
+  #  error DEAL_II_WITH_PETSC or DEAL_II_WITH_TRILINOS required
+  #endif
+} // namespace LA
+
+static constexpr data_type pi = PI;
+static constexpr data_type e = E;
+
+
+bool float_equals(data_type a, data_type b) {
+  const data_type base_epsilon = 1e-8;
+  const data_type epsilon = base_epsilon * std::max(1.0, std::max(std::abs(a), std::abs(b)));
+  return std::fabs(b - a) < epsilon;
+}
+
+Point<dim> make_point(data_type x, data_type y, data_type z) {
+  const data_type coordinates[] = {x, y, z};
+  Point<dim> point;
+  for (int d = 0; d < dim; ++d)
+    point[d] = coordinates[d];
+  return point;
+}
+
+
+class Fn_bc : public Function<dim> {
+public:
+  virtual double value(const Point<dim> &point,
+                       const unsigned int component = 0) const override {
+    if (point[0] >= 0 && point[0] <= 0.1) {
+      return t_hot;
+    }
+    else if (point[0] >= 0.9 && point[0] <= 1) {
+      return t_cold;
+    }
+    else {
+      return 293;
+    }
+  }
+};
+
+
+class Fn_293 : public Function<dim> {
+public:
+  virtual double value(const Point<dim> &point,
+                       const unsigned int component = 0) const override {
+    return 293;
+  }
+};
+
+
+class Sim {
+public:
+  Sim()
+    : mpi_rank(Utilities::MPI::this_mpi_process(MPI_COMM_WORLD))
+    , n_mpi_processes(Utilities::MPI::n_mpi_processes(MPI_COMM_WORLD))
+    , pcout(std::cout, mpi_rank == 0)
+    , mesh(MPI_COMM_WORLD,
+        typename Triangulation<dim>::MeshSmoothing(
+            Triangulation<dim>::smoothing_on_refinement |
+            Triangulation<dim>::smoothing_on_coarsening)), element(1), element_mapping(1), element_quadrature(element.degree + 1), element_face_quadrature(element.degree + 1), dof_handler(mesh)
+  {}
+
+  void run();
+  void output_results();
+
+  void setup_mesh() {
+    GridGenerator::hyper_cube(mesh, 0, 1);
+    mesh.refine_global(5);
+    pcout << "Cell size: " << 0.03125 << " m" << std::endl;
+  }
+
+  void setup_system() {
+    constraints.clear();
+    dof_handler.distribute_dofs(element);
+    pcout << "Number of degrees of freedom: " << dof_handler.n_dofs() << "\n" << std::endl;
+    dof_handler_owned_dofs = dof_handler.locally_owned_dofs();
+    dof_handler_relevant_dofs = DoFTools::extract_locally_relevant_dofs(dof_handler);
+    constraints.clear();
+    constraints.reinit(dof_handler_owned_dofs, dof_handler_relevant_dofs);
+    DoFTools::make_hanging_node_constraints(dof_handler, constraints);
+    t.reinit(dof_handler_owned_dofs, MPI_COMM_WORLD);
+    t_prev.reinit(dof_handler_owned_dofs, MPI_COMM_WORLD);
+    constraints.close();
+    
+    // Sparsity Pattern
+    DynamicSparsityPattern dof_handler_dsp(dof_handler_relevant_dofs);
+    DoFTools::make_sparsity_pattern(dof_handler, dof_handler_dsp, constraints, false);
+    SparsityTools::distribute_sparsity_pattern(dof_handler_dsp, dof_handler_owned_dofs, MPI_COMM_WORLD, dof_handler_relevant_dofs);
+    rhs.reinit(dof_handler_owned_dofs, MPI_COMM_WORLD);
+    matrix_t.reinit(dof_handler_owned_dofs, dof_handler_owned_dofs, dof_handler_dsp, MPI_COMM_WORLD);
+    pristine_matrix_t.reinit(dof_handler_owned_dofs, dof_handler_owned_dofs, dof_handler_dsp, MPI_COMM_WORLD);
+    laplace_mat.reinit(dof_handler_owned_dofs, dof_handler_owned_dofs, dof_handler_dsp, MPI_COMM_WORLD);
+    MatrixCreator::create_laplace_matrix(element_mapping, dof_handler, element_quadrature, laplace_mat);
+    mass_mat.reinit(dof_handler_owned_dofs, dof_handler_owned_dofs, dof_handler_dsp, MPI_COMM_WORLD);
+    MatrixCreator::create_mass_matrix(element_mapping, dof_handler, element_quadrature, mass_mat);
+    t_ghosted.reinit(dof_handler_owned_dofs, dof_handler_relevant_dofs, MPI_COMM_WORLD);
+    equation_0_mat0.reinit(dof_handler_owned_dofs, dof_handler_owned_dofs, dof_handler_dsp, MPI_COMM_WORLD);
+    constraints.close();
+  }
+
+  AffineConstraints<data_type> constraints;
+  data_type time;
+  unsigned long timestep_number = 1;
+  const data_type time_step = 0.01;
+  const unsigned int mpi_rank = 0;
+  const unsigned int n_mpi_processes = 1;
+  ConditionalOStream pcout;
+  std::vector<std::pair<double, std::string>> times_and_names;
+
+  Fn_bc fn_T_hot_cold_bc;
+  parallel::distributed::Triangulation<dim> mesh;
+  const FE_Q<dim> element;
+  const MappingQ<dim> element_mapping;
+  const QGauss<dim> element_quadrature;
+  const QGauss<dim - 1> element_face_quadrature;
+  DoFHandler<dim> dof_handler;
+  IndexSet dof_handler_owned_dofs;
+  IndexSet dof_handler_relevant_dofs;
+  LA::MPI::Vector t;
+  LA::MPI::Vector t_prev;
+  LA::MPI::Vector rhs;
+  LA::MPI::SparseMatrix matrix_t;
+  LA::MPI::SparseMatrix pristine_matrix_t;
+  LA::MPI::PreconditionAMG solve_t_preconditioner;
+  bool solve_t_initialized = false;
+  LA::MPI::SparseMatrix laplace_mat;
+  LA::MPI::SparseMatrix mass_mat;
+  const data_type equation_0_c0 = 1 / time_step;
+  const data_type equation_0_c1 = (1./2.) * thermal_diffusivity;
+  const data_type equation_0_c2 = (-1./2.) * thermal_diffusivity;
+  Fn_293 fn_293;
+  LA::MPI::Vector t_ghosted;
+  LA::MPI::SparseMatrix equation_0_mat0;
+
+  void solve_t();
+};
+
+void Sim::solve_t() {
+  SolverControl solver_control(dof_handler.n_dofs(), 1e-6 * rhs.l2_norm());
+  LA::SolverCG solver(solver_control);
+
+  // The matrix is the same at every step, the preconditioner is only initialized once
+  if (!solve_t_initialized) {
+    // Preconditioner configuration
+    LA::MPI::PreconditionAMG::AdditionalData data;
+#ifdef USE_PETSC_LA
+    data.symmetric_operator = true;
+#else
+    /* Trilinos defaults are good */
+#endif
+
+    solve_t_preconditioner.initialize(matrix_t, data);
+    solve_t_initialized = true;
+  }
+
+  solver.solve(matrix_t, t, rhs, solve_t_preconditioner);
+
+  pcout << "    solve_t: " << solver_control.last_step()
+            << "  CG iterations." << std::endl;
+
+  constraints.distribute(t);
+}
+
+void Sim::run() {
+  pcout << "Running with "
+#ifdef USE_PETSC_LA
+  << "PETSc"
+#else
+  << "Trilinos"
+#endif
+  << " on " << Utilities::MPI::n_mpi_processes(MPI_COMM_WORLD)
+  << " MPI rank(s)..." << std::endl;
+
+  setup_mesh();
+  setup_system();
+  time = 0 + time_step;
+
+  // Prepare time stepping
+  // # Assemble the time invariant system of equation ((1 / k)M^n + (1/2)thermal_diffusivity.A^n)(T^n) = ((1 / k)M^n + (-1/2)thermal_diffusivity.A^n)(T^n-1)
+  // ## Compute system for T^n
+  // pristine_matrix_t = equation_0_c0.M^n + equation_0_c1.A^n
+  // pristine_matrix_t = equation_0_c0 * M^n
+  pristine_matrix_t.copy_from(mass_mat); pristine_matrix_t *= equation_0_c0;
+  
+  // pristine_matrix_t += equation_0_c1.A^n
+  pristine_matrix_t.add(equation_0_c1, laplace_mat);
+  
+  pristine_matrix_t.compress(VectorOperation::add);
+  
+  // # Assemble the time invariant right-hand side operators of equation ((1 / k)M^n + (1/2)thermal_diffusivity.A^n)(T^n) = ((1 / k)M^n + (-1/2)thermal_diffusivity.A^n)(T^n-1)
+  // equation_0_mat0 = equation_0_c0.M^n + equation_0_c2.A^n
+  // equation_0_mat0 = equation_0_c0 * M^n
+  equation_0_mat0.copy_from(mass_mat); equation_0_mat0 *= equation_0_c0;
+  
+  // equation_0_mat0 += equation_0_c2.A^n
+  equation_0_mat0.add(equation_0_c2, laplace_mat);
+  
+  equation_0_mat0.compress(VectorOperation::add);
+  
+  // Apply Intial Condition for t
+  VectorTools::interpolate(element_mapping, dof_handler, fn_293, t_prev);
+  constraints.distribute(t_prev);
+
+  // Run time stepping
+  for (; time <= 10; time += time_step, ++timestep_number) {
+    pcout << "Time step " << timestep_number << " at t=" << time << std::endl;
+
+    // # Setup equation ((1 / k)M^n + (1/2)thermal_diffusivity.A^n)(T^n) = ((1 / k)M^n + (-1/2)thermal_diffusivity.A^n)(T^n-1)
+    // ## Copy system for T^n, assembled once
+    matrix_t.copy_from(pristine_matrix_t);
+    
+    
+    // ## Compute rhs for T^n
+    // rhs = equation_0_mat0.(T^n-1)
+    equation_0_mat0.vmult(rhs, t_prev);
+    
+    rhs.compress(VectorOperation::add);
+    
+    
+
+    // Apply boundary condition to the equation for solving t
+    {
+      fn_T_hot_cold_bc.set_time(time);
+    
+      std::map<types::global_dof_index, double> boundary_values;
+      VectorTools::interpolate_boundary_values(
+          element_mapping, dof_handler, 0, fn_T_hot_cold_bc, boundary_values);
+      MatrixTools::apply_boundary_values(boundary_values, matrix_t, t,
+                                         rhs);
+    }
+    
+
+    solve_t();
+    
+
+    output_results();
+    
+
+    // Swap new values with previous values for the next step
+    swap(t, t_prev);
+  }
+
+}
+
+void Sim::output_results() {
+  DataOut<dim> data_out;
+
+  data_out.attach_dof_handler(dof_handler);
+  t_ghosted = t;
+  data_out.add_data_vector(t_ghosted, "t");
+  data_out.build_patches(element_mapping);
+
+  DataOutBase::VtkFlags vtk_flags;
+  vtk_flags.compression_level = DataOutBase::CompressionLevel::best_speed;
+  data_out.set_flags(vtk_flags);
+  const std::string filename = data_out.write_vtu_with_pvtu_record(
+      "./", "solution", timestep_number, MPI_COMM_WORLD, 2, 8);
+
+  // The files are indexed by time, for ParaView to open them as a series
+  times_and_names.emplace_back(time, filename);
+  if (mpi_rank == 0) {
+    std::ofstream pvd_output("./solution.pvd");
+    DataOutBase::write_pvd_record(pvd_output, times_and_names);
+  }
+}
+
+int main(int argc, char *argv[]) {
+  try {
+    Utilities::MPI::MPI_InitFinalize mpi_initialization(argc, argv, numbers::invalid_unsigned_int);
+
+    Sim sim;
+    sim.run();
+
+    } catch (std::exception &exc) {
+    std::cerr << std::endl
+                << std::endl
+                << "----------------------------------------------------"
+                << std::endl;
+      std::cerr << "Exception on processing: " << std::endl
+                << exc.what() << std::endl
+                << "Aborting!" << std::endl
+                << "----------------------------------------------------"
+                << std::endl;
+
+      return 1;
+
+  } catch (...) {
+    std::cerr << std::endl
+                << std::endl
+                << "----------------------------------------------------"
+                << std::endl;
+      std::cerr << "Unknown exception!" << std::endl
+                << "Aborting!" << std::endl
+                << "----------------------------------------------------"
+                << std::endl;
+      return 1;
+  }
+
+  return 0;
+}
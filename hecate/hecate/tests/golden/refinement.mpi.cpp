@@ -0,0 +1,378 @@
+// Time scheme: Crank-Nicolson (θ = 0.5)
+#include <iostream>
+#include <fstream>
+#include <cmath>
+#include <deal.II/lac/affine_constraints.h>
+#include <deal.II/numerics/data_out.h>
+#include <deal.II/base/conditional_ostream.h>
+#include <deal.II/base/function.h>
+#include <deal.II/distributed/tria.h>
+#include <deal.II/distributed/grid_refinement.h>
+#include <deal.II/grid/grid_generator.h>
+#include <deal.II/fe/fe_q.h>
+#include <deal.II/fe/mapping_q.h>
+#include <deal.II/base/quadrature_lib.h>
+#include <deal.II/dofs/dof_handler.h>
+#include <deal.II/dofs/dof_tools.h>
+#include <deal.II/lac/vector.h>
+#include <deal.II/lac/petsc_vector.h>
+#include <deal.II/lac/dynamic_sparsity_pattern.h>
+#include <deal.II/lac/sparsity_pattern.h>
+#include <deal.II/lac/sparsity_tools.h>
+#include <deal.II/lac/sparse_matrix.h>
+#include <deal.II/lac/petsc_sparse_matrix.h>
+#include <deal.II/lac/solver_cg.h>
+#include <deal.II/lac/precondition.h>
+#include <deal.II/lac/solver_control.h>
+#include <deal.II/numerics/matrix_creator.h>
+#include <deal.II/numerics/vector_tools_interpolate.h>
+#include <deal.II/numerics/vector_tools_boundary.h>
+#include <deal.II/numerics/matrix_tools.h>
+#include <deal.II/numerics/error_estimator.h>
+#include <deal.II/distributed/solution_transfer.h>
+#include <deal.II/lac/generic_linear_algebra.h>
+#include <deal.II/base/mpi.h>
+#include <mpi.h>
+#ifdef DEAL_II_WITH_KOKKOS
+#include <Kokkos_Core.hpp>
+#endif
+
+const int dim = 2;
+using data_type = double;
+
+
+// Parameters
+const data_type kappa = 1e-2;
+
+using namespace dealii;
+using namespace dealii::numbers;
+
+// Parellel linear algebra library namespace (PETSc or Trilinos)
+namespace LA
+{
+  #if defined(DEAL_II_WITH_PETSC) && !defined(DEAL_II_PETSC_WITH_COMPLEX) && \
+    !(defined(DEAL_II_WITH_TRILINOS) && defined(FORCE_USE_OF_TRILINOS))
+    using namespace dealii::LinearAlgebraPETSc;
+  #  define USE_PETSC_LA
+  #elif defined(DEAL_II_WITH_TRILINOS)
+    using namespace dealii::LinearAlgebraTrilinos;
+  #else
+  #  error DEAL_II_WITH_PETSC or DEAL_II_WITH_TRILINOS required
+  #endif
+} // namespace LA
+
+static constexpr data_type pi = PI;
+static constexpr data_type e = E;
+
+
+bool float_equals(data_type a, data_type b) {
+  const data_type base_epsilon = 1e-8;
+  const data_type epsilon = base_epsilon * std::max(1.0, std::max(std::abs(a), std::abs(b)));
+  return std::fabs(b - a) < epsilon;
+}
+
+Point<dim> make_point(data_type x, data_type y, data_type z) {
+  const data_type coordinates[] = {x, y, z};
+  Point<dim> point;
+  for (int d = 0; d < dim; ++d)
+    point[d] = coordinates[d];
+  return point;
+}
+
+
+class Fn_T0 : public Function<dim> {
+public:
+  virtual double value(const Point<dim> &point,
+                       const unsigned int component = 0) const override {
+    return std::exp(-100 * ((point[0] + -1./2.) * (point[0] + -1./2.) + (point[1] + -1./2.) * (point[1] + -1./2.)));
+  }
+};
+
+
+class Fn_0 : public Function<dim> {
+public:
+  virtual double value(const Point<dim> &point,
+                       const unsigned int component = 0) const override {
+    return 0;
+  }
+};
+
+
+class Sim {
+public:
+  Sim()
+    : mpi_rank(Utilities::MPI::this_mpi_process(MPI_COMM_WORLD))
+    , n_mpi_processes(Utilities::MPI::n_mpi_processes(MPI_COMM_WORLD))
+    , pcout(std::cout, mpi_rank == 0)
+    , mesh(MPI_COMM_WORLD,
+        typename Triangulation<dim>::MeshSmoothing(
+            Triangulation<dim>::smoothing_on_refinement |
+            Triangulation<dim>::smoothing_on_coarsening)), element(1), element_mapping(1), element_quadrature(element.degree + 1), element_face_quadrature(element.degree + 1), dof_handler(mesh)
+  {}
+
+  void run();
+  void output_results();
+
+  void setup_mesh() {
+    GridGenerator::hyper_cube(mesh, 0, 1);
+    mesh.refine_global(3);
+    pcout << "Cell size: " << 0.125 << " m" << std::endl;
+  }
+
+  void setup_system() {
+    constraints.clear();
+    dof_handler.distribute_dofs(element);
+    pcout << "Number of degrees of freedom: " << dof_handler.n_dofs() << "\n" << std::endl;
+    dof_handler_owned_dofs = dof_handler.locally_owned_dofs();
+    dof_handler_relevant_dofs = DoFTools::extract_locally_relevant_dofs(dof_handler);
+    constraints.clear();
+    constraints.reinit(dof_handler_owned_dofs, dof_handler_relevant_dofs);
+    DoFTools::make_hanging_node_constraints(dof_handler, constraints);
+    t.reinit(dof_handler_owned_dofs, MPI_COMM_WORLD);
+    t_prev.reinit(dof_handler_owned_dofs, MPI_COMM_WORLD);
+    constraints.close();
+    
+    // Sparsity Pattern
+    DynamicSparsityPattern dof_handler_dsp(dof_handler_relevant_dofs);
+    DoFTools::make_sparsity_pattern(dof_handler, dof_handler_dsp, constraints, false);
+    SparsityTools::distribute_sparsity_pattern(dof_handler_dsp, dof_handler_owned_dofs, MPI_COMM_WORLD, dof_handler_relevant_dofs);
+    rhs.reinit(dof_handler_owned_dofs, MPI_COMM_WORLD);
+    matrix_t.reinit(dof_handler_owned_dofs, dof_handler_owned_dofs, dof_handler_dsp, MPI_COMM_WORLD);
+    laplace_mat.reinit(dof_handler_owned_dofs, dof_handler_owned_dofs, dof_handler_dsp, MPI_COMM_WORLD);
+    MatrixCreator::create_laplace_matrix(element_mapping, dof_handler, element_quadrature, laplace_mat, nullptr, constraints);
+    mass_mat.reinit(dof_handler_owned_dofs, dof_handler_owned_dofs, dof_handler_dsp, MPI_COMM_WORLD);
+    MatrixCreator::create_mass_matrix(element_mapping, dof_handler, element_quadrature, mass_mat, nullptr, constraints);
+    t_ghosted.reinit(dof_handler_owned_dofs, dof_handler_relevant_dofs, MPI_COMM_WORLD);
+    mtmp.reinit(dof_handler_owned_dofs, dof_handler_owned_dofs, dof_handler_dsp, MPI_COMM_WORLD);
+    constraints.close();
+  }
+
+  AffineConstraints<data_type> constraints;
+  data_type time;
+  unsigned long timestep_number = 1;
+  const data_type time_step = 0.01;
+  const unsigned int mpi_rank = 0;
+  const unsigned int n_mpi_processes = 1;
+  ConditionalOStream pcout;
+  std::vector<std::pair<double, std::string>> times_and_names;
+
+  Fn_T0 fn_T0;
+  parallel::distributed::Triangulation<dim> mesh;
+  const FE_Q<dim> element;
+  const MappingQ<dim> element_mapping;
+  const QGauss<dim> element_quadrature;
+  const QGauss<dim - 1> element_face_quadrature;
+  DoFHandler<dim> dof_handler;
+  IndexSet dof_handler_owned_dofs;
+  IndexSet dof_handler_relevant_dofs;
+  LA::MPI::Vector t;
+  LA::MPI::Vector t_prev;
+  LA::MPI::Vector rhs;
+  LA::MPI::SparseMatrix matrix_t;
+  LA::MPI::SparseMatrix laplace_mat;
+  LA::MPI::SparseMatrix mass_mat;
+  const data_type equation_0_c0 = 1 / time_step;
+  const data_type equation_0_c1 = (1./2.) * kappa;
+  const data_type equation_0_c2 = (-1./2.) * kappa;
+  Fn_0 fn_0;
+  LA::MPI::Vector t_ghosted;
+  LA::MPI::SparseMatrix mtmp;
+
+  void solve_t();
+  void refine_mesh();
+};
+
+void Sim::solve_t() {
+  SolverControl solver_control(dof_handler.n_dofs(), 1e-6 * rhs.l2_norm());
+  LA::SolverCG solver(solver_control);
+
+  // Preconditioner configuration
+  LA::MPI::PreconditionAMG::AdditionalData data;
+#ifdef USE_PETSC_LA
+  data.symmetric_operator = true;
+#else
+  /* Trilinos defaults are good */
+#endif
+
+  // Preconditioner initialization
+  LA::MPI::PreconditionAMG precondtioner;
+  precondtioner.initialize(matrix_t, data);
+
+  solver.solve(matrix_t, t, rhs, precondtioner);
+
+  pcout << "    solve_t: " << solver_control.last_step()
+            << "  CG iterations." << std::endl;
+
+  constraints.distribute(t);
+}
+
+void Sim::refine_mesh() {
+  // The estimator and the transfer read the values of the ghost dofs
+  std::vector<LA::MPI::Vector> previous(
+      1, LA::MPI::Vector(dof_handler_owned_dofs, dof_handler_relevant_dofs, MPI_COMM_WORLD));
+  previous[0] = t_prev;
+
+  Vector<float> estimated_error_per_cell(mesh.n_active_cells());
+  KellyErrorEstimator<dim>::estimate(
+      element_mapping, dof_handler, element_face_quadrature,
+      std::map<types::boundary_id, const Function<dim> *>(),
+      previous[0], estimated_error_per_cell);
+  parallel::distributed::GridRefinement::refine_and_coarsen_fixed_fraction(
+      mesh, estimated_error_per_cell, 0.3, 0.03);
+  if (mesh.n_levels() > 5)
+    for (const auto &cell : mesh.active_cell_iterators_on_level(5))
+      cell->clear_refine_flag();
+  if (mesh.n_levels() > 1)
+    for (const auto &cell : mesh.active_cell_iterators_on_level(1))
+      cell->clear_coarsen_flag();
+
+  // Carry the previous values over to the refined mesh
+  parallel::distributed::SolutionTransfer<dim, LA::MPI::Vector> solution_transfer(dof_handler);
+  mesh.prepare_coarsening_and_refinement();
+  solution_transfer.prepare_for_coarsening_and_refinement(
+      std::vector<const LA::MPI::Vector *>{&previous[0]});
+  mesh.execute_coarsening_and_refinement();
+
+  setup_system();
+  std::vector<LA::MPI::Vector *> transferred = {&t_prev};
+  solution_transfer.interpolate(transferred);
+  constraints.distribute(t_prev);
+
+  pcout << "Refined mesh: " << mesh.n_global_active_cells() << " active cells" << std::endl;
+}
+
+void Sim::run() {
+  pcout << "Running with "
+#ifdef USE_PETSC_LA
+  << "PETSc"
+#else
+  << "Trilinos"
+#endif
+  << " on " << Utilities::MPI::n_mpi_processes(MPI_COMM_WORLD)
+  << " MPI rank(s)..." << std::endl;
+
+  setup_mesh();
+  setup_system();
+  time = 0 + time_step;
+
+  // Prepare time stepping
+  // Apply Intial Condition for t
+  VectorTools::interpolate(element_mapping, dof_handler, fn_T0, t_prev);
+  constraints.distribute(t_prev);
+
+  // Run time stepping
+  for (; time <= 1; time += time_step, ++timestep_number) {
+    pcout << "Time step " << timestep_number << " at t=" << time << std::endl;
+
+    // # Setup equation ((1 / k)M^n + (1/2)kappa.A^n)(T^n) = ((1 / k)M^n + (-1/2)kappa.A^n)(T^n-1)
+    // ## Compute system for T^n
+    // matrix_t = equation_0_c0.M^n + equation_0_c1.A^n
+    // matrix_t = equation_0_c0 * M^n
+    matrix_t.copy_from(mass_mat); matrix_t *= equation_0_c0;
+    
+    // matrix_t += equation_0_c1.A^n
+    matrix_t.add(equation_0_c1, laplace_mat);
+    
+    matrix_t.compress(VectorOperation::add);
+    
+    
+    // ## Compute rhs for T^n
+    // mtmp = equation_0_c0.M^n + equation_0_c2.A^n
+    // mtmp = equation_0_c0 * M^n
+    mtmp.copy_from(mass_mat); mtmp *= equation_0_c0;
+    
+    // mtmp += equation_0_c2.A^n
+    mtmp.add(equation_0_c2, laplace_mat);
+    
+    // rhs = mtmp * T^n-1
+    mtmp.vmult(rhs, t_prev);
+    
+    rhs.compress(VectorOperation::add);
+    
+    
+
+    // Apply boundary condition to the equation for solving t
+    {
+      fn_0.set_time(time);
+    
+      std::map<types::global_dof_index, double> boundary_values;
+      VectorTools::interpolate_boundary_values(
+          element_mapping, dof_handler, 0, fn_0, boundary_values);
+      MatrixTools::apply_boundary_values(boundary_values, matrix_t, t,
+                                         rhs);
+    }
+    
+
+    solve_t();
+    
+
+    output_results();
+    
+
+    // Swap new values with previous values for the next step
+    swap(t, t_prev);
+    
+
+    // Adapt the mesh to the estimated error every 5 steps
+    if (timestep_number % 5 == 0)
+      refine_mesh();
+  }
+
+}
+
+void Sim::output_results() {
+  DataOut<dim> data_out;
+
+  data_out.attach_dof_handler(dof_handler);
+  t_ghosted = t;
+  data_out.add_data_vector(t_ghosted, "t");
+  data_out.build_patches(element_mapping);
+
+  DataOutBase::VtkFlags vtk_flags;
+  vtk_flags.compression_level = DataOutBase::CompressionLevel::best_speed;
+  data_out.set_flags(vtk_flags);
+  const std::string filename = data_out.write_vtu_with_pvtu_record(
+      "./", "solution", timestep_number, MPI_COMM_WORLD, 2, 8);
+
+  // The files are indexed by time, for ParaView to open them as a series
+  times_and_names.emplace_back(time, filename);
+  if (mpi_rank == 0) {
+    std::ofstream pvd_output("./solution.pvd");
+    DataOutBase::write_pvd_record(pvd_output, times_and_names);
+  }
+}
+
+int main(int argc, char *argv[]) {
+  try {
+    Utilities::MPI::MPI_InitFinalize mpi_initialization(argc, argv, numbers::invalid_unsigned_int);
+
+    Sim sim;
+    sim.run();
+
+    } catch (std::exception &exc) {
+    std::cerr << std::endl
+                << std::endl
+                << "----------------------------------------------------"
+                << std::endl;
+      std::cerr << "Exception on processing: " << std::endl
+                << exc.what() << std::endl
+                << "Aborting!" << std::endl
+                << "----------------------------------------------------"
+                << std::endl;
+
+      return 1;
+
+  } catch (...) {
+    std::cerr << std::endl
+                << std::endl
+                << "----------------------------------------------------"
+                << std::endl;
+      std::cerr << "Unknown exception!" << std::endl
+                << "Aborting!" << std::endl
+                << "----------------------------------------------------"
+                << std::endl;
+      return 1;
+  }
+
+  return 0;
+}
@@ -67,6 +67,14 @@ struct JobRequest {
     job_id: i64,
 }
 
+#[derive(JsonSchema, Deserialize)]
+struct MonitorRequest {
+    job_id: i64,
+
+    /// Name of the monitor in the input schema of the job
+    monitor: String,
+}
+
 /// A request to evaluate a mathematical expression
 /// Variables can be supplied to substitute the symbols with values
 #[derive(JsonSchema, Deserialize)]
@@ -283,6 +291,34 @@ impl HecateSimulator {
         ))]))
     }
 
+    #[tool(
+        description = "Restart an interrupted simulation job, for example a job killed on a best-effort queue, from the latest checkpoint it saved. The input schema of the job needs a checkpoint section."
+    )]
+    async fn restart_job(
+        &self,
+        Parameters(JobRequest { job_id }): Parameters<JobRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let job = Job::find_by_id(job_id)
+            .one(&self.db)
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?
+            .ok_or_else(|| McpError::resource_not_found("Job not found", None))?;
+        // Checked before spawning for the client to know why a job cannot be restarted
+        let checkpoint = job.latest_checkpoint().await?;
+        let db = self.db.clone();
+
+        tokio::spawn(async move {
+            match job.restart(&db, &checkpoint).await {
+                Ok(_) => info!("Successfully restarted job {job_id}"),
+                Err(e) => error!("Failed to restart job: {e}"),
+            }
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "restarting job {job_id}"
+        ))]))
+    }
+
     #[tool(description = "List unfinished simulation jobs ids and status.")]
     async fn list_unfinished_jobs(&self) -> Result<CallToolResult, McpError> {
         #[derive(DerivePartialModel, FromQueryResult, Serialize)]
@@ -358,6 +394,25 @@ impl HecateSimulator {
 
         Ok(CallToolResult::success(vec![Content::text(job.to_json())]))
     }
+
+    #[tool(
+        description = "Read the records written so far by a monitor of a simulation job, as columns and rows of values"
+    )]
+    pub async fn read_monitor(
+        &self,
+        Parameters(MonitorRequest { job_id, monitor }): Parameters<MonitorRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let job = Job::find_by_id(job_id)
+            .one(&self.db)
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?
+            .ok_or_else(|| McpError::resource_not_found("Job not found", None))?;
+        let series = job.read_monitor(&monitor).await?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            series.to_json(),
+        )]))
+    }
 }
 
 #[tool_handler]
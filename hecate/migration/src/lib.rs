@@ -9,6 +9,7 @@ mod m20250624_093459_add_code_filename;
 mod m20250624_094955_add_remote_job_id;
 mod m20250624_123858_add_compiler;
 mod m20250624_135928_add_walltime;
+mod m20250702_101500_create_file_table;
 
 pub struct Migrator;
 
@@ -25,6 +26,7 @@ impl MigratorTrait for Migrator {
             Box::new(m20250624_094955_add_remote_job_id::Migration),
             Box::new(m20250624_123858_add_compiler::Migration),
             Box::new(m20250624_135928_add_walltime::Migration),
+            Box::new(m20250702_101500_create_file_table::Migration),
         ]
     }
 }
@@ -0,0 +1,42 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(File::Table)
+                    .if_not_exists()
+                    .col(pk_auto(File::Id))
+                    .col(string(File::Name))
+                    .col(string(File::Host))
+                    .col(string(File::Path))
+                    .col(string(File::AddedAt))
+                    .col(string(File::Type))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(File::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum File {
+    Table,
+    Id,
+    Name,
+    Host,
+    Path,
+    AddedAt,
+    #[sea_orm(iden = "file_type")]
+    Type,
+}
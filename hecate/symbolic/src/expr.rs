@@ -36,6 +36,9 @@ pub use integer::*;
 pub mod rational;
 pub use rational::*;
 
+pub mod matrix;
+pub use matrix::*;
+
 pub mod ops;
 // pub use ops::*;
 
@@ -191,6 +194,16 @@ impl Arg for Box<dyn Expr> {
     }
 }
 
+impl Arg for bool {
+    fn srepr(&self) -> String {
+        self.to_string()
+    }
+
+    fn clone_arg(&self) -> Box<dyn Arg> {
+        Box::new(*self)
+    }
+}
+
 impl Arg for usize {
     fn srepr(&self) -> String {
         self.to_string()
@@ -374,8 +387,11 @@ pub trait Expr: Arg + Sync + Send {
         None
     }
 
+    /// Prints the expression as C++ for the deal.II code generator,
+    /// a shorthand for [`printer::DEAL_II_PRINTER`](crate::printer::DEAL_II_PRINTER).
     fn to_cpp(&self) -> String {
-        self.str()
+        use crate::printer::Printer;
+        crate::printer::DEAL_II_PRINTER.print(self.get_ref())
     }
 
     fn simplify(&self) -> Box<dyn Expr> {
@@ -444,10 +460,13 @@ pub trait Expr: Arg + Sync + Send {
         if self.equals(expr) {
             true
         } else {
-            self.args()
-                .iter()
-                .filter_map(|a| a.as_expr())
-                .any(|e| e.has(expr))
+            self.args().iter().any(|a| {
+                if let Some(exprs) = a.as_any().downcast_ref::<Vec<Box<dyn Expr>>>() {
+                    exprs.iter().any(|e| e.has(expr))
+                } else {
+                    a.as_expr().is_some_and(|e| e.has(expr))
+                }
+            })
         }
     }
 
@@ -499,6 +518,12 @@ pub trait Expr: Arg + Sync + Send {
         false
     }
 
+    /// Whether the expression commutes with other expressions in products.
+    /// Matrices, and expressions containing them, do not.
+    fn is_commutative(&self) -> bool {
+        true
+    }
+
     fn known_expr(&self) -> KnownExpr {
         KnownExpr::Unknown
     }
@@ -520,9 +545,11 @@ pub trait Expr: Arg + Sync + Send {
                 }
                 let coeff_box = (pow_coeff).pow(&(pow.exponent()).clone_box());
 
+                // The rest of the base keeps the exponent
+                let rest = || pow_expr.pow(&pow.exponent().clone_box());
                 match coeff_box.known_expr() {
-                    KnownExpr::Integer(i) => return (i.into(), pow_expr),
-                    KnownExpr::Rational(r) => return (*r, pow_expr),
+                    KnownExpr::Integer(i) => return (i.into(), rest()),
+                    KnownExpr::Rational(r) => return (*r, rest()),
                     KnownExpr::Pow(Pow {
                         base: coeff_base,
                         exponent: _,
@@ -733,6 +760,18 @@ mod tests {
         assert_eq!(expr.get_coeff(), (Rational::new(5, 7), x.clone_box()));
     }
 
+    #[test]
+    fn test_get_coeff_inverse_product() {
+        let x = &Symbol::new("x");
+        let expr = Pow::new_box(Integer::new_box(2) * x, Integer::new_box(-1));
+
+        assert_eq!(expr.get_coeff(), (Rational::new(1, 2), x.ipow(-1)));
+        assert_eq!(
+            (x.get_ref() * expr.get_ref()).simplify(),
+            Rational::new(1, 2).clone_box()
+        );
+    }
+
     #[test]
     fn test_check_hashing_works() {
         let mut set = HashSet::with_capacity(2);
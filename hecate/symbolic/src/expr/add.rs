@@ -78,6 +78,10 @@ impl Expr for Add {
         Box::new(self.clone())
     }
 
+    fn is_commutative(&self) -> bool {
+        self.operands.iter().all(|op| op.is_commutative())
+    }
+
     fn str(&self) -> String {
         let pieces: Vec<_> = self
             .operands
@@ -111,29 +115,6 @@ impl Expr for Add {
         format!("{}", pieces.join(""))
     }
 
-    // Same as str(&self) but calls to_cpp() on each operand
-    fn to_cpp(&self) -> String {
-        let pieces: Vec<_> = self
-            .operands
-            .iter()
-            .enumerate()
-            .map(|(i, op)| match KnownExpr::from_expr_box(op) {
-                KnownExpr::Mul(Mul { operands }) if operands.len() > 0 && i > 0 => {
-                    match KnownExpr::from_expr_box(&operands[0]) {
-                        KnownExpr::Integer(Integer { value: -1 }) => {
-                            let mul = op.to_cpp();
-                            format!(" - {}", mul[1..].to_string())
-                        }
-                        _ => format!(" + {}", op.to_cpp()),
-                    }
-                }
-                _ if i > 0 => format!(" + {}", op.to_cpp()),
-                _ => op.to_cpp(),
-            })
-            .collect();
-        format!("{}", pieces.join(""))
-    }
-
     fn simplify(&self) -> Box<dyn Expr> {
         if self.operands.len() == 2 {
             match (self.operands[0].known_expr(), self.operands[1].known_expr()) {
@@ -146,12 +127,25 @@ impl Expr for Add {
                 _ => (),
             }
         }
-        return self.from_args(
+        let simplified = self.from_args(
             self.args()
                 .iter()
                 .map(|a| a.map_expr(&|e| e.simplify()))
                 .collect(),
         );
+        // Terms vanishing once simplified, such as the weights of a time scheme, are dropped
+        match simplified.known_expr() {
+            KnownExpr::Add(Add { operands }) if operands.iter().any(|op| op.is_zero()) => {
+                Add::new_box_v2(
+                    operands
+                        .iter()
+                        .filter(|op| !op.is_zero())
+                        .cloned()
+                        .collect(),
+                )
+            }
+            _ => simplified,
+        }
         // For some reason the code below isn't equivalent
         // self.from_args(self.args_map_exprs(&|expr| match expr.known_expr() {
         //     _ => expr.simplify(),
@@ -1,7 +1,5 @@
 // use std::{collections::HashMap, sync::LazyLock};
 
-use itertools::Itertools;
-
 use super::*;
 
 #[derive(Clone, PartialEq, Eq, Hash)]
@@ -92,25 +90,6 @@ impl Expr for Func {
         )
     }
 
-    fn to_cpp(&self) -> String {
-        if !self.name.contains("^") && self.name.len() > 1 {
-            format!(
-                "std::{}({})",
-                self.name,
-                self.args
-                    .iter()
-                    .map(|x| x.to_cpp())
-                    .collect_vec()
-                    .join(", ")
-            )
-        } else {
-            self.name
-                .replace("^n-1", "_prev")
-                .replace("^n", "")
-                .to_lowercase()
-        }
-    }
-
     fn as_function(&self) -> Option<&Func> {
         Some(self)
     }
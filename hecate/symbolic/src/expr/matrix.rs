@@ -0,0 +1,619 @@
+use itertools::Itertools;
+use thiserror::Error;
+
+use super::*;
+
+/// # Matrix Properties
+/// Structural properties of a matrix, used to pick solvers and preconditioners.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct MatrixProperties {
+    pub symmetric: bool,
+    pub positive_definite: bool,
+}
+
+impl MatrixProperties {
+    pub const GENERAL: MatrixProperties = MatrixProperties {
+        symmetric: false,
+        positive_definite: false,
+    };
+    pub const SYMMETRIC: MatrixProperties = MatrixProperties {
+        symmetric: true,
+        positive_definite: false,
+    };
+    /// Symmetric positive definite
+    pub const SPD: MatrixProperties = MatrixProperties {
+        symmetric: true,
+        positive_definite: true,
+    };
+
+    pub fn is_spd(&self) -> bool {
+        self.symmetric && self.positive_definite
+    }
+
+    /// Properties that hold for both `self` and `other`.
+    pub fn intersection(self, other: Self) -> Self {
+        MatrixProperties {
+            symmetric: self.symmetric && other.symmetric,
+            positive_definite: self.positive_definite && other.positive_definite,
+        }
+    }
+}
+
+impl Arg for MatrixProperties {
+    fn srepr(&self) -> String {
+        match (self.symmetric, self.positive_definite) {
+            (true, true) => "SPD",
+            (true, false) => "Symmetric",
+            (false, true) => "PositiveDefinite",
+            (false, false) => "General",
+        }
+        .to_string()
+    }
+
+    fn clone_arg(&self) -> Box<dyn Arg> {
+        Box::new(*self)
+    }
+}
+
+/// # Shape
+/// The number of rows and columns of a matrix, possibly symbolic (ie. `n_dofs`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shape {
+    pub rows: Box<dyn Expr>,
+    pub cols: Box<dyn Expr>,
+}
+
+impl Shape {
+    pub fn new(rows: Box<dyn Expr>, cols: Box<dyn Expr>) -> Self {
+        Shape { rows, cols }
+    }
+
+    pub fn square(size: Box<dyn Expr>) -> Self {
+        Shape {
+            rows: size.clone_box(),
+            cols: size,
+        }
+    }
+
+    pub fn is_square(&self) -> bool {
+        self.rows == self.cols
+    }
+
+    pub fn transpose(&self) -> Self {
+        Shape {
+            rows: self.cols.clone_box(),
+            cols: self.rows.clone_box(),
+        }
+    }
+}
+
+impl fmt::Display for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}×{}", self.rows.str(), self.cols.str())
+    }
+}
+
+impl Arg for Shape {
+    fn srepr(&self) -> String {
+        format!("({}, {})", self.rows.srepr(), self.cols.srepr())
+    }
+
+    fn clone_arg(&self) -> Box<dyn Arg> {
+        Box::new(self.clone())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum MatrixError {
+    #[error("cannot multiply a {lhs} matrix by a {rhs} matrix")]
+    MulShapeMismatch { lhs: Shape, rhs: Shape },
+    #[error("cannot add a {lhs} matrix to a {rhs} matrix")]
+    AddShapeMismatch { lhs: Shape, rhs: Shape },
+    #[error("block ({row}, {col}) has shape {found}, expected {expected}")]
+    BlockShapeMismatch {
+        row: usize,
+        col: usize,
+        expected: String,
+        found: Shape,
+    },
+    #[error("block matrix rows have different lengths")]
+    RaggedBlocks,
+}
+
+/// # Matrix
+/// A named matrix or linear operator.
+///
+/// Unlike other expressions, matrices do not commute with each other:
+/// `A * B` and `B * A` are kept distinct.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Matrix {
+    pub name: String,
+    pub shape: Shape,
+    pub properties: MatrixProperties,
+    pub transposed: bool,
+}
+
+impl Matrix {
+    pub fn new(name: &str, shape: Shape) -> Self {
+        Matrix {
+            name: name.to_string(),
+            shape,
+            properties: MatrixProperties::GENERAL,
+            transposed: false,
+        }
+    }
+
+    /// A square matrix, typically acting on the degrees of freedom of a finite element space.
+    pub fn square(name: &str, size: &str) -> Self {
+        Self::new(name, Shape::square(Symbol::new_box(size)))
+    }
+
+    pub fn with_properties(mut self, properties: MatrixProperties) -> Self {
+        self.properties = properties;
+        self
+    }
+
+    pub fn is_symmetric(&self) -> bool {
+        self.properties.symmetric
+    }
+
+    pub fn transpose(&self) -> Self {
+        if self.is_symmetric() {
+            return self.clone();
+        }
+        Matrix {
+            name: self.name.clone(),
+            shape: self.shape.transpose(),
+            properties: self.properties,
+            transposed: !self.transposed,
+        }
+    }
+}
+
+impl Expr for Matrix {
+    fn known_expr(&self) -> KnownExpr<'_> {
+        KnownExpr::Matrix(self)
+    }
+
+    fn get_ref(&self) -> &dyn Expr {
+        self as &dyn Expr
+    }
+
+    fn for_each_arg(&self, f: &mut dyn FnMut(&dyn Arg)) {
+        f(&self.name);
+        f(&self.shape);
+        f(&self.properties);
+        f(&self.transposed);
+    }
+
+    fn from_args(&self, args: Vec<Box<dyn Arg>>) -> Box<dyn Expr> {
+        let downcast = |i: usize| args[i].as_any();
+        Box::new(Matrix {
+            name: downcast(0).downcast_ref::<String>().unwrap().clone(),
+            shape: downcast(1).downcast_ref::<Shape>().unwrap().clone(),
+            properties: *downcast(2).downcast_ref::<MatrixProperties>().unwrap(),
+            transposed: *downcast(3).downcast_ref::<bool>().unwrap(),
+        })
+    }
+
+    fn clone_box(&self) -> Box<dyn Expr> {
+        Box::new(self.clone())
+    }
+
+    fn is_commutative(&self) -> bool {
+        false
+    }
+
+    fn str(&self) -> String {
+        if self.transposed {
+            format!("{}ᵀ", self.name)
+        } else {
+            self.name.clone()
+        }
+    }
+}
+
+/// Sizes of the block rows or columns of a block matrix, `None` when unknown
+type BlockSizes = Vec<Option<Box<dyn Expr>>>;
+
+/// # Block Matrix
+/// A matrix made of blocks, each block being a matrix expression.
+/// Zero blocks can be given as `0`.
+#[derive(Debug, Clone)]
+pub struct BlockMatrix {
+    pub blocks: Vec<Vec<Box<dyn Expr>>>,
+}
+
+impl BlockMatrix {
+    /// Creates a block matrix, checking that blocks of the same block row (resp. column)
+    /// have the same number of rows (resp. columns).
+    pub fn new(blocks: Vec<Vec<Box<dyn Expr>>>) -> Result<Self, MatrixError> {
+        let n_cols = blocks.first().map(|row| row.len()).unwrap_or(0);
+        if blocks.iter().any(|row| row.len() != n_cols) {
+            return Err(MatrixError::RaggedBlocks);
+        }
+        let res = BlockMatrix { blocks };
+        res.block_shapes()?;
+        Ok(res)
+    }
+
+    pub fn n_block_rows(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn n_block_cols(&self) -> usize {
+        self.blocks.first().map(|row| row.len()).unwrap_or(0)
+    }
+
+    pub fn block(&self, row: usize, col: usize) -> &dyn Expr {
+        self.blocks[row][col].get_ref()
+    }
+
+    /// Rows of each block row and columns of each block column, when known.
+    fn block_shapes(&self) -> Result<(BlockSizes, BlockSizes), MatrixError> {
+        let mut rows: BlockSizes = vec![None; self.n_block_rows()];
+        let mut cols: BlockSizes = vec![None; self.n_block_cols()];
+        for (i, row) in self.blocks.iter().enumerate() {
+            for (j, block) in row.iter().enumerate() {
+                let Some(found) = shape(block.get_ref())? else {
+                    continue;
+                };
+                for (expected, size, dim) in [
+                    (&mut rows[i], &found.rows, "rows"),
+                    (&mut cols[j], &found.cols, "columns"),
+                ] {
+                    match expected {
+                        None => *expected = Some(size.clone_box()),
+                        Some(expected) if expected != size => {
+                            return Err(MatrixError::BlockShapeMismatch {
+                                row: i,
+                                col: j,
+                                expected: format!("{} {dim}", expected.str()),
+                                found: found.clone(),
+                            });
+                        }
+                        _ => (),
+                    }
+                }
+            }
+        }
+        Ok((rows, cols))
+    }
+
+    pub fn transpose(&self) -> Self {
+        BlockMatrix {
+            blocks: (0..self.n_block_cols())
+                .map(|j| {
+                    (0..self.n_block_rows())
+                        .map(|i| transpose(self.block(i, j)))
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+
+    /// Block wise product of two block matrices.
+    pub fn block_mul(&self, rhs: &BlockMatrix) -> Result<BlockMatrix, MatrixError> {
+        if self.n_block_cols() != rhs.n_block_rows() {
+            return Err(MatrixError::RaggedBlocks);
+        }
+        BlockMatrix::new(
+            (0..self.n_block_rows())
+                .map(|i| {
+                    (0..rhs.n_block_cols())
+                        .map(|j| {
+                            (0..self.n_block_cols())
+                                .map(|k| self.block(i, k) * rhs.block(k, j))
+                                .fold(Integer::zero_box(), |acc, term| acc + term)
+                        })
+                        .collect()
+                })
+                .collect(),
+        )
+    }
+
+    /// A block matrix is symmetric if its diagonal blocks are symmetric
+    /// and its off-diagonal blocks are the transposes of each other.
+    /// It is positive definite if moreover its off-diagonal blocks are zero
+    /// and its diagonal blocks are positive definite.
+    pub fn properties(&self) -> MatrixProperties {
+        if self.n_block_rows() != self.n_block_cols() {
+            return MatrixProperties::GENERAL;
+        }
+        let mut res = MatrixProperties::SPD;
+        for i in 0..self.n_block_rows() {
+            let diagonal = self.block(i, i);
+            res = res.intersection(if diagonal.is_zero() {
+                MatrixProperties::SYMMETRIC
+            } else {
+                matrix_properties(diagonal)
+            });
+            for j in 0..i {
+                let (lower, upper) = (self.block(i, j), self.block(j, i));
+                if !lower.is_zero() || !upper.is_zero() {
+                    res.positive_definite = false;
+                }
+                if transpose(upper) != lower {
+                    res.symmetric = false;
+                }
+            }
+        }
+        res
+    }
+}
+
+impl Expr for BlockMatrix {
+    fn get_ref(&self) -> &dyn Expr {
+        self as &dyn Expr
+    }
+
+    fn for_each_arg(&self, f: &mut dyn FnMut(&dyn Arg)) {
+        f(&self.n_block_cols());
+        self.blocks.iter().flatten().for_each(|block| f(&**block));
+    }
+
+    fn from_args(&self, args: Vec<Box<dyn Arg>>) -> Box<dyn Expr> {
+        let n_cols = *args[0].as_any().downcast_ref::<usize>().unwrap();
+        let blocks: Vec<Box<dyn Expr>> = args[1..].iter().cloned().collect();
+        Box::new(BlockMatrix {
+            blocks: blocks
+                .into_iter()
+                .chunks(n_cols.max(1))
+                .into_iter()
+                .map(|row| row.collect())
+                .collect(),
+        })
+    }
+
+    fn clone_box(&self) -> Box<dyn Expr> {
+        Box::new(self.clone())
+    }
+
+    fn is_commutative(&self) -> bool {
+        false
+    }
+
+    fn str(&self) -> String {
+        format!(
+            "[{}]",
+            self.blocks
+                .iter()
+                .map(|row| format!("[{}]", row.iter().map(|b| b.str()).join(", ")))
+                .join(", ")
+        )
+    }
+}
+
+/// Transposes a matrix expression, using `(AB)ᵀ = BᵀAᵀ`.
+/// Scalars are left untouched.
+pub fn transpose(expr: &dyn Expr) -> Box<dyn Expr> {
+    if expr.is_commutative() {
+        return expr.clone_box();
+    }
+    match expr.known_expr() {
+        KnownExpr::Matrix(matrix) => matrix.transpose().clone_box(),
+        KnownExpr::Add(Add { operands }) => {
+            Add::new_box_v2(operands.iter().map(|op| transpose(op.get_ref())).collect())
+        }
+        KnownExpr::Mul(Mul { operands }) => operands
+            .iter()
+            .rev()
+            .map(|op| transpose(op.get_ref()))
+            .fold(Integer::one_box(), |acc, op| acc * op),
+        KnownExpr::Pow(pow) => Pow::pow(transpose(pow.base()), pow.exponent.clone_box()),
+        _ => match (expr as &dyn Any).downcast_ref::<BlockMatrix>() {
+            Some(block) => block.transpose().clone_box(),
+            None => expr.clone_box(),
+        },
+    }
+}
+
+/// Computes the shape of a matrix expression, checking that products and sums are well defined.
+/// Returns `None` for scalars.
+pub fn shape(expr: &dyn Expr) -> Result<Option<Shape>, MatrixError> {
+    if expr.is_commutative() {
+        return Ok(None);
+    }
+    match expr.known_expr() {
+        KnownExpr::Matrix(matrix) => Ok(Some(matrix.shape.clone())),
+        KnownExpr::Add(Add { operands }) => {
+            let mut res: Option<Shape> = None;
+            for op in operands {
+                match (&res, shape(op.get_ref())?) {
+                    (_, None) => (),
+                    (None, found) => res = found,
+                    (Some(lhs), Some(rhs)) if *lhs != rhs => {
+                        return Err(MatrixError::AddShapeMismatch {
+                            lhs: lhs.clone(),
+                            rhs,
+                        });
+                    }
+                    _ => (),
+                }
+            }
+            Ok(res)
+        }
+        KnownExpr::Mul(Mul { operands }) => {
+            let mut res: Option<Shape> = None;
+            for op in operands {
+                match (res.take(), shape(op.get_ref())?) {
+                    (lhs, None) => res = lhs,
+                    (None, rhs) => res = rhs,
+                    (Some(lhs), Some(rhs)) if lhs.cols != rhs.rows => {
+                        return Err(MatrixError::MulShapeMismatch { lhs, rhs });
+                    }
+                    (Some(lhs), Some(rhs)) => res = Some(Shape::new(lhs.rows, rhs.cols)),
+                }
+            }
+            Ok(res)
+        }
+        KnownExpr::Pow(pow) => match shape(pow.base())? {
+            Some(base) if !base.is_square() => Err(MatrixError::MulShapeMismatch {
+                lhs: base.clone(),
+                rhs: base,
+            }),
+            base => Ok(base),
+        },
+        _ => match (expr as &dyn Any).downcast_ref::<BlockMatrix>() {
+            Some(block) => {
+                let (rows, cols) = block.block_shapes()?;
+                let sum = |sizes: BlockSizes| -> Option<Box<dyn Expr>> {
+                    sizes
+                        .into_iter()
+                        .try_fold(Integer::zero_box(), |acc, size| Some(acc + size?))
+                };
+                Ok(sum(rows).zip(sum(cols)).map(|(r, c)| Shape::new(r, c)))
+            }
+            None => Ok(None),
+        },
+    }
+}
+
+/// Infers the properties of a matrix expression from the properties of its matrices.
+///
+/// Sums and scalar multiples of symmetric matrices are symmetric.
+/// Positive definiteness is only kept through positive numerical coefficients,
+/// as the sign of symbolic coefficients is unknown.
+pub fn matrix_properties(expr: &dyn Expr) -> MatrixProperties {
+    match expr.known_expr() {
+        KnownExpr::Matrix(matrix) => matrix.properties,
+        KnownExpr::Add(Add { operands }) => operands
+            .iter()
+            .filter(|op| !op.is_commutative())
+            .map(|op| matrix_properties(op.get_ref()))
+            .reduce(MatrixProperties::intersection)
+            .unwrap_or(MatrixProperties::GENERAL),
+        KnownExpr::Mul(Mul { operands }) => {
+            let (matrixes, scalars): (Vec<_>, Vec<_>) =
+                operands.iter().partition(|op| !op.is_commutative());
+            let [matrix] = matrixes.as_slice() else {
+                return MatrixProperties::GENERAL;
+            };
+            let mut res = matrix_properties(matrix.get_ref());
+            if !scalars
+                .iter()
+                .all(|s| s.is_number() && !s.is_negative_number())
+            {
+                res.positive_definite = false;
+            }
+            res
+        }
+        _ => match (expr as &dyn Any).downcast_ref::<BlockMatrix>() {
+            Some(block) => block.properties(),
+            None => MatrixProperties::GENERAL,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spd(name: &str) -> Box<dyn Expr> {
+        Matrix::square(name, "n")
+            .with_properties(MatrixProperties::SPD)
+            .clone_box()
+    }
+
+    #[test]
+    fn test_matrices_do_not_commute() {
+        let a = Matrix::square("A", "n").clone_box();
+        let b = Matrix::square("B", "n").clone_box();
+
+        assert_ne!(&a * &b, &b * &a);
+        assert_eq!((&a * &b * &a).str(), "A.B.A");
+        assert_eq!((&a * &a * &b).srepr(), (a.ipow(2) * &b).srepr());
+    }
+
+    #[test]
+    fn test_scalars_commute_with_matrices() {
+        let a = Matrix::square("A", "n").clone_box();
+        let [c] = symbols!("c");
+
+        assert_eq!(&(c * a.get_ref() * c), &(c.ipow(2) * &a));
+    }
+
+    #[test]
+    fn test_transpose() {
+        let a = Matrix::square("A", "n").clone_box();
+        let b = Matrix::new("B", Shape::new(Symbol::new_box("n"), Symbol::new_box("m")));
+        let m = spd("M");
+
+        let expr = &a * b.get_ref() + Integer::new_box(2) * &m * b.get_ref();
+        assert_eq!(transpose(expr.get_ref()).str(), "Bᵀ.Aᵀ + 2Bᵀ.M");
+        assert_eq!(transpose(transpose(expr.get_ref()).get_ref()), expr);
+        assert_eq!(transpose(m.get_ref()), m);
+    }
+
+    #[test]
+    fn test_shape() {
+        let [n, m] = symbols!("n", "m");
+        let a = Matrix::square("A", "n").clone_box();
+        let b = Matrix::new("B", Shape::new(n.clone_box(), m.clone_box())).clone_box();
+
+        let ab = shape((&a * &b).get_ref()).unwrap().unwrap();
+        assert_eq!(ab, Shape::new(n.clone_box(), m.clone_box()));
+        assert!(matches!(
+            shape((&b * &a).get_ref()),
+            Err(MatrixError::MulShapeMismatch { .. })
+        ));
+        assert!(matches!(
+            shape((&a + &b).get_ref()),
+            Err(MatrixError::AddShapeMismatch { .. })
+        ));
+        assert!(shape(n).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_properties() {
+        let [k] = symbols!("k");
+        let mass = spd("M");
+        let laplace = Matrix::square("A", "n")
+            .with_properties(MatrixProperties::SYMMETRIC)
+            .clone_box();
+
+        let scaled = mass.clone_box() + Rational::new_box(1, 2) * &mass;
+        assert!(matrix_properties(scaled.get_ref()).is_spd());
+        let system = mass.clone_box() + k * laplace.get_ref();
+        assert_eq!(
+            matrix_properties(system.get_ref()),
+            MatrixProperties::SYMMETRIC
+        );
+        assert_eq!(
+            matrix_properties((&mass * &laplace).get_ref()),
+            MatrixProperties::GENERAL
+        );
+    }
+
+    #[test]
+    fn test_block_matrix() {
+        let mass = spd("M");
+        let b = Matrix::square("B", "n").clone_box();
+        let zero = Integer::zero_box();
+
+        let saddle = BlockMatrix::new(vec![
+            vec![mass.clone_box(), transpose(b.get_ref())],
+            vec![b.clone_box(), zero.clone_box()],
+        ])
+        .unwrap();
+        assert_eq!(saddle.properties(), MatrixProperties::SYMMETRIC);
+        assert_eq!(saddle.str(), "[[M, Bᵀ], [B, 0]]");
+        let shape = shape(saddle.get_ref()).unwrap().unwrap();
+        assert_eq!(shape.rows.str(), "2n");
+
+        let diagonal = BlockMatrix::new(vec![
+            vec![mass.clone_box(), zero.clone_box()],
+            vec![zero.clone_box(), mass.clone_box()],
+        ])
+        .unwrap();
+        assert!(diagonal.properties().is_spd());
+        let product = diagonal.block_mul(&saddle).unwrap();
+        assert_eq!(product.str(), "[[M^2, M.Bᵀ], [M.B, 0]]");
+
+        let rectangular = Matrix::new("C", Shape::new(Symbol::new_box("m"), Symbol::new_box("n")));
+        assert!(matches!(
+            BlockMatrix::new(vec![vec![mass, rectangular.clone_box()]]),
+            Err(MatrixError::BlockShapeMismatch { .. })
+        ));
+    }
+}
@@ -1,5 +1,4 @@
 use super::*;
-use indexmap::IndexMap;
 
 #[derive(Clone)]
 pub struct Mul {
@@ -45,6 +44,10 @@ impl Expr for Mul {
                 {
                     format!("{name}.")
                 }
+                KnownExpr::Matrix(matrix) if i < self.operands.len() - 1 => {
+                    format!("{}.", matrix.str())
+                }
+                KnownExpr::Matrix(matrix) => matrix.str(),
                 KnownExpr::Integer(Integer { value }) => value.to_string(),
                 KnownExpr::Symbol(Symbol { name }) => name.to_string(),
                 _ if self.operands.len() > 1 => format!("({})", op.str()),
@@ -58,30 +61,8 @@ impl Expr for Mul {
         self.operands.iter().all(|op| op.is_number())
     }
 
-    fn to_cpp(&self) -> String {
-        let mut ops = self.operands.iter().peekable();
-        let mut res = String::new();
-        // If expression starts with -1, transform it into -
-        if let Some(first_op) = ops.peek()
-            && first_op.is_neg_one()
-        {
-            ops.next();
-            res += "-";
-        }
-        ops.map(|op| match op.known_expr() {
-            KnownExpr::Add(_) if self.operands.len() > 1 => format!("({})", op.to_cpp()),
-            KnownExpr::Pow(pow) if self.operands.len() > 1 => format!("({})", pow.to_cpp()),
-            KnownExpr::Rational(r) if self.operands.len() > 1 => format!("({})", r.to_cpp()),
-            _ => op.to_cpp(),
-        })
-        .enumerate()
-        .for_each(|(i, op)| {
-            if i > 0 {
-                res += " * ";
-            }
-            res += &op
-        });
-        res
+    fn is_commutative(&self) -> bool {
+        self.operands.iter().all(|op| op.is_commutative())
     }
 
     fn expand(&self) -> Box<dyn Expr> {
@@ -310,7 +291,7 @@ impl std::ops::Mul for &dyn Expr {
             new_operands.insert(0, &coeff);
         }
 
-        let mut operands_exponents: IndexMap<Box<dyn Expr>, Box<dyn Expr>> = IndexMap::new();
+        let mut operands_exponents: Vec<(Box<dyn Expr>, Box<dyn Expr>)> = Vec::new();
 
         for op in new_operands
             .iter()
@@ -318,7 +299,8 @@ impl std::ops::Mul for &dyn Expr {
             .flat_map(|op| match op.known_expr() {
                 KnownExpr::Mul(Mul { operands }) => operands.clone(),
                 KnownExpr::Pow(Pow { base, exponent })
-                    if matches!(base.known_expr(), KnownExpr::Mul(Mul { .. })) =>
+                    if matches!(base.known_expr(), KnownExpr::Mul(Mul { .. }))
+                        && base.is_commutative() =>
                 {
                     let mul = base.as_mul().unwrap();
                     mul.operands.iter().map(|op| op.pow(exponent)).collect()
@@ -327,10 +309,20 @@ impl std::ops::Mul for &dyn Expr {
             })
         {
             let (expr, exponent) = op.get_exponent();
-            let entry = operands_exponents
-                .entry(expr)
-                .or_insert(Integer::zero_box());
-            *entry += exponent;
+            // Non commutative factors (matrices) can only be merged with
+            // the closest non commutative factor on their left
+            let entry = if expr.is_commutative() {
+                operands_exponents.iter().position(|(e, _)| *e == expr)
+            } else {
+                operands_exponents
+                    .iter()
+                    .rposition(|(e, _)| !e.is_commutative())
+                    .filter(|&i| operands_exponents[i].0 == expr)
+            };
+            match entry {
+                Some(i) => operands_exponents[i].1 += exponent,
+                None => operands_exponents.push((expr, exponent)),
+            }
         }
         let mut new_operands = Vec::with_capacity(operands_exponents.len());
 
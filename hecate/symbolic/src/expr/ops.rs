@@ -4,8 +4,14 @@ pub use factor::*;
 pub mod compare;
 pub use compare::*;
 
+pub mod cse;
+pub use cse::*;
+
 pub mod subs;
 pub use subs::*;
 
 mod parse;
 pub use parse::*;
+
+pub mod integrate;
+pub use integrate::*;
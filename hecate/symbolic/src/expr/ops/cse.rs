@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+
+use crate::*;
+
+/// Result of a common subexpression elimination.
+#[derive(Debug, Clone)]
+pub struct Cse {
+    /// Temporaries to compute, in order, before evaluating the reduced expressions.
+    pub temporaries: Vec<(Symbol, Box<dyn Expr>)>,
+    /// The expressions rewritten in terms of the temporaries.
+    pub exprs: Vec<Box<dyn Expr>>,
+}
+
+/// Common subexpression elimination.
+///
+/// Every compound subexpression (addition, multiplication, power or function call)
+/// appearing more than once across `exprs` is replaced by a temporary symbol named `{prefix}{i}`.
+pub fn cse<'a, I: IntoIterator<Item = &'a dyn Expr>>(exprs: I, prefix: &str) -> Cse {
+    let exprs = exprs.into_iter().collect_vec();
+
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for expr in &exprs {
+        count_subexprs(*expr, &mut counts);
+    }
+
+    let mut eliminator = Eliminator {
+        counts,
+        prefix,
+        names: HashMap::new(),
+        temporaries: Vec::new(),
+    };
+    let exprs = exprs.iter().map(|e| eliminator.reduce(*e)).collect();
+
+    Cse {
+        temporaries: eliminator.temporaries,
+        exprs,
+    }
+}
+
+fn is_compound(expr: &dyn Expr) -> bool {
+    match expr.known_expr() {
+        KnownExpr::Add(_) | KnownExpr::Mul(_) | KnownExpr::Pow(_) => true,
+        _ => expr.as_function().is_some_and(|f| !f.args.is_empty()),
+    }
+}
+
+fn count_subexprs(expr: &dyn Expr, counts: &mut HashMap<String, usize>) {
+    if !is_compound(expr) {
+        return;
+    }
+    let count = counts.entry(expr.srepr()).or_insert(0);
+    *count += 1;
+    // Subexpressions of an already seen expression have already been counted
+    if *count > 1 {
+        return;
+    }
+    for arg in expr.args() {
+        if let Some(exprs) = arg.as_any().downcast_ref::<Vec<Box<dyn Expr>>>() {
+            for e in exprs {
+                count_subexprs(e.get_ref(), counts);
+            }
+        } else if let Some(e) = arg.as_expr() {
+            count_subexprs(e.get_ref(), counts);
+        }
+    }
+}
+
+struct Eliminator<'p> {
+    counts: HashMap<String, usize>,
+    prefix: &'p str,
+    names: HashMap<String, Symbol>,
+    temporaries: Vec<(Symbol, Box<dyn Expr>)>,
+}
+
+impl Eliminator<'_> {
+    fn reduce(&mut self, expr: &dyn Expr) -> Box<dyn Expr> {
+        if !is_compound(expr) {
+            return expr.clone_box();
+        }
+        let key = expr.srepr();
+        if let Some(symbol) = self.names.get(&key) {
+            return symbol.clone_box();
+        }
+
+        let reduced = expr.from_args(
+            expr.args()
+                .into_iter()
+                .map(|arg| {
+                    if let Some(exprs) = arg.as_any().downcast_ref::<Vec<Box<dyn Expr>>>() {
+                        exprs
+                            .iter()
+                            .map(|e| self.reduce(e.get_ref()))
+                            .collect_vec()
+                            .clone_arg()
+                    } else if let Some(e) = arg.as_expr() {
+                        self.reduce(e.get_ref()).into()
+                    } else {
+                        arg
+                    }
+                })
+                .collect(),
+        );
+
+        if self.counts.get(&key).copied().unwrap_or(0) > 1 {
+            let symbol = Symbol::new(&format!("{}{}", self.prefix, self.temporaries.len()));
+            self.names.insert(key, symbol.clone());
+            self.temporaries.push((symbol.clone(), reduced));
+            symbol.clone_box()
+        } else {
+            reduced
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cse_nothing_to_eliminate() {
+        let [x, y] = symbols!("x", "y");
+        let expr = x * y + 2;
+        let res = cse([expr.get_ref()], "tmp");
+
+        assert!(res.temporaries.is_empty());
+        assert_eq!(res.exprs[0], expr);
+    }
+
+    #[test]
+    fn test_cse_repeated_function() {
+        let expr: Box<dyn Expr> = "sin(4 * pi * t) * sin(4 * pi * t) + cos(4 * pi * t)"
+            .parse()
+            .unwrap();
+        let res = cse([expr.get_ref()], "tmp");
+
+        assert_eq!(res.temporaries.len(), 2);
+        assert_eq!(res.temporaries[0].0.name, "tmp0");
+        assert_eq!(res.temporaries[0].1.to_cpp(), "4 * pi * t");
+        assert_eq!(res.temporaries[1].0.name, "tmp1");
+        assert_eq!(res.temporaries[1].1.to_cpp(), "std::sin(tmp0)");
+        assert_eq!(res.exprs[0].to_cpp(), "tmp1 * tmp1 + std::cos(tmp0)");
+    }
+
+    #[test]
+    fn test_cse_across_exprs() {
+        let [c, k, u] = symbols!("c", "k", "u");
+        let a = c.ipow(2) * k * u;
+        let b = c.ipow(2) * k + 1;
+        let res = cse([a.get_ref(), b.get_ref()], "x");
+
+        assert_eq!(res.temporaries.len(), 1);
+        assert_eq!(res.temporaries[0].1, c.ipow(2));
+    }
+}
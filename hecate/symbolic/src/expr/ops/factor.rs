@@ -88,7 +88,8 @@ pub fn get_operands_exponents<E: Expr + ?Sized>(
                 .flat_map(|op| match op.known_expr() {
                     KnownExpr::Mul(Mul { operands }) => operands.clone(),
                     KnownExpr::Pow(Pow { base, exponent })
-                        if matches!(base.known_expr(), KnownExpr::Mul(Mul { .. })) =>
+                        if matches!(base.known_expr(), KnownExpr::Mul(Mul { .. }))
+                            && base.is_commutative() =>
                     {
                         let mul = base.as_mul().unwrap();
                         mul.operands.iter().map(|op| op.pow(exponent)).collect()
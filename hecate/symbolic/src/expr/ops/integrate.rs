@@ -0,0 +1,369 @@
+use thiserror::Error;
+
+use crate::*;
+
+#[derive(Error, Debug)]
+pub enum IntegrationError {
+    #[error("don't know how to integrate {expr} with respect to {var}")]
+    Unsupported { expr: String, var: String },
+}
+
+/// Variable of integration, with optional bounds for definite integrals.
+pub trait IntoIntegrationVar {
+    fn into_integration_var(self) -> (Symbol, Option<[Box<dyn Expr>; 2]>);
+}
+
+impl IntoIntegrationVar for Symbol {
+    fn into_integration_var(self) -> (Symbol, Option<[Box<dyn Expr>; 2]>) {
+        (self, None)
+    }
+}
+
+impl IntoIntegrationVar for &str {
+    fn into_integration_var(self) -> (Symbol, Option<[Box<dyn Expr>; 2]>) {
+        (Symbol::new(self), None)
+    }
+}
+
+impl IntoIntegrationVar for (&str, &dyn Expr, &dyn Expr) {
+    fn into_integration_var(self) -> (Symbol, Option<[Box<dyn Expr>; 2]>) {
+        (
+            Symbol::new(self.0),
+            Some([self.1.clone_box(), self.2.clone_box()]),
+        )
+    }
+}
+
+impl IntoIntegrationVar for (Symbol, Box<dyn Expr>, Box<dyn Expr>) {
+    fn into_integration_var(self) -> (Symbol, Option<[Box<dyn Expr>; 2]>) {
+        (self.0, Some([self.1, self.2]))
+    }
+}
+
+/// Integrates an expression.
+///
+/// `integrate(expr, "x")` computes an antiderivative (without integration constant),
+/// `integrate(expr, ("x", a, b))` computes the definite integral from `a` to `b`.
+///
+/// Supported integrands are polynomials, powers of linear expressions (including `1 / (ax + b)`),
+/// rational functions whose denominator is a power of a linear expression,
+/// elementary functions of linear arguments and their products with monomials.
+pub fn integrate<V: IntoIntegrationVar>(
+    expr: &dyn Expr,
+    var: V,
+) -> Result<Box<dyn Expr>, IntegrationError> {
+    let (var, bounds) = var.into_integration_var();
+    let antiderivative = antiderivative(expr, &var)?.simplify();
+
+    Ok(match bounds {
+        None => antiderivative,
+        Some([lower, upper]) => {
+            let at = |bound: Box<dyn Expr>| {
+                evaluate(antiderivative.subs(&[[var.clone_box(), bound]]).get_ref())
+            };
+            evaluate((at(upper) - at(lower)).get_ref()).simplify()
+        }
+    })
+}
+
+/// Antiderivative of an elementary function, in terms of its argument `u`
+type Rule = fn(&dyn Expr) -> Box<dyn Expr>;
+
+/// Antiderivatives of elementary functions of `u`
+const RULES: &[(&str, Rule)] = &[
+    ("sin", |u| -func("cos", u)),
+    ("cos", |u| func("sin", u)),
+    ("exp", |u| func("exp", u)),
+    ("sinh", |u| func("cosh", u)),
+    ("cosh", |u| func("sinh", u)),
+    ("tan", |u| -func("log", func("cos", u).get_ref())),
+    ("log", |u| u * func("log", u) - u.clone_box()),
+];
+
+/// Exact values of elementary functions at remarkable points
+const SPECIAL_VALUES: &[(&str, &str, isize)] = &[
+    ("sin", "0", 0),
+    ("cos", "0", 1),
+    ("exp", "0", 1),
+    ("sinh", "0", 0),
+    ("cosh", "0", 1),
+    ("tan", "0", 0),
+    ("log", "1", 0),
+    ("sin", "pi", 0),
+    ("cos", "pi", -1),
+];
+
+fn func(name: &str, arg: &dyn Expr) -> Box<dyn Expr> {
+    Func::new_move_box(name.to_string(), vec![arg.clone_box()])
+}
+
+fn unsupported(expr: &dyn Expr, var: &Symbol) -> IntegrationError {
+    IntegrationError::Unsupported {
+        expr: expr.to_cpp(),
+        var: var.name.clone(),
+    }
+}
+
+fn antiderivative(expr: &dyn Expr, var: &Symbol) -> Result<Box<dyn Expr>, IntegrationError> {
+    let x = var.get_ref();
+    if !expr.has(x) {
+        return Ok(expr * x);
+    }
+    if expr == x {
+        return Ok(Rational::new_box(1, 2) * x.ipow(2));
+    }
+
+    match expr.known_expr() {
+        KnownExpr::Add(Add { operands }) => operands.iter().try_fold(
+            Integer::zero_box(),
+            |acc, op| -> Result<_, IntegrationError> {
+                Ok(acc + antiderivative(op.get_ref(), var)?)
+            },
+        ),
+        KnownExpr::Mul(Mul { operands }) => {
+            let (constants, dependents): (Vec<_>, Vec<_>) =
+                operands.iter().partition(|op| !op.has(x));
+            let coeff = constants
+                .iter()
+                .fold(Integer::one_box(), |acc, op| acc * op.get_ref());
+
+            if let [f] = dependents.as_slice() {
+                return Ok(coeff * antiderivative(f.get_ref(), var)?);
+            }
+            let expanded = expr.expand();
+            if matches!(expanded.known_expr(), KnownExpr::Add(_)) {
+                return antiderivative(expanded.get_ref(), var);
+            }
+
+            // Split the monomial part from the rest
+            let mut degree = 0;
+            let mut others = Vec::new();
+            for f in &dependents {
+                match monomial_degree(f.get_ref(), var) {
+                    Some(d) => degree += d,
+                    None => others.push(f.get_ref()),
+                }
+            }
+            let [other] = others.as_slice() else {
+                return Err(unsupported(expr, var));
+            };
+            let res = match other.as_pow() {
+                Some(pow) if !pow.exponent().has(x) => {
+                    substitute_linear(degree, pow.base(), pow.exponent(), var)
+                        .ok_or_else(|| unsupported(expr, var))?
+                }
+                _ => by_parts(degree, *other, var)?,
+            };
+            Ok(coeff * res)
+        }
+        KnownExpr::Pow(pow) => {
+            let (base, exponent) = (pow.base(), pow.exponent());
+            if !exponent.has(x) {
+                let (a, _) = linear_coeffs(base, var).ok_or_else(|| unsupported(expr, var))?;
+                if exponent.is_neg_one() {
+                    return Ok(func("log", base) / a);
+                }
+                let exponent = exponent.clone_box() + 1;
+                return Ok(base.pow(&exponent) / (exponent * a));
+            }
+            if !base.has(x) {
+                let (a, _) = linear_coeffs(exponent, var).ok_or_else(|| unsupported(expr, var))?;
+                let log_base = match base.known_expr() {
+                    KnownExpr::Symbol(Symbol { name }) if name == "e" => Integer::one_box(),
+                    _ => func("log", base),
+                };
+                return Ok(expr / (a * log_base).get_ref());
+            }
+            Err(unsupported(expr, var))
+        }
+        KnownExpr::Diff(Diff { f, vars }) if vars.contains_key(var) => {
+            let mut vars = vars.clone();
+            vars[var] -= 1;
+            vars.retain(|_, order| *order > 0);
+            Ok(if vars.is_empty() {
+                f.clone_box()
+            } else {
+                Box::new(Diff::new_v2(f.clone_box(), vars))
+            })
+        }
+        _ => match expr.as_function() {
+            Some(Func { name, args }) if args.len() == 1 => {
+                let (_, rule) = RULES
+                    .iter()
+                    .find(|(f, _)| f == name)
+                    .ok_or_else(|| unsupported(expr, var))?;
+                let u = args[0].get_ref();
+                let (a, _) = linear_coeffs(u, var).ok_or_else(|| unsupported(expr, var))?;
+                Ok(rule(u) / a)
+            }
+            _ => Err(unsupported(expr, var)),
+        },
+    }
+}
+
+/// Returns `(a, b)` if `expr` is `a * var + b`, with `a` not zero.
+fn linear_coeffs(expr: &dyn Expr, var: &Symbol) -> Option<(Box<dyn Expr>, Box<dyn Expr>)> {
+    let x = var.get_ref();
+    let expanded = expr.expand();
+    let mut a = Integer::zero_box();
+    let mut b = Integer::zero_box();
+    for term in expanded.terms() {
+        if !term.has(x) {
+            b += term.clone_box();
+            continue;
+        }
+        let coeff = term / x;
+        if coeff.has(x) {
+            return None;
+        }
+        a += coeff;
+    }
+    (!a.is_zero()).then_some((a, b))
+}
+
+/// Returns `n` if `expr` is `var^n`, with `n` a positive integer.
+fn monomial_degree(expr: &dyn Expr, var: &Symbol) -> Option<usize> {
+    if expr == var.get_ref() {
+        return Some(1);
+    }
+    let pow = expr.as_pow()?;
+    match pow.exponent().known_expr() {
+        KnownExpr::Integer(Integer { value }) if *value > 0 && pow.base() == var.get_ref() => {
+            Some(*value as usize)
+        }
+        _ => None,
+    }
+}
+
+/// Integrates `var^degree * f` by parts, `f` having a known antiderivative `F`:
+/// `∫ x^n f = x^n F - n ∫ x^(n-1) F`
+fn by_parts(degree: usize, f: &dyn Expr, var: &Symbol) -> Result<Box<dyn Expr>, IntegrationError> {
+    let x = var.get_ref();
+    let antiderivative_f = antiderivative(f, var)?;
+    if degree == 0 {
+        return Ok(antiderivative_f);
+    }
+    let n = degree as isize;
+    let rest = Integer::new_box(n) * x.ipow(n - 1) * antiderivative_f.get_ref();
+    Ok(x.ipow(n) * antiderivative_f.get_ref() - antiderivative(rest.get_ref(), var)?)
+}
+
+/// Integrates `var^degree * (a var + b)^exponent` by substituting `u = a var + b`.
+fn substitute_linear(
+    degree: usize,
+    base: &dyn Expr,
+    exponent: &dyn Expr,
+    var: &Symbol,
+) -> Option<Box<dyn Expr>> {
+    let (a, b) = linear_coeffs(base, var)?;
+    let u = Symbol::new(&format!("_{}", var.name));
+    // x = (u - b) / a and dx = du / a
+    let x = (u.clone_box() - b) / a.get_ref();
+    let integrand = (0..degree).fold(Integer::one_box(), |acc, _| acc * x.get_ref())
+        * u.pow(&exponent.clone_box())
+        / a.get_ref();
+    let res = antiderivative(integrand.expand().get_ref(), &u).ok()?;
+    Some(res.subs(&[[u.clone_box(), base.clone_box()]]))
+}
+
+/// Rebuilds an expression bottom-up with the arithmetic operators,
+/// folding numbers and known values of elementary functions.
+fn evaluate(expr: &dyn Expr) -> Box<dyn Expr> {
+    match expr.known_expr() {
+        KnownExpr::Add(Add { operands }) => operands
+            .iter()
+            .fold(Integer::zero_box(), |acc, op| acc + evaluate(op.get_ref())),
+        KnownExpr::Mul(Mul { operands }) => operands
+            .iter()
+            .fold(Integer::one_box(), |acc, op| acc * evaluate(op.get_ref())),
+        KnownExpr::Pow(pow) => Pow::pow(evaluate(pow.base()), evaluate(pow.exponent())),
+        _ => match expr.as_function() {
+            Some(Func { name, args }) => {
+                let args: Vec<Box<dyn Expr>> =
+                    args.iter().map(|arg| evaluate(arg.get_ref())).collect();
+                if let [arg] = args.as_slice()
+                    && let Some((_, _, value)) = SPECIAL_VALUES
+                        .iter()
+                        .find(|(f, at, _)| f == name && arg.str() == *at)
+                {
+                    return Integer::new_box(*value);
+                }
+                Func::new_move_box(name.clone(), args)
+            }
+            None => expr.clone_box(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Box<dyn Expr> {
+        s.parse().unwrap()
+    }
+
+    fn definite(expr: &str, a: isize, b: isize) -> Box<dyn Expr> {
+        integrate(
+            &*parse(expr),
+            ("x", Integer::new(a).get_ref(), Integer::new(b).get_ref()),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_polynomials() {
+        let res = integrate(&*parse("3 * x^2 + 2 * x + 1"), "x").unwrap();
+        assert_eq!(res.to_cpp(), "x * x * x + x * x + x");
+
+        assert_eq!(definite("x^2", 0, 1), Rational::new_box(1, 3));
+        assert_eq!(
+            definite("(x + 1) * (x - 1)", -1, 1),
+            Rational::new_box(-4, 3)
+        );
+    }
+
+    #[test]
+    fn test_linear_denominators() {
+        let res = integrate(&*parse("1 / (2 * x + 1)"), "x").unwrap();
+        assert_eq!(res.to_cpp(), "(1./2.) * std::log(2 * x + 1)");
+
+        let res = integrate(&*parse("x / (x + 1)^2"), "x").unwrap();
+        assert_eq!(res.to_cpp(), "std::log(x + 1) + 1 / (x + 1)");
+    }
+
+    #[test]
+    fn test_elementary_functions() {
+        let res = integrate(&*parse("cos(2 * x) + exp(-2 * x)"), "x").unwrap();
+        assert_eq!(
+            res.to_cpp(),
+            "(1./2.) * std::sin(2 * x) + (-1./2.) * std::exp(-2 * x)"
+        );
+
+        assert_eq!(
+            integrate(
+                &*parse("sin(x)"),
+                ("x", Integer::zero().get_ref(), symbol!("pi"))
+            )
+            .unwrap(),
+            Integer::new_box(2)
+        );
+    }
+
+    #[test]
+    fn test_by_parts() {
+        let res = integrate(&*parse("x * exp(x)"), "x").unwrap();
+        assert_eq!(res.to_cpp(), "x * std::exp(x) - std::exp(x)");
+
+        assert_eq!(definite("x * exp(x)", 0, 1), Integer::new_box(1));
+    }
+
+    #[test]
+    fn test_unsupported() {
+        assert!(matches!(
+            integrate(&*parse("exp(x^2)"), "x"),
+            Err(IntegrationError::Unsupported { .. })
+        ));
+        assert!(integrate(&*parse("1 / (x^2 + 1)"), "x").is_err());
+    }
+}
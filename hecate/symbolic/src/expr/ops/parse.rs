@@ -164,6 +164,10 @@ const FUNC_PATTERN: &str = r"^(\w+)\((.*?)\)$";
 
 static FUNC_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(FUNC_PATTERN).unwrap());
 
+/// A gradient weighted by a coefficient, `c * grad(u)`
+static WEIGHTED_GRAD_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\w+)\s*\*\s*grad\((.*)\)$").unwrap());
+
 #[derive(Error, Debug, PartialEq)]
 pub enum ParseFunctionError {
     #[error("bad args")]
@@ -178,6 +182,8 @@ pub enum ParseFunctionError {
     InvalidArg(String, #[source] Box<ParseExprError>),
     #[error("invalid differential expression")]
     InvalidDiff(String, #[source] ParseDiffError),
+    #[error("unsupported composition of operators: {0}")]
+    UnsupportedOperator(String),
 }
 
 static D_DVAR_RE: LazyLock<Regex> = LazyLock::new(|| {
@@ -278,6 +284,68 @@ pub fn parse_function(name: &str, args: &str) -> Result<Box<dyn Expr>, ParseFunc
 
             laplacian * f
         }
+        // Second order vector operators, written as compositions of first order ones
+        "div" | "grad" => {
+            // The arguments of the inner operator were split along with the outer ones
+            let arg = args.join(", ");
+            // The divergence of a weighted gradient is a weighted Laplacian
+            if let Some(captures) = WEIGHTED_GRAD_RE.captures(&arg)
+                && name == "div"
+            {
+                let f = parse_expr(&captures[2])
+                    .map_err(|e| ParseFunctionError::InvalidFuncExpr(Box::new(e)))?;
+                return Ok(Symbol::new_box(&format!("laplacian_{}", &captures[1])) * f);
+            }
+            let unsupported = || ParseFunctionError::UnsupportedOperator(format!("{name}({arg})"));
+            let captures = FUNC_RE.captures(&arg).ok_or_else(unsupported)?;
+            let inner = &captures[1];
+            let inner_args: Vec<_> = captures[2].split(',').map(|arg| arg.trim()).collect();
+            let parse_arg = |arg: &str| {
+                parse_expr(arg).map_err(|e| ParseFunctionError::InvalidFuncExpr(Box::new(e)))
+            };
+            let f = parse_arg(inner_args[0])?;
+            let f = f.get_ref();
+            let [laplacian, grad_div, div_eps] = symbols!("laplacian", "grad_div", "div_eps");
+            match (name, inner, &inner_args[1..]) {
+                ("div", "grad", []) => laplacian * f,
+                ("grad", "div", []) => grad_div * f,
+                ("div", "eps", []) => div_eps * f,
+                // Linear elastic stress σ(u) = λ div(u) I + 2 μ ε(u), the Lamé parameters being
+                // the parameters `lambda` and `mu` unless given
+                ("div", "sigma", lame) => {
+                    let (lambda, mu) = match lame {
+                        [] => (Symbol::new_box("lambda"), Symbol::new_box("mu")),
+                        [lambda, mu] => (parse_arg(lambda)?, parse_arg(mu)?),
+                        _ => Err(ParseFunctionError::BadArgCount(
+                            inner.to_string(),
+                            inner_args.len(),
+                            "1 or 3".to_string(),
+                        ))?,
+                    };
+                    lambda * grad_div * f + Integer::new_box(2) * mu * div_eps * f
+                }
+                _ => Err(unsupported())?,
+            }
+        }
+        // Derivative along a velocity field, `dot(v, grad(u))`
+        "dot" => {
+            if args.len() != 2 {
+                Err(ParseFunctionError::BadArgCount(
+                    name.to_string(),
+                    args.len(),
+                    "2".to_string(),
+                ))?
+            }
+            let unsupported =
+                || ParseFunctionError::UnsupportedOperator(format!("{name}({})", args.join(", ")));
+            let captures = FUNC_RE.captures(args[1]).ok_or_else(unsupported)?;
+            if &captures[1] != "grad" {
+                Err(unsupported())?
+            }
+            let f = parse_expr(&captures[2])
+                .map_err(|e| ParseFunctionError::InvalidFuncExpr(Box::new(e)))?;
+            Symbol::new_box(&format!("advection_{}", args[0])) * f
+        }
         "diff" => {
             let n_args = args.len();
             if n_args < 2 || n_args > 3 {
@@ -506,6 +574,10 @@ pub fn parse_expr(s: &str) -> Result<Box<dyn Expr>, ParseExprError> {
     {
         Box::new(s.parse::<Add>()?)
     }
+    // Negations, such as `-laplacian(u)`
+    else if let Some(negated) = s.strip_prefix('-') {
+        -parse_expr(negated)?
+    }
     // Differentiations
     else if let Some(captured_diff) = DIFF_RE.captures(s) {
         Box::new(TryInto::<Diff>::try_into(captured_diff)?)
@@ -643,6 +715,12 @@ mod tests {
         assert_eq!(expr.srepr(), "Integer(-128)")
     }
 
+    #[test]
+    fn test_parse_negated_function() {
+        let expr = parse_expr("-laplacian(u)").unwrap();
+        assert_eq!(expr, -parse_expr("laplacian(u)").unwrap())
+    }
+
     #[test]
     fn test_parse_laplacian() {
         let expr = parse_expr("laplacian").unwrap();
@@ -727,6 +805,57 @@ mod tests {
         assert_eq!(res, expected)
     }
 
+    #[test]
+    fn parse_elastic_wave_eq() {
+        let res = parse_expr("rho * diff(u, t, 2) = div(sigma(u)) + f").unwrap();
+        assert_eq!(
+            res.str(),
+            "rho.(∂^2u / ∂t^2) = lambda.grad_div.u + 2mu.div_eps.u + f"
+        );
+
+        let res = parse_expr("rho * diff(u, t, 2) = div(sigma(u, lame, 2 * shear))").unwrap();
+        assert_eq!(
+            res.str(),
+            "rho.(∂^2u / ∂t^2) = lame.grad_div.u + 4shear.div_eps.u"
+        );
+        assert!(matches!(
+            parse_expr("div(sigma(u, lame))"),
+            Err(ParseExprError::BadFunction(_))
+        ));
+
+        let [u, laplacian] = symbols!("u", "laplacian");
+        assert_eq!(parse_expr("div(grad(u))").unwrap(), laplacian * u);
+        assert!(matches!(
+            parse_expr("div(curl(u))"),
+            Err(ParseExprError::BadFunction(
+                ParseFunctionError::UnsupportedOperator(_)
+            ))
+        ));
+    }
+
+    #[test]
+    fn parse_weighted_laplacian() {
+        let res = parse_expr("dt(T) = div(kappa * grad(T))").unwrap();
+        let [t, laplacian_kappa] = symbols!("T", "laplacian_kappa");
+        assert_eq!(res, Equation::new_box(t.diff("t", 1), laplacian_kappa * t));
+    }
+
+    #[test]
+    fn parse_advection() {
+        let res = parse_expr("dt(c) + dot(v, grad(c)) = 0").unwrap();
+        let [c, advection_v] = symbols!("c", "advection_v");
+        assert_eq!(
+            res,
+            Equation::new_box(c.diff("t", 1) + advection_v * c, Integer::zero_box())
+        );
+        assert!(matches!(
+            parse_expr("dot(v, div(c))"),
+            Err(ParseExprError::BadFunction(
+                ParseFunctionError::UnsupportedOperator(_)
+            ))
+        ));
+    }
+
     #[test]
     fn parse_2d_wave_eq() {
         let res = parse_expr("d2u/dt^2 = c^2 * (d2u/dx2 + d2u/dy2) + source").unwrap();
@@ -37,6 +37,10 @@ impl Expr for Pow {
         self.base.is_number() && self.exponent.is_number()
     }
 
+    fn is_commutative(&self) -> bool {
+        self.base.is_commutative()
+    }
+
     fn str(&self) -> String {
         match (
             self.base.known_expr(),
@@ -57,35 +61,6 @@ impl Expr for Pow {
         self.exponent.is_neg_one() && self.base.is_one() || self.exponent.is_zero()
     }
 
-    fn to_cpp(&self) -> String {
-        let exponent = &self.exponent;
-        if exponent.is_zero() {
-            String::from("1")
-        } else if exponent.is_one() {
-            self.base.to_cpp()
-        } else if exponent.is_neg_one() {
-            format!("1 / {}", self.base.to_cpp())
-        } else {
-            if let KnownExpr::Integer(Integer { value: n }) = exponent.known_expr()
-                && *n > 0
-            {
-                let n = *n as usize;
-                let base_cpp = self.base.to_cpp();
-
-                let mut res =
-                    String::with_capacity((base_cpp.len() + 3) * (n - 1) + base_cpp.len());
-                res += &base_cpp;
-                for _ in 1..n {
-                    res += " * ";
-                    res += &base_cpp;
-                }
-                res
-            } else {
-                format!("pow({}, {})", self.base.to_cpp(), self.exponent.to_cpp())
-            }
-        }
-    }
-
     fn simplify(&self) -> Box<dyn Expr> {
         let Pow { base, exponent } = self;
 
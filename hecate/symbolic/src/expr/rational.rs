@@ -32,10 +32,6 @@ impl Expr for Rational {
         format!("{}/{}", self.num, self.denom)
     }
 
-    fn to_cpp(&self) -> String {
-        format!("{}./{}.", self.num, self.denom)
-    }
-
     fn get_ref<'a>(&'a self) -> &'a dyn Expr {
         self as &dyn Expr
     }
@@ -64,7 +60,7 @@ impl Expr for Rational {
     }
     fn simplify(&self) -> Box<dyn Expr> {
         let mut res = self.clone();
-        if self.num < 0 && self.denom < 0 {
+        if self.denom < 0 {
             res.num *= -1;
             res.denom *= -1;
         }
@@ -141,20 +137,26 @@ impl<N: ToInteger> From<N> for Rational {
 
 impl Rational {
     pub fn from_float<N: Float + ToString>(value: N) -> Rational {
+        Self::try_from_float(value).expect("float with too many digits for a rational")
+    }
+
+    /// The rational of the decimal representation of `value`, `None` when its digits overflow
+    /// the numerator or the denominator.
+    pub fn try_from_float<N: Float + ToString>(value: N) -> Option<Rational> {
         let srepr = value.to_string();
-        let decimals = srepr.split('.').last().unwrap_or("");
+        let decimals = srepr.split_once('.').map_or("", |(_, decimals)| decimals);
         let num_decimals = decimals.len();
 
         let mut rational = Rational {
-            num: srepr.replace(".", "").parse().expect("valid integer"),
-            denom: (10 as isize).pow(num_decimals as u32),
+            num: srepr.replace(".", "").parse().ok()?,
+            denom: (10 as isize).checked_pow(num_decimals as u32)?,
         };
 
         let gcd = gcd(rational.num, rational.denom);
         rational.num /= gcd;
         rational.denom /= gcd;
 
-        rational
+        Some(rational)
     }
 }
 
@@ -407,4 +409,16 @@ mod tests {
             Rational::new(-1, 4)
         )
     }
+
+    #[test]
+    fn test_from_float() {
+        assert_eq!(Rational::from_float(0.75), Rational::new(3, 4));
+        assert_eq!(Rational::from_float(1.), Rational::new(1, 1));
+        assert_eq!(
+            Rational::try_from_float(1e-18),
+            Some(Rational::new(1, 1_000_000_000_000_000_000))
+        );
+        assert_eq!(Rational::try_from_float(1e-19), None);
+        assert_eq!(Rational::try_from_float(1e30), None);
+    }
 }
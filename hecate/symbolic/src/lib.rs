@@ -5,6 +5,7 @@ use std::{
 
 pub mod abc;
 pub mod expr;
+pub mod printer;
 pub mod system;
 pub use expr::Equation;
 pub use expr::*;
@@ -20,6 +21,7 @@ pub enum KnownExpr<'a> {
     Rational(&'a Rational),
     Diff(&'a Diff),
     Eq(&'a Equation),
+    Matrix(&'a Matrix),
     Unknown,
 }
 
@@ -46,6 +48,8 @@ impl<'a> KnownExpr<'a> {
             KnownExpr::Eq(eq)
         } else if let Some(rational) = expr.downcast_ref::<Rational>() {
             KnownExpr::Rational(rational)
+        } else if let Some(matrix) = expr.downcast_ref::<Matrix>() {
+            KnownExpr::Matrix(matrix)
         } else {
             KnownExpr::Unknown
         }
@@ -0,0 +1,651 @@
+//! Code printers, turning expressions into source code for various target languages.
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use itertools::Itertools;
+
+use crate::*;
+
+/// Replaces the time discretization markers of a name by identifier friendly suffixes.
+/// (ie. `U^n-1` becomes `U_prev` and `U^n` becomes `U`)
+pub fn mangle_name(name: &str) -> String {
+    name.replace("^n-1", "_prev").replace("^n", "")
+}
+
+/// # Printer Settings
+/// How symbols and functions are named in the target language.
+/// Symbols and functions missing from the tables keep their (mangled) name.
+#[derive(Debug, Clone, Default)]
+pub struct PrinterSettings {
+    pub symbols: HashMap<String, String>,
+    pub functions: HashMap<String, String>,
+}
+
+impl PrinterSettings {
+    pub fn new<'a, S, F>(symbols: S, functions: F) -> Self
+    where
+        S: IntoIterator<Item = (&'a str, &'a str)>,
+        F: IntoIterator<Item = (&'a str, &'a str)>,
+    {
+        PrinterSettings {
+            symbols: symbols
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            functions: functions
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+
+    /// Prints the symbol `name` as `printed`.
+    pub fn with_symbol(mut self, name: &str, printed: &str) -> Self {
+        self.symbols.insert(name.to_string(), printed.to_string());
+        self
+    }
+
+    /// Prints calls to the function `name` as calls to `printed`.
+    pub fn with_function(mut self, name: &str, printed: &str) -> Self {
+        self.functions.insert(name.to_string(), printed.to_string());
+        self
+    }
+}
+
+/// A code printer for a target language.
+///
+/// Implementors only need to provide their settings and the language specific bits
+/// (numbers, powers...), the traversal of the expression is shared.
+pub trait Printer {
+    fn settings(&self) -> &PrinterSettings;
+
+    fn print(&self, expr: &dyn Expr) -> String {
+        match expr.known_expr() {
+            KnownExpr::Symbol(Symbol { name }) => self.print_symbol(name),
+            KnownExpr::Matrix(matrix) => self.print_matrix(matrix),
+            KnownExpr::Integer(Integer { value }) => self.print_integer(*value),
+            KnownExpr::Rational(Rational { num, denom }) => self.print_rational(*num, *denom),
+            KnownExpr::Add(Add { operands }) => self.print_add(operands),
+            KnownExpr::Mul(Mul { operands }) => self.print_mul(operands),
+            KnownExpr::Pow(Pow { base, exponent }) => {
+                self.print_pow(base.get_ref(), exponent.get_ref())
+            }
+            _ => match expr.as_function() {
+                Some(Func { name, args }) if args.is_empty() => self.print_symbol(name),
+                Some(Func { name, args }) => self.print_function(name, args),
+                None => expr.str(),
+            },
+        }
+    }
+
+    fn print_symbol(&self, name: &str) -> String {
+        self.settings()
+            .symbols
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| mangle_name(name))
+    }
+
+    fn print_matrix(&self, matrix: &Matrix) -> String {
+        self.print_symbol(&matrix.str())
+    }
+
+    fn print_integer(&self, value: isize) -> String {
+        value.to_string()
+    }
+
+    fn print_rational(&self, num: isize, denom: isize) -> String;
+
+    fn print_add(&self, operands: &[Box<dyn Expr>]) -> String {
+        let mut res = String::new();
+        for (i, op) in operands.iter().enumerate() {
+            let printed = self.print(op.get_ref());
+            match printed.strip_prefix('-') {
+                Some(negated) if i > 0 => {
+                    res += " - ";
+                    res += negated;
+                }
+                _ if i > 0 => {
+                    res += " + ";
+                    res += &printed;
+                }
+                _ => res += &printed,
+            }
+        }
+        res
+    }
+
+    fn print_mul(&self, operands: &[Box<dyn Expr>]) -> String {
+        let mut sign = "";
+        let mut numerator = Vec::with_capacity(operands.len());
+        let mut denominator = Vec::new();
+        for op in operands {
+            if op.is_neg_one() {
+                sign = if sign.is_empty() { "-" } else { "" };
+                continue;
+            }
+            match op.known_expr() {
+                KnownExpr::Pow(Pow { base, exponent }) if exponent.is_negative_number() => {
+                    let exponent = -exponent.clone_box();
+                    denominator.push(if exponent.is_one() {
+                        base.clone_box()
+                    } else {
+                        Pow::new_box(base.clone_box(), exponent)
+                    });
+                }
+                _ => numerator.push(op.get_ref()),
+            }
+        }
+
+        let mut res = if numerator.is_empty() {
+            self.print_integer(1)
+        } else {
+            numerator
+                .iter()
+                .map(|op| self.parenthesize(*op, Precedence::Mul))
+                .join(" * ")
+        };
+        match denominator.as_slice() {
+            [] => (),
+            [op] => {
+                res += " / ";
+                res += &self.parenthesize(op.get_ref(), Precedence::Pow);
+            }
+            ops => {
+                res += &format!(
+                    " / ({})",
+                    ops.iter()
+                        .map(|op| self.parenthesize(op.get_ref(), Precedence::Mul))
+                        .join(" * ")
+                );
+            }
+        }
+        format!("{sign}{res}")
+    }
+
+    fn print_pow(&self, base: &dyn Expr, exponent: &dyn Expr) -> String;
+
+    fn print_function(&self, name: &str, args: &[Box<dyn Expr>]) -> String {
+        let name = self
+            .settings()
+            .functions
+            .get(name)
+            .map(|s| s.as_str())
+            .unwrap_or(name);
+        format!(
+            "{name}({})",
+            args.iter().map(|arg| self.print(arg.get_ref())).join(", ")
+        )
+    }
+
+    /// Prints the expression, wrapping it in parentheses if it binds less tightly than `context`.
+    fn parenthesize(&self, expr: &dyn Expr, context: Precedence) -> String {
+        let printed = self.print(expr);
+        if precedence(expr) < context {
+            format!("({printed})")
+        } else {
+            printed
+        }
+    }
+}
+
+/// Operator precedence, from the loosest to the tightest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Precedence {
+    Add,
+    Mul,
+    Pow,
+    Atom,
+}
+
+pub fn precedence(expr: &dyn Expr) -> Precedence {
+    match expr.known_expr() {
+        KnownExpr::Add(_) => Precedence::Add,
+        KnownExpr::Mul(_) | KnownExpr::Rational(_) => Precedence::Mul,
+        KnownExpr::Integer(Integer { value }) if *value < 0 => Precedence::Mul,
+        KnownExpr::Pow(_) => Precedence::Pow,
+        _ => Precedence::Atom,
+    }
+}
+
+/// Printer for C99, relying on `math.h`.
+#[derive(Debug, Clone)]
+pub struct CPrinter {
+    pub settings: PrinterSettings,
+}
+
+impl Default for CPrinter {
+    fn default() -> Self {
+        CPrinter {
+            settings: PrinterSettings::new(
+                [("pi", "M_PI"), ("e", "M_E")],
+                [("abs", "fabs"), ("ln", "log")],
+            ),
+        }
+    }
+}
+
+impl Printer for CPrinter {
+    fn settings(&self) -> &PrinterSettings {
+        &self.settings
+    }
+
+    fn print_rational(&self, num: isize, denom: isize) -> String {
+        format!("{num}.0/{denom}.0")
+    }
+
+    fn print_pow(&self, base: &dyn Expr, exponent: &dyn Expr) -> String {
+        if exponent.is_neg_one() {
+            format!("1.0 / {}", self.parenthesize(base, Precedence::Pow))
+        } else if let KnownExpr::Rational(Rational { num: 1, denom: 2 }) = exponent.known_expr() {
+            format!("sqrt({})", self.print(base))
+        } else {
+            format!("pow({}, {})", self.print(base), self.print(exponent))
+        }
+    }
+}
+
+/// Printer for the C++ of the deal.II code generator.
+///
+/// Symbols are lowercased to match the generated variable names, the time step `k` being
+/// `time_step`, and the discretized operators are named after the matrices assembled for them.
+/// Functions of a single letter or of a discretized quantity (ie. `u^n(x)`) are values of the
+/// program, printed without their arguments.
+#[derive(Debug, Clone)]
+pub struct DealIIPrinter {
+    pub settings: PrinterSettings,
+}
+
+impl Default for DealIIPrinter {
+    fn default() -> Self {
+        DealIIPrinter {
+            settings: PrinterSettings::new([("k", "time_step")], []),
+        }
+    }
+}
+
+/// The printer used by [`Expr::to_cpp`].
+pub static DEAL_II_PRINTER: LazyLock<DealIIPrinter> = LazyLock::new(DealIIPrinter::default);
+
+impl Printer for DealIIPrinter {
+    fn settings(&self) -> &PrinterSettings {
+        &self.settings
+    }
+
+    fn print_symbol(&self, name: &str) -> String {
+        self.settings
+            .symbols
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| mangle_name(name).to_lowercase())
+    }
+
+    fn print_matrix(&self, matrix: &Matrix) -> String {
+        let name = match matrix.name.as_str() {
+            "M^n" => "mass_mat".to_string(),
+            "A^n" => "laplace_mat".to_string(),
+            "B^n" => "grad_div_mat".to_string(),
+            "S^n" => "strain_mat".to_string(),
+            // Operators weighted by a coefficient function
+            name if ["A_", "C_", "P_"].iter().any(|p| name.starts_with(p)) => {
+                let operator = match &name[..1] {
+                    "A" => "laplace",
+                    "C" => "advection",
+                    _ => "supg",
+                };
+                match name[2..].split_once('^') {
+                    Some((coefficient, "n")) => format!("{operator}_{coefficient}_mat"),
+                    Some((coefficient, "n,n-1")) => format!("{operator}_{coefficient}_mat_prev"),
+                    _ => self.print_symbol(name),
+                }
+            }
+            name => self.print_symbol(name),
+        };
+        if matrix.transposed {
+            format!("transpose({name})")
+        } else {
+            name
+        }
+    }
+
+    fn print_rational(&self, num: isize, denom: isize) -> String {
+        format!("{num}./{denom}.")
+    }
+
+    fn print_add(&self, operands: &[Box<dyn Expr>]) -> String {
+        let mut res = String::new();
+        for (i, op) in operands.iter().enumerate() {
+            let printed = self.print(op.get_ref());
+            if i == 0 {
+                res += &printed;
+                continue;
+            }
+            // Only products by -1 are turned into subtractions
+            let negated = match op.known_expr() {
+                KnownExpr::Mul(Mul { operands })
+                    if operands.first().is_some_and(|o| o.is_neg_one()) =>
+                {
+                    printed.strip_prefix('-')
+                }
+                _ => None,
+            };
+            match negated {
+                Some(negated) => {
+                    res += " - ";
+                    res += negated;
+                }
+                None => {
+                    res += " + ";
+                    res += &printed;
+                }
+            }
+        }
+        res
+    }
+
+    fn print_mul(&self, operands: &[Box<dyn Expr>]) -> String {
+        // The factor -1 counts as an operand, so that `-(1 / k)` keeps its parentheses
+        let parenthesize = operands.len() > 1;
+        let (sign, operands) = match operands {
+            [first, rest @ ..] if first.is_neg_one() => ("-", rest),
+            _ => ("", operands),
+        };
+        let res = operands
+            .iter()
+            .map(|op| {
+                let printed = self.print(op.get_ref());
+                match op.known_expr() {
+                    KnownExpr::Add(_) | KnownExpr::Pow(_) | KnownExpr::Rational(_)
+                        if parenthesize =>
+                    {
+                        format!("({printed})")
+                    }
+                    _ => printed,
+                }
+            })
+            .join(" * ");
+        format!("{sign}{res}")
+    }
+
+    fn print_pow(&self, base: &dyn Expr, exponent: &dyn Expr) -> String {
+        if exponent.is_zero() {
+            return self.print_integer(1);
+        }
+        if exponent.is_one() {
+            return self.print(base);
+        }
+        if exponent.is_neg_one() {
+            return format!("1 / {}", self.parenthesize(base, Precedence::Atom));
+        }
+        match exponent.known_expr() {
+            // Small powers are cheaper as products than calls to `pow`
+            KnownExpr::Integer(Integer { value: n }) if *n > 0 => {
+                vec![self.parenthesize(base, Precedence::Atom); *n as usize].join(" * ")
+            }
+            _ => format!("pow({}, {})", self.print(base), self.print(exponent)),
+        }
+    }
+
+    fn print_function(&self, name: &str, args: &[Box<dyn Expr>]) -> String {
+        if name.contains('^') || name.len() <= 1 {
+            return self.print_symbol(name);
+        }
+        let name = match self.settings.functions.get(name) {
+            Some(name) => name.clone(),
+            None => format!("std::{name}"),
+        };
+        format!(
+            "{name}({})",
+            args.iter().map(|arg| self.print(arg.get_ref())).join(", ")
+        )
+    }
+}
+
+/// Printer for Rust, using `f64` methods.
+#[derive(Debug, Clone)]
+pub struct RustPrinter {
+    pub settings: PrinterSettings,
+}
+
+impl Default for RustPrinter {
+    fn default() -> Self {
+        RustPrinter {
+            settings: PrinterSettings::new(
+                [("pi", "std::f64::consts::PI"), ("e", "std::f64::consts::E")],
+                [("log", "ln"), ("atan2", "atan2")],
+            ),
+        }
+    }
+}
+
+/// Functions that are methods of `f64` in Rust.
+const RUST_F64_METHODS: &[&str] = &[
+    "abs", "sqrt", "cbrt", "exp", "exp2", "ln", "log10", "log2", "sin", "cos", "tan", "asin",
+    "acos", "atan", "atan2", "sinh", "cosh", "tanh", "asinh", "acosh", "atanh", "floor", "ceil",
+    "round", "signum", "min", "max", "hypot",
+];
+
+impl RustPrinter {
+    /// Prints the receiver of an `f64` method call.
+    /// Number literals are suffixed, `2.0.sin()` being ambiguous to the compiler.
+    fn print_receiver(&self, expr: &dyn Expr) -> String {
+        match expr.known_expr() {
+            KnownExpr::Integer(Integer { value }) if *value < 0 => format!("({value}.0_f64)"),
+            KnownExpr::Integer(Integer { value }) => format!("{value}.0_f64"),
+            KnownExpr::Rational(Rational { num, denom }) => format!("({num}.0_f64 / {denom}.0)"),
+            _ => self.parenthesize(expr, Precedence::Atom),
+        }
+    }
+}
+
+impl Printer for RustPrinter {
+    fn settings(&self) -> &PrinterSettings {
+        &self.settings
+    }
+
+    fn print_integer(&self, value: isize) -> String {
+        format!("{value}.0")
+    }
+
+    fn print_rational(&self, num: isize, denom: isize) -> String {
+        format!("{num}.0 / {denom}.0")
+    }
+
+    fn print_pow(&self, base: &dyn Expr, exponent: &dyn Expr) -> String {
+        let base_printed = self.print_receiver(base);
+        match exponent.known_expr() {
+            KnownExpr::Integer(Integer { value: -1 }) => format!("1.0 / {base_printed}"),
+            KnownExpr::Integer(Integer { value }) => format!("{base_printed}.powi({value})"),
+            KnownExpr::Rational(Rational { num: 1, denom: 2 }) => {
+                format!("{base_printed}.sqrt()")
+            }
+            _ => format!("{base_printed}.powf({})", self.print(exponent)),
+        }
+    }
+
+    fn print_function(&self, name: &str, args: &[Box<dyn Expr>]) -> String {
+        let name = self
+            .settings
+            .functions
+            .get(name)
+            .map(|s| s.as_str())
+            .unwrap_or(name);
+        match args {
+            [receiver, rest @ ..] if RUST_F64_METHODS.contains(&name) => format!(
+                "{}.{name}({})",
+                self.print_receiver(receiver.get_ref()),
+                rest.iter().map(|arg| self.print(arg.get_ref())).join(", ")
+            ),
+            _ => format!(
+                "{name}({})",
+                args.iter().map(|arg| self.print(arg.get_ref())).join(", ")
+            ),
+        }
+    }
+}
+
+/// Printer for Python, using NumPy's vectorized functions.
+#[derive(Debug, Clone)]
+pub struct NumpyPrinter {
+    pub settings: PrinterSettings,
+}
+
+/// Functions available under the same name in NumPy.
+const NUMPY_FUNCTIONS: &[&str] = &[
+    "sin", "cos", "tan", "arcsin", "arccos", "arctan", "arctan2", "sinh", "cosh", "tanh", "exp",
+    "log", "log10", "log2", "sqrt", "cbrt", "abs", "floor", "ceil", "sign", "minimum", "maximum",
+    "hypot",
+];
+
+impl Default for NumpyPrinter {
+    fn default() -> Self {
+        let mut settings = PrinterSettings::new(
+            [("pi", "np.pi"), ("e", "np.e")],
+            [
+                ("asin", "np.arcsin"),
+                ("acos", "np.arccos"),
+                ("atan", "np.arctan"),
+                ("atan2", "np.arctan2"),
+                ("ln", "np.log"),
+                ("min", "np.minimum"),
+                ("max", "np.maximum"),
+            ],
+        );
+        for f in NUMPY_FUNCTIONS {
+            settings = settings.with_function(f, &format!("np.{f}"));
+        }
+        NumpyPrinter { settings }
+    }
+}
+
+impl Printer for NumpyPrinter {
+    fn settings(&self) -> &PrinterSettings {
+        &self.settings
+    }
+
+    fn print_rational(&self, num: isize, denom: isize) -> String {
+        format!("{num}/{denom}")
+    }
+
+    fn print_pow(&self, base: &dyn Expr, exponent: &dyn Expr) -> String {
+        format!(
+            "{}**{}",
+            self.parenthesize(base, Precedence::Atom),
+            self.parenthesize(exponent, Precedence::Atom)
+        )
+    }
+}
+
+/// Printer for Julia, whose math functions are available without imports.
+#[derive(Debug, Clone)]
+pub struct JuliaPrinter {
+    pub settings: PrinterSettings,
+}
+
+impl Default for JuliaPrinter {
+    fn default() -> Self {
+        JuliaPrinter {
+            settings: PrinterSettings::new(
+                [("pi", "pi"), ("e", "ℯ")],
+                [("ln", "log"), ("atan2", "atan")],
+            ),
+        }
+    }
+}
+
+impl Printer for JuliaPrinter {
+    fn settings(&self) -> &PrinterSettings {
+        &self.settings
+    }
+
+    fn print_rational(&self, num: isize, denom: isize) -> String {
+        format!("{num}/{denom}")
+    }
+
+    fn print_pow(&self, base: &dyn Expr, exponent: &dyn Expr) -> String {
+        format!(
+            "{}^{}",
+            self.parenthesize(base, Precedence::Atom),
+            self.parenthesize(exponent, Precedence::Atom)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Box<dyn Expr> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_c_printer() {
+        let printer = CPrinter::default();
+        assert_eq!(
+            printer.print(&*parse("sin(4 * pi * t) * x^2 / (1 + y)")),
+            "sin(4 * M_PI * t) * pow(x, 2) / (1 + y)"
+        );
+        assert_eq!(printer.print(&*parse("abs(x) - 1/2")), "fabs(x) - 1.0/2.0");
+    }
+
+    #[test]
+    fn test_deal_ii_printer() {
+        let printer = DealIIPrinter::default();
+        let expr = parse("k * sin(4 * pi * t) * (x + 1)^2 - y / 2") + Symbol::new_box("U^n-1");
+        assert_eq!(
+            printer.print(&*expr),
+            "time_step * std::sin(4 * pi * t) * ((x + 1) * (x + 1)) + (-1./2.) * y + u_prev"
+        );
+        assert_eq!(
+            printer.print(&*parse("f(t) / (x * y)")),
+            "f * (1 / (x * y))"
+        );
+        assert_eq!(printer.print(&Matrix::square("M^n", "n")), "mass_mat");
+        assert_eq!(
+            printer.print(&Matrix::square("A_kappa^n,n-1", "n").transpose()),
+            "transpose(laplace_kappa_mat_prev)"
+        );
+    }
+
+    #[test]
+    fn test_rust_printer() {
+        let printer = RustPrinter::default();
+        assert_eq!(
+            printer.print(&*parse("sin(2 * pi * t) + (x + 1)^3")),
+            "(2.0 * std::f64::consts::PI * t).sin() + (x + 1.0).powi(3)"
+        );
+        assert_eq!(printer.print(&*parse("log(x) / y")), "x.ln() / y");
+        assert_eq!(printer.print(&*parse("sin(2)")), "2.0_f64.sin()");
+        assert_eq!(printer.print(&*parse("2^t")), "2.0_f64.powf(t)");
+    }
+
+    #[test]
+    fn test_numpy_printer() {
+        let printer = NumpyPrinter::default();
+        assert_eq!(
+            printer.print(&*parse("exp(-x^2) * atan(y)")),
+            "np.exp(-x**2) * np.arctan(y)"
+        );
+    }
+
+    #[test]
+    fn test_julia_printer() {
+        let printer = JuliaPrinter::default();
+        assert_eq!(printer.print(&*parse("e^(2 * t) * c^2")), "ℯ^(2 * t) * c^2");
+        assert_eq!(printer.print(&*parse("atan2(y, x)")), "atan(y, x)");
+    }
+
+    #[test]
+    fn test_custom_symbols() {
+        let printer = NumpyPrinter {
+            settings: NumpyPrinter::default()
+                .settings
+                .with_symbol("k", "dt")
+                .with_function("f", "source"),
+        };
+        let expr = parse("k * f(t)") + Symbol::new_box("U^n-1");
+        assert_eq!(printer.print(&*expr), "dt * source(t) + U_prev");
+    }
+}
@@ -4,7 +4,6 @@ use itertools::Itertools;
 use log::{debug, info};
 
 use super::*;
-use crate::symbol;
 
 #[derive(Debug, Clone)]
 pub struct System {
@@ -12,17 +11,108 @@ pub struct System {
     pub known_unknowns: Vec<Func>,
     pub knowns: Vec<Func>,
     pub equations: Vec<Equation>,
+    /// Assembled operators appearing in the equations once the system is matrixified
+    pub matrixes: Vec<Matrix>,
+    /// Functions weighting the operators of the unknowns
+    pub coefficients: Vec<Coefficient>,
 }
 
-static SHAPE_MATRIXES: LazyLock<[Symbol; 4]> = LazyLock::new(|| {
+/// # Weighted Operator
+/// A spatial operator depending on a function, assembled into its own matrix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WeightedOperator {
+    /// The divergence of a weighted gradient, `div(c * grad(u))`, written `laplacian_c`
+    Laplacian,
+    /// The derivative along a velocity field, `dot(v, grad(u))`, written `advection_v`
+    Advection,
+    /// The streamline upwind Petrov-Galerkin weighting of the time derivative along a
+    /// velocity field, written `supg_v`
+    Supg,
+}
+
+impl WeightedOperator {
+    fn prefixes(&self) -> (&'static str, &'static str) {
+        match self {
+            WeightedOperator::Laplacian => ("laplacian", "A"),
+            WeightedOperator::Advection => ("advection", "C"),
+            WeightedOperator::Supg => ("supg", "P"),
+        }
+    }
+}
+
+/// # Coefficient
+/// A function weighting an operator of the unknowns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Coefficient {
+    pub name: String,
+    pub operator: WeightedOperator,
+    /// Whether the function depends on time, its matrix then being reassembled at each step
+    pub time_dependent: bool,
+}
+
+impl Coefficient {
+    pub fn new(name: &str, operator: WeightedOperator, time_dependent: bool) -> Self {
+        Coefficient {
+            name: name.to_string(),
+            operator,
+            time_dependent,
+        }
+    }
+
+    /// The symbol of the weighted operator
+    pub fn symbol(&self) -> Box<dyn Expr> {
+        let (prefix, _) = self.operator.prefixes();
+        Symbol::new_box(&format!("{prefix}_{}", self.name))
+    }
+
+    /// The matrices of the weighted operator at the current and previous time steps
+    pub fn matrixes(&self) -> [Matrix; 2] {
+        let (_, prefix) = self.operator.prefixes();
+        let properties = match self.operator {
+            WeightedOperator::Laplacian => MatrixProperties::SYMMETRIC,
+            _ => MatrixProperties::GENERAL,
+        };
+        [
+            Matrix::square(&format!("{prefix}_{}^n", self.name), "n_dofs")
+                .with_properties(properties),
+            Matrix::square(&format!("{prefix}_{}^n,n-1", self.name), "n_dofs")
+                .with_properties(properties),
+        ]
+    }
+
+    /// The matrix replacing the operator applied to `unknown`, the Laplacian being negative
+    fn matrix_product(&self, matrix: &Matrix, unknown: &dyn Expr) -> Box<dyn Expr> {
+        match self.operator {
+            WeightedOperator::Laplacian => -matrix.get_ref() * unknown,
+            _ => matrix.get_ref() * unknown,
+        }
+    }
+}
+
+/// Mass and Laplace matrices of the current and previous time steps
+static SHAPE_MATRIXES: LazyLock<[Matrix; 4]> = LazyLock::new(|| {
+    [
+        Matrix::square("M^n", "n_dofs").with_properties(MatrixProperties::SPD),
+        Matrix::square("A^n", "n_dofs").with_properties(MatrixProperties::SYMMETRIC),
+        Matrix::square("M^n,n-1", "n_dofs").with_properties(MatrixProperties::SPD),
+        Matrix::square("A^n,n-1", "n_dofs").with_properties(MatrixProperties::SYMMETRIC),
+    ]
+});
+
+/// Grad-div and strain matrices of the current and previous time steps, from the operators of
+/// vector unknowns
+static VECTOR_SHAPE_MATRIXES: LazyLock<[Matrix; 4]> = LazyLock::new(|| {
     [
-        Symbol::new("M^n"),
-        Symbol::new("A^n"),
-        Symbol::new("M^n,n-1"),
-        Symbol::new("A^n,n-1"),
+        Matrix::square("B^n", "n_dofs").with_properties(MatrixProperties::SYMMETRIC),
+        Matrix::square("S^n", "n_dofs").with_properties(MatrixProperties::SYMMETRIC),
+        Matrix::square("B^n,n-1", "n_dofs").with_properties(MatrixProperties::SYMMETRIC),
+        Matrix::square("S^n,n-1", "n_dofs").with_properties(MatrixProperties::SYMMETRIC),
     ]
 });
 
+/// Symbols of the spatial operators, each assembled into a matrix by `matrixify`
+const OPERATORS: [&str; 3] = ["laplacian", "grad_div", "div_eps"];
+
 impl std::fmt::Display for System {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
@@ -74,9 +164,29 @@ impl System {
             knowns: knowns.into_iter().map(|s| Func::new(s, [])).collect(),
             equations: equations.into_iter().cloned().collect(),
             known_unknowns: vec![],
+            matrixes: vec![],
+            coefficients: vec![],
         }
     }
 
+    /// Declares the functions weighting operators in the equations
+    pub fn with_coefficients<T: IntoIterator<Item = Coefficient>>(
+        mut self,
+        coefficients: T,
+    ) -> Self {
+        self.coefficients = coefficients.into_iter().collect();
+        self
+    }
+
+    /// Symbols of the spatial operators, including the weighted ones
+    fn operators(&self) -> Vec<Box<dyn Expr>> {
+        OPERATORS
+            .map(Symbol::new_box)
+            .into_iter()
+            .chain(self.coefficients.iter().map(|c| c.symbol()))
+            .collect()
+    }
+
     pub fn to_first_order_in_time(&self) -> Self {
         let mut unknowns_with_snd_time_derivatives = HashSet::new();
         for unknown in &self.unknowns {
@@ -121,9 +231,48 @@ impl System {
             knowns: self.knowns.clone(),
             equations,
             known_unknowns: vec![],
+            matrixes: self.matrixes.clone(),
+            coefficients: self.coefficients.clone(),
         }
     }
 
+    /// Drops the time derivatives of the unknowns, the equations then describing the steady
+    /// state
+    pub fn to_steady(&self) -> Self {
+        info!("Dropping the time derivatives for the steady state");
+        let substitutions = self
+            .unknowns
+            .iter()
+            .flat_map(|unknown| [1, 2].map(|order| [unknown.diff("t", order), Integer::zero_box()]))
+            .collect_vec();
+        self.subs(&substitutions)
+    }
+
+    /// Gathers the terms of the unknowns in the left-hand sides of the equations, the other
+    /// terms going to the right-hand sides.
+    /// Unlike solving the equations for the unknowns, the operators applied to them are kept.
+    pub fn separated(&self) -> Self {
+        info!("Gathering the terms of the unknowns in the left-hand sides");
+        let equations = self
+            .equations
+            .iter()
+            .map(|eq| {
+                let expr = (eq.lhs.clone_box() - eq.rhs.clone_box())
+                    .expand()
+                    .simplify();
+                let (lhs, rhs): (Vec<_>, Vec<_>) = expr
+                    .terms()
+                    .map(|term| term.clone_box())
+                    .partition(|term| self.unknowns.iter().any(|u| term.has(u)));
+                Equation {
+                    lhs: Add::new_box_v2(lhs),
+                    rhs: (-Add::new_box_v2(rhs)).expand(),
+                }
+            })
+            .collect();
+        self.with_equations(equations)
+    }
+
     pub fn time_discretized(&self) -> Self {
         let mut unknowns: Vec<Func> = Vec::with_capacity(self.unknowns.len());
         let mut known_unknowns: Vec<Func> = Vec::with_capacity(self.unknowns.len());
@@ -172,6 +321,8 @@ impl System {
             knowns,
             equations,
             known_unknowns,
+            matrixes: self.matrixes.clone(),
+            coefficients: self.coefficients.clone(),
         }
     }
 
@@ -225,16 +376,21 @@ impl System {
     }
 
     pub fn factor(&self) -> Self {
-        let laplacian = symbol!("laplacian");
-        let matrixes = [Symbol::new_box("M^n"), Symbol::new_box("A^n")];
+        let operators = self.operators();
         let symbols: Vec<Box<dyn Expr>> = self
             .unknowns
             .iter()
             .map(|f| f.get_ref())
             .chain(self.known_unknowns.iter().map(|f| f.get_ref()))
-            .chain(matrixes.iter().map(|m| m.get_ref()))
+            .chain(self.matrixes())
             .chain(self.knowns.iter().map(|f| f.get_ref()))
-            .flat_map(|f| [laplacian * f, f.clone_box()])
+            .flat_map(|f| {
+                operators
+                    .iter()
+                    .map(|operator| operator.get_ref() * f)
+                    .chain([f.clone_box()])
+                    .collect::<Vec<_>>()
+            })
             .chain([Symbol::new_box("k"), Symbol::new_box("theta")])
             .collect();
         let symbols: Vec<_> = symbols.iter().map(|e| e.get_ref()).collect();
@@ -251,12 +407,13 @@ impl System {
         let mut knowns: Vec<Func> = Vec::with_capacity(self.knowns.len());
         let mut known_unknowns: Vec<Func> = Vec::with_capacity(self.known_unknowns.len());
         let mut unknowns: Vec<Func> = Vec::with_capacity(self.unknowns.len());
-        let laplacian = symbol!("laplacian");
+        let operators = OPERATORS.map(Symbol::new_box);
+        let [laplacian, grad_div, div_eps] = operators.each_ref().map(|o| o.get_ref());
 
-        let mass_mat = symbol!("M^n");
-        let mass_mat_prev = symbol!("M^n,n-1");
-        let laplace_mat = symbol!("A^n");
-        let laplace_mat_prev = symbol!("A^n,n-1");
+        let [mass_mat, laplace_mat, mass_mat_prev, laplace_mat_prev] =
+            SHAPE_MATRIXES.each_ref().map(|m| m.get_ref());
+        let [grad_div_mat, strain_mat, grad_div_mat_prev, strain_mat_prev] =
+            VECTOR_SHAPE_MATRIXES.each_ref().map(|m| m.get_ref());
 
         let mut substitutions =
             Vec::with_capacity(2 * knowns.len() + 2 * known_unknowns.len() + unknowns.len());
@@ -273,6 +430,21 @@ impl System {
                 laplacian * unknown.get_ref(),
                 -laplace_mat * unknown_vec.get_ref(),
             ]);
+            substitutions.push([
+                grad_div * unknown.get_ref(),
+                -grad_div_mat * unknown_vec.get_ref(),
+            ]);
+            substitutions.push([
+                div_eps * unknown.get_ref(),
+                -strain_mat * unknown_vec.get_ref(),
+            ]);
+            for coefficient in &self.coefficients {
+                let [weighted_mat, _] = coefficient.matrixes();
+                substitutions.push([
+                    coefficient.symbol() * unknown.get_ref(),
+                    coefficient.matrix_product(&weighted_mat, unknown_vec.get_ref()),
+                ]);
+            }
             substitutions.push([unknown.clone_box(), mass_mat * unknown_vec.get_ref()]);
             unknowns.push(unknown_vec);
         }
@@ -283,6 +455,21 @@ impl System {
                 laplacian * unknown.get_ref(),
                 -laplace_mat_prev * unknown_vec.get_ref(),
             ]);
+            substitutions.push([
+                grad_div * unknown.get_ref(),
+                -grad_div_mat_prev * unknown_vec.get_ref(),
+            ]);
+            substitutions.push([
+                div_eps * unknown.get_ref(),
+                -strain_mat_prev * unknown_vec.get_ref(),
+            ]);
+            for coefficient in &self.coefficients {
+                let [_, weighted_mat_prev] = coefficient.matrixes();
+                substitutions.push([
+                    coefficient.symbol() * unknown.get_ref(),
+                    coefficient.matrix_product(&weighted_mat_prev, unknown_vec.get_ref()),
+                ]);
+            }
             substitutions.push([unknown.clone_box(), mass_mat_prev * unknown_vec.get_ref()]);
             known_unknowns.push(unknown_vec);
         }
@@ -296,31 +483,70 @@ impl System {
                     .as_eq()
                     .unwrap()
             })
-            .collect();
+            .collect::<Vec<_>>();
+
+        // The operators of vector unknowns and weighted operators are only assembled when they
+        // are used
+        let mut matrixes = SHAPE_MATRIXES.to_vec();
+        matrixes.extend(
+            VECTOR_SHAPE_MATRIXES
+                .iter()
+                .cloned()
+                .chain(self.coefficients.iter().flat_map(|c| c.matrixes()))
+                .filter(|m| equations.iter().any(|e| e.has(m))),
+        );
 
         System {
             unknowns,
             knowns,
             known_unknowns,
             equations,
+            matrixes,
+            coefficients: self.coefficients.clone(),
         }
     }
 
     pub fn to_constant_mesh(&self) -> Self {
-        let mass_mat = Symbol::new_box("M^n");
-        let mass_mat_prev = Symbol::new_box("M^n,n-1");
-        let laplace_mat = Symbol::new_box("A^n");
-        let laplace_mat_prev = Symbol::new_box("A^n,n-1");
-
-        let subs = [[mass_mat_prev, mass_mat], [laplace_mat_prev, laplace_mat]];
+        let [mass_mat, laplace_mat, mass_mat_prev, laplace_mat_prev] =
+            SHAPE_MATRIXES.each_ref().map(|m| m.clone_box());
+
+        let [grad_div_mat, strain_mat, grad_div_mat_prev, strain_mat_prev] =
+            VECTOR_SHAPE_MATRIXES.each_ref().map(|m| m.clone_box());
+
+        let mut subs = vec![
+            [mass_mat_prev, mass_mat],
+            [laplace_mat_prev, laplace_mat],
+            [grad_div_mat_prev, grad_div_mat.clone_box()],
+            [strain_mat_prev, strain_mat.clone_box()],
+        ];
+        // Weighted operators varying in time keep the matrix of the previous time step
+        let constant_coefficients = self.coefficients.iter().filter(|c| !c.time_dependent);
+        let constant_weighted_mats = constant_coefficients.map(|c| c.matrixes()).collect_vec();
+        for [weighted_mat, weighted_mat_prev] in &constant_weighted_mats {
+            subs.push([weighted_mat_prev.clone_box(), weighted_mat.clone_box()]);
+        }
 
-        self.with_equations(
+        let mut system = self.with_equations(
             self.equations
                 .iter()
                 .map(|eq| eq.subs(&subs).as_eq().unwrap())
                 .collect(),
-        )
-        .factor()
+        );
+        // Matrices of the previous time step are the same as the current ones
+        system.matrixes.retain(|m| {
+            !SHAPE_MATRIXES[2..].contains(m)
+                && !VECTOR_SHAPE_MATRIXES[2..].contains(m)
+                && !constant_weighted_mats.iter().any(|[_, prev]| prev == m)
+        });
+        let current_mats = VECTOR_SHAPE_MATRIXES[..2]
+            .iter()
+            .chain(constant_weighted_mats.iter().map(|[current, _]| current));
+        for matrix in current_mats {
+            if system.equations.iter().any(|e| e.has(matrix)) && !system.matrixes.contains(matrix) {
+                system.matrixes.push(matrix.clone());
+            }
+        }
+        system.factor()
     }
 
     pub fn to_crank_nikolson(&self) -> Self {
@@ -341,6 +567,13 @@ impl System {
         self.subs(&[[theta, Integer::new_box(1)]])
     }
 
+    pub fn to_theta_scheme(&self, theta: f64) -> Self {
+        info!("Applying a θ-scheme time discretization with θ = {theta}");
+        let theta_symbol = Symbol::new_box("θ");
+        self.subs(&[[theta_symbol, Rational::from_float(theta).simplify()]])
+            .simplify()
+    }
+
     pub fn subs(&self, substitutions: &[[Box<dyn Expr>; 2]]) -> Self {
         self.with_equations(
             self.equations
@@ -374,6 +607,8 @@ impl System {
             unknowns: self.unknowns.clone(),
             knowns: self.knowns.clone(),
             equations,
+            matrixes: self.matrixes.clone(),
+            coefficients: self.coefficients.clone(),
         }
     }
 
@@ -387,7 +622,7 @@ impl System {
     }
 
     pub fn matrixes(&self) -> impl Iterator<Item = &dyn Expr> {
-        SHAPE_MATRIXES.iter().map(|m| m.get_ref())
+        self.matrixes.iter().map(|m| m.get_ref())
     }
 
     pub fn num_vectors(&self) -> usize {
@@ -416,6 +651,53 @@ impl System {
             .map(|(e, _)| e)
     }
 
+    /// The matrixes of the operators applied to `unknown` in the left-hand sides
+    pub fn lhs_matrixes(&self, unknown: &dyn Expr) -> impl Iterator<Item = &Matrix> {
+        self.matrixes.iter().filter(move |m| {
+            self.equations
+                .iter()
+                .any(|eq| eq.lhs.has(unknown) && eq.lhs.has(*m))
+        })
+    }
+
+    /// Whether `expr` is the same at every time step, none of its matrices being reassembled and
+    /// its scalars not depending on time
+    fn is_time_invariant(&self, expr: &dyn Expr) -> bool {
+        let time = Symbol::new("t");
+        !expr.has(&time)
+            && self
+                .coefficients
+                .iter()
+                .filter(|c| c.time_dependent)
+                .flat_map(|c| c.matrixes())
+                .all(|m| !expr.has(&m))
+    }
+
+    /// Whether the left-hand sides applied to `unknown` are the same at every time step, none of
+    /// their matrices being reassembled and their scalars not depending on time
+    pub fn has_constant_lhs(&self, unknown: &dyn Expr) -> bool {
+        self.equations
+            .iter()
+            .filter(|eq| eq.lhs.has(unknown))
+            .all(|eq| self.is_time_invariant(eq.lhs.get_ref()))
+    }
+
+    /// Whether the operators of the right-hand sides of the equations of `unknown` are the same
+    /// at every time step, the terms applying matrices being time invariant
+    pub fn has_constant_rhs_operators(&self, unknown: &dyn Expr) -> bool {
+        self.equations
+            .iter()
+            .filter(|eq| eq.lhs.has(unknown))
+            .flat_map(|eq| eq.rhs.terms())
+            .filter(|term| self.matrixes.iter().any(|m| term.has(m)))
+            .all(|term| self.is_time_invariant(term))
+    }
+
+    /// Whether the operators applied to `unknown` in the left-hand sides are all symmetric
+    pub fn is_symmetric(&self, unknown: &dyn Expr) -> bool {
+        self.lhs_matrixes(unknown).all(|m| m.is_symmetric())
+    }
+
     pub fn equation_lhs_unknowns(&self, equation: &Equation) -> impl Iterator<Item = &dyn Expr> {
         self.unknowns.iter().filter_map(|unknown| {
             if equation.has(unknown) {
@@ -426,3 +708,65 @@ impl System {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The Crank-Nicolson system of the heat equation `equation` on a constant mesh
+    fn heat_system(equation: &str, coefficients: &[Coefficient]) -> System {
+        let equation: Equation = equation.parse().unwrap();
+        let equation = equation
+            .subs(&[[Symbol::new_box("T"), Func::new("T", []).clone_box()]])
+            .as_eq()
+            .unwrap();
+        System::new(["T"], [], [&equation])
+            .with_coefficients(coefficients.iter().cloned())
+            .to_first_order_in_time()
+            .time_discretized()
+            .simplified()
+            .unwrap()
+            .matrixify()
+            .to_crank_nikolson()
+            .to_constant_mesh()
+            .simplify()
+    }
+
+    #[test]
+    fn test_constant_operators() {
+        let system = heat_system("dt(T) = c * laplacian(T)", &[]);
+        let unknown = system.unknowns[0].get_ref();
+        assert!(system.has_constant_lhs(unknown));
+        assert!(system.has_constant_rhs_operators(unknown));
+
+        let kappa = Coefficient::new("kappa", WeightedOperator::Laplacian, false);
+        let system = heat_system("dt(T) = div(kappa * grad(T))", &[kappa]);
+        let unknown = system.unknowns[0].get_ref();
+        assert!(system.has_constant_lhs(unknown));
+        assert!(system.has_constant_rhs_operators(unknown));
+    }
+
+    #[test]
+    fn test_time_dependent_operators() {
+        // Scalars depending on time
+        let system = heat_system("dt(T) = (1 + t) * laplacian(T)", &[]);
+        let unknown = system.unknowns[0].get_ref();
+        assert!(!system.has_constant_lhs(unknown));
+        assert!(!system.has_constant_rhs_operators(unknown));
+
+        // Matrices reassembled at each step
+        let kappa = Coefficient::new("kappa", WeightedOperator::Laplacian, true);
+        let system = heat_system("dt(T) = div(kappa * grad(T))", &[kappa]);
+        let unknown = system.unknowns[0].get_ref();
+        assert!(!system.has_constant_lhs(unknown));
+        assert!(!system.has_constant_rhs_operators(unknown));
+    }
+
+    #[test]
+    fn test_time_dependent_source_keeps_constant_operators() {
+        let system = heat_system("dt(T) = c * laplacian(T) + t", &[]);
+        let unknown = system.unknowns[0].get_ref();
+        assert!(system.has_constant_lhs(unknown));
+        assert!(system.has_constant_rhs_operators(unknown));
+    }
+}